@@ -0,0 +1,49 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use trovo::chat::{ChatMessageDataRef, ChatSocketMessage};
+
+/// A busy chat container, similar to what spell-heavy channels deliver
+fn chat_frame(chats: usize) -> String {
+    let chats: Vec<_> = (0..chats)
+        .map(|i| {
+            serde_json::json!({
+                "type": 5,
+                "content": format!("{{\"gift\":\"Spell {}\",\"num\":1}}", i),
+                "nick_name": format!("viewer{}", i),
+                "avatar": "https://headicon.trovo.live/user/avatar.png",
+                "sub_lv": "sub_L1",
+                "medals": ["sub_L1_1", "creator"],
+                "decos": ["deco"],
+                "roles": ["supporter", "follower"],
+                "message_id": format!("1700000000_{}", i),
+                "sender_id": 100000 + i,
+                "send_time": 1700000000,
+                "content_data": { "gift_id": i, "gift_value": 100, "value_type": "Elixir" },
+                "custom_role": "[]",
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "type": "CHAT",
+        "channel_info": { "channel_id": "100000" },
+        "data": { "eid": "1700000000", "chats": chats },
+    })
+    .to_string()
+}
+
+fn parse(c: &mut Criterion) {
+    let frame = chat_frame(100);
+    let mut group = c.benchmark_group("parse chat frame");
+    group.bench_function("owned", |b| {
+        b.iter(|| {
+            let msg: ChatSocketMessage = serde_json::from_str(black_box(&frame)).unwrap();
+            msg
+        })
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| ChatMessageDataRef::from_frame(black_box(&frame)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
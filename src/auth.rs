@@ -1,5 +1,24 @@
+//! Auth providers supplying the client id and access tokens for requests.
+//!
+//! Everything here is also re-exported from the crate root. With the `auth-flow` feature this
+//! module also has `interactive_setup`, which walks a chat bot through the oauth flow on its
+//! first run, and with the `oauth2` feature `Oauth2Provider`, which renews tokens managed with the
+//! `oauth2` crate.
+
 use crate::AccessTokenExpired;
-use std::fmt::{Debug, Display};
+use std::fmt::{self, Debug, Display};
+
+#[cfg(feature = "auth-flow")]
+mod flow;
+#[cfg(feature = "auth-flow")]
+pub use flow::*;
+#[cfg(feature = "oauth2")]
+mod oauth2_provider;
+#[cfg(feature = "oauth2")]
+pub use oauth2_provider::*;
+
+/// Shown in place of secrets when formatting
+pub(crate) const REDACTED: &str = "<redacted>";
 
 /// A trait for an auth provider that can provide
 /// a client id
@@ -26,7 +45,9 @@ impl ClientIdProvider for ClientId {
 }
 
 /// Represents an access token
-#[derive(Debug, Clone)]
+///
+/// The token is redacted from the `Debug` output.
+#[derive(Clone)]
 pub enum AccessToken {
     /// Access token
     Token(String),
@@ -35,6 +56,15 @@ pub enum AccessToken {
     NeedsRefresh,
 }
 
+impl Debug for AccessToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Token(_) => f.debug_tuple("Token").field(&REDACTED).finish(),
+            Self::NeedsRefresh => f.write_str("NeedsRefresh"),
+        }
+    }
+}
+
 impl From<String> for AccessToken {
     fn from(token: String) -> Self {
         Self::Token(token)
@@ -60,7 +90,8 @@ pub trait AccessTokenProvider: ClientIdProvider {
 
 /// A simple access token provider that errors if refreshing is attempted. It is strongly advised
 /// that you implement your own [`AccessTokenProvider`] so that you can handle refreshing.
-#[derive(Debug)]
+///
+/// The token is redacted from the `Debug` output.
 pub struct AccessTokenOnly {
     client_id: String,
     token: String,
@@ -76,6 +107,15 @@ impl AccessTokenOnly {
     }
 }
 
+impl Debug for AccessTokenOnly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessTokenOnly")
+            .field("client_id", &self.client_id)
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
 impl ClientIdProvider for AccessTokenOnly {
     fn client_id(&self) -> &str {
         &self.client_id
@@ -0,0 +1,764 @@
+use crate::{
+    auth::REDACTED, AccessToken, AccessTokenProvider, Client, ClientIdProvider, ClientSecret,
+    SetupError, TokenResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    fmt, fs,
+    hash::{BuildHasher, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use url::Url;
+
+const AUTHORIZE_URL: &str = "https://open.trovo.live/page/login.html";
+
+/// Tokens are renewed this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Largest redirect request head read by the capture server.
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// Settings for [`interactive_setup`].
+#[derive(Clone)]
+pub struct SetupConfig {
+    /// Client id of the Trovo application
+    pub client_id: String,
+
+    /// Client secret of the Trovo application
+    pub client_secret: String,
+
+    /// Second client secret, tried when Trovo rejects `client_secret` while it is being rotated.
+    /// See [`FileTokenProvider::with_fallback_secret`].
+    pub fallback_client_secret: Option<String>,
+
+    /// Scopes to request, e.g. `chat_connect` and `chat_send_self` for a chat bot
+    pub scopes: Vec<String>,
+
+    /// File the token is stored in, and loaded from on later runs
+    pub token_path: PathBuf,
+
+    /// Redirect uri registered for the application. The capture server listens on its port, so
+    /// it should point at this machine. Defaults to `http://localhost:3000/callback`.
+    pub redirect_uri: String,
+
+    /// Whether to try opening the authorize url in a browser, besides printing it. Defaults to
+    /// `true`.
+    pub open_browser: bool,
+
+    /// How long to wait for the user to authorize. Defaults to five minutes.
+    pub timeout: Duration,
+}
+
+impl Default for SetupConfig {
+    fn default() -> Self {
+        Self {
+            client_id: String::new(),
+            client_secret: String::new(),
+            fallback_client_secret: None,
+            scopes: Vec::new(),
+            token_path: PathBuf::from("trovo-token.json"),
+            redirect_uri: "http://localhost:3000/callback".to_string(),
+            open_browser: true,
+            timeout: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl fmt::Debug for SetupConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SetupConfig")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &REDACTED)
+            .field(
+                "fallback_client_secret",
+                &self.fallback_client_secret.as_ref().map(|_| REDACTED),
+            )
+            .field("scopes", &self.scopes)
+            .field("token_path", &self.token_path)
+            .field("redirect_uri", &self.redirect_uri)
+            .field("open_browser", &self.open_browser)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// Gets a client authenticated as a user, walking through the oauth flow on the first run.
+///
+/// If the token file exists, the token is loaded from it and no browser is involved. Otherwise
+/// the authorize url is printed (and opened, see [`SetupConfig::open_browser`]), the redirect is
+/// captured by a local server listening on the redirect uri's port, the code exchanged for a
+/// token, and the token written to the file. The returned client renews the token as needed,
+/// saving it back to the file, see [`FileTokenProvider`].
+///
+/// Scopes are only requested on the first run. Delete the token file to authorize again with
+/// different scopes.
+///
+/// ```no_run
+/// use futures::prelude::*;
+/// use trovo::auth::{interactive_setup, SetupConfig};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let client = interactive_setup(SetupConfig {
+///         client_id: std::env::var("CLIENT_ID")?,
+///         client_secret: std::env::var("CLIENT_SECRET")?,
+///         scopes: vec!["chat_connect".to_string(), "chat_send_self".to_string()],
+///         token_path: "token.json".into(),
+///         ..Default::default()
+///     })
+///     .await?;
+///
+///     let mut messages = client.chat_messages_for_user().await?;
+///     while let Some(message) = messages.next().await {
+///         if message?.content == "!ping" {
+///             client.send_chat_message(None, "pong").await?;
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+pub async fn interactive_setup(
+    config: SetupConfig,
+) -> Result<Client<FileTokenProvider>, SetupError> {
+    let http = Client::try_new(())
+        .map_err(|err| SetupError::OAuth(crate::RequestError::from(err).into()))?;
+    interactive_setup_with(config, &http).await
+}
+
+/// Same as [`interactive_setup`], but exchanges tokens through the given client and returns a
+/// client sharing its settings, e.g. a proxy or a scripted transport in tests.
+pub async fn interactive_setup_with<A>(
+    config: SetupConfig,
+    client: &Client<A>,
+) -> Result<Client<FileTokenProvider>, SetupError> {
+    let mut secret = ClientSecret::new(&config.client_secret);
+    if let Some(fallback) = &config.fallback_client_secret {
+        secret.set_fallback(fallback);
+    }
+
+    if config.token_path.exists() {
+        let token = StoredToken::load(&config.token_path)?;
+        let provider = FileTokenProvider::with_secret(
+            client,
+            config.client_id,
+            secret,
+            config.token_path,
+            token,
+        );
+        return Ok(client.with_auth_provider(provider));
+    }
+
+    let redirect = Url::parse(&config.redirect_uri)
+        .map_err(|_| SetupError::InvalidRedirectUri(config.redirect_uri.clone()))?;
+    let port = redirect
+        .port_or_known_default()
+        .ok_or_else(|| SetupError::InvalidRedirectUri(config.redirect_uri.clone()))?;
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(SetupError::CaptureServer)?;
+
+    let state = random_state();
+    let url = authorize_url(
+        &config.client_id,
+        &config.redirect_uri,
+        &config.scopes,
+        &state,
+    );
+    println!(
+        "Open this url to authorize the application:\n\n    {}\n",
+        url
+    );
+    if config.open_browser {
+        open_browser(url.as_str());
+    }
+
+    let code = tokio::time::timeout(
+        config.timeout,
+        capture_redirect(&listener, redirect.path(), &state),
+    )
+    .await
+    .map_err(|_| SetupError::Timeout)??;
+    drop(listener);
+
+    let (client_id, redirect_uri) = (&config.client_id, &config.redirect_uri);
+    let response = secret
+        .exchange(|client_secret| {
+            let code = &code;
+            async move {
+                client
+                    .exchange_code(client_id, &client_secret, code, redirect_uri)
+                    .await
+            }
+        })
+        .await?;
+    let token =
+        StoredToken::from_response(response, config.scopes, client.pipeline.clock.system_time());
+    token.save(&config.token_path)?;
+
+    let provider =
+        FileTokenProvider::with_secret(client, config.client_id, secret, config.token_path, token);
+    Ok(client.with_auth_provider(provider))
+}
+
+/// Url the user is sent to for authorizing the application.
+///
+/// `state` is sent back in the redirect, to check that it answers this request, see
+/// [`capture_redirect`].
+pub fn authorize_url(client_id: &str, redirect_uri: &str, scopes: &[String], state: &str) -> Url {
+    let mut url = Url::parse(AUTHORIZE_URL).expect("authorize url is valid");
+    url.query_pairs_mut()
+        .append_pair("client_id", client_id)
+        .append_pair("response_type", "code")
+        // Trovo separates scopes with `+`, which is how the form encoding writes spaces
+        .append_pair("scope", &scopes.join(" "))
+        .append_pair("redirect_uri", redirect_uri)
+        .append_pair("state", state);
+    url
+}
+
+/// Serves redirects on the listener until one to `path` arrives, returning its authorization
+/// code.
+///
+/// Requests to other paths, such as the browser asking for a favicon, get a 404 and are
+/// otherwise ignored. The redirect is answered with a short page telling the user whether to
+/// return to the application.
+pub async fn capture_redirect(
+    listener: &TcpListener,
+    path: &str,
+    state: &str,
+) -> Result<String, SetupError> {
+    loop {
+        let (mut stream, _) = listener.accept().await.map_err(SetupError::CaptureServer)?;
+        let target = match read_request_target(&mut stream).await {
+            Ok(Some(target)) => target,
+            // Not an http request, or the connection dropped early
+            Ok(None) | Err(_) => continue,
+        };
+        let url = match Url::parse("http://localhost")
+            .and_then(|base| base.join(&target))
+            .ok()
+            .filter(|url| url.path() == path)
+        {
+            Some(url) => url,
+            None => {
+                respond(&mut stream, "404 Not Found", "Not found.").await;
+                continue;
+            }
+        };
+
+        let query = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let result = if let Some(error) = query("error") {
+            Err(SetupError::Denied {
+                error,
+                description: query("error_description"),
+            })
+        } else if query("state").as_deref() != Some(state) {
+            Err(SetupError::StateMismatch)
+        } else {
+            query("code").ok_or(SetupError::MissingCode)
+        };
+
+        match &result {
+            Ok(_) => {
+                respond(
+                    &mut stream,
+                    "200 OK",
+                    "Authorized, you can close this window and return to the application.",
+                )
+                .await
+            }
+            Err(err) => respond(&mut stream, "400 Bad Request", &err.to_string()).await,
+        }
+        return result;
+    }
+}
+
+/// Reads the request head, returning the target of the request line.
+async fn read_request_target(stream: &mut tokio::net::TcpStream) -> io::Result<Option<String>> {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || head.len() + read > MAX_REQUEST_HEAD {
+            return Ok(None);
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    Ok(match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(target)) => Some(target.to_string()),
+        _ => None,
+    })
+}
+
+async fn respond(stream: &mut tokio::net::TcpStream, status: &str, message: &str) {
+    let body = format!(
+        "<!DOCTYPE html><html><body><p>{}</p></body></html>",
+        message
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    // The user only misses a confirmation page if this fails
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Random hex string for the oauth state parameter.
+fn random_state() -> String {
+    (0..2)
+        .map(|_| format!("{:016x}", RandomState::new().build_hasher().finish()))
+        .collect()
+}
+
+/// Best effort attempt to open the url in the default browser.
+fn open_browser(url: &str) {
+    #[cfg(target_os = "windows")]
+    let command = std::process::Command::new("cmd")
+        .args(["/C", "start", ""])
+        .arg(url)
+        .spawn();
+
+    #[cfg(target_os = "macos")]
+    let command = std::process::Command::new("open").arg(url).spawn();
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let command = std::process::Command::new("xdg-open").arg(url).spawn();
+
+    if let Err(err) = command {
+        debug!(?err, "failed to open browser");
+    }
+}
+
+/// Token stored by a [`FileTokenProvider`], as json.
+///
+/// The tokens are redacted from the `Debug` output.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredToken {
+    /// The access token
+    pub access_token: String,
+
+    /// Token to renew the access token with
+    pub refresh_token: Option<String>,
+
+    /// When the access token expires, in unix seconds
+    pub expires_at: u64,
+
+    /// Scopes that were requested for the token
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl fmt::Debug for StoredToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StoredToken")
+            .field("access_token", &REDACTED)
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| REDACTED),
+            )
+            .field("expires_at", &self.expires_at)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+impl StoredToken {
+    /// Token from a token endpoint response received at `received_at`, expiring `expires_in`
+    /// after it.
+    pub fn from_response(
+        response: TokenResponse,
+        scopes: Vec<String>,
+        received_at: SystemTime,
+    ) -> Self {
+        Self {
+            expires_at: unix_seconds(received_at).saturating_add(response.expires_in),
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            scopes,
+        }
+    }
+
+    /// Reads a token from a json file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SetupError> {
+        let contents = fs::read(path).map_err(SetupError::TokenFile)?;
+        serde_json::from_slice(&contents).map_err(SetupError::InvalidTokenFile)
+    }
+
+    /// Writes the token to a json file, replacing it atomically. On unix the file is only
+    /// readable by the current user.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SetupError> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let write = || -> io::Result<()> {
+            let mut file = options.open(&temp)?;
+            serde_json::to_writer_pretty(&mut file, self)?;
+            io::Write::flush(&mut file)?;
+            fs::rename(&temp, path)
+        };
+        write().map_err(SetupError::TokenFile)
+    }
+
+    /// Whether the access token expires within `margin` of `now`
+    fn expires_within(&self, margin: Duration, now: SystemTime) -> bool {
+        unix_seconds(now).saturating_add(margin.as_secs()) >= self.expires_at
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// An [`AccessTokenProvider`] for a user token stored in a file, renewing it with its refresh
+/// token when it is about to expire and saving the renewed token back to the file.
+///
+/// Usually created by [`interactive_setup`]. Clones share the same token.
+///
+/// The client secret and tokens are redacted from the `Debug` output.
+#[derive(Clone)]
+pub struct FileTokenProvider {
+    client: Client<()>,
+    client_id: String,
+    client_secret: ClientSecret,
+    path: PathBuf,
+    token: Arc<Mutex<StoredToken>>,
+    refresh: Arc<futures::lock::Mutex<()>>,
+}
+
+impl FileTokenProvider {
+    /// Creates a provider for the token, saving renewed tokens to `path`. Tokens are renewed
+    /// through the given client, sharing its transport and other settings.
+    pub fn new<A>(
+        client: &Client<A>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        path: impl Into<PathBuf>,
+        token: StoredToken,
+    ) -> Self {
+        Self::with_secret(
+            client,
+            client_id,
+            ClientSecret::new(client_secret),
+            path,
+            token,
+        )
+    }
+
+    fn with_secret<A>(
+        client: &Client<A>,
+        client_id: impl Into<String>,
+        client_secret: ClientSecret,
+        path: impl Into<PathBuf>,
+        token: StoredToken,
+    ) -> Self {
+        Self {
+            client: client.with_auth_provider(()),
+            client_id: client_id.into(),
+            client_secret,
+            path: path.into(),
+            token: Arc::new(Mutex::new(token)),
+            refresh: Arc::default(),
+        }
+    }
+
+    /// Creates a provider for the token stored in the file at `path`.
+    pub fn load<A>(
+        client: &Client<A>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        path: impl Into<PathBuf>,
+    ) -> Result<Self, SetupError> {
+        let path = path.into();
+        let token = StoredToken::load(&path)?;
+        Ok(Self::new(client, client_id, client_secret, path, token))
+    }
+
+    /// Sets a second client secret, tried when the token endpoint rejects the primary one as
+    /// [`ErrorStatus::InvalidClientSecret`](crate::ErrorStatus::InvalidClientSecret), e.g.
+    /// while rotating the secret. A warning is logged whenever the fallback is accepted.
+    pub fn with_fallback_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret.set_fallback(secret);
+        self
+    }
+
+    /// The current token
+    pub fn token(&self) -> StoredToken {
+        self.token.lock().unwrap().clone()
+    }
+}
+
+impl fmt::Debug for FileTokenProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileTokenProvider")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &self.client_secret)
+            .field("path", &self.path)
+            .field("token", &*self.token.lock().unwrap())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClientIdProvider for FileTokenProvider {
+    fn client_id(&self) -> &str {
+        &self.client_id
+    }
+}
+
+#[async_trait::async_trait]
+impl AccessTokenProvider for FileTokenProvider {
+    type Error = SetupError;
+
+    fn access_token(&self) -> AccessToken {
+        let now = self.client.pipeline.clock.system_time();
+        let token = self.token.lock().unwrap();
+        if token.expires_within(EXPIRY_MARGIN, now) {
+            AccessToken::NeedsRefresh
+        } else {
+            AccessToken::Token(token.access_token.clone())
+        }
+    }
+
+    async fn refresh_token(&self) -> Result<String, Self::Error> {
+        let _refresh = self.refresh.lock().await;
+        let current = self.token();
+        // Another caller may have renewed the token while we waited
+        if !current.expires_within(EXPIRY_MARGIN, self.client.pipeline.clock.system_time()) {
+            return Ok(current.access_token);
+        }
+
+        let refresh_token = current
+            .refresh_token
+            .as_deref()
+            .ok_or(SetupError::MissingRefreshToken)?;
+        let response = self
+            .client_secret
+            .exchange(|client_secret| async move {
+                self.client
+                    .refresh_access_token(&self.client_id, &client_secret, refresh_token)
+                    .await
+            })
+            .await?;
+        let mut renewed = StoredToken::from_response(
+            response,
+            current.scopes,
+            self.client.pipeline.clock.system_time(),
+        );
+        if renewed.refresh_token.is_none() {
+            renewed.refresh_token = current.refresh_token;
+        }
+        renewed.save(&self.path)?;
+        *self.token.lock().unwrap() = renewed.clone();
+        Ok(renewed.access_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockClock, ScriptedTransport};
+    use reqwest::{header, StatusCode};
+    use tokio::net::TcpStream;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("trovo-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn authorize_url_params() {
+        let url = authorize_url(
+            "client-id",
+            "http://localhost:3000/callback",
+            &["chat_connect".to_string(), "chat_send_self".to_string()],
+            "state",
+        );
+        let params: Vec<_> = url.query_pairs().into_owned().collect();
+        assert_eq!(url.host_str(), Some("open.trovo.live"));
+        assert!(url
+            .query()
+            .unwrap()
+            .contains("scope=chat_connect+chat_send_self&"));
+        assert_eq!(
+            params,
+            [
+                ("client_id".to_string(), "client-id".to_string()),
+                ("response_type".to_string(), "code".to_string()),
+                (
+                    "scope".to_string(),
+                    "chat_connect chat_send_self".to_string()
+                ),
+                (
+                    "redirect_uri".to_string(),
+                    "http://localhost:3000/callback".to_string()
+                ),
+                ("state".to_string(), "state".to_string()),
+            ]
+        );
+        assert_ne!(random_state(), random_state());
+    }
+
+    async fn get(port: u16, target: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn capture_redirect_code() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let browser = tokio::spawn(async move {
+            let favicon = get(port, "/favicon.ico").await;
+            let redirect = get(port, "/callback?code=the-code&state=expected").await;
+            (favicon, redirect)
+        });
+
+        let code = capture_redirect(&listener, "/callback", "expected")
+            .await
+            .unwrap();
+        assert_eq!(code, "the-code");
+        let (favicon, redirect) = browser.await.unwrap();
+        assert!(favicon.starts_with("HTTP/1.1 404"));
+        assert!(redirect.starts_with("HTTP/1.1 200"));
+    }
+
+    #[tokio::test]
+    async fn capture_redirect_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let browser = tokio::spawn(get(port, "/callback?code=the-code&state=forged"));
+        let err = capture_redirect(&listener, "/callback", "expected")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SetupError::StateMismatch));
+        assert!(browser.await.unwrap().starts_with("HTTP/1.1 400"));
+
+        let browser = tokio::spawn(get(
+            port,
+            "/callback?error=access_denied&error_description=User%20declined&state=expected",
+        ));
+        let err = capture_redirect(&listener, "/callback", "expected")
+            .await
+            .unwrap_err();
+        match err {
+            SetupError::Denied { error, description } => {
+                assert_eq!(error, "access_denied");
+                assert_eq!(description.as_deref(), Some("User declined"));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        browser.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_token_refresh() {
+        let path = temp_path("file-token");
+        StoredToken {
+            access_token: "expired".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            expires_at: 1_700_000_000 - 10,
+            scopes: vec!["chat_connect".to_string()],
+        }
+        .save(&path)
+        .unwrap();
+
+        let transport = ScriptedTransport::new();
+        transport.push(
+            StatusCode::OK,
+            r#"{"access_token": "renewed", "token_type": "OAuth", "expires_in": 3600, "refresh_token": "refresh2"}"#,
+        );
+        transport.push(StatusCode::OK, r#"{"token": "chat"}"#);
+        let clock = MockClock::new();
+        clock.set_system_time(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let http = Client::builder()
+            .transport(transport.clone())
+            .clock(clock)
+            .build(())
+            .unwrap();
+        let client = interactive_setup_with(
+            SetupConfig {
+                client_id: "client-id".to_string(),
+                client_secret: "secret".to_string(),
+                token_path: path.clone(),
+                open_browser: false,
+                ..SetupConfig::default()
+            },
+            &http,
+        )
+        .await
+        .unwrap();
+
+        client.chat_token_for_user().await.unwrap();
+        let requests = transport.requests();
+        assert!(requests[0].url.path().ends_with("/refreshtoken"));
+        assert_eq!(requests[0].json()["refresh_token"], "refresh");
+        assert_eq!(requests[1].headers[header::AUTHORIZATION], "OAuth renewed");
+
+        let saved = StoredToken::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved.access_token, "renewed");
+        assert_eq!(saved.refresh_token.as_deref(), Some("refresh2"));
+        assert_eq!(saved.scopes, ["chat_connect"]);
+        assert_eq!(saved.expires_at, 1_700_003_600);
+        assert!(!format!("{:?}", client.auth_provider).contains("renewed"));
+    }
+
+    #[tokio::test]
+    async fn missing_refresh_token() {
+        let transport = ScriptedTransport::new();
+        let http = Client::with_transport(transport.clone(), ());
+        let provider = FileTokenProvider::new(
+            &http,
+            "client-id",
+            "secret",
+            temp_path("no-refresh"),
+            StoredToken {
+                access_token: "expired".to_string(),
+                refresh_token: None,
+                expires_at: 0,
+                scopes: Vec::new(),
+            },
+        );
+        assert!(matches!(provider.access_token(), AccessToken::NeedsRefresh));
+        assert!(matches!(
+            provider.refresh_token().await,
+            Err(SetupError::MissingRefreshToken)
+        ));
+        assert!(transport.requests().is_empty());
+    }
+}
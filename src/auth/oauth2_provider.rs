@@ -0,0 +1,456 @@
+use crate::{
+    auth::REDACTED, AccessToken, AccessTokenProvider, Client, ClientIdProvider,
+    Oauth2ProviderError, RequestError,
+};
+use oauth2::{
+    basic::{BasicClient, BasicTokenResponse},
+    AsyncHttpClient, AuthType, EndpointNotSet, EndpointSet, EndpointState, HttpRequest,
+    HttpResponse, RefreshToken, TokenResponse as _, TokenUrl,
+};
+use reqwest::{header, Method};
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+/// Trovo's token refresh endpoint, set as the token url of the wrapped oauth2 client.
+pub const TROVO_REFRESH_TOKEN_URL: &str = "https://open-api.trovo.live/openplatform/refreshtoken";
+
+/// Tokens are renewed this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// A token used and renewed by an [`Oauth2Provider`].
+///
+/// The `oauth2` secret types keep the tokens out of the `Debug` output.
+#[derive(Debug, Clone)]
+pub struct Oauth2Token {
+    /// The access token
+    pub access_token: oauth2::AccessToken,
+
+    /// Token to renew the access token with
+    pub refresh_token: Option<RefreshToken>,
+
+    /// When the access token expires, if known. Tokens without an expiry are used until a
+    /// request is rejected.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl Oauth2Token {
+    /// Builds a token from an oauth2 token response received at `received_at`.
+    pub fn from_response(response: &BasicTokenResponse, received_at: SystemTime) -> Self {
+        Self {
+            access_token: response.access_token().clone(),
+            refresh_token: response.refresh_token().cloned(),
+            expires_at: response
+                .expires_in()
+                .map(|expires_in| received_at + expires_in),
+        }
+    }
+
+    fn expires_within(&self, margin: Duration, now: SystemTime) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= now + margin)
+    }
+}
+
+/// Storage for the token of an [`Oauth2Provider`], e.g. the token cache the rest of an app
+/// already keeps for its `oauth2` client.
+///
+/// Implemented for `Mutex<Option<Oauth2Token>>` to keep the token in memory, and for `Arc`s of
+/// stores to share one with the rest of the app.
+pub trait Oauth2TokenStore: Send + Sync {
+    /// The current token, if there is one
+    fn load(&self) -> Option<Oauth2Token>;
+
+    /// Replaces the token after it was renewed. Failing to persist it should be logged rather
+    /// than panicking, the renewed token is still used for the request.
+    fn store(&self, token: &Oauth2Token);
+}
+
+impl Oauth2TokenStore for Mutex<Option<Oauth2Token>> {
+    fn load(&self) -> Option<Oauth2Token> {
+        self.lock().unwrap().clone()
+    }
+
+    fn store(&self, token: &Oauth2Token) {
+        *self.lock().unwrap() = Some(token.clone());
+    }
+}
+
+impl<T> Oauth2TokenStore for Arc<T>
+where
+    T: Oauth2TokenStore + ?Sized,
+{
+    fn load(&self) -> Option<Oauth2Token> {
+        (**self).load()
+    }
+
+    fn store(&self, token: &Oauth2Token) {
+        (**self).store(token)
+    }
+}
+
+/// Provides access tokens managed with an [`oauth2::basic::BasicClient`], renewing them through
+/// the `oauth2` crate.
+///
+/// The oauth2 client's token url is pointed at [`TROVO_REFRESH_TOKEN_URL`], and its requests are
+/// sent through the given [`Client`], sharing its transport and other settings. Trovo expects
+/// json rather than a form, so the requests are translated on the way out. Concurrent requests
+/// wait for a single refresh.
+///
+/// ```no_run
+/// use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret};
+/// use std::sync::Mutex;
+/// use trovo::{Client, Oauth2Provider, Oauth2Token};
+///
+/// let oauth = BasicClient::new(ClientId::new("client-id".to_string()))
+///     .set_client_secret(ClientSecret::new("client-secret".to_string()))
+///     .set_auth_uri(AuthUrl::new("https://open.trovo.live/page/login.html".to_string()).unwrap());
+///
+/// // e.g. the token your app obtained through the authorization code flow
+/// let token: Option<Oauth2Token> = None;
+///
+/// let http = Client::new(());
+/// let client = Client::new(Oauth2Provider::new(&http, oauth, Mutex::new(token)));
+/// ```
+///
+/// The client secret and tokens are redacted from the `Debug` output.
+pub struct Oauth2Provider<
+    HasAuthUrl = EndpointSet,
+    HasDeviceAuthUrl = EndpointNotSet,
+    HasIntrospectionUrl = EndpointNotSet,
+    HasRevocationUrl = EndpointNotSet,
+> where
+    HasAuthUrl: EndpointState,
+    HasDeviceAuthUrl: EndpointState,
+    HasIntrospectionUrl: EndpointState,
+    HasRevocationUrl: EndpointState,
+{
+    client: Client<()>,
+    oauth: Arc<
+        BasicClient<
+            HasAuthUrl,
+            HasDeviceAuthUrl,
+            HasIntrospectionUrl,
+            HasRevocationUrl,
+            EndpointSet,
+        >,
+    >,
+    store: Arc<dyn Oauth2TokenStore>,
+    refresh: Arc<futures::lock::Mutex<()>>,
+}
+
+impl<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl>
+    Oauth2Provider<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl>
+where
+    HasAuthUrl: EndpointState,
+    HasDeviceAuthUrl: EndpointState,
+    HasIntrospectionUrl: EndpointState,
+    HasRevocationUrl: EndpointState,
+{
+    /// Creates a provider for the token in `store`, renewed with the given oauth2 client through
+    /// `client`. Any token url and client auth type set on the oauth2 client are replaced.
+    pub fn new<A, HasTokenUrl>(
+        client: &Client<A>,
+        oauth: BasicClient<
+            HasAuthUrl,
+            HasDeviceAuthUrl,
+            HasIntrospectionUrl,
+            HasRevocationUrl,
+            HasTokenUrl,
+        >,
+        store: impl Oauth2TokenStore + 'static,
+    ) -> Self
+    where
+        HasTokenUrl: EndpointState,
+    {
+        let token_url =
+            TokenUrl::new(TROVO_REFRESH_TOKEN_URL.to_string()).expect("valid token url");
+        Self {
+            client: client.with_auth_provider(()),
+            oauth: Arc::new(
+                oauth
+                    .set_token_uri(token_url)
+                    .set_auth_type(AuthType::RequestBody),
+            ),
+            store: Arc::new(store),
+            refresh: Arc::default(),
+        }
+    }
+
+    /// The current token, if there is one
+    pub fn token(&self) -> Option<Oauth2Token> {
+        self.store.load()
+    }
+
+    fn now(&self) -> SystemTime {
+        self.client.pipeline.clock.system_time()
+    }
+}
+
+impl<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl> Clone
+    for Oauth2Provider<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl>
+where
+    HasAuthUrl: EndpointState,
+    HasDeviceAuthUrl: EndpointState,
+    HasIntrospectionUrl: EndpointState,
+    HasRevocationUrl: EndpointState,
+{
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            oauth: self.oauth.clone(),
+            store: self.store.clone(),
+            refresh: self.refresh.clone(),
+        }
+    }
+}
+
+impl<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl> fmt::Debug
+    for Oauth2Provider<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl>
+where
+    HasAuthUrl: EndpointState,
+    HasDeviceAuthUrl: EndpointState,
+    HasIntrospectionUrl: EndpointState,
+    HasRevocationUrl: EndpointState,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Oauth2Provider")
+            .field("client_id", &self.oauth.client_id().as_str())
+            .field("client_secret", &REDACTED)
+            .field("token", &self.store.load())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl> ClientIdProvider
+    for Oauth2Provider<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl>
+where
+    HasAuthUrl: EndpointState,
+    HasDeviceAuthUrl: EndpointState,
+    HasIntrospectionUrl: EndpointState,
+    HasRevocationUrl: EndpointState,
+{
+    fn client_id(&self) -> &str {
+        self.oauth.client_id().as_str()
+    }
+}
+
+#[async_trait::async_trait]
+impl<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl> AccessTokenProvider
+    for Oauth2Provider<HasAuthUrl, HasDeviceAuthUrl, HasIntrospectionUrl, HasRevocationUrl>
+where
+    HasAuthUrl: EndpointState + Send + Sync + 'static,
+    HasDeviceAuthUrl: EndpointState + Send + Sync + 'static,
+    HasIntrospectionUrl: EndpointState + Send + Sync + 'static,
+    HasRevocationUrl: EndpointState + Send + Sync + 'static,
+{
+    type Error = Oauth2ProviderError;
+
+    fn access_token(&self) -> AccessToken {
+        match self.store.load() {
+            Some(token) if !token.expires_within(EXPIRY_MARGIN, self.now()) => {
+                AccessToken::Token(token.access_token.secret().clone())
+            }
+            _ => AccessToken::NeedsRefresh,
+        }
+    }
+
+    async fn refresh_token(&self) -> Result<String, Self::Error> {
+        let _refresh = self.refresh.lock().await;
+        let current = self.store.load();
+        // Another caller may have renewed the token while we waited
+        if let Some(token) = &current {
+            if !token.expires_within(EXPIRY_MARGIN, self.now()) {
+                return Ok(token.access_token.secret().clone());
+            }
+        }
+
+        let refresh_token = current
+            .and_then(|token| token.refresh_token)
+            .ok_or(Oauth2ProviderError::MissingRefreshToken)?;
+        let requested_at = self.now();
+        let http = TokenHttpClient(&self.client);
+        let response = self
+            .oauth
+            .exchange_refresh_token(&refresh_token)
+            .request_async(&http)
+            .await?;
+
+        let mut renewed = Oauth2Token::from_response(&response, requested_at);
+        if renewed.refresh_token.is_none() {
+            renewed.refresh_token = Some(refresh_token);
+        }
+        self.store.store(&renewed);
+        Ok(renewed.access_token.secret().clone())
+    }
+}
+
+/// Sends the oauth2 crate's token requests through a [`Client`]
+struct TokenHttpClient<'a>(&'a Client<()>);
+
+impl<'c> AsyncHttpClient<'c> for TokenHttpClient<'_> {
+    type Error = RequestError;
+    type Future = Pin<Box<dyn Future<Output = Result<HttpResponse, RequestError>> + Send + 'c>>;
+
+    fn call(&'c self, request: HttpRequest) -> Self::Future {
+        Box::pin(Self::send(self.0, request))
+    }
+}
+
+impl TokenHttpClient<'_> {
+    /// Sends a token request built by the oauth2 crate to Trovo, translating the form body to
+    /// json and the client id to a header.
+    async fn send(client: &Client<()>, request: HttpRequest) -> Result<HttpResponse, RequestError> {
+        let mut client_id = String::new();
+        let mut payload = serde_json::Map::new();
+        for (key, value) in url::form_urlencoded::parse(request.body()) {
+            if key == "client_id" {
+                client_id = value.into_owned();
+            } else {
+                payload.insert(key.into_owned(), value.into_owned().into());
+            }
+        }
+
+        let res = client
+            .send(
+                client
+                    .http
+                    .request(Method::POST, request.uri().to_string())
+                    .header(header::ACCEPT, "application/json")
+                    .headers(client.default_headers.clone())
+                    .header("Client-ID", client_id)
+                    .json(&payload),
+            )
+            .await?;
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+        res.error_for_status()?;
+
+        Ok(http::Response::builder()
+            .status(res.status())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(res.body().to_vec())
+            .expect("valid response"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Clock, MockClock, ScriptedTransport};
+    use oauth2::{AuthUrl, ClientId, ClientSecret};
+    use reqwest::StatusCode;
+
+    fn provider(
+        token: Oauth2Token,
+    ) -> (
+        Client<Oauth2Provider>,
+        Arc<Mutex<Option<Oauth2Token>>>,
+        ScriptedTransport,
+        MockClock,
+    ) {
+        let transport = ScriptedTransport::new();
+        let clock = MockClock::new();
+        let http = Client::builder()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build(())
+            .unwrap();
+        let oauth = BasicClient::new(ClientId::new("client-id".to_string()))
+            .set_client_secret(ClientSecret::new("client-secret".to_string()))
+            .set_auth_uri(
+                AuthUrl::new("https://open.trovo.live/page/login.html".to_string()).unwrap(),
+            );
+        let store = Arc::new(Mutex::new(Some(token)));
+        let provider = Oauth2Provider::new(&http, oauth, store.clone());
+        let client = Client::builder()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build(provider)
+            .unwrap();
+        (client, store, transport, clock)
+    }
+
+    fn expired(clock: &MockClock, refresh_token: Option<&str>) -> Oauth2Token {
+        Oauth2Token {
+            access_token: oauth2::AccessToken::new("old-token".to_string()),
+            refresh_token: refresh_token.map(|t| RefreshToken::new(t.to_string())),
+            expires_at: Some(clock.system_time()),
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh() {
+        let clock = MockClock::new();
+        let (client, store, transport, clock) = provider(expired(&clock, Some("refresh-1")));
+        assert!(matches!(
+            client.auth_provider.access_token(),
+            AccessToken::NeedsRefresh
+        ));
+
+        transport.push(
+            StatusCode::OK,
+            r#"{"access_token": "new-token", "token_type": "OAuth", "expires_in": 14400, "refresh_token": "refresh-2"}"#,
+        );
+        let token = client.auth_provider.refresh_token().await.unwrap();
+        assert_eq!(token, "new-token");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url.as_str(), TROVO_REFRESH_TOKEN_URL);
+        assert_eq!(requests[0].headers["Client-ID"], "client-id");
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({
+                "client_secret": "client-secret",
+                "grant_type": "refresh_token",
+                "refresh_token": "refresh-1",
+            })
+        );
+
+        let stored = store.load().unwrap();
+        assert_eq!(stored.access_token.secret(), "new-token");
+        assert_eq!(stored.refresh_token.unwrap().secret(), "refresh-2");
+        assert_eq!(
+            stored.expires_at,
+            Some(clock.system_time() + Duration::from_secs(14400))
+        );
+        assert!(matches!(
+            client.auth_provider.access_token(),
+            AccessToken::Token(token) if token == "new-token"
+        ));
+    }
+
+    #[tokio::test]
+    async fn refresh_errors() {
+        let clock = MockClock::new();
+        let (client, _, transport, _) = provider(expired(&clock, None));
+        assert!(matches!(
+            client.auth_provider.refresh_token().await,
+            Err(Oauth2ProviderError::MissingRefreshToken)
+        ));
+        assert!(transport.requests().is_empty());
+
+        let (client, store, transport, _) = provider(expired(&clock, Some("refresh-1")));
+        transport.push(
+            StatusCode::BAD_REQUEST,
+            r#"{"status": 1002, "message": "invalid refresh token"}"#,
+        );
+        let err = client.auth_provider.refresh_token().await.unwrap_err();
+        assert!(matches!(
+            err,
+            Oauth2ProviderError::Request(RequestError::ApiError(_))
+        ));
+        assert!(err.to_string().contains("invalid refresh token"));
+        assert_eq!(store.load().unwrap().access_token.secret(), "old-token");
+
+        transport.push(StatusCode::OK, r#"{"token_type": "OAuth"}"#);
+        let err = client.auth_provider.refresh_token().await.unwrap_err();
+        assert!(matches!(err, Oauth2ProviderError::InvalidResponse(_)));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::Client;
+use futures::{stream::FuturesUnordered, StreamExt};
+use std::future::Future;
+
+type ProgressCallback<'c> = Box<dyn FnMut(usize, usize) + Send + 'c>;
+type StopPredicate<'c, T, E> = Box<dyn Fn(&Result<T, E>) -> bool + Send + Sync + 'c>;
+
+impl<A> Client<A> {
+    /// Runs `f` for each of the inputs with at most `concurrency` calls in flight, collecting the
+    /// results in input order.
+    ///
+    /// Configure progress reporting and early stopping on the returned [`Batch`], then call
+    /// [`Batch::run`].
+    ///
+    /// ```no_run
+    /// # async fn example(client: trovo::Client<trovo::ClientId>) {
+    /// let usernames = vec!["one".to_string(), "two".to_string()];
+    /// let results = client
+    ///     .batch(usernames, 8, |client, username| client.user(username))
+    ///     .on_progress(|done, total| println!("{}/{}", done, total))
+    ///     .stop_when(|res| res.is_err())
+    ///     .run()
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn batch<'c, I, T, E, F, Fut>(
+        &'c self,
+        inputs: impl IntoIterator<Item = I>,
+        concurrency: usize,
+        f: F,
+    ) -> Batch<'c, A, I, T, E, F>
+    where
+        I: Clone,
+        F: Fn(&'c Client<A>, I) -> Fut,
+        Fut: Future<Output = Result<T, E>> + 'c,
+    {
+        Batch {
+            client: self,
+            inputs: inputs.into_iter().collect(),
+            concurrency: concurrency.max(1),
+            f,
+            progress: None,
+            stop_when: None,
+        }
+    }
+}
+
+/// A batch of api calls, created via [`Client::batch`].
+#[must_use = "batches do nothing until `run` is awaited"]
+pub struct Batch<'c, A, I, T, E, F> {
+    client: &'c Client<A>,
+    inputs: Vec<I>,
+    concurrency: usize,
+    f: F,
+    progress: Option<ProgressCallback<'c>>,
+    stop_when: Option<StopPredicate<'c, T, E>>,
+}
+
+impl<A, I, T, E, F> std::fmt::Debug for Batch<'_, A, I, T, E, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Batch")
+            .field("inputs", &self.inputs.len())
+            .field("concurrency", &self.concurrency)
+            .finish()
+    }
+}
+
+impl<'c, A, I, T, E, F, Fut> Batch<'c, A, I, T, E, F>
+where
+    I: Clone,
+    F: Fn(&'c Client<A>, I) -> Fut,
+    Fut: Future<Output = Result<T, E>> + 'c,
+{
+    /// Calls `progress` with the number of completed calls and the total number of inputs after
+    /// each call completes.
+    pub fn on_progress(mut self, progress: impl FnMut(usize, usize) + Send + 'c) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Stops the batch as soon as a result matches `predicate`, e.g. on the first rate limit
+    /// error. Calls still in flight are cancelled, and inputs that never completed are left out
+    /// of the results.
+    pub fn stop_when(
+        mut self,
+        predicate: impl Fn(&Result<T, E>) -> bool + Send + Sync + 'c,
+    ) -> Self {
+        self.stop_when = Some(Box::new(predicate));
+        self
+    }
+
+    /// Runs the batch, returning each completed input alongside its result, in input order.
+    pub async fn run(mut self) -> Vec<(I, Result<T, E>)> {
+        let total = self.inputs.len();
+        let mut pending = self.inputs.into_iter().enumerate();
+        let mut in_flight = FuturesUnordered::new();
+        let mut results = Vec::with_capacity(total);
+
+        loop {
+            while in_flight.len() < self.concurrency {
+                let Some((index, input)) = pending.next() else {
+                    break;
+                };
+                let call = (self.f)(self.client, input.clone());
+                in_flight.push(async move { (index, input, call.await) });
+            }
+
+            let Some((index, input, result)) = in_flight.next().await else {
+                break;
+            };
+            let stop = self.stop_when.as_ref().is_some_and(|stop| stop(&result));
+            results.push((index, input, result));
+            if let Some(progress) = &mut self.progress {
+                progress(results.len(), total);
+            }
+            if stop {
+                break;
+            }
+        }
+
+        results.sort_by_key(|(index, _, _)| *index);
+        results
+            .into_iter()
+            .map(|(_, input, result)| (input, result))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{client::tests::CHANNEL_FIXTURE, ClientId, RequestError, ScriptedTransport};
+    use reqwest::StatusCode;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn ordered_and_bounded() {
+        let transport = ScriptedTransport::new().with_delay(Duration::from_millis(20));
+        for _ in 0..10 {
+            transport.push(StatusCode::OK, CHANNEL_FIXTURE);
+        }
+        let client = crate::Client::with_transport(transport.clone(), ClientId::new("client-id"));
+        let progress = Arc::new(Mutex::new(Vec::new()));
+
+        let ids: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        let results = client
+            .batch(ids.clone(), 3, |client, id| client.channel_by_id(id))
+            .on_progress({
+                let progress = progress.clone();
+                move |done, total| progress.lock().unwrap().push((done, total))
+            })
+            .run()
+            .await;
+
+        let inputs: Vec<_> = results.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(inputs, ids);
+        assert!(results.iter().all(|(_, res)| res.is_ok()));
+        assert_eq!(transport.max_in_flight(), 3);
+        assert_eq!(progress.lock().unwrap().last(), Some(&(10, 10)));
+    }
+
+    #[tokio::test]
+    async fn stops_early() {
+        let transport = ScriptedTransport::new();
+        transport
+            .push(StatusCode::OK, CHANNEL_FIXTURE)
+            .push(StatusCode::OK, CHANNEL_FIXTURE)
+            .push(StatusCode::SERVICE_UNAVAILABLE, "")
+            .push(StatusCode::OK, CHANNEL_FIXTURE);
+        let client = crate::Client::with_transport(transport.clone(), ClientId::new("client-id"));
+
+        let results = client
+            .batch(0..4, 1, |client, id| client.channel_by_id(id.to_string()))
+            .stop_when(|res| matches!(res, Err(RequestError::Other(_))))
+            .run()
+            .await;
+
+        assert_eq!(results.len(), 3);
+        assert!(results[2].1.is_err());
+        assert_eq!(transport.requests().len(), 3);
+    }
+}
@@ -0,0 +1,565 @@
+use crate::{
+    CircuitBreaker, CircuitBreakerConfig, Client, Clock, Coalescer, HttpTransport, Pipeline,
+    StrictDecoding, UnexpectedFields,
+};
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    Proxy,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// User agent used to identify this crate to Trovo
+pub(crate) const DEFAULT_USER_AGENT: &str = concat!("trovo-rs/", env!("CARGO_PKG_VERSION"));
+
+/// Builder for configuring a [`Client`].
+///
+/// Obtained via [`Client::builder`].
+#[derive(Debug)]
+pub struct ClientBuilder {
+    timeout: Duration,
+    proxies: Vec<Proxy>,
+    no_proxy: bool,
+    default_headers: HeaderMap,
+    user_agent: Option<String>,
+    coalesce_requests: bool,
+    compression: bool,
+    circuit_breaker: Option<CircuitBreakerConfig>,
+    clock: Option<Arc<dyn Clock>>,
+    strict: Option<StrictDecoding>,
+    #[cfg(any(test, feature = "record"))]
+    record_to: Option<std::path::PathBuf>,
+    transport: Option<Arc<dyn HttpTransport>>,
+    #[cfg(feature = "tower")]
+    service: Option<crate::ServiceStack>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            proxies: Vec::new(),
+            no_proxy: false,
+            default_headers: HeaderMap::new(),
+            user_agent: None,
+            coalesce_requests: false,
+            compression: true,
+            circuit_breaker: None,
+            clock: None,
+            strict: None,
+            #[cfg(any(test, feature = "record"))]
+            record_to: None,
+            transport: None,
+            #[cfg(feature = "tower")]
+            service: None,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// Creates a new builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the timeout applied to each http request. Defaults to 30 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adds a proxy that http requests will be routed through.
+    ///
+    /// Can be called multiple times to add multiple proxies. If no proxy is added, the standard
+    /// `HTTP_PROXY`/`HTTPS_PROXY`/`ALL_PROXY` environment variables are honoured as reqwest
+    /// normally does.
+    ///
+    /// Note: the chat websocket connection does not currently go through the proxy.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Disables all proxying, including any proxies picked up from the environment.
+    pub fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// Adds headers that will be sent with every api request, e.g. organization-wide tracing
+    /// headers.
+    ///
+    /// The Client-ID header is always set from the auth provider and cannot be overridden here.
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers.extend(headers);
+        self.default_headers.remove("Client-ID");
+        self
+    }
+
+    /// Identifies your application to Trovo. The given value is prepended to the default
+    /// `trovo-rs/<version>` user agent, e.g. `my-bot/1.0 trovo-rs/0.6.0`.
+    ///
+    /// Applies to both http requests and the chat websocket handshake.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Enables coalescing of identical lookups, e.g. [`Client::users`] and
+    /// [`Client::channel_by_id`]. Disabled by default.
+    ///
+    /// While a lookup is in flight, any identical lookup (same endpoint and payload) awaits the
+    /// same response instead of sending a duplicate request. Only in-flight requests are shared,
+    /// responses are not cached once complete. The state is shared between clones of the client.
+    pub fn coalesce_requests(mut self, enabled: bool) -> Self {
+        self.coalesce_requests = enabled;
+        self
+    }
+
+    /// Sets whether compressed responses are requested and decoded. Enabled by default, turning
+    /// it off can be handy when inspecting traffic through a debugging proxy.
+    ///
+    /// Which encodings are supported depends on the `gzip` (default) and `brotli` crate features.
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Enables a circuit breaker to stop hammering the api during an outage. Disabled by default.
+    ///
+    /// After `threshold` consecutive network failures or 5xx responses within `window`, the
+    /// circuit opens and requests fail fast with [`RequestError::CircuitOpen`](crate::RequestError::CircuitOpen)
+    /// for `cooldown`. After that a single probe request is let through; if it succeeds the
+    /// circuit closes again, otherwise it re-opens for another `cooldown`.
+    ///
+    /// The state is shared between clones of the client.
+    pub fn circuit_breaker(mut self, threshold: u32, window: Duration, cooldown: Duration) -> Self {
+        self.circuit_breaker = Some(CircuitBreakerConfig {
+            threshold,
+            window,
+            cooldown,
+        });
+        self
+    }
+
+    /// Sets the source of time used for the circuit breaker, response latency, and chat pings.
+    /// Defaults to [`TokioClock`](crate::TokioClock).
+    ///
+    /// Mostly useful for testing time-dependent behaviour, see `MockClock` under the `test-util`
+    /// feature.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Reports fields in api responses that the crate doesn't know about, e.g. to notice api
+    /// changes early in a canary deployment. Disabled by default.
+    ///
+    /// Responses are decoded as normal, then decoded a second time to collect the paths of any
+    /// unknown fields, which are passed to `handler`. What the client methods return is
+    /// unaffected, but the extra decoding makes this unsuitable where performance matters.
+    pub fn strict_decoding(
+        mut self,
+        handler: impl Fn(&UnexpectedFields) + Send + Sync + 'static,
+    ) -> Self {
+        self.strict = Some(StrictDecoding(Arc::new(handler)));
+        self
+    }
+
+    /// Appends every api request and response to the given file as json lines, e.g. to attach a
+    /// capture of an exchange to a bug report. Disabled by default.
+    ///
+    /// Only allow-listed headers are kept and the `Client-ID` and `Authorization` values are
+    /// redacted, as is the token in chat token responses. Check recordings for anything else
+    /// sensitive before sharing them. Recordings can be played back with `ReplayTransport` under
+    /// the `test-util` feature.
+    #[cfg(any(test, feature = "record"))]
+    pub fn record_to(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.record_to = Some(path.into());
+        self
+    }
+
+    /// Executes all requests via the given transport instead of the internally constructed
+    /// reqwest client.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Sends all requests through the given tower service, e.g. a [`TrovoService`](crate::TrovoService)
+    /// wrapped in middleware, instead of the internally constructed reqwest client.
+    ///
+    /// The client's own features, e.g. the circuit breaker and request coalescing, still apply
+    /// on top of the service.
+    #[cfg(feature = "tower")]
+    pub fn service<S>(mut self, service: S) -> Self
+    where
+        S: tower::Service<crate::TrovoRequest, Response = crate::TrovoResponse>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Error: Into<crate::BoxError>,
+        S::Future: Send + 'static,
+    {
+        self.service = Some(crate::ServiceStack::new(service));
+        self
+    }
+
+    /// Builds the client with the given auth provider.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a TLS backend cannot be initialized, or the resolver cannot load the system
+    /// configuration.
+    pub fn build<A>(self, auth_provider: A) -> Result<Client<A>, reqwest::Error> {
+        let mut http = reqwest::Client::builder().timeout(self.timeout);
+        #[cfg(feature = "gzip")]
+        {
+            http = http.gzip(self.compression);
+        }
+        #[cfg(feature = "brotli")]
+        {
+            http = http.brotli(self.compression);
+        }
+        if self.no_proxy {
+            http = http.no_proxy();
+        }
+        for proxy in self.proxies {
+            http = http.proxy(proxy);
+        }
+
+        let user_agent = match self.user_agent {
+            Some(user_agent) => format!("{} {}", user_agent, DEFAULT_USER_AGENT),
+            None => DEFAULT_USER_AGENT.to_string(),
+        };
+        // Building the reqwest client validates the user agent for us
+        let http = http.user_agent(&user_agent).build()?;
+        let mut default_headers = self.default_headers;
+        if let Ok(user_agent) = HeaderValue::from_str(&user_agent) {
+            default_headers.insert(header::USER_AGENT, user_agent);
+        }
+
+        let transport = self.transport.unwrap_or_else(|| Arc::new(http.clone()));
+        #[cfg(any(test, feature = "record"))]
+        let transport = match self.record_to {
+            Some(path) => Arc::new(crate::RecordingTransport::new(transport, path)),
+            None => transport,
+        };
+        let mut pipeline = Pipeline::new(transport);
+        if let Some(clock) = self.clock {
+            pipeline.clock = clock;
+        }
+        pipeline.strict = self.strict;
+        #[cfg(feature = "tower")]
+        {
+            pipeline.service = self.service;
+        }
+        if self.coalesce_requests {
+            pipeline.coalescer = Some(Coalescer::default());
+        }
+        if let Some(config) = self.circuit_breaker {
+            pipeline.circuit_breaker = Some(Mutex::new(CircuitBreaker::new(config)));
+        }
+
+        Ok(Client {
+            pipeline: Arc::new(pipeline),
+            http,
+            default_headers,
+            auth_provider,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientId, RequestError, ScriptedTransport};
+    use reqwest::{header::HeaderValue, Method, StatusCode};
+
+    #[cfg(any(
+        feature = "tls-rustls-webpki",
+        feature = "tls-rustls-native",
+        feature = "tls-native"
+    ))]
+    #[tokio::test]
+    async fn proxy_is_used() {
+        use tokio::{io::AsyncReadExt, net::TcpListener};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stub = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+
+        let client = Client::builder()
+            .proxy(Proxy::all(format!("http://{}", addr)).unwrap())
+            .build(ClientId::new("client-id"))
+            .unwrap();
+        assert!(client.user("someone").await.is_err());
+
+        let request = stub.await.unwrap();
+        assert!(request.starts_with("CONNECT open-api.trovo.live:443"));
+    }
+
+    #[tokio::test]
+    async fn common_headers_applied() {
+        let transport = ScriptedTransport::new();
+        transport.push(StatusCode::OK, "{}");
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Org", HeaderValue::from_static("acme"));
+        headers.insert("Client-ID", HeaderValue::from_static("overridden"));
+        let client = Client::builder()
+            .transport(transport.clone())
+            .default_headers(headers)
+            .build(ClientId::new("client-id"))
+            .unwrap();
+
+        client
+            .send(client.request(Method::GET, "some/new/endpoint"))
+            .await
+            .unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/some/new/endpoint"
+        );
+        assert_eq!(requests[0].headers["Client-ID"], "client-id");
+        assert_eq!(requests[0].headers["Accept"], "application/json");
+        assert_eq!(requests[0].headers["X-Org"], "acme");
+    }
+
+    #[tokio::test]
+    async fn user_agent() {
+        let transport = ScriptedTransport::new();
+        transport
+            .push(StatusCode::OK, "{}")
+            .push(StatusCode::OK, "{}");
+
+        let client = Client::builder()
+            .transport(transport.clone())
+            .build(ClientId::new("client-id"))
+            .unwrap();
+        client
+            .send(client.request(Method::GET, "endpoint"))
+            .await
+            .unwrap();
+
+        let client = Client::builder()
+            .transport(transport.clone())
+            .user_agent("my-bot/1.0")
+            .build(ClientId::new("client-id"))
+            .unwrap();
+        client
+            .send(client.request(Method::GET, "endpoint"))
+            .await
+            .unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].headers["User-Agent"], DEFAULT_USER_AGENT);
+        assert_eq!(
+            requests[1].headers["User-Agent"],
+            format!("my-bot/1.0 {}", DEFAULT_USER_AGENT)
+        );
+    }
+
+    #[tokio::test]
+    async fn coalesce_identical_lookups() {
+        let transport = ScriptedTransport::new().with_delay(Duration::from_millis(50));
+        transport.push(
+            StatusCode::OK,
+            r#"{"users": [{"user_id": "1", "channel_id": "2", "username": "someone", "nickname": "Someone"}]}"#,
+        );
+        let client = Client::builder()
+            .transport(transport.clone())
+            .coalesce_requests(true)
+            .build(ClientId::new("client-id"))
+            .unwrap();
+
+        let lookups = (0..10).map(|_| client.user("someone"));
+        let users = futures::future::join_all(lookups).await;
+
+        assert_eq!(transport.requests().len(), 1);
+        for user in users {
+            assert_eq!(user.unwrap().unwrap().username, "someone");
+        }
+    }
+
+    #[tokio::test]
+    async fn strict_decoding() {
+        use crate::client::tests::CHANNEL_FIXTURE;
+
+        let extra = CHANNEL_FIXTURE.replacen(
+            r#""is_live": true,"#,
+            r#""is_live": true, "drops_enabled": false,"#,
+            1,
+        );
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let transport = ScriptedTransport::new();
+        let strict = Client::builder()
+            .transport(transport.clone())
+            .strict_decoding({
+                let reported = reported.clone();
+                move |fields| reported.lock().unwrap().push(fields.clone())
+            })
+            .build(ClientId::new("client-id"))
+            .unwrap();
+        let lenient = Client::with_transport(transport.clone(), ClientId::new("client-id"));
+
+        transport.push(StatusCode::OK, CHANNEL_FIXTURE);
+        transport.push(StatusCode::OK, extra.clone());
+        transport.push(StatusCode::OK, extra);
+        let known = strict.channel_by_id("100000").await.unwrap();
+        assert!(reported.lock().unwrap().is_empty());
+
+        let strict_channel = strict.channel_by_id("100000").await.unwrap();
+        let lenient_channel = lenient.channel_by_id("100000").await.unwrap();
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![UnexpectedFields {
+                endpoint: "channels/id (channel 100000)".to_string(),
+                paths: vec!["drops_enabled".to_string()],
+            }]
+        );
+        assert_eq!(
+            serde_json::to_value(&strict_channel).unwrap(),
+            serde_json::to_value(&lenient_channel).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&known).unwrap(),
+            serde_json::to_value(&lenient_channel).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn circuit_breaker_fails_fast() {
+        let transport = ScriptedTransport::new();
+        transport
+            .push(StatusCode::BAD_GATEWAY, "")
+            .push(StatusCode::SERVICE_UNAVAILABLE, "");
+        let client = Client::builder()
+            .transport(transport.clone())
+            .circuit_breaker(2, Duration::from_secs(60), Duration::from_secs(60))
+            .build(ClientId::new("client-id"))
+            .unwrap();
+
+        assert!(matches!(
+            client.channel_by_id("1").await,
+            Err(RequestError::Other(_))
+        ));
+        assert!(matches!(
+            client.clone().channel_by_id("1").await,
+            Err(RequestError::Other(_))
+        ));
+        assert!(matches!(
+            client.channel_by_id("1").await,
+            Err(RequestError::CircuitOpen { .. })
+        ));
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn gzip_responses_decoded() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stub = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(br#"{"hello":"world"}"#).unwrap();
+            let body = encoder.finish().unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(&body).await.unwrap();
+            request
+        });
+
+        let client = Client::builder()
+            .no_proxy()
+            .build(ClientId::new("client-id"))
+            .unwrap();
+        let res = client
+            .send(client.http.get(format!("http://{}/", addr)))
+            .await
+            .unwrap();
+        let body: serde_json::Value = res.json().unwrap();
+        assert_eq!(body["hello"], "world");
+
+        let request = stub.await.unwrap();
+        assert!(request.contains("accept-encoding: gzip"));
+    }
+
+    /// Accepts one connection and returns the first byte the client sent, which is `0x16` for a
+    /// TLS handshake record.
+    #[cfg(any(
+        feature = "tls-rustls-webpki",
+        feature = "tls-rustls-native",
+        feature = "tls-native"
+    ))]
+    async fn first_byte(listener: tokio::net::TcpListener) -> u8 {
+        use tokio::io::AsyncReadExt;
+
+        let (mut socket, _) = listener.accept().await.unwrap();
+        socket.read_u8().await.unwrap()
+    }
+
+    #[cfg(any(
+        feature = "tls-rustls-webpki",
+        feature = "tls-rustls-native",
+        feature = "tls-native"
+    ))]
+    #[tokio::test]
+    async fn tls_backend_enabled() {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stub = tokio::spawn(first_byte(listener));
+        let client = Client::new(ClientId::new("client-id"));
+        let res = client
+            .send(client.http.get(format!("https://{}/", addr)))
+            .await;
+        assert!(res.is_err());
+        assert_eq!(stub.await.unwrap(), 0x16);
+
+        #[cfg(feature = "chat")]
+        {
+            use crate::chat::{ChatConfig, ChatMessageStream, ChatToken};
+
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let stub = tokio::spawn(first_byte(listener));
+            let token = ChatToken {
+                token: "token".to_string(),
+            };
+            let config = ChatConfig::new().url(format!("wss://{}/", addr));
+            assert!(ChatMessageStream::connect_with_config(token, config)
+                .await
+                .is_err());
+            assert_eq!(stub.await.unwrap(), 0x16);
+        }
+    }
+}
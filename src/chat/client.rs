@@ -1,16 +1,35 @@
 use crate::{
     access_token,
     auth::{AccessTokenProvider, ClientIdProvider},
-    chat::{ChatConnectError, ChatMessageStream, ChatToken, SendChatMessagePayload},
-    ApiError, AuthenticatedRequestError, Client, RequestError,
+    chat::{
+        ChatConfig, ChatConnectError, ChatMessageStream, ChatToken, SendChatMessagePayload,
+        ShardChatToken,
+    },
+    client::path_segment,
+    AuthenticatedRequestError, ChannelId, Client, RequestError, ResponseMeta, ValidationError,
 };
-use reqwest::header;
+use reqwest::{header, Method};
 use std::{
     error::Error,
     fmt::{Debug, Display},
 };
 use thiserror::Error;
 
+impl<A> Client<A> {
+    /// Chat connection config matching this client's configuration
+    pub(crate) fn chat_config(&self) -> ChatConfig {
+        let mut config = ChatConfig::default().clock_arc(self.pipeline.clock.clone());
+        if let Some(user_agent) = self
+            .default_headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+        {
+            config = config.user_agent(user_agent);
+        }
+        config
+    }
+}
+
 impl<A> Client<A>
 where
     A: ClientIdProvider,
@@ -18,34 +37,29 @@ where
     /// Get a chat token for the provided channel id
     pub async fn chat_token_for_channel(
         &self,
-        channel_id: impl AsRef<str>,
+        channel_id: impl Into<ChannelId>,
     ) -> Result<ChatToken, RequestError> {
-        let res = self
-            .http
-            .get(&format!(
-                "https://open-api.trovo.live/openplatform/chat/channel-token/{}",
-                channel_id.as_ref()
-            ))
-            .header("Client-ID", self.auth_provider.client_id())
-            .send()
-            .await?;
+        let path = format!(
+            "chat/channel-token/{}",
+            path_segment(channel_id.into().as_str())
+        );
+        let res = self.send(self.request(Method::GET, &path)).await?;
 
-        if ApiError::can_handle_code(res.status()) {
-            let err: ApiError = res.json().await.unwrap_or_default();
+        if let Some(err) = res.api_error() {
             Err(RequestError::ApiError(err))
         } else {
-            let response = res.error_for_status()?.json().await?;
-            Ok(response)
+            res.error_for_status()?;
+            Ok(res.json()?)
         }
     }
 
     /// Connect to the given channel id and receive a stream of messages.
     pub async fn chat_messages_for_channel(
         &self,
-        channel_id: impl AsRef<str>,
+        channel_id: impl Into<ChannelId>,
     ) -> Result<ChatMessageStream, ChatMessagesForChannelError> {
         let token = self.chat_token_for_channel(channel_id).await?;
-        let messages = ChatMessageStream::connect(token).await?;
+        let messages = ChatMessageStream::connect_with_config(token, self.chat_config()).await?;
         Ok(messages)
     }
 }
@@ -59,25 +73,71 @@ where
         &self,
     ) -> Result<ChatToken, AuthenticatedRequestError<A::Error>> {
         let res = self
-            .http
-            .get("https://open-api.trovo.live/openplatform/chat/token")
-            .header("Client-ID", self.auth_provider.client_id())
-            .header(
+            .send(self.request(Method::GET, "chat/token").header(
                 header::AUTHORIZATION,
                 format!(
                     "OAuth {}",
                     access_token!(self.auth_provider, AuthenticatedRequestError)
                 ),
+            ))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            Err(AuthenticatedRequestError::ApiError(err))
+        } else {
+            res.error_for_status()?;
+            Ok(res.json()?)
+        }
+    }
+
+    /// Get a chat token for one shard of the authenticated user's channel, for bots that split a
+    /// busy chat across several connections.
+    ///
+    /// `current_shard` must be less than `total_shard`, otherwise this fails with an
+    /// [`AuthenticatedRequestError::Validation`] without making a request. Requires the
+    /// `chat_connect` scope, and the channel to be allowed to use sharding, tokens without
+    /// permission fail with an [`AuthenticatedRequestError::ApiError`] of
+    /// [`ErrorStatus::MissingShardTokenPermission`](crate::ErrorStatus::MissingShardTokenPermission).
+    pub async fn chat_shard_token(
+        &self,
+        total_shard: u32,
+        current_shard: u32,
+    ) -> Result<ShardChatToken, AuthenticatedRequestError<A::Error>> {
+        if current_shard >= total_shard {
+            return Err(ValidationError::InvalidShard {
+                current_shard,
+                total_shard,
+            }
+            .into());
+        }
+
+        let res = self
+            .send(
+                self.request(Method::GET, "chat/shard-token")
+                    .header(
+                        header::AUTHORIZATION,
+                        format!(
+                            "OAuth {}",
+                            access_token!(self.auth_provider, AuthenticatedRequestError)
+                        ),
+                    )
+                    .query(&[
+                        ("total_shard", total_shard),
+                        ("current_shard", current_shard),
+                    ]),
             )
-            .send()
             .await?;
 
-        if ApiError::can_handle_code(res.status()) {
-            let err: ApiError = res.json().await.unwrap_or_default();
+        if let Some(err) = res.api_error() {
             Err(AuthenticatedRequestError::ApiError(err))
         } else {
-            let response = res.error_for_status()?.json().await?;
-            Ok(response)
+            res.error_for_status()?;
+            let token: ChatToken = res.json()?;
+            Ok(ShardChatToken {
+                token: token.token,
+                total_shard,
+                current_shard,
+            })
         }
     }
 
@@ -89,7 +149,7 @@ where
             .chat_token_for_user()
             .await
             .map_err(ChatMessagesForUserError::Request)?;
-        let messages = ChatMessageStream::connect(token).await?;
+        let messages = ChatMessageStream::connect_with_config(token, self.chat_config()).await?;
         Ok(messages)
     }
 
@@ -110,30 +170,39 @@ where
         channel_id: Option<String>,
         message: impl Into<String>,
     ) -> Result<(), AuthenticatedRequestError<A::Error>> {
+        self.send_chat_message_with_meta(channel_id, message)
+            .await?;
+        Ok(())
+    }
+
+    /// Same as [`Client::send_chat_message`], but also returns the response metadata.
+    pub async fn send_chat_message_with_meta(
+        &self,
+        channel_id: Option<String>,
+        message: impl Into<String>,
+    ) -> Result<ResponseMeta, AuthenticatedRequestError<A::Error>> {
         let res = self
-            .http
-            .post("https://open-api.trovo.live/openplatform/chat/send")
-            .header("Client-ID", self.auth_provider.client_id())
-            .header(
-                header::AUTHORIZATION,
-                format!(
-                    "OAuth {}",
-                    access_token!(self.auth_provider, AuthenticatedRequestError)
-                ),
+            .send(
+                self.request(Method::POST, "chat/send")
+                    .header(
+                        header::AUTHORIZATION,
+                        format!(
+                            "OAuth {}",
+                            access_token!(self.auth_provider, AuthenticatedRequestError)
+                        ),
+                    )
+                    .json(&SendChatMessagePayload {
+                        content: message.into(),
+                        channel_id,
+                    }),
             )
-            .json(&SendChatMessagePayload {
-                content: message.into(),
-                channel_id,
-            })
-            .send()
             .await?;
 
-        if ApiError::can_handle_code(res.status()) {
-            let err: ApiError = res.json().await.unwrap_or_default();
+        if let Some(err) = res.api_error() {
             Err(AuthenticatedRequestError::ApiError(err))
         } else {
             res.error_for_status()?;
-            Ok(())
+            Ok(res.meta)
         }
     }
 }
@@ -195,3 +264,87 @@ pub enum ChatMessagesForChannelError {
     #[error(transparent)]
     ChatConnect(#[from] ChatConnectError),
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        client::tests::{authenticated_client, scripted_client},
+        AuthenticatedRequestError, ErrorStatus, ValidationError,
+    };
+    use reqwest::StatusCode;
+
+    #[tokio::test]
+    async fn chat_token_for_channel() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(StatusCode::OK, r#"{"token": "channel-token"}"#)
+            .push(StatusCode::OK, r#"{"token": "channel-token"}"#);
+
+        let token = client.chat_token_for_channel("100000").await.unwrap();
+        assert_eq!(token.token, "channel-token");
+        client.chat_token_for_channel("1/../x?y#z").await.unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/chat/channel-token/100000"
+        );
+        assert_eq!(
+            requests[1].url.path(),
+            "/openplatform/chat/channel-token/1%2F..%2Fx%3Fy%23z"
+        );
+        assert_eq!(requests[1].url.query(), None);
+    }
+
+    #[tokio::test]
+    async fn chat_shard_token() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(StatusCode::OK, r#"{"token": "shard-token"}"#)
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11709, "message": "no permission to get shard token"}"#,
+            );
+
+        let token = client.chat_shard_token(4, 1).await.unwrap();
+        assert_eq!(token.token, "shard-token");
+        assert_eq!((token.total_shard, token.current_shard), (4, 1));
+
+        match client.chat_shard_token(4, 3).await.unwrap_err() {
+            AuthenticatedRequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::MissingShardTokenPermission);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/chat/shard-token?total_shard=4&current_shard=1"
+        );
+    }
+
+    #[tokio::test]
+    async fn chat_shard_token_validation() {
+        let (client, transport) = authenticated_client();
+
+        for (total_shard, current_shard) in [(4, 4), (4, 5), (0, 0)] {
+            match client
+                .chat_shard_token(total_shard, current_shard)
+                .await
+                .unwrap_err()
+            {
+                AuthenticatedRequestError::Validation(err) => assert_eq!(
+                    err,
+                    ValidationError::InvalidShard {
+                        current_shard,
+                        total_shard
+                    }
+                ),
+                err => panic!("unexpected error {:?}", err),
+            }
+        }
+        assert!(transport.requests().is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{Clock, TokioClock, DEFAULT_USER_AGENT};
+use std::{sync::Arc, time::Duration};
+
+const DEFAULT_CHAT_URL: &str = "wss://open-chat.trovo.live/chat";
+const DEFAULT_BLOCKING_PARSE_THRESHOLD: usize = 64 * 1024;
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Configuration for a chat connection.
+///
+/// Used with [`ChatMessageStream::connect_with_config`](crate::chat::ChatMessageStream::connect_with_config).
+#[derive(Debug, Clone)]
+pub struct ChatConfig {
+    pub(crate) url: String,
+    pub(crate) user_agent: String,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) blocking_parse_threshold: usize,
+    pub(crate) max_message_age: Option<Duration>,
+    pub(crate) max_message_size: usize,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_CHAT_URL.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            clock: Arc::new(TokioClock),
+            blocking_parse_threshold: DEFAULT_BLOCKING_PARSE_THRESHOLD,
+            max_message_age: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl ChatConfig {
+    /// Creates a new config with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the websocket url to connect to. Defaults to Trovo's chat service, only really useful
+    /// for testing.
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with the websocket handshake. Defaults to
+    /// `trovo-rs/<version>`.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets the frame size in bytes above which messages are parsed on a blocking thread via
+    /// [`tokio::task::spawn_blocking`], so large chat containers don't stall the socket reader.
+    /// Pings are still sent and later frames still read while one is parsed, and message order is
+    /// preserved. Defaults to 64 KiB.
+    pub fn blocking_parse_threshold(mut self, bytes: usize) -> Self {
+        self.blocking_parse_threshold = bytes;
+        self
+    }
+
+    /// Discards messages whose `send_time` is more than `age` behind the clock, e.g. replays
+    /// after reconnecting or messages delayed by the server. Messages with a `send_time` of `0`
+    /// count as having no timestamp and are kept. Discarded messages are counted in
+    /// [`ChatStats::stale_discarded`](crate::chat::ChatStats::stale_discarded). Off by default.
+    pub fn max_message_age(mut self, age: Duration) -> Self {
+        self.max_message_age = Some(age);
+        self
+    }
+
+    /// Sets the largest websocket message, and frame, in bytes accepted from the server. Larger
+    /// ones are rejected as soon as their header arrives, without buffering them, ending the
+    /// stream with [`ChatMessageStreamError::MessageTooLarge`](crate::chat::ChatMessageStreamError::MessageTooLarge).
+    /// Defaults to 1 MiB.
+    pub fn max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Sets the source of time used for pings and [`max_message_age`](Self::max_message_age).
+    /// Defaults to [`TokioClock`].
+    pub fn clock(self, clock: impl Clock + 'static) -> Self {
+        self.clock_arc(Arc::new(clock))
+    }
+
+    pub(crate) fn clock_arc(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+}
@@ -0,0 +1,504 @@
+//! # Enrich
+//!
+//! Resolve the sender of each chat message to a [`User`], for the stable username and channel id
+//! that chat messages don't carry.
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use trovo::chat::enrich::{enrich_senders, SenderCacheConfig};
+//!
+//! # async fn run(client: trovo::Client<trovo::ClientId>) -> Result<(), Box<dyn std::error::Error>> {
+//! let messages = client.chat_messages_for_channel("100000").await?;
+//! let mut enriched = enrich_senders(messages, &client, SenderCacheConfig::default());
+//! while let Some(enriched) = enriched.next().await {
+//!     let enriched = enriched?;
+//!     match &enriched.sender {
+//!         Some(user) => println!("{} ({}): {}", user.username, user.channel_id, enriched.message.content),
+//!         None => println!("{}: {}", enriched.message.nick_name, enriched.message.content),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Lookups
+//!
+//! Senders are looked up with [`Client::users_detailed_with_limit`] by the message's `nick_name`,
+//! and only accepted when the returned user id matches the message's `sender_id`. Senders whose
+//! nickname differs from their username can't be resolved this way and get `None`. Messages
+//! without a `sender_id`, e.g. some events, are never looked up.
+//!
+//! Messages that have already arrived are taken together, up to
+//! [`max_burst`](SenderCacheConfig::max_burst), so a burst of new chatters costs one lookup
+//! rather than one per chatter. Results, including senders that weren't found, are cached for
+//! [`ttl`](SenderCacheConfig::ttl). A failed lookup doesn't hold up the stream: its messages are
+//! returned without a sender, and the senders are looked up again after
+//! [`retry_after`](SenderCacheConfig::retry_after). See [`EnrichSenders::stats`] for the lookup
+//! volume.
+
+use crate::{chat::ChatMessage, Client, ClientIdProvider, GetUsersPayload, User};
+use futures::{
+    stream::{self, BoxStream},
+    FutureExt, Stream, StreamExt,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Settings for [`enrich_senders`].
+#[derive(Debug, Clone)]
+pub struct SenderCacheConfig {
+    /// Maximum number of senders cached, the oldest lookups are evicted first. Defaults to 10,000.
+    pub capacity: usize,
+
+    /// How long a looked up sender is cached, found or not. Defaults to one hour.
+    pub ttl: Duration,
+
+    /// How long to wait before looking up senders again after a failed lookup. Defaults to 30
+    /// seconds.
+    pub retry_after: Duration,
+
+    /// Maximum number of already arrived messages enriched together. Defaults to 100.
+    pub max_burst: usize,
+
+    /// Extra requests each lookup may make to isolate unknown nicknames, see
+    /// [`Client::users_detailed_with_limit`]. Defaults to 4.
+    pub max_split_requests: usize,
+}
+
+impl Default for SenderCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            ttl: Duration::from_secs(60 * 60),
+            retry_after: Duration::from_secs(30),
+            max_burst: 100,
+            max_split_requests: 4,
+        }
+    }
+}
+
+/// A chat message with its resolved sender.
+#[derive(Debug)]
+pub struct EnrichedMessage {
+    /// The message
+    pub message: ChatMessage,
+
+    /// The sender, if it could be resolved
+    pub sender: Option<User>,
+}
+
+/// Counters for an [`EnrichSenders`] stream, see [`EnrichSenders::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EnrichStats {
+    /// Lookups made, each of at most
+    /// [`max_split_requests`](SenderCacheConfig::max_split_requests) + 1 requests per 100 senders
+    pub lookups: u64,
+
+    /// Lookups that failed
+    pub failed_lookups: u64,
+
+    /// Messages whose sender was already cached
+    pub cache_hits: u64,
+}
+
+#[derive(Debug, Default)]
+struct StatsCounters {
+    lookups: AtomicU64,
+    failed_lookups: AtomicU64,
+    cache_hits: AtomicU64,
+}
+
+/// Resolves the sender of each message of a chat stream, see the [module docs](self).
+///
+/// Errors from the underlying stream are passed through. Lookups use the given client's clock
+/// for expiry.
+pub fn enrich_senders<S, E, A>(
+    messages: S,
+    client: &Client<A>,
+    config: SenderCacheConfig,
+) -> EnrichSenders<E>
+where
+    S: Stream<Item = Result<ChatMessage, E>> + Unpin + Send + 'static,
+    E: Send + 'static,
+    A: ClientIdProvider + Clone + Send + Sync + 'static,
+{
+    let stats = Arc::<StatsCounters>::default();
+    let state = Enricher {
+        messages,
+        client: client.clone(),
+        cache: SenderCache::new(config.capacity),
+        config,
+        stats: stats.clone(),
+        ready: VecDeque::new(),
+        done: false,
+    };
+    let inner = stream::unfold(state, |mut state| async move {
+        let item = state.next().await?;
+        Some((item, state))
+    })
+    .boxed();
+    EnrichSenders { inner, stats }
+}
+
+/// Chat messages with their senders, created via [`enrich_senders`].
+pub struct EnrichSenders<E> {
+    inner: BoxStream<'static, Result<EnrichedMessage, E>>,
+    stats: Arc<StatsCounters>,
+}
+
+impl<E> EnrichSenders<E> {
+    /// Current counters for the stream
+    pub fn stats(&self) -> EnrichStats {
+        EnrichStats {
+            lookups: self.stats.lookups.load(Ordering::Relaxed),
+            failed_lookups: self.stats.failed_lookups.load(Ordering::Relaxed),
+            cache_hits: self.stats.cache_hits.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<E> fmt::Debug for EnrichSenders<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnrichSenders")
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E> Stream for EnrichSenders<E> {
+    type Item = Result<EnrichedMessage, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+struct Enricher<S, E, A> {
+    messages: S,
+    client: Client<A>,
+    config: SenderCacheConfig,
+    cache: SenderCache,
+    stats: Arc<StatsCounters>,
+
+    /// Enriched messages and errors waiting to be returned, in the order they arrived
+    ready: VecDeque<Result<EnrichedMessage, E>>,
+    done: bool,
+}
+
+impl<S, E, A> Enricher<S, E, A>
+where
+    S: Stream<Item = Result<ChatMessage, E>> + Unpin,
+    A: ClientIdProvider,
+{
+    async fn next(&mut self) -> Option<Result<EnrichedMessage, E>> {
+        loop {
+            if let Some(item) = self.ready.pop_front() {
+                return Some(item);
+            }
+            if self.done {
+                return None;
+            }
+
+            let mut burst = Vec::new();
+            let mut error = None;
+            match self.messages.next().await {
+                Some(Ok(message)) => burst.push(message),
+                Some(Err(err)) => return Some(Err(err)),
+                None => return None,
+            }
+            // Take whatever else has already arrived, without waiting for more
+            while burst.len() < self.config.max_burst {
+                match self.messages.next().now_or_never() {
+                    Some(Some(Ok(message))) => burst.push(message),
+                    Some(Some(Err(err))) => {
+                        error = Some(err);
+                        break;
+                    }
+                    Some(None) => {
+                        self.done = true;
+                        break;
+                    }
+                    None => break,
+                }
+            }
+
+            self.lookup(&burst).await;
+            let now = self.client.pipeline.clock.now();
+            for message in burst {
+                let sender = match message.sender_id {
+                    Some(id) => self.cache.get(id, now, self.config.ttl).flatten(),
+                    None => None,
+                };
+                self.ready
+                    .push_back(Ok(EnrichedMessage { message, sender }));
+            }
+            if let Some(err) = error {
+                self.ready.push_back(Err(err));
+            }
+        }
+    }
+
+    /// Looks up the senders of the messages that aren't cached, or waiting to be retried.
+    async fn lookup(&mut self, messages: &[ChatMessage]) {
+        let now = self.client.pipeline.clock.now();
+        let mut unknown: Vec<(i64, String)> = Vec::new();
+        for message in messages {
+            let Some(id) = message.sender_id else {
+                continue;
+            };
+            if self.cache.get(id, now, self.config.ttl).is_some() {
+                self.stats.cache_hits.fetch_add(1, Ordering::Relaxed);
+            } else if !self
+                .cache
+                .is_retry_pending(id, now, self.config.retry_after)
+                && !unknown.iter().any(|(unknown, _)| *unknown == id)
+            {
+                unknown.push((id, message.nick_name.clone()));
+            }
+        }
+
+        for chunk in unknown.chunks(GetUsersPayload::MAX_USERS) {
+            let nick_names = chunk.iter().map(|(_, name)| name.clone()).collect();
+            self.stats.lookups.fetch_add(1, Ordering::Relaxed);
+            let result = self
+                .client
+                .users_detailed_with_limit(nick_names, self.config.max_split_requests)
+                .await;
+            let now = self.client.pipeline.clock.now();
+            match result {
+                Ok(lookup) => {
+                    for (id, _) in chunk {
+                        let user = lookup
+                            .found
+                            .iter()
+                            .find(|user| user.user_id == id.to_string())
+                            .cloned();
+                        self.cache.insert(*id, user, now);
+                    }
+                }
+                Err(err) => {
+                    self.stats.failed_lookups.fetch_add(1, Ordering::Relaxed);
+                    let senders = chunk.len();
+                    warn!(?err, ?senders, "failed to look up chat senders");
+                    for (id, _) in chunk {
+                        self.cache.failed(*id, now);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Looked up senders by user id, bounded to a capacity
+struct SenderCache {
+    capacity: usize,
+    entries: HashMap<i64, (Option<User>, Instant)>,
+
+    /// Insertion order for eviction, entries replaced since are skipped
+    order: VecDeque<(i64, Instant)>,
+
+    /// When lookups of senders last failed
+    failures: HashMap<i64, Instant>,
+}
+
+impl SenderCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            failures: HashMap::new(),
+        }
+    }
+
+    /// The cached sender, `Some(None)` if the sender was looked up but not found
+    fn get(&self, id: i64, now: Instant, ttl: Duration) -> Option<Option<User>> {
+        let (user, looked_up) = self.entries.get(&id)?;
+        (now.saturating_duration_since(*looked_up) < ttl).then(|| user.clone())
+    }
+
+    fn is_retry_pending(&self, id: i64, now: Instant, retry_after: Duration) -> bool {
+        self.failures
+            .get(&id)
+            .is_some_and(|failed| now.saturating_duration_since(*failed) < retry_after)
+    }
+
+    fn insert(&mut self, id: i64, user: Option<User>, now: Instant) {
+        self.failures.remove(&id);
+        self.entries.insert(id, (user, now));
+        self.order.push_back((id, now));
+        while self.entries.len() > self.capacity {
+            let Some((id, inserted)) = self.order.pop_front() else {
+                break;
+            };
+            if self.entries.get(&id).is_some_and(|(_, at)| *at == inserted) {
+                self.entries.remove(&id);
+            }
+        }
+        // Drop order entries of senders that have since been replaced
+        if self.order.len() > self.capacity.saturating_mul(2) {
+            let entries = &self.entries;
+            self.order
+                .retain(|(id, inserted)| entries.get(id).is_some_and(|(_, at)| at == inserted));
+        }
+    }
+
+    fn failed(&mut self, id: i64, now: Instant) {
+        if self.failures.len() >= self.capacity {
+            self.failures.clear();
+        }
+        self.failures.insert(id, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientId, MockClock, ScriptedTransport};
+    use reqwest::StatusCode;
+    use std::convert::Infallible;
+
+    fn message(message_id: &str, sender_id: i64, nick_name: &str) -> ChatMessage {
+        serde_json::from_value(serde_json::json!({
+            "type": 0,
+            "content": "hi",
+            "nick_name": nick_name,
+            "message_id": message_id,
+            "sender_id": sender_id,
+            "send_time": 1700000000,
+        }))
+        .unwrap()
+    }
+
+    fn user(id: i64, name: &str) -> String {
+        format!(
+            r#"{{"user_id": "{}", "channel_id": "c{}", "username": "{}", "nickname": "{}"}}"#,
+            id, id, name, name
+        )
+    }
+
+    fn client() -> (Client<ClientId>, ScriptedTransport, MockClock) {
+        let transport = ScriptedTransport::new();
+        let clock = MockClock::new();
+        let client = Client::builder()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build(ClientId::new("client-id"))
+            .unwrap();
+        (client, transport, clock)
+    }
+
+    #[tokio::test]
+    async fn one_lookup_per_sender() {
+        let (client, transport, _) = client();
+        let (sender, messages) = futures::channel::mpsc::unbounded::<Result<_, Infallible>>();
+        let mut enriched = enrich_senders(messages, &client, SenderCacheConfig::default());
+
+        transport.push(
+            StatusCode::OK,
+            format!(r#"{{"users": [{}, {}]}}"#, user(1, "alice"), user(2, "bob")),
+        );
+        for (i, (id, name)) in [(1, "alice"), (2, "bob"), (1, "alice"), (2, "bob")]
+            .into_iter()
+            .enumerate()
+        {
+            sender
+                .unbounded_send(Ok(message(&i.to_string(), id, name)))
+                .unwrap();
+        }
+        for expected in ["alice", "bob", "alice", "bob"] {
+            let enriched = enriched.next().await.unwrap().unwrap();
+            assert_eq!(enriched.sender.unwrap().username, expected);
+        }
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(
+            transport.requests()[0].json(),
+            serde_json::json!({"user": ["alice", "bob"]})
+        );
+
+        // Later messages from the same senders are served from the cache
+        for i in 0..10 {
+            sender
+                .unbounded_send(Ok(message(&format!("later-{}", i), 1 + i % 2, "someone")))
+                .unwrap();
+        }
+        for _ in 0..10 {
+            assert!(enriched.next().await.unwrap().unwrap().sender.is_some());
+        }
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(
+            enriched.stats(),
+            EnrichStats {
+                lookups: 1,
+                failed_lookups: 0,
+                cache_hits: 10,
+            }
+        );
+
+        drop(sender);
+        assert!(enriched.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_lookups_retried_later() {
+        let (client, transport, clock) = client();
+        let (sender, messages) = futures::channel::mpsc::unbounded::<Result<_, Infallible>>();
+        let mut enriched = enrich_senders(messages, &client, SenderCacheConfig::default());
+
+        transport.push(StatusCode::INTERNAL_SERVER_ERROR, "");
+        sender.unbounded_send(Ok(message("1", 1, "alice"))).unwrap();
+        assert!(enriched.next().await.unwrap().unwrap().sender.is_none());
+
+        // Not retried until retry_after has passed
+        sender.unbounded_send(Ok(message("2", 1, "alice"))).unwrap();
+        assert!(enriched.next().await.unwrap().unwrap().sender.is_none());
+        assert_eq!(transport.requests().len(), 1);
+
+        clock.advance(Duration::from_secs(30));
+        transport.push(
+            StatusCode::OK,
+            format!(r#"{{"users": [{}]}}"#, user(1, "alice")),
+        );
+        sender.unbounded_send(Ok(message("3", 1, "alice"))).unwrap();
+        let enriched_message = enriched.next().await.unwrap().unwrap();
+        assert_eq!(enriched_message.sender.unwrap().username, "alice");
+        assert_eq!(transport.requests().len(), 2);
+        assert_eq!(enriched.stats().failed_lookups, 1);
+
+        // Expired senders are looked up again, and a mismatched id isn't accepted
+        clock.advance(Duration::from_secs(60 * 60));
+        transport.push(
+            StatusCode::OK,
+            format!(r#"{{"users": [{}]}}"#, user(9, "alice")),
+        );
+        sender.unbounded_send(Ok(message("4", 1, "alice"))).unwrap();
+        assert!(enriched.next().await.unwrap().unwrap().sender.is_none());
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[test]
+    fn cache_capacity() {
+        let mut cache = SenderCache::new(2);
+        let now = Instant::now();
+        let ttl = Duration::from_secs(60);
+        cache.insert(1, None, now);
+        cache.insert(2, None, now);
+        cache.insert(1, None, now + Duration::from_secs(1));
+        cache.insert(3, None, now + Duration::from_secs(2));
+
+        // Sender 2 is the oldest lookup, sender 1 was refreshed
+        assert!(cache.get(1, now, ttl).is_some());
+        assert!(cache.get(2, now, ttl).is_none());
+        assert!(cache.get(3, now, ttl).is_some());
+    }
+}
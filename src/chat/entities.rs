@@ -1,25 +1,135 @@
-use chrono::{DateTime, Utc};
+use crate::{auth::REDACTED, timestamp, ChannelId, Timestamp, UserId};
 use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 use serde_repr::*;
-use serde_with::{serde_as, TimestampSeconds};
-use std::collections::HashMap;
+use serde_with::{serde_as, BorrowCow, DisplayFromStr, PickFirst};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    time::{Duration, SystemTime},
+};
 
 /// Holds a chat token obtained via the api to authenticate
 /// a chat session.
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// The token is redacted from the `Debug` output.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ChatToken {
     /// Chat token to authenticate to chat with
     pub token: String,
 }
 
+impl fmt::Debug for ChatToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChatToken")
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
+impl ChatToken {
+    /// When the token expires, read from the `exp` claim of the JWT.
+    ///
+    /// The signature isn't verified. Returns `None` if the token isn't a JWT or has no `exp`
+    /// claim.
+    pub fn expires_at(&self) -> Option<Timestamp> {
+        timestamp::from_unix(self.expiry_secs()?, 0)
+    }
+
+    /// Whether the token expires within `margin` of `now`, e.g. the client's
+    /// [`Clock::system_time`](crate::Clock::system_time). Tokens with an unknown expiry are never
+    /// considered expired.
+    pub fn is_expired(&self, margin: Duration, now: SystemTime) -> bool {
+        let now = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let deadline = now.saturating_add(margin).as_secs();
+        self.expiry_secs()
+            .is_some_and(|exp| exp <= deadline.try_into().unwrap_or(i64::MAX))
+    }
+
+    /// The `exp` claim of the token, in unix seconds
+    fn expiry_secs(&self) -> Option<i64> {
+        #[derive(Deserialize)]
+        struct Claims {
+            exp: Option<i64>,
+        }
+
+        let payload = self.token.split('.').nth(1)?;
+        let claims: Claims = serde_json::from_slice(&decode_base64_url(payload)?).ok()?;
+        claims.exp
+    }
+}
+
+/// Chat token for one shard of the authenticated user's channel, see
+/// [`Client::chat_shard_token`](crate::Client::chat_shard_token).
+///
+/// The token is redacted from the `Debug` output.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardChatToken {
+    /// Chat token to authenticate to chat with
+    pub token: String,
+
+    /// Total number of shards the channel's chat is split into
+    pub total_shard: u32,
+
+    /// Index of this token's shard, below `total_shard`
+    pub current_shard: u32,
+}
+
+impl fmt::Debug for ShardChatToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShardChatToken")
+            .field("token", &REDACTED)
+            .field("total_shard", &self.total_shard)
+            .field("current_shard", &self.current_shard)
+            .finish()
+    }
+}
+
+impl From<ShardChatToken> for ChatToken {
+    fn from(token: ShardChatToken) -> Self {
+        ChatToken { token: token.token }
+    }
+}
+
+/// Decodes unpadded base64url, as used by JWTs
+fn decode_base64_url(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in input.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' | b'+' => 62,
+            b'_' | b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
 /// Messages that can be sent over the socket to interact
 /// with the Trovo chat api
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "UPPERCASE")]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum ChatSocketMessage {
     /// Authenticate the chat session
     Auth {
         /// Sent back in responses, used to map a request to a reply
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::fuzz::ascii_string))]
         nonce: String,
 
         /// Object containing the chat token to authenticate with
@@ -29,18 +139,21 @@ pub enum ChatSocketMessage {
     /// Sent by Trovo to acknowledge the auth message
     Response {
         /// Sent back in responses, used to map a request to a reply
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::fuzz::ascii_string))]
         nonce: String,
     },
 
     /// A simple ping message to keep the chat socket alive
     Ping {
         /// Sent back in responses, used to map a request to a reply
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::fuzz::ascii_string))]
         nonce: String,
     },
 
     /// Response to sending a ping message.
     Pong {
         /// Sent back in responses, used to map a request to a reply
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::fuzz::ascii_string))]
         nonce: String,
 
         /// Ping response data
@@ -64,20 +177,32 @@ pub enum ChatSocketMessage {
 
 /// Data sent back in response to a Ping message
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct PongMessageData {
     /// Interval in seconds that the server advises you to ping it.
     pub gap: u64,
 }
 
 /// Channel information sent with a chat message
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ChannelInfo {
     /// Id of the channel the chat messages were sent in
     pub channel_id: String,
 }
 
+impl ChannelInfo {
+    /// Typed id of the channel the chat messages were sent in
+    pub fn channel_id(&self) -> ChannelId {
+        ChannelId::new(&self.channel_id)
+    }
+}
+
 /// List of chat messages that were sent
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ChatMessageData {
     /// Message container ID. This is different from message ID. One message
     /// container may contain one or multiple messages.
@@ -89,8 +214,11 @@ pub struct ChatMessageData {
 }
 
 /// Type of the chat message
-#[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug)]
+#[derive(
+    Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
 #[repr(u16)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum ChatMessageType {
     /// Normal chat messages.
     Normal = 0,
@@ -138,9 +266,33 @@ pub enum ChatMessageType {
     CustomSpell = 5009,
 }
 
+#[cfg(feature = "schemars")]
+impl ChatMessageType {
+    /// Codes of all message types, for the json schema
+    pub(crate) const CODES: [u16; 15] = [
+        Self::Normal as u16,
+        Self::Spell as u16,
+        Self::MagicSuperCap as u16,
+        Self::MagicColorful as u16,
+        Self::MagicSpell as u16,
+        Self::MagicBulletScreen as u16,
+        Self::Subscription as u16,
+        Self::System as u16,
+        Self::Follow as u16,
+        Self::Welcome as u16,
+        Self::GiftSub as u16,
+        Self::GiftSubDetailed as u16,
+        Self::Event as u16,
+        Self::Raid as u16,
+        Self::CustomSpell as u16,
+    ];
+}
+
 /// A single chat message
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ChatMessage {
     /// Type of chat message.
     #[serde(rename = "type")]
@@ -174,15 +326,20 @@ pub struct ChatMessage {
     /// ID of the message.
     pub message_id: String,
 
-    /// User ID of the sender. Maybe be `None` for `Event` message types, possibly others
+    /// User ID of the sender. Maybe be `None` for `Event` message types, possibly others. Accepts
+    /// both numbers and numeric strings.
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
     pub sender_id: Option<i64>,
 
     /// Time that the message was sent
-    #[serde_as(as = "TimestampSeconds<i64>")]
-    pub send_time: DateTime<Utc>,
+    #[serde(with = "timestamp::seconds")]
+    #[cfg_attr(feature = "schemars", schemars(with = "i64"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::fuzz::timestamp_seconds))]
+    pub send_time: Timestamp,
 
     /// Extra info of chat
     #[serde(default)]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::fuzz::json_map))]
     pub content_data: HashMap<String, serde_json::Value>,
 
     /// The list of role of the message sender which is a json string. Different from "roles", "custom_role"
@@ -190,8 +347,147 @@ pub struct ChatMessage {
     pub custom_role: Option<String>,
 }
 
+impl ChatMessage {
+    /// Typed user id of the sender, if present
+    pub fn sender_user_id(&self) -> Option<UserId> {
+        self.sender_id.map(|id| UserId::new(id.to_string()))
+    }
+}
+
+/// Borrowed variant of [`ChatMessageData`], see [`ChatMessageRef`].
+#[serde_as]
+#[derive(Debug, Deserialize)]
+pub struct ChatMessageDataRef<'a> {
+    /// Message container ID. This is different from message ID. One message
+    /// container may contain one or multiple messages.
+    #[serde(borrow)]
+    #[serde_as(as = "BorrowCow")]
+    pub eid: Cow<'a, str>,
+
+    /// A list of chats. One chat message may contain multiple chats.
+    #[serde(default, borrow)]
+    pub chats: Vec<ChatMessageRef<'a>>,
+}
+
+impl<'a> ChatMessageDataRef<'a> {
+    /// Parses the chat messages out of a raw chat socket frame, borrowing from it where possible.
+    ///
+    /// Returns `None` for frames that aren't chat messages, e.g. pongs.
+    pub fn from_frame(frame: &'a str) -> Result<Option<Self>, serde_json::Error> {
+        #[derive(Deserialize)]
+        struct Frame<'a> {
+            #[serde(rename = "type")]
+            type_: &'a str,
+            #[serde(borrow)]
+            data: Option<&'a RawValue>,
+        }
+
+        let frame: Frame<'a> = serde_json::from_str(frame)?;
+        match (frame.type_, frame.data) {
+            ("CHAT", Some(data)) => serde_json::from_str(data.get()).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Borrowed variant of [`ChatMessage`], avoiding allocations for strings that don't need
+/// unescaping and leaving `content_data` as raw json.
+///
+/// Useful on busy channels when only a few fields are looked at, see
+/// [`ChatMessageDataRef::from_frame`]. Convert with [`ChatMessageRef::into_owned`] if needed.
+#[serde_as]
+#[derive(Debug, Deserialize)]
+pub struct ChatMessageRef<'a> {
+    /// Type of chat message.
+    #[serde(rename = "type")]
+    pub type_: ChatMessageType,
+
+    /// Content of the message.
+    #[serde(borrow)]
+    #[serde_as(as = "BorrowCow")]
+    pub content: Cow<'a, str>,
+
+    /// Display name of the sender.
+    #[serde(borrow)]
+    #[serde_as(as = "BorrowCow")]
+    pub nick_name: Cow<'a, str>,
+
+    /// URL of the sender’s profile picture.
+    #[serde(default, borrow)]
+    #[serde_as(as = "Option<BorrowCow>")]
+    pub avatar: Option<Cow<'a, str>>,
+
+    /// The subscription level of the user in the channel. “sub_L1” for tier 1 subscriber.
+    #[serde(default, borrow)]
+    #[serde_as(as = "Option<BorrowCow>")]
+    pub sub_lv: Option<Cow<'a, str>>,
+
+    /// The list of badge names of the sender.
+    #[serde(default, borrow)]
+    #[serde_as(as = "Vec<BorrowCow>")]
+    pub medals: Vec<Cow<'a, str>>,
+
+    /// The list of decoration names of sender.
+    #[serde(default, borrow)]
+    #[serde_as(as = "Vec<BorrowCow>")]
+    pub decos: Vec<Cow<'a, str>>,
+
+    /// The list of roles of the message sender.
+    #[serde(default, borrow)]
+    #[serde_as(as = "Vec<BorrowCow>")]
+    pub roles: Vec<Cow<'a, str>>,
+
+    /// ID of the message.
+    #[serde(borrow)]
+    #[serde_as(as = "BorrowCow")]
+    pub message_id: Cow<'a, str>,
+
+    /// User ID of the sender. Maybe be `None` for `Event` message types, possibly others. Accepts
+    /// both numbers and numeric strings.
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    pub sender_id: Option<i64>,
+
+    /// Time that the message was sent
+    #[serde(with = "timestamp::seconds")]
+    pub send_time: Timestamp,
+
+    /// Extra info of chat, as raw json
+    #[serde(default, borrow)]
+    pub content_data: Option<&'a RawValue>,
+
+    /// The list of role of the message sender which is a json string.
+    #[serde(default, borrow)]
+    #[serde_as(as = "Option<BorrowCow>")]
+    pub custom_role: Option<Cow<'a, str>>,
+}
+
+impl ChatMessageRef<'_> {
+    /// Converts into an owned [`ChatMessage`], parsing `content_data`.
+    pub fn into_owned(self) -> Result<ChatMessage, serde_json::Error> {
+        let owned = |values: Vec<Cow<'_, str>>| values.into_iter().map(Cow::into_owned).collect();
+        Ok(ChatMessage {
+            type_: self.type_,
+            content: self.content.into_owned(),
+            nick_name: self.nick_name.into_owned(),
+            avatar: self.avatar.map(Cow::into_owned),
+            sub_lv: self.sub_lv.map(Cow::into_owned),
+            medals: owned(self.medals),
+            decos: owned(self.decos),
+            roles: owned(self.roles),
+            message_id: self.message_id.into_owned(),
+            sender_id: self.sender_id,
+            send_time: self.send_time,
+            content_data: match self.content_data {
+                Some(raw) => serde_json::from_str(raw.get())?,
+                None => HashMap::new(),
+            },
+            custom_role: self.custom_role.map(Cow::into_owned),
+        })
+    }
+}
+
 /// Payload for the send chat message request
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SendChatMessagePayload {
     /// The message contents
     pub content: String,
@@ -200,3 +496,208 @@ pub struct SendChatMessagePayload {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub channel_id: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::unix_seconds;
+
+    const CHAT_FRAME: &str = r#"{
+        "type": "CHAT",
+        "channel_info": { "channel_id": "100000" },
+        "data": {
+            "eid": "1700000000",
+            "chats": [
+                {
+                    "type": 0,
+                    "content": "hello \"world\" \u2764",
+                    "nick_name": "someone",
+                    "avatar": "https://example.com/a.png",
+                    "sub_lv": "sub_L1",
+                    "medals": ["sub_L1"],
+                    "roles": ["mod", "follower"],
+                    "message_id": "m1",
+                    "sender_id": 123,
+                    "send_time": 1700000000,
+                    "content_data": { "gift_id": 1, "normal": { "x": [1, 2] } },
+                    "custom_role": "[]"
+                },
+                {
+                    "type": 5003,
+                    "content": "followed",
+                    "nick_name": "other",
+                    "message_id": "m2",
+                    "send_time": 1700000001
+                }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn borrowed_matches_owned() {
+        let owned = match serde_json::from_str(CHAT_FRAME).unwrap() {
+            ChatSocketMessage::Chat { data, .. } => data.chats,
+            msg => panic!("expected chat message, got {:?}", msg),
+        };
+        let borrowed = ChatMessageDataRef::from_frame(CHAT_FRAME).unwrap().unwrap();
+        assert_eq!(borrowed.eid, "1700000000");
+        assert!(matches!(borrowed.chats[0].nick_name, Cow::Borrowed(_)));
+        assert!(matches!(borrowed.chats[0].content, Cow::Owned(_)));
+
+        let borrowed: Vec<ChatMessage> = borrowed
+            .chats
+            .into_iter()
+            .map(|chat| chat.into_owned().unwrap())
+            .collect();
+        assert_eq!(
+            serde_json::to_value(&borrowed).unwrap(),
+            serde_json::to_value(&owned).unwrap()
+        );
+    }
+
+    #[test]
+    fn chat_token_redacted() {
+        let msg = ChatSocketMessage::Auth {
+            nonce: "authenticate".to_string(),
+            data: ChatToken {
+                token: "SENTINEL-CHAT-TOKEN".to_string(),
+            },
+        };
+        assert!(!format!("{:?}", msg).contains("SENTINEL"));
+        assert!(serde_json::to_string(&msg)
+            .unwrap()
+            .contains("SENTINEL-CHAT-TOKEN"));
+    }
+
+    #[test]
+    fn borrowed_ignores_other_frames() {
+        let pong = r#"{"type":"PONG","nonce":"1","data":{"gap":30}}"#;
+        assert!(ChatMessageDataRef::from_frame(pong).unwrap().is_none());
+    }
+
+    #[test]
+    fn sender_id_as_string() {
+        let frame = CHAT_FRAME.replace(r#""sender_id": 123"#, r#""sender_id": "123""#);
+        let owned = match serde_json::from_str(&frame).unwrap() {
+            ChatSocketMessage::Chat { data, .. } => data.chats,
+            msg => panic!("expected chat message, got {:?}", msg),
+        };
+        assert_eq!(owned[0].sender_id, Some(123));
+        assert_eq!(serde_json::to_value(&owned[0]).unwrap()["sender_id"], 123);
+
+        let borrowed = ChatMessageDataRef::from_frame(&frame).unwrap().unwrap();
+        assert_eq!(borrowed.chats[0].sender_id, Some(123));
+    }
+
+    fn jwt(payload: &str) -> ChatToken {
+        // base64url encoded {"alg":"HS256","typ":"JWT"}
+        const HEADER: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9";
+        let mut encoded = String::new();
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+        for chunk in payload.as_bytes().chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+            for i in 0..=chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }
+        }
+        ChatToken {
+            token: format!("{}.{}.signature", HEADER, encoded),
+        }
+    }
+
+    #[test]
+    fn chat_token_expiry() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let valid = jwt(r#"{"sub":"100000","exp":1700000120}"#);
+        assert_eq!(unix_seconds(&valid.expires_at().unwrap()), 1700000120);
+        assert!(!valid.is_expired(Duration::from_secs(60), now));
+        assert!(valid.is_expired(Duration::from_secs(120), now));
+
+        let expired = jwt(r#"{"exp":1600000000}"#);
+        assert_eq!(unix_seconds(&expired.expires_at().unwrap()), 1600000000);
+        assert!(expired.is_expired(Duration::ZERO, now));
+
+        for malformed in [
+            jwt(r#"{"sub":"100000"}"#),
+            jwt("not json"),
+            ChatToken {
+                token: "opaque-token".to_string(),
+            },
+            ChatToken {
+                token: "a.!!!.c".to_string(),
+            },
+        ] {
+            assert_eq!(malformed.expires_at(), None);
+            assert!(!malformed.is_expired(Duration::from_secs(60), now));
+        }
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn chat_message_type_codes() {
+        // Exhaustive, so a new variant fails to compile until it's added here and to CODES
+        let index = |message_type: ChatMessageType| match message_type {
+            ChatMessageType::Normal => 0,
+            ChatMessageType::Spell => 1,
+            ChatMessageType::MagicSuperCap => 2,
+            ChatMessageType::MagicColorful => 3,
+            ChatMessageType::MagicSpell => 4,
+            ChatMessageType::MagicBulletScreen => 5,
+            ChatMessageType::Subscription => 6,
+            ChatMessageType::System => 7,
+            ChatMessageType::Follow => 8,
+            ChatMessageType::Welcome => 9,
+            ChatMessageType::GiftSub => 10,
+            ChatMessageType::GiftSubDetailed => 11,
+            ChatMessageType::Event => 12,
+            ChatMessageType::Raid => 13,
+            ChatMessageType::CustomSpell => 14,
+        };
+        for (i, code) in ChatMessageType::CODES.into_iter().enumerate() {
+            let message_type: ChatMessageType = serde_json::from_value(code.into()).unwrap();
+            assert_eq!(index(message_type), i);
+        }
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn chat_message_schema() {
+        use crate::schema::tests::validate;
+
+        let schema = serde_json::to_value(schemars::schema_for!(ChatMessage)).unwrap();
+        let frame: serde_json::Value = serde_json::from_str(CHAT_FRAME).unwrap();
+        for chat in frame["data"]["chats"].as_array().unwrap() {
+            validate(&schema, chat).unwrap();
+        }
+
+        let mut invalid = frame["data"]["chats"][0].clone();
+        invalid["type"] = 1.into();
+        assert!(validate(&schema, &invalid).is_err());
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_messages_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        // xorshift, so failures are reproducible without a rng dependency
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut bytes = vec![0u8; 4096];
+        for _ in 0..500 {
+            for byte in bytes.iter_mut() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                *byte = state as u8;
+            }
+            let msg = ChatSocketMessage::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let json = serde_json::to_value(&msg).unwrap();
+            let parsed: ChatSocketMessage = serde_json::from_value(json.clone())
+                .unwrap_or_else(|e| panic!("{} failed to parse: {}", json, e));
+            assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+        }
+    }
+}
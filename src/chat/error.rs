@@ -1,5 +1,5 @@
-use async_tungstenite::tungstenite::{self, protocol::CloseFrame};
-use std::{error::Error, fmt::Display};
+use async_tungstenite::tungstenite::{self, error::CapacityError, protocol::CloseFrame};
+use std::{error::Error, fmt::Display, io};
 
 /// Errors that can happen with authenticated requests
 #[derive(Debug)]
@@ -26,6 +26,35 @@ impl From<serde_json::Error> for ChatConnectError {
     }
 }
 
+impl ChatConnectError {
+    /// Whether connecting again later may succeed.
+    ///
+    /// - `WebSocket`: connection failures, timeouts, and 5xx handshake responses
+    /// - `Serde`: never
+    /// - `SocketClosed`: always
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::WebSocket(e) => is_retryable_websocket(e),
+            Self::Serde(_) => false,
+            Self::SocketClosed => true,
+        }
+    }
+
+    /// Whether the connection timed out.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::WebSocket(e) if is_timeout_websocket(e))
+    }
+
+    /// Whether the connection failed or was dropped.
+    pub fn is_connection(&self) -> bool {
+        match self {
+            Self::WebSocket(e) => is_connection_websocket(e),
+            Self::Serde(_) => false,
+            Self::SocketClosed => true,
+        }
+    }
+}
+
 impl Display for ChatConnectError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -50,7 +79,7 @@ impl Error for ChatConnectError {
 #[derive(Debug)]
 pub enum ChatMessageStreamError {
     /// Error connecting to socket
-    WebSocket(tungstenite::Error),
+    WebSocket(Box<tungstenite::Error>),
 
     /// Error serialising or deserialising entities
     Serde(serde_json::Error),
@@ -60,11 +89,26 @@ pub enum ChatMessageStreamError {
 
     /// The server never responsed to our pings
     PingTimeout,
+
+    /// The server sent a message larger than
+    /// [`ChatConfig::max_message_size`](crate::chat::ChatConfig::max_message_size)
+    MessageTooLarge {
+        /// Size of the message, or of the frame if it was rejected by its header
+        size: usize,
+
+        /// The configured limit
+        max_size: usize,
+    },
 }
 
 impl From<tungstenite::Error> for ChatMessageStreamError {
     fn from(error: tungstenite::Error) -> Self {
-        Self::WebSocket(error)
+        match error {
+            tungstenite::Error::Capacity(CapacityError::MessageTooLong { size, max_size }) => {
+                Self::MessageTooLarge { size, max_size }
+            }
+            error => Self::WebSocket(Box::new(error)),
+        }
     }
 }
 
@@ -74,6 +118,43 @@ impl From<serde_json::Error> for ChatMessageStreamError {
     }
 }
 
+impl ChatMessageStreamError {
+    /// Whether reconnecting may succeed.
+    ///
+    /// - `WebSocket`: connection failures and timeouts
+    /// - `Serde`: never, the server sent something we don't understand
+    /// - `SocketClosed`: always
+    /// - `PingTimeout`: always
+    /// - `MessageTooLarge`: never, the server would likely send it again
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::WebSocket(e) => is_retryable_websocket(e),
+            Self::Serde(_) => false,
+            Self::SocketClosed(_) => true,
+            Self::PingTimeout => true,
+            Self::MessageTooLarge { .. } => false,
+        }
+    }
+
+    /// Whether the connection timed out, including the server not responding to pings.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Self::WebSocket(e) => is_timeout_websocket(e),
+            Self::PingTimeout => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the connection failed or was dropped.
+    pub fn is_connection(&self) -> bool {
+        match self {
+            Self::WebSocket(e) => is_connection_websocket(e),
+            Self::SocketClosed(_) => true,
+            _ => false,
+        }
+    }
+}
+
 impl Display for ChatMessageStreamError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -88,6 +169,9 @@ impl Display for ChatMessageStreamError {
             Self::PingTimeout => {
                 write!(f, "server stopped responding to pings")
             }
+            Self::MessageTooLarge { size, max_size } => {
+                write!(f, "message too large: {} > {} bytes", size, max_size)
+            }
         }
     }
 }
@@ -95,10 +179,175 @@ impl Display for ChatMessageStreamError {
 impl Error for ChatMessageStreamError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            Self::WebSocket(e) => Some(e),
+            Self::WebSocket(e) => Some(&**e),
             Self::Serde(e) => Some(e),
             Self::SocketClosed(_) => None,
             Self::PingTimeout => None,
+            Self::MessageTooLarge { .. } => None,
+        }
+    }
+}
+
+/// Errors that stop [`persist_chat`](crate::chat::persist::persist_chat), with the progress made
+/// before the error.
+#[derive(Debug)]
+pub enum PersistError<S, K> {
+    /// The chat stream failed. Messages received before the error were stored.
+    Stream {
+        /// Error from the chat stream
+        source: S,
+
+        /// Progress up to the error
+        progress: crate::chat::persist::PersistProgress,
+    },
+
+    /// Storing a batch failed on every attempt, so its messages weren't stored.
+    Sink {
+        /// Error from the last attempt
+        source: K,
+
+        /// Number of attempts made
+        attempts: u32,
+
+        /// Progress up to the failed batch
+        progress: crate::chat::persist::PersistProgress,
+    },
+}
+
+impl<S, K> PersistError<S, K> {
+    /// Progress made before the error, for resuming after the last stored message.
+    pub fn progress(&self) -> &crate::chat::persist::PersistProgress {
+        match self {
+            Self::Stream { progress, .. } | Self::Sink { progress, .. } => progress,
+        }
+    }
+}
+
+impl<S: Display, K: Display> Display for PersistError<S, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stream { source, .. } => write!(f, "chat stream failed: {}", source),
+            Self::Sink {
+                source, attempts, ..
+            } => write!(
+                f,
+                "failed to store chat messages after {} attempts: {}",
+                attempts, source
+            ),
+        }
+    }
+}
+
+impl<S, K> Error for PersistError<S, K>
+where
+    S: Error + 'static,
+    K: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Stream { source, .. } => Some(source),
+            Self::Sink { source, .. } => Some(source),
         }
     }
 }
+
+fn is_timeout_websocket(error: &tungstenite::Error) -> bool {
+    matches!(error, tungstenite::Error::Io(e) if e.kind() == io::ErrorKind::TimedOut)
+}
+
+fn is_connection_websocket(error: &tungstenite::Error) -> bool {
+    match error {
+        tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => true,
+        tungstenite::Error::Io(e) => matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
+
+fn is_retryable_websocket(error: &tungstenite::Error) -> bool {
+    match error {
+        tungstenite::Error::Http(response) => response.status().is_server_error(),
+        e => is_timeout_websocket(e) || is_connection_websocket(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tungstenite::error::ProtocolError;
+
+    #[test]
+    fn retryable() {
+        let refused = || tungstenite::Error::Io(io::ErrorKind::ConnectionRefused.into());
+        let timed_out = || tungstenite::Error::Io(io::ErrorKind::TimedOut.into());
+        let serde = || serde_json::from_str::<u8>("x").unwrap_err();
+
+        // (error, retryable, timeout, connection)
+        let connect = [
+            (ChatConnectError::WebSocket(refused()), true, false, true),
+            (ChatConnectError::WebSocket(timed_out()), true, true, false),
+            (
+                ChatConnectError::WebSocket(tungstenite::Error::Protocol(
+                    ProtocolError::HandshakeIncomplete,
+                )),
+                false,
+                false,
+                false,
+            ),
+            (ChatConnectError::Serde(serde()), false, false, false),
+            (ChatConnectError::SocketClosed, true, false, true),
+        ];
+        for (err, retryable, timeout, connection) in connect {
+            assert_eq!(err.is_retryable(), retryable, "{:?}", err);
+            assert_eq!(err.is_timeout(), timeout, "{:?}", err);
+            assert_eq!(err.is_connection(), connection, "{:?}", err);
+        }
+
+        let stream = [
+            (
+                ChatMessageStreamError::WebSocket(Box::new(tungstenite::Error::ConnectionClosed)),
+                true,
+                false,
+                true,
+            ),
+            (ChatMessageStreamError::Serde(serde()), false, false, false),
+            (
+                ChatMessageStreamError::SocketClosed(None),
+                true,
+                false,
+                true,
+            ),
+            (ChatMessageStreamError::PingTimeout, true, true, false),
+            (
+                ChatMessageStreamError::from(tungstenite::Error::Capacity(
+                    CapacityError::MessageTooLong {
+                        size: 2048,
+                        max_size: 1024,
+                    },
+                )),
+                false,
+                false,
+                false,
+            ),
+        ];
+        for (err, retryable, timeout, connection) in stream {
+            assert_eq!(err.is_retryable(), retryable, "{:?}", err);
+            assert_eq!(err.is_timeout(), timeout, "{:?}", err);
+            assert_eq!(err.is_connection(), connection, "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn stream_error_is_small() {
+        // The socket's sync helpers like `handle_socket_item` return this error directly,
+        // clippy's `result_large_err` flags anything above 128 bytes.
+        assert!(std::mem::size_of::<ChatMessageStreamError>() <= 128);
+    }
+}
@@ -3,10 +3,18 @@
 //! Connect to Trovo chat via websockets
 
 mod client;
+mod config;
+pub mod enrich;
 mod entities;
 mod error;
+pub mod moderation;
+pub mod monetization;
+pub mod persist;
 mod socket;
+pub mod summary;
 
+pub use client::{ChatMessagesForChannelError, ChatMessagesForUserError};
+pub use config::*;
 pub use entities::*;
 pub use error::*;
 pub use socket::*;
@@ -0,0 +1,1174 @@
+//! # Moderation
+//!
+//! Content checks shared by moderation bots: blocked words, hyperlinks, excessive caps and
+//! repeated characters, and a [`ModerationPipeline`] acting on the messages they flag.
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use trovo::chat::moderation::{ContentFilter, ModerationStream};
+//!
+//! # async fn run(client: trovo::Client<trovo::ClientId>) -> Result<(), Box<dyn std::error::Error>> {
+//! let filter = ContentFilter::new()
+//!     .block_words(["badword", "another phrase"])
+//!     .block_hyperlinks()
+//!     .excessive_caps(12, 0.8)
+//!     .max_repeated_chars(8);
+//!
+//! let messages = client.chat_messages_for_channel("100000").await?;
+//! let mut flagged = ModerationStream::new(messages, filter).flagged_only();
+//! while let Some(moderated) = flagged.next().await {
+//!     let moderated = moderated?;
+//!     println!("{}: {:?}", moderated.message.nick_name, moderated.verdict.matched_rules);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Normalization
+//!
+//! Blocked words are matched against a normalized form of the message, see [`normalize`], so
+//! that common ways of dodging a word list still match. The word list entries are normalized the
+//! same way. In order:
+//!
+//! 1. Zero-width and invisible formatting characters are removed: zero-width space, (non-)joiner,
+//!    word joiner, byte order mark, soft hyphen and the mongolian vowel separator.
+//! 2. Text is lowercased.
+//! 3. Combining diacritical marks (`U+0300`-`U+036F`) are removed, and precomposed latin letters
+//!    with diacritics are folded to their base letter, e.g. `é` to `e`. Only the Latin-1
+//!    Supplement and Latin Extended-A blocks are folded.
+//! 4. Cyrillic and greek letters that look like latin letters, in either case, are replaced by
+//!    them, e.g. the cyrillic `а` by `a` and `в` (from `В`) by `b`, so words mixing scripts match.
+//! 5. Leetspeak is undone within each whitespace separated chunk. Digits `0 1 3 4 5 7 8` become
+//!    `o i e a s t b`, only when the chunk also contains a letter, so plain numbers are kept.
+//!    Symbols `@ $ !` become `a s i` when followed by a letter or digit, so `b@d` is `bad` while
+//!    a trailing `bad!` is still `bad`.
+//! 6. The text is split into words on anything that isn't a letter or digit.
+//! 7. Runs of three or more of the same character are collapsed to one, e.g. `baaad` to `bad`.
+//!    Shorter runs are kept so that `good` isn't mistaken for `god`.
+//!
+//! A word list entry matches when its words appear consecutively in the message.
+//!
+//! ## Pipeline
+//!
+//! [`ModerationPipeline`] deletes flagged messages and escalates from timeouts to a ban for
+//! repeat offenders, as the user of an authenticated client who moderates the channel.
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use trovo::chat::moderation::{ContentFilter, ModerationConfig, ModerationPipeline};
+//!
+//! # async fn run(client: trovo::Client<trovo::AccessTokenOnly>) -> Result<(), Box<dyn std::error::Error>> {
+//! let filter = ContentFilter::new().block_words(["badword"]).block_hyperlinks();
+//! let config = ModerationConfig {
+//!     dry_run: true,
+//!     ..ModerationConfig::default()
+//! };
+//! let (pipeline, mut failures) = ModerationPipeline::new(&client, "100000", filter, config);
+//! tokio::spawn(async move {
+//!     while let Some(failure) = failures.next().await {
+//!         eprintln!("{:?} of {} failed: {}", failure.action, failure.nick_name, failure.error);
+//!     }
+//! });
+//!
+//! let messages = client.chat_messages_for_channel("100000").await?;
+//! pipeline.run(messages).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    chat::ChatMessage, AccessTokenProvider, ChannelCommandError, ChannelId, Client, UserId,
+};
+use futures::{channel::mpsc, ready, Stream, StreamExt};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Debug, Display},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// A rule of a [`ContentFilter`] that matched a message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RuleId {
+    /// An entry of the word list matched, given as it was configured
+    BlockedWord(String),
+
+    /// The message contains a hyperlink. Channels in block hyperlink mode reject these for users
+    /// without permission, see [`ErrorStatus::UnauthorizedHyperlink`](crate::ErrorStatus::UnauthorizedHyperlink).
+    Hyperlink,
+
+    /// Too much of the message is in capitals
+    ExcessiveCaps,
+
+    /// A character is repeated too many times in a row
+    RepeatedCharacters,
+}
+
+/// Result of checking a message with a [`ContentFilter`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Verdict {
+    /// Rules that matched, in the order they are checked. Empty if the message is clean.
+    pub matched_rules: Vec<RuleId>,
+}
+
+impl Verdict {
+    /// Whether no rules matched
+    pub fn is_clean(&self) -> bool {
+        self.matched_rules.is_empty()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BlockedWord {
+    configured: String,
+    words: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CapsRule {
+    min_letters: usize,
+    max_ratio: f32,
+}
+
+/// Checks chat messages against a configurable set of rules. No rules are enabled by default.
+#[derive(Debug, Clone, Default)]
+pub struct ContentFilter {
+    blocked_words: Vec<BlockedWord>,
+    hyperlinks: bool,
+    caps: Option<CapsRule>,
+    max_repeated_chars: Option<usize>,
+}
+
+impl ContentFilter {
+    /// Creates a filter with no rules enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag messages containing any of the given words or phrases, after normalizing both, see
+    /// the [module docs](self#normalization). Entries that normalize to nothing are ignored.
+    pub fn block_words<I, S>(mut self, words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for configured in words {
+            let configured = configured.into();
+            let words = normalized_words(&configured);
+            if !words.is_empty() {
+                self.blocked_words.push(BlockedWord { configured, words });
+            }
+        }
+        self
+    }
+
+    /// Flag messages containing hyperlinks, see [`contains_hyperlink`].
+    pub fn block_hyperlinks(mut self) -> Self {
+        self.hyperlinks = true;
+        self
+    }
+
+    /// Flag messages with at least `min_letters` cased letters, of which more than `max_ratio`
+    /// (between 0 and 1) are capitals.
+    pub fn excessive_caps(mut self, min_letters: usize, max_ratio: f32) -> Self {
+        self.caps = Some(CapsRule {
+            min_letters,
+            max_ratio,
+        });
+        self
+    }
+
+    /// Flag messages repeating any character other than whitespace more than `max` times in a row.
+    pub fn max_repeated_chars(mut self, max: usize) -> Self {
+        self.max_repeated_chars = Some(max);
+        self
+    }
+
+    /// Checks the content of a chat message.
+    ///
+    /// All message types are checked as they are, including spells whose content is json.
+    pub fn check(&self, message: &ChatMessage) -> Verdict {
+        self.check_text(&message.content)
+    }
+
+    /// Checks a piece of text, e.g. a message before sending it.
+    pub fn check_text(&self, text: &str) -> Verdict {
+        let mut matched_rules = Vec::new();
+
+        if !self.blocked_words.is_empty() {
+            let words = normalized_words(text);
+            for blocked in &self.blocked_words {
+                if words
+                    .windows(blocked.words.len())
+                    .any(|window| window == blocked.words.as_slice())
+                {
+                    matched_rules.push(RuleId::BlockedWord(blocked.configured.clone()));
+                }
+            }
+        }
+
+        if self.hyperlinks && contains_hyperlink(text) {
+            matched_rules.push(RuleId::Hyperlink);
+        }
+
+        if let Some(caps) = self.caps {
+            let (upper, cased) = text.chars().fold((0, 0), |(upper, cased), c| {
+                if c.is_uppercase() {
+                    (upper + 1, cased + 1)
+                } else if c.is_lowercase() {
+                    (upper, cased + 1)
+                } else {
+                    (upper, cased)
+                }
+            });
+            if cased > 0
+                && cased >= caps.min_letters
+                && upper as f32 / cased as f32 > caps.max_ratio
+            {
+                matched_rules.push(RuleId::ExcessiveCaps);
+            }
+        }
+
+        if let Some(max) = self.max_repeated_chars {
+            if longest_run(
+                text.chars().filter(|c| !is_invisible(*c)),
+                char::is_whitespace,
+            ) > max
+            {
+                matched_rules.push(RuleId::RepeatedCharacters);
+            }
+        }
+
+        Verdict { matched_rules }
+    }
+}
+
+/// Normalizes text for word matching, as described in the [module docs](self#normalization).
+/// Words are joined by single spaces.
+///
+/// ```
+/// use trovo::chat::moderation::normalize;
+///
+/// assert_eq!(normalize("H3LL0   Wörld!!"), "hello world");
+/// assert_eq!(normalize("b\u{200B}@d w\u{043E}rd"), "bad word");
+/// ```
+pub fn normalize(text: &str) -> String {
+    normalized_words(text).join(" ")
+}
+
+fn normalized_words(text: &str) -> Vec<String> {
+    let folded: String = text
+        .chars()
+        .filter(|c| !is_invisible(*c))
+        .flat_map(char::to_lowercase)
+        .filter(|c| !('\u{0300}'..='\u{036F}').contains(c))
+        .map(|c| fold_latin(c).or_else(|| fold_homoglyph(c)).unwrap_or(c))
+        .collect();
+
+    let mut words = Vec::new();
+    for chunk in folded.split_whitespace() {
+        let chars: Vec<char> = chunk.chars().collect();
+        let has_letter = chars.iter().any(|c| c.is_alphabetic());
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let next_alphanumeric = chars.get(i + 1).is_some_and(|c| c.is_alphanumeric());
+            let c = match c {
+                '0' if has_letter => 'o',
+                '1' if has_letter => 'i',
+                '3' if has_letter => 'e',
+                '4' if has_letter => 'a',
+                '5' if has_letter => 's',
+                '7' if has_letter => 't',
+                '8' if has_letter => 'b',
+                '@' if next_alphanumeric => 'a',
+                '$' if next_alphanumeric => 's',
+                '!' if next_alphanumeric => 'i',
+                c => c,
+            };
+            if c.is_alphanumeric() {
+                word.push(c);
+            } else if !word.is_empty() {
+                words.push(collapse_runs(&std::mem::take(&mut word)));
+            }
+        }
+        if !word.is_empty() {
+            words.push(collapse_runs(&word));
+        }
+    }
+    words
+}
+
+/// Collapses runs of three or more of the same character into one.
+fn collapse_runs(word: &str) -> String {
+    let chars: Vec<char> = word.chars().collect();
+    let mut collapsed = String::with_capacity(word.len());
+    let mut start = 0;
+    while start < chars.len() {
+        let c = chars[start];
+        let len = chars[start..]
+            .iter()
+            .take_while(|&&other| other == c)
+            .count();
+        let keep = if len >= 3 { 1 } else { len };
+        collapsed.extend(std::iter::repeat_n(c, keep));
+        start += len;
+    }
+    collapsed
+}
+
+/// Zero-width and invisible formatting characters removed before matching.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}' | '\u{180E}'
+    )
+}
+
+/// Folds lowercase latin letters with diacritics to their base letter.
+fn fold_latin(c: char) -> Option<char> {
+    Some(match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ĥ' | 'ħ' => 'h',
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'ĵ' => 'j',
+        'ķ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => return None,
+    })
+}
+
+/// Replaces lowercase cyrillic and greek letters that look like latin letters in either case,
+/// e.g. `в` as the lowercase of `В`.
+fn fold_homoglyph(c: char) -> Option<char> {
+    Some(match c {
+        'а' | 'α' => 'a',
+        'в' | 'β' => 'b',
+        'с' => 'c',
+        'е' | 'ё' | 'ε' => 'e',
+        'і' | 'ї' | 'ι' => 'i',
+        'н' | 'η' => 'h',
+        'ј' => 'j',
+        'к' | 'κ' => 'k',
+        'м' | 'μ' => 'm',
+        'о' | 'ο' => 'o',
+        'р' | 'ρ' => 'p',
+        'ѕ' => 's',
+        'т' | 'τ' => 't',
+        'υ' => 'u',
+        'ν' => 'v',
+        'х' | 'χ' => 'x',
+        'у' => 'y',
+        _ => return None,
+    })
+}
+
+/// Length of the longest run of the same character, not counting ignored characters.
+fn longest_run(chars: impl Iterator<Item = char>, ignore: impl Fn(char) -> bool) -> usize {
+    let mut longest = 0;
+    let mut current = None;
+    let mut run = 0;
+    for c in chars {
+        if ignore(c) {
+            current = None;
+            continue;
+        }
+        if current == Some(c) {
+            run += 1;
+        } else {
+            current = Some(c);
+            run = 1;
+        }
+        longest = longest.max(run);
+    }
+    longest
+}
+
+/// Top level domains recognised in links without a scheme or `www.` prefix.
+const LINK_TLDS: &[&str] = &[
+    "app", "biz", "cc", "co", "com", "de", "dev", "fr", "gg", "info", "io", "live", "ly", "me",
+    "net", "org", "ru", "tv", "uk", "us", "xyz",
+];
+
+/// Whether the text contains something that looks like a hyperlink: an `http(s)://` url, a
+/// `www.` address, or a bare domain such as `example.com` ending in a common top level domain.
+pub fn contains_hyperlink(text: &str) -> bool {
+    let text: String = text
+        .chars()
+        .filter(|c| !is_invisible(*c))
+        .flat_map(char::to_lowercase)
+        .collect();
+    text.split_whitespace().any(|token| {
+        let token = token.trim_matches(|c: char| "()[]<>{}\"'.,!?:;".contains(c));
+        if token.contains("http://") || token.contains("https://") || token.starts_with("www.") {
+            return true;
+        }
+
+        let host = token.split(['/', '?', '#']).next().unwrap_or_default();
+        let labels: Vec<&str> = host.split('.').collect();
+        labels.len() >= 2
+            && labels.iter().all(|label| {
+                !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+            })
+            && LINK_TLDS.contains(labels.last().unwrap())
+    })
+}
+
+/// A chat message with the verdict of a [`ContentFilter`].
+#[derive(Debug)]
+pub struct ModeratedMessage {
+    /// The checked message
+    pub message: ChatMessage,
+
+    /// Rules the message matched
+    pub verdict: Verdict,
+}
+
+/// Adapts a chat message stream by checking every message with a [`ContentFilter`].
+///
+/// By default every message is returned along with its verdict, see
+/// [`flagged_only`](Self::flagged_only) to skip clean messages. Errors from the underlying stream
+/// are passed through.
+#[derive(Debug)]
+pub struct ModerationStream<S> {
+    messages: S,
+    filter: ContentFilter,
+    flagged_only: bool,
+}
+
+impl<S> ModerationStream<S> {
+    /// Wraps a stream of chat messages, e.g. a [`ChatMessageStream`](crate::chat::ChatMessageStream).
+    pub fn new(messages: S, filter: ContentFilter) -> Self {
+        Self {
+            messages,
+            filter,
+            flagged_only: false,
+        }
+    }
+
+    /// Only return messages that matched at least one rule.
+    pub fn flagged_only(mut self) -> Self {
+        self.flagged_only = true;
+        self
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.messages
+    }
+}
+
+impl<S, E> Stream for ModerationStream<S>
+where
+    S: Stream<Item = Result<ChatMessage, E>> + Unpin,
+{
+    type Item = Result<ModeratedMessage, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match ready!(Pin::new(&mut self.messages).poll_next(cx)) {
+                Some(Ok(message)) => message,
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => return Poll::Ready(None),
+            };
+            let verdict = self.filter.check(&message);
+            if !self.flagged_only || !verdict.is_clean() {
+                return Poll::Ready(Some(Ok(ModeratedMessage { message, verdict })));
+            }
+        }
+    }
+}
+
+/// Failures waiting to be received before further ones are dropped
+const FAILURES_BUFFER: usize = 64;
+
+/// Settings for a [`ModerationPipeline`].
+#[derive(Debug, Clone)]
+pub struct ModerationConfig {
+    /// Delete every flagged message. Defaults to `true`.
+    pub delete_messages: bool,
+
+    /// How long to time a user out on their first, second and later strikes, the last duration
+    /// repeating. Each must be at most [`MAX_BAN_DURATION`](crate::MAX_BAN_DURATION). Empty to
+    /// never time users out. Defaults to 1 minute, 10 minutes and 1 hour.
+    pub timeouts: Vec<Duration>,
+
+    /// Ban users permanently on this many strikes instead of timing them out, `None` to never
+    /// ban. Defaults to 5.
+    pub ban_after: Option<usize>,
+
+    /// How long a strike counts against a user. Defaults to 24 hours.
+    pub strike_ttl: Duration,
+
+    /// Maximum number of users whose strikes are kept, the users with the oldest strikes are
+    /// forgotten first. Defaults to 10,000.
+    pub capacity: usize,
+
+    /// Only log the actions that would be taken, without making any requests. Strikes are still
+    /// counted. Defaults to `false`.
+    pub dry_run: bool,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            delete_messages: true,
+            timeouts: vec![
+                Duration::from_secs(60),
+                Duration::from_secs(10 * 60),
+                Duration::from_secs(60 * 60),
+            ],
+            ban_after: Some(5),
+            strike_ttl: Duration::from_secs(24 * 60 * 60),
+            capacity: 10_000,
+            dry_run: false,
+        }
+    }
+}
+
+impl ModerationConfig {
+    /// The timeout or ban for a user with this many strikes, if any
+    fn escalation(&self, strikes: usize) -> Option<ModerationAction> {
+        if self.ban_after.is_some_and(|ban_after| strikes >= ban_after) {
+            return Some(ModerationAction::Ban);
+        }
+        let timeout = self
+            .timeouts
+            .get(strikes.saturating_sub(1))
+            .or(self.timeouts.last())?;
+        Some(ModerationAction::Timeout(*timeout))
+    }
+}
+
+/// An action a [`ModerationPipeline`] takes on a flagged message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ModerationAction {
+    /// Delete the message, with [`Client::delete_chat_message`]
+    Delete,
+
+    /// Time the sender out, with a timed [`Client::ban_user`]
+    Timeout(Duration),
+
+    /// Ban the sender permanently, with [`Client::ban_user`]
+    Ban,
+}
+
+/// An action of a [`ModerationPipeline`] that failed.
+#[derive(Debug)]
+pub struct ModerationFailure<E>
+where
+    E: Display + Debug,
+{
+    /// Id of the flagged message
+    pub message_id: String,
+
+    /// Nickname of the sender, used as their username for timeouts and bans
+    pub nick_name: String,
+
+    /// The action that failed
+    pub action: ModerationAction,
+
+    /// Why it failed, e.g. an api error of
+    /// [`ErrorStatus::MissingChatPermission`](crate::ErrorStatus::MissingChatPermission) when the
+    /// user isn't a moderator of the channel
+    pub error: ChannelCommandError<E>,
+}
+
+/// Takes moderation actions on the messages of a chat stream that match a [`ContentFilter`].
+///
+/// Each flagged message counts as a strike against its sender. The message is deleted, and the
+/// sender timed out for longer on each strike, or banned once they reach
+/// [`ban_after`](ModerationConfig::ban_after) strikes. Strikes expire after
+/// [`strike_ttl`](ModerationConfig::strike_ttl), measured with the client's clock. Messages
+/// without a sender, e.g. some events, are left alone.
+///
+/// Timeouts and bans go by the sender's nickname, so senders whose nickname differs from their
+/// username fail with [`ChannelCommandError::UnknownUser`]. Failed actions don't stop the
+/// pipeline, they are sent to the receiver returned by [`ModerationPipeline::new`].
+pub struct ModerationPipeline<A>
+where
+    A: AccessTokenProvider,
+{
+    client: Client<A>,
+    channel_id: ChannelId,
+    filter: ContentFilter,
+    config: ModerationConfig,
+    strikes: Strikes,
+    failures: mpsc::Sender<ModerationFailure<A::Error>>,
+}
+
+impl<A> fmt::Debug for ModerationPipeline<A>
+where
+    A: AccessTokenProvider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModerationPipeline")
+            .field("channel_id", &self.channel_id)
+            .field("filter", &self.filter)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<A> ModerationPipeline<A>
+where
+    A: AccessTokenProvider + Clone,
+{
+    /// Creates a pipeline acting in the given channel as the client's user, along with the
+    /// receiver of failed actions. A bounded number of failures wait to be received, further ones
+    /// are dropped until there is room.
+    pub fn new(
+        client: &Client<A>,
+        channel_id: impl Into<ChannelId>,
+        filter: ContentFilter,
+        config: ModerationConfig,
+    ) -> (Self, mpsc::Receiver<ModerationFailure<A::Error>>) {
+        let (failures, receiver) = mpsc::channel(FAILURES_BUFFER);
+        let pipeline = Self {
+            client: client.clone(),
+            channel_id: channel_id.into(),
+            filter,
+            strikes: Strikes::new(&config),
+            config,
+            failures,
+        };
+        (pipeline, receiver)
+    }
+
+    /// Moderates every message of the stream, until it ends or returns an error.
+    pub async fn run<S, E>(mut self, messages: S) -> Result<(), E>
+    where
+        S: Stream<Item = Result<ChatMessage, E>>,
+    {
+        let mut messages = std::pin::pin!(messages);
+        while let Some(message) = messages.next().await {
+            self.moderate(&message?).await;
+        }
+        Ok(())
+    }
+
+    /// Checks a single message, and takes the configured actions if it matched any rule.
+    ///
+    /// Returns the actions attempted, or in [`dry_run`](ModerationConfig::dry_run) mode the ones
+    /// that would have been.
+    pub async fn moderate(&mut self, message: &ChatMessage) -> Vec<ModerationAction> {
+        let Some(sender_id) = message.sender_id else {
+            return Vec::new();
+        };
+        let verdict = self.filter.check(message);
+        if verdict.is_clean() {
+            return Vec::new();
+        }
+
+        let strikes = self
+            .strikes
+            .add(sender_id, self.client.pipeline.clock.now());
+        let mut actions = Vec::new();
+        if self.config.delete_messages {
+            actions.push(ModerationAction::Delete);
+        }
+        actions.extend(self.config.escalation(strikes));
+
+        let message_id = &message.message_id;
+        let nick_name = &message.nick_name;
+        let matched_rules = &verdict.matched_rules;
+        for &action in &actions {
+            if self.config.dry_run {
+                info!(
+                    ?action,
+                    ?message_id,
+                    ?nick_name,
+                    ?matched_rules,
+                    "dry run, skipping moderation action"
+                );
+                continue;
+            }
+
+            debug!(
+                ?action,
+                ?message_id,
+                ?nick_name,
+                ?matched_rules,
+                "moderating"
+            );
+            if let Err(error) = self.act(action, message, sender_id).await {
+                warn!(?error, ?action, ?message_id, "moderation action failed");
+                self.report(ModerationFailure {
+                    message_id: message_id.clone(),
+                    nick_name: nick_name.clone(),
+                    action,
+                    error,
+                });
+            }
+        }
+        actions
+    }
+
+    async fn act(
+        &self,
+        action: ModerationAction,
+        message: &ChatMessage,
+        sender_id: i64,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        let channel_id = self.channel_id.clone();
+        let username = message.nick_name.as_str();
+        match action {
+            ModerationAction::Delete => self
+                .client
+                .delete_chat_message(
+                    channel_id,
+                    &message.message_id,
+                    UserId::new(sender_id.to_string()),
+                )
+                .await
+                .map_err(ChannelCommandError::Request),
+            ModerationAction::Timeout(duration) => {
+                self.client
+                    .ban_user(channel_id, username, Some(duration))
+                    .await
+            }
+            ModerationAction::Ban => self.client.ban_user(channel_id, username, None).await,
+        }
+    }
+
+    fn report(&mut self, failure: ModerationFailure<A::Error>) {
+        if let Err(err) = self.failures.try_send(failure) {
+            if err.is_full() {
+                warn!("moderation failures aren't being received, dropping one");
+            }
+        }
+    }
+}
+
+/// Unexpired strikes by sender id, bounded to a capacity
+#[derive(Debug)]
+struct Strikes {
+    capacity: usize,
+    ttl: Duration,
+
+    /// Most strikes kept per user, further strikes wouldn't escalate any more
+    max_per_user: usize,
+    users: HashMap<i64, VecDeque<Instant>>,
+}
+
+impl Strikes {
+    fn new(config: &ModerationConfig) -> Self {
+        Self {
+            capacity: config.capacity.max(1),
+            ttl: config.strike_ttl,
+            max_per_user: config
+                .ban_after
+                .unwrap_or_default()
+                .max(config.timeouts.len())
+                .max(1),
+            users: HashMap::new(),
+        }
+    }
+
+    /// Adds a strike against the user, returning their unexpired strikes including this one.
+    fn add(&mut self, user: i64, now: Instant) -> usize {
+        let ttl = self.ttl;
+        let expired = |strike: &Instant| now.saturating_duration_since(*strike) >= ttl;
+
+        if !self.users.contains_key(&user) && self.users.len() >= self.capacity {
+            self.users
+                .retain(|_, strikes| strikes.back().is_some_and(|last| !expired(last)));
+            // Still full, so forget the user whose last strike is the oldest
+            if self.users.len() >= self.capacity {
+                if let Some(oldest) = self
+                    .users
+                    .iter()
+                    .min_by_key(|(_, strikes)| strikes.back().copied())
+                    .map(|(id, _)| *id)
+                {
+                    self.users.remove(&oldest);
+                }
+            }
+        }
+
+        let strikes = self.users.entry(user).or_default();
+        while strikes.front().is_some_and(expired) {
+            strikes.pop_front();
+        }
+        strikes.push_back(now);
+        if strikes.len() > self.max_per_user {
+            strikes.pop_front();
+        }
+        strikes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AccessTokenExpired, AccessTokenOnly, AuthenticatedRequestError, ErrorStatus, MockClock,
+        ScriptedTransport,
+    };
+    use futures::stream;
+    use reqwest::{header, Method, StatusCode};
+    use std::convert::Infallible;
+
+    #[test]
+    fn normalization() {
+        let cases = [
+            ("Hello World", "hello world"),
+            // Zero-width characters inside and between letters
+            (
+                "b\u{200B}a\u{200C}d\u{200D}w\u{2060}o\u{FEFF}r\u{00AD}d",
+                "badword",
+            ),
+            // Precomposed and combining diacritics
+            ("Bàd Wörd", "bad word"),
+            ("ba\u{0301}d", "bad"),
+            // Cyrillic and greek lookalikes mixed with latin
+            ("b\u{0430}d w\u{043E}rd", "bad word"),
+            ("\u{0392}\u{0391}D", "bad"),
+            // Leetspeak, keeping plain numbers and trailing punctuation
+            ("b4d w0rd", "bad word"),
+            ("b@d $hit!", "bad shit"),
+            ("bad! 2024", "bad 2024"),
+            // Separators and repeated letters
+            ("bad-word...", "bad word"),
+            ("baaaaad good", "bad good"),
+            ("", ""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize(input), expected, "normalizing {:?}", input);
+        }
+    }
+
+    #[test]
+    fn blocked_words() {
+        let filter = ContentFilter::new().block_words(["bad", "Bad Phrase", "   "]);
+
+        assert!(filter.check_text("all good here").is_clean());
+        assert!(filter.check_text("badger").is_clean());
+        assert_eq!(
+            filter.check_text("that's B\u{200B}4D").matched_rules,
+            [RuleId::BlockedWord("bad".to_string())]
+        );
+        assert_eq!(
+            filter.check_text("a bad  phrase").matched_rules,
+            [
+                RuleId::BlockedWord("bad".to_string()),
+                RuleId::BlockedWord("Bad Phrase".to_string()),
+            ]
+        );
+        assert_eq!(
+            filter.check_text("a phrase bad").matched_rules,
+            [RuleId::BlockedWord("bad".to_string())]
+        );
+    }
+
+    #[test]
+    fn hyperlinks() {
+        for text in [
+            "see https://example.com/path",
+            "go to www.example.org",
+            "(trovo.live/someone)",
+            "EXAMPLE.COM",
+            "exam\u{200B}ple.com",
+        ] {
+            assert!(contains_hyperlink(text), "{:?}", text);
+        }
+        for text in [
+            "e.g. this",
+            "version 1.2.3",
+            "end of sentence.next",
+            "no links",
+        ] {
+            assert!(!contains_hyperlink(text), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn caps_and_repeats() {
+        let filter = ContentFilter::new()
+            .excessive_caps(10, 0.7)
+            .max_repeated_chars(4);
+
+        assert!(filter.check_text("OK").is_clean());
+        assert!(filter.check_text("This Is Fine Really").is_clean());
+        assert_eq!(
+            filter.check_text("WHY IS EVERYONE SHOUTING").matched_rules,
+            [RuleId::ExcessiveCaps]
+        );
+        assert!(filter.check_text("nooo").is_clean());
+        assert!(filter.check_text("a     b").is_clean());
+        assert_eq!(
+            filter.check_text("no\u{200B}oo\u{200B}oo").matched_rules,
+            [RuleId::RepeatedCharacters]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_adapter() {
+        let messages: Vec<ChatMessage> =
+            serde_json::from_str(include_str!("../../fixtures/monetization_session.json")).unwrap();
+        let filter = ContentFilter::new().block_words(["hello", "followed"]);
+
+        let annotated: Vec<_> = ModerationStream::new(
+            stream::iter(messages.into_iter().map(Ok::<_, Infallible>)),
+            filter.clone(),
+        )
+        .collect()
+        .await;
+        assert_eq!(annotated.len(), 8);
+
+        let messages: Vec<ChatMessage> =
+            serde_json::from_str(include_str!("../../fixtures/monetization_session.json")).unwrap();
+        let flagged: Vec<_> = ModerationStream::new(
+            stream::iter(messages.into_iter().map(Ok::<_, Infallible>)),
+            filter,
+        )
+        .flagged_only()
+        .map(|moderated| moderated.unwrap().message.message_id)
+        .collect()
+        .await;
+        assert_eq!(flagged, ["1", "7"]);
+    }
+
+    const DELETED: &str = "{}";
+    const SUCCESS: &str = r#"{"is_success": true, "display_msg": ""}"#;
+
+    fn pipeline(
+        config: ModerationConfig,
+    ) -> (
+        ModerationPipeline<AccessTokenOnly>,
+        mpsc::Receiver<ModerationFailure<AccessTokenExpired>>,
+        ScriptedTransport,
+        MockClock,
+    ) {
+        let transport = ScriptedTransport::new();
+        let clock = MockClock::new();
+        let client = Client::builder()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build(AccessTokenOnly::new("client-id", "access-token"))
+            .unwrap();
+        let filter = ContentFilter::new().block_words(["spam"]);
+        let (pipeline, failures) = ModerationPipeline::new(&client, "100000", filter, config);
+        (pipeline, failures, transport, clock)
+    }
+
+    fn chat(message_id: &str, sender_id: i64, nick_name: &str, content: &str) -> ChatMessage {
+        serde_json::from_value(serde_json::json!({
+            "type": 0,
+            "content": content,
+            "nick_name": nick_name,
+            "message_id": message_id,
+            "sender_id": sender_id,
+            "send_time": 1700000000,
+        }))
+        .unwrap()
+    }
+
+    /// The api calls made, as the deleted message path or the channel command
+    fn calls(transport: &ScriptedTransport) -> Vec<String> {
+        transport
+            .requests()
+            .iter()
+            .map(|request| {
+                if request.method == Method::DELETE {
+                    request
+                        .url
+                        .path()
+                        .replace("/openplatform/channels/100000/", "")
+                } else {
+                    request.json()["command"].as_str().unwrap().to_string()
+                }
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn escalating_actions() {
+        let (mut pipeline, _failures, transport, _) = pipeline(ModerationConfig {
+            timeouts: vec![Duration::from_secs(60), Duration::from_secs(600)],
+            ban_after: Some(4),
+            ..ModerationConfig::default()
+        });
+        for _ in 0..5 {
+            transport
+                .push(StatusCode::OK, DELETED)
+                .push(StatusCode::OK, SUCCESS);
+        }
+
+        assert_eq!(
+            pipeline.moderate(&chat("1", 1, "Spammer", "spam")).await,
+            [
+                ModerationAction::Delete,
+                ModerationAction::Timeout(Duration::from_secs(60))
+            ]
+        );
+        assert!(pipeline
+            .moderate(&chat("2", 1, "Spammer", "hello"))
+            .await
+            .is_empty());
+        for message_id in ["3", "4", "5"] {
+            pipeline
+                .moderate(&chat(message_id, 1, "Spammer", "SPAM"))
+                .await;
+        }
+        pipeline
+            .moderate(&chat("6", 2, "Other", "s p a m spam"))
+            .await;
+
+        assert_eq!(
+            calls(&transport),
+            [
+                "messages/1/users/1",
+                "/ban spammer 60",
+                "messages/3/users/1",
+                "/ban spammer 600",
+                "messages/4/users/1",
+                "/ban spammer 600",
+                "messages/5/users/1",
+                "/ban spammer",
+                "messages/6/users/2",
+                "/ban other 60",
+            ]
+        );
+        assert!(transport
+            .requests()
+            .iter()
+            .all(|request| request.headers[header::AUTHORIZATION] == "OAuth access-token"));
+    }
+
+    #[tokio::test]
+    async fn strikes_decay() {
+        let (mut pipeline, _failures, transport, clock) = pipeline(ModerationConfig {
+            delete_messages: false,
+            strike_ttl: Duration::from_secs(60),
+            ..ModerationConfig::default()
+        });
+        for _ in 0..3 {
+            transport.push(StatusCode::OK, SUCCESS);
+        }
+
+        pipeline.moderate(&chat("1", 1, "spammer", "spam")).await;
+        clock.advance(Duration::from_secs(30));
+        pipeline.moderate(&chat("2", 1, "spammer", "spam")).await;
+        // The first strike has expired, the second hasn't
+        clock.advance(Duration::from_secs(30));
+        pipeline.moderate(&chat("3", 1, "spammer", "spam")).await;
+
+        assert_eq!(
+            calls(&transport),
+            ["/ban spammer 60", "/ban spammer 600", "/ban spammer 600"]
+        );
+    }
+
+    #[test]
+    fn strikes_capacity() {
+        let config = ModerationConfig {
+            capacity: 2,
+            ban_after: Some(3),
+            ..ModerationConfig::default()
+        };
+        let mut strikes = Strikes::new(&config);
+        let now = Instant::now();
+        let at = |secs| now + Duration::from_secs(secs);
+
+        assert_eq!(strikes.add(1, at(0)), 1);
+        assert_eq!(strikes.add(2, at(1)), 1);
+        assert_eq!(strikes.add(1, at(2)), 2);
+        // User 2 has the oldest last strike, so they are forgotten to make room
+        assert_eq!(strikes.add(3, at(3)), 1);
+        assert_eq!(strikes.add(2, at(4)), 1);
+        assert_eq!(strikes.users.len(), 2);
+        assert!(!strikes.users.contains_key(&1));
+
+        // Strikes per user are capped at what still escalates
+        for secs in 5..10 {
+            strikes.add(3, at(secs));
+        }
+        assert_eq!(strikes.users[&3].len(), 3);
+    }
+
+    #[tokio::test]
+    async fn dry_run() {
+        let (mut pipeline, _failures, transport, _) = pipeline(ModerationConfig {
+            dry_run: true,
+            ban_after: Some(2),
+            ..ModerationConfig::default()
+        });
+
+        pipeline.moderate(&chat("1", 1, "spammer", "spam")).await;
+        assert_eq!(
+            pipeline.moderate(&chat("2", 1, "spammer", "spam")).await,
+            [ModerationAction::Delete, ModerationAction::Ban]
+        );
+        assert!(transport.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failures_reported() {
+        let (pipeline, failures, transport, _) = pipeline(ModerationConfig::default());
+        transport
+            .push(
+                StatusCode::FORBIDDEN,
+                r#"{"status": 11707, "message": "no permission"}"#,
+            )
+            .push(
+                StatusCode::OK,
+                r#"{"is_success": false, "display_msg": "You are not a moderator"}"#,
+            )
+            .push(StatusCode::OK, DELETED)
+            .push(StatusCode::OK, SUCCESS);
+
+        let messages = [
+            chat("1", 1, "spammer", "spam"),
+            chat("2", 2, "other", "fine"),
+            chat("3", 2, "other", "spam"),
+        ];
+        pipeline
+            .run(stream::iter(messages.into_iter().map(Ok::<_, Infallible>)))
+            .await
+            .unwrap();
+        assert_eq!(
+            calls(&transport),
+            [
+                "messages/1/users/1",
+                "/ban spammer 60",
+                "messages/3/users/2",
+                "/ban other 60"
+            ]
+        );
+
+        let failures: Vec<_> = failures.collect().await;
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].message_id, "1");
+        assert_eq!(failures[0].action, ModerationAction::Delete);
+        match &failures[0].error {
+            ChannelCommandError::Request(AuthenticatedRequestError::ApiError(err)) => {
+                assert_eq!(err.status, ErrorStatus::MissingChatPermission);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(
+            failures[1].action,
+            ModerationAction::Timeout(Duration::from_secs(60))
+        );
+        assert!(matches!(
+            &failures[1].error,
+            ChannelCommandError::Rejected { display_msg } if display_msg == "You are not a moderator"
+        ));
+    }
+}
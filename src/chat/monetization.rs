@@ -0,0 +1,351 @@
+//! # Monetization
+//!
+//! Spells and gift subs picked out of a chat message stream, e.g. for donation trackers.
+//!
+//! ```no_run
+//! use futures::prelude::*;
+//! use trovo::chat::monetization::{MonetizationItem, MonetizationStream};
+//!
+//! # async fn run(client: trovo::Client<trovo::ClientId>) -> Result<(), Box<dyn std::error::Error>> {
+//! let messages = client.chat_messages_for_channel("100000").await?;
+//! let mut events = MonetizationStream::new(messages);
+//! while let Some(item) = events.next().await {
+//!     if let MonetizationItem::Event(event) = item? {
+//!         println!("{} sent {} {:?}", event.sender, event.value.amount, event.value.currency);
+//!     }
+//!     println!("totals so far: {:?}", events.snapshot());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    chat::{ChatMessage, ChatMessageType},
+    UserId,
+};
+use futures::{ready, Stream};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Currency that a monetization event was paid in.
+///
+/// Serialized as its name, e.g. `"Mana"`, so that it can be used as a json map key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+#[non_exhaustive]
+pub enum Currency {
+    /// Mana, the free currency earned by watching
+    Mana,
+
+    /// Elixir, the paid currency
+    Elixir,
+
+    /// Gifted subscriptions, counted per sub as chat doesn't include their price
+    Subscriptions,
+
+    /// Any other `value_type` sent with a spell
+    Other(String),
+}
+
+impl From<&str> for Currency {
+    fn from(value_type: &str) -> Self {
+        if value_type.eq_ignore_ascii_case("mana") {
+            Self::Mana
+        } else if value_type.eq_ignore_ascii_case("elixir") {
+            Self::Elixir
+        } else if value_type.eq_ignore_ascii_case("subscriptions") {
+            Self::Subscriptions
+        } else {
+            Self::Other(value_type.to_string())
+        }
+    }
+}
+
+impl From<String> for Currency {
+    fn from(value_type: String) -> Self {
+        Self::from(value_type.as_str())
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        match currency {
+            Currency::Mana => "Mana".to_string(),
+            Currency::Elixir => "Elixir".to_string(),
+            Currency::Subscriptions => "Subscriptions".to_string(),
+            Currency::Other(value_type) => value_type,
+        }
+    }
+}
+
+/// An amount of a [`Currency`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CurrencyAmount {
+    /// Currency of the amount
+    pub currency: Currency,
+
+    /// Amount in the currency's smallest unit
+    pub amount: u64,
+}
+
+/// What was sent in a [`MonetizationEvent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MonetizationKind {
+    /// A spell or custom spell was cast
+    Spell {
+        /// Name of the spell, e.g. `Cheers`
+        name: String,
+
+        /// Number of spells cast at once
+        count: u64,
+
+        /// Id of the spell, if sent
+        gift_id: Option<i64>,
+
+        /// Whether it was a custom spell of the channel
+        custom: bool,
+    },
+
+    /// Subscriptions were gifted to the channel's viewers
+    GiftSub {
+        /// Number of subscriptions gifted
+        count: u64,
+    },
+}
+
+/// A spell or gift sub sent in chat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonetizationEvent {
+    /// Display name of the sender
+    pub sender: String,
+
+    /// User id of the sender, if present
+    pub sender_id: Option<UserId>,
+
+    /// What was sent
+    pub kind: MonetizationKind,
+
+    /// Total value of the event, e.g. the unit price of a spell times the number cast
+    pub value: CurrencyAmount,
+}
+
+/// Item produced by a [`MonetizationStream`].
+#[derive(Debug)]
+pub enum MonetizationItem {
+    /// A parsed spell or gift sub
+    Event(MonetizationEvent),
+
+    /// A spell or gift sub message that couldn't be parsed, so its value isn't in the totals
+    Unparsed(ChatMessage),
+}
+
+impl MonetizationItem {
+    /// Picks the monetization event out of a chat message.
+    ///
+    /// Returns `None` for messages that aren't spells or gift subs. The detailed gift sub
+    /// messages, one per recipient, are skipped too as they repeat the summary gift sub message.
+    pub fn from_message(message: ChatMessage) -> Option<Self> {
+        Some(match MonetizationEvent::parse(&message)? {
+            Some(event) => Self::Event(event),
+            None => Self::Unparsed(message),
+        })
+    }
+}
+
+impl MonetizationEvent {
+    /// Parses a spell or gift sub message, `None` for other messages and `Some(None)` if it
+    /// couldn't be parsed.
+    pub(crate) fn parse(message: &ChatMessage) -> Option<Option<Self>> {
+        let parsed = match message.type_ {
+            ChatMessageType::Spell => parse_spell(message, false),
+            ChatMessageType::CustomSpell => parse_spell(message, true),
+            ChatMessageType::GiftSub => parse_gift_sub(message),
+            _ => return None,
+        };
+        Some(parsed.map(|(kind, value)| Self {
+            sender: message.nick_name.clone(),
+            sender_id: message.sender_user_id(),
+            kind,
+            value,
+        }))
+    }
+}
+
+/// Spell messages have the spell name and count in the content, and the unit price and currency
+/// in the content data.
+fn parse_spell(message: &ChatMessage, custom: bool) -> Option<(MonetizationKind, CurrencyAmount)> {
+    #[derive(Deserialize)]
+    struct SpellContent {
+        gift: String,
+        num: u64,
+    }
+
+    let content: SpellContent = serde_json::from_str(&message.content).ok()?;
+    let unit_value = match message.content_data.get("gift_value")? {
+        serde_json::Value::Number(value) => value.as_u64()?,
+        serde_json::Value::String(value) => value.trim().parse().ok()?,
+        _ => return None,
+    };
+    let currency = Currency::from(message.content_data.get("value_type")?.as_str()?);
+    let gift_id = message.content_data.get("gift_id").and_then(|id| match id {
+        serde_json::Value::Number(id) => id.as_i64(),
+        serde_json::Value::String(id) => id.parse().ok(),
+        _ => None,
+    });
+
+    Some((
+        MonetizationKind::Spell {
+            name: content.gift,
+            count: content.num,
+            gift_id,
+            custom,
+        },
+        CurrencyAmount {
+            currency,
+            amount: unit_value.checked_mul(content.num)?,
+        },
+    ))
+}
+
+/// Gift sub messages only describe the gift in text, the first number being how many were sent.
+fn parse_gift_sub(message: &ChatMessage) -> Option<(MonetizationKind, CurrencyAmount)> {
+    let count: u64 = message
+        .content
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|digits| !digits.is_empty())?
+        .parse()
+        .ok()?;
+    Some((
+        MonetizationKind::GiftSub { count },
+        CurrencyAmount {
+            currency: Currency::Subscriptions,
+            amount: count,
+        },
+    ))
+}
+
+/// Adapts a chat message stream into a stream of [`MonetizationItem`]s, keeping running totals
+/// per currency.
+///
+/// Messages that aren't spells or gift subs are skipped, see [`MonetizationItem::from_message`].
+/// Errors from the underlying stream are passed through.
+#[derive(Debug)]
+pub struct MonetizationStream<S> {
+    messages: S,
+    totals: BTreeMap<Currency, u64>,
+}
+
+impl<S> MonetizationStream<S> {
+    /// Wraps a stream of chat messages, e.g. a [`ChatMessageStream`](crate::chat::ChatMessageStream).
+    pub fn new(messages: S) -> Self {
+        Self {
+            messages,
+            totals: BTreeMap::new(),
+        }
+    }
+
+    /// Totals per currency of the events produced so far. Unparsed messages aren't included.
+    pub fn snapshot(&self) -> BTreeMap<Currency, u64> {
+        self.totals.clone()
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.messages
+    }
+}
+
+impl<S, E> Stream for MonetizationStream<S>
+where
+    S: Stream<Item = Result<ChatMessage, E>> + Unpin,
+{
+    type Item = Result<MonetizationItem, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match ready!(Pin::new(&mut self.messages).poll_next(cx)) {
+                Some(Ok(message)) => message,
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => return Poll::Ready(None),
+            };
+            if let Some(item) = MonetizationItem::from_message(message) {
+                if let MonetizationItem::Event(event) = &item {
+                    let total = self.totals.entry(event.value.currency.clone()).or_default();
+                    *total = total.saturating_add(event.value.amount);
+                }
+                return Poll::Ready(Some(Ok(item)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{stream, StreamExt};
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn session_totals() {
+        let messages: Vec<ChatMessage> =
+            serde_json::from_str(include_str!("../../fixtures/monetization_session.json")).unwrap();
+        let mut events =
+            MonetizationStream::new(stream::iter(messages.into_iter().map(Ok::<_, Infallible>)));
+
+        let mut items = Vec::new();
+        while let Some(item) = events.next().await {
+            items.push(item.unwrap());
+        }
+
+        let parsed: Vec<_> = items
+            .iter()
+            .filter_map(|item| match item {
+                MonetizationItem::Event(event) => Some(event),
+                MonetizationItem::Unparsed(_) => None,
+            })
+            .collect();
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(
+            parsed[0],
+            &MonetizationEvent {
+                sender: "alice".to_string(),
+                sender_id: Some(UserId::new("101")),
+                kind: MonetizationKind::Spell {
+                    name: "Cheers".to_string(),
+                    count: 2,
+                    gift_id: Some(1),
+                    custom: false,
+                },
+                value: CurrencyAmount {
+                    currency: Currency::Mana,
+                    amount: 200,
+                },
+            }
+        );
+        assert_eq!(parsed[3].kind, MonetizationKind::GiftSub { count: 5 });
+
+        // The malformed spell is surfaced rather than dropped
+        let unparsed: Vec<_> = items
+            .iter()
+            .filter_map(|item| match item {
+                MonetizationItem::Unparsed(message) => Some(&message.message_id),
+                MonetizationItem::Event(_) => None,
+            })
+            .collect();
+        assert_eq!(unparsed, ["6"]);
+
+        assert_eq!(
+            events.snapshot(),
+            BTreeMap::from([
+                (Currency::Mana, 230),
+                (Currency::Elixir, 50),
+                (Currency::Subscriptions, 5),
+            ])
+        );
+    }
+}
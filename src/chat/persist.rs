@@ -0,0 +1,487 @@
+//! # Persist
+//!
+//! Batch chat messages from a stream into a storage backend, retrying failed writes.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use trovo::chat::persist::{persist_chat, ChatSink, PersistConfig};
+//! use trovo::chat::ChatMessage;
+//!
+//! #[derive(Debug)]
+//! struct Database;
+//!
+//! #[async_trait::async_trait]
+//! impl ChatSink for Database {
+//!     type Error = std::io::Error;
+//!
+//!     async fn store(&mut self, batch: &[ChatMessage]) -> Result<(), Self::Error> {
+//!         // insert the batch
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # async fn run(client: trovo::Client<trovo::ClientId>) -> Result<(), Box<dyn std::error::Error>> {
+//! let messages = client.chat_messages_for_channel("100000").await?;
+//! let config = PersistConfig {
+//!     batch_size: 50,
+//!     flush_interval: Duration::from_secs(2),
+//!     ..PersistConfig::default()
+//! };
+//! let progress = persist_chat(messages, Database, config)
+//!     .on_progress(|progress| println!("stored up to {:?}", progress.last_message_id))
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{chat::ChatMessage, chat::PersistError, Clock, TokioClock};
+use futures::{
+    future::{self, Either},
+    Stream, StreamExt,
+};
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+#[cfg(feature = "jsonl-sink")]
+pub use jsonl::*;
+
+/// Storage backend for chat messages, see [`persist_chat`].
+#[async_trait::async_trait]
+pub trait ChatSink: Send {
+    /// Error returned when storing fails
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Stores a batch of messages, in the order they were received.
+    ///
+    /// A failed batch is retried in full, so sinks should store it atomically or tolerate
+    /// duplicates, e.g. by ignoring already stored message ids.
+    async fn store(&mut self, batch: &[ChatMessage]) -> Result<(), Self::Error>;
+}
+
+/// Settings for [`persist_chat`].
+#[derive(Debug, Clone)]
+pub struct PersistConfig {
+    /// Maximum number of messages stored at once. Defaults to 100.
+    pub batch_size: usize,
+
+    /// Maximum time a message waits for its batch to fill up before being stored anyway.
+    /// Defaults to 5 seconds.
+    pub flush_interval: Duration,
+
+    /// Number of times a failed batch is retried before giving up. Defaults to 5.
+    pub max_retries: u32,
+
+    /// Wait before the first retry, doubling after each further failure. Defaults to 500ms.
+    pub initial_backoff: Duration,
+
+    /// Upper limit of the wait between retries. Defaults to 30 seconds.
+    pub max_backoff: Duration,
+
+    /// Source of time for flush intervals and backoff. Defaults to [`TokioClock`].
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Default for PersistConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            flush_interval: Duration::from_secs(5),
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            clock: Arc::new(TokioClock),
+        }
+    }
+}
+
+/// How far [`persist_chat`] got, for resuming after a failure.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PersistProgress {
+    /// Number of messages stored
+    pub stored: u64,
+
+    /// Number of batches stored
+    pub batches: u64,
+
+    /// Id of the last message stored, if any
+    pub last_message_id: Option<String>,
+}
+
+type ProgressCallback = Box<dyn FnMut(&PersistProgress) + Send>;
+
+/// Stores the messages of a chat stream in a sink, in batches of up to
+/// [`batch_size`](PersistConfig::batch_size) messages or after
+/// [`flush_interval`](PersistConfig::flush_interval), whichever comes first.
+///
+/// Configure progress reporting on the returned [`Persist`], then call [`Persist::run`].
+pub fn persist_chat<S, K, E>(messages: S, sink: K, config: PersistConfig) -> Persist<S, K>
+where
+    S: Stream<Item = Result<ChatMessage, E>>,
+    K: ChatSink,
+{
+    Persist {
+        messages,
+        sink,
+        config,
+        progress: None,
+    }
+}
+
+/// Persisting of a chat stream, created via [`persist_chat`].
+#[must_use = "persisting does nothing until `run` is awaited"]
+pub struct Persist<S, K> {
+    messages: S,
+    sink: K,
+    config: PersistConfig,
+    progress: Option<ProgressCallback>,
+}
+
+impl<S, K> std::fmt::Debug for Persist<S, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Persist")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, K, E> Persist<S, K>
+where
+    S: Stream<Item = Result<ChatMessage, E>>,
+    K: ChatSink,
+{
+    /// Calls `progress` after each batch is stored.
+    pub fn on_progress(mut self, progress: impl FnMut(&PersistProgress) + Send + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Stores messages until the stream ends, returning the final progress.
+    ///
+    /// If the stream fails, the messages received before the error are stored first. If a batch
+    /// still fails after all retries, its messages aren't stored and persisting stops. Either way
+    /// the error includes the progress so far, for resuming after the last stored message.
+    pub async fn run(mut self) -> Result<PersistProgress, PersistError<E, K::Error>> {
+        let batch_size = self.config.batch_size.max(1);
+        let clock = self.config.clock.clone();
+        let mut messages = std::pin::pin!(self.messages);
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut flush_timer: Option<Pin<Box<dyn Future<Output = ()> + Send + '_>>> = None;
+        let mut progress = PersistProgress::default();
+
+        loop {
+            let next = match &mut flush_timer {
+                Some(timer) => match future::select(messages.next(), timer).await {
+                    Either::Left((next, _)) => Some(next),
+                    Either::Right(_) => None,
+                },
+                None => Some(messages.next().await),
+            };
+
+            let end = match next {
+                Some(Some(Ok(message))) => {
+                    if batch.is_empty() {
+                        flush_timer =
+                            Some(clock.sleep_until(clock.now() + self.config.flush_interval));
+                    }
+                    batch.push(message);
+                    if batch.len() < batch_size {
+                        continue;
+                    }
+                    None
+                }
+                Some(Some(Err(err))) => Some(Err(err)),
+                Some(None) => Some(Ok(())),
+                // Flush interval elapsed
+                None => None,
+            };
+
+            flush_timer = None;
+            if !batch.is_empty() {
+                if let Err((source, attempts)) =
+                    store_with_retries(&mut self.sink, &batch, &self.config).await
+                {
+                    return Err(PersistError::Sink {
+                        source,
+                        attempts,
+                        progress,
+                    });
+                }
+                progress.stored += batch.len() as u64;
+                progress.batches += 1;
+                progress.last_message_id = batch.last().map(|message| message.message_id.clone());
+                batch.clear();
+                if let Some(callback) = &mut self.progress {
+                    callback(&progress);
+                }
+            }
+
+            match end {
+                Some(Ok(())) => return Ok(progress),
+                Some(Err(source)) => return Err(PersistError::Stream { source, progress }),
+                None => {}
+            }
+        }
+    }
+}
+
+/// Stores a batch, retrying with exponential backoff. Returns the last error and the number of
+/// attempts made if every attempt failed.
+async fn store_with_retries<K: ChatSink>(
+    sink: &mut K,
+    batch: &[ChatMessage],
+    config: &PersistConfig,
+) -> Result<(), (K::Error, u32)> {
+    let mut attempts = 0;
+    let mut backoff = config.initial_backoff;
+    loop {
+        attempts += 1;
+        match sink.store(batch).await {
+            Ok(()) => return Ok(()),
+            Err(err) if attempts > config.max_retries => return Err((err, attempts)),
+            Err(err) => {
+                warn!(?err, "failed to store chat messages, retrying...");
+                config.clock.sleep(backoff).await;
+                backoff = (backoff * 2).min(config.max_backoff);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "jsonl-sink")]
+mod jsonl {
+    use super::ChatSink;
+    use crate::chat::ChatMessage;
+    use std::{io, path::Path};
+    use tokio::{
+        fs::{File, OpenOptions},
+        io::AsyncWriteExt,
+    };
+
+    /// A [`ChatSink`] appending messages to a file as json lines, one message per line.
+    ///
+    /// Each batch is written with a single write, but a failed write may still leave part of a
+    /// batch behind, which is then written again by the retry. Readers should skip duplicate
+    /// message ids and a truncated last line.
+    #[derive(Debug)]
+    pub struct JsonlSink {
+        file: File,
+    }
+
+    impl JsonlSink {
+        /// Opens the file for appending, creating it if needed.
+        pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            Ok(Self { file })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ChatSink for JsonlSink {
+        type Error = io::Error;
+
+        async fn store(&mut self, batch: &[ChatMessage]) -> io::Result<()> {
+            let mut lines = Vec::new();
+            for message in batch {
+                serde_json::to_writer(&mut lines, message)?;
+                lines.push(b'\n');
+            }
+            self.file.write_all(&lines).await?;
+            self.file.flush().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+    use futures::{channel::mpsc, stream};
+    use std::{
+        convert::Infallible,
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    fn message(id: usize) -> ChatMessage {
+        serde_json::from_value(serde_json::json!({
+            "type": 0,
+            "content": "hi",
+            "nick_name": "someone",
+            "message_id": id.to_string(),
+            "sender_id": 101,
+            "send_time": 1700000000,
+        }))
+        .unwrap()
+    }
+
+    fn ids(messages: &[ChatMessage]) -> Vec<String> {
+        messages.iter().map(|m| m.message_id.clone()).collect()
+    }
+
+    /// In-memory sink failing the calls whose (1-based) number is listed.
+    #[derive(Debug, Clone, Default)]
+    struct FlakySink {
+        fail_calls: Vec<usize>,
+        calls: Arc<Mutex<usize>>,
+        batches: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ChatSink for FlakySink {
+        type Error = io::Error;
+
+        async fn store(&mut self, batch: &[ChatMessage]) -> io::Result<()> {
+            let call = {
+                let mut calls = self.calls.lock().unwrap();
+                *calls += 1;
+                *calls
+            };
+            if self.fail_calls.contains(&call) {
+                return Err(io::Error::other("database unavailable"));
+            }
+            self.batches.lock().unwrap().push(ids(batch));
+            Ok(())
+        }
+    }
+
+    fn config() -> PersistConfig {
+        PersistConfig {
+            batch_size: 3,
+            flush_interval: Duration::from_secs(3600),
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            ..PersistConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_in_order() {
+        let sink = FlakySink {
+            fail_calls: vec![1, 3, 4],
+            ..FlakySink::default()
+        };
+        let messages = stream::iter((0..8).map(message).map(Ok::<_, Infallible>));
+        let reported = Arc::new(Mutex::new(Vec::new()));
+
+        let progress = persist_chat(messages, sink.clone(), config())
+            .on_progress({
+                let reported = reported.clone();
+                move |progress| reported.lock().unwrap().push(progress.stored)
+            })
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *sink.batches.lock().unwrap(),
+            [vec!["0", "1", "2"], vec!["3", "4", "5"], vec!["6", "7"]]
+        );
+        assert_eq!(*sink.calls.lock().unwrap(), 6);
+        assert_eq!(*reported.lock().unwrap(), [3, 6, 8]);
+        assert_eq!(
+            progress,
+            PersistProgress {
+                stored: 8,
+                batches: 3,
+                last_message_id: Some("7".to_string()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_exhausted() {
+        let sink = FlakySink {
+            fail_calls: vec![2, 3, 4],
+            ..FlakySink::default()
+        };
+        let messages = stream::iter((0..8).map(message).map(Ok::<_, Infallible>));
+
+        let err = persist_chat(messages, sink.clone(), config())
+            .run()
+            .await
+            .unwrap_err();
+        match err {
+            PersistError::Sink {
+                attempts, progress, ..
+            } => {
+                assert_eq!(attempts, 3);
+                assert_eq!(progress.last_message_id.as_deref(), Some("2"));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(*sink.batches.lock().unwrap(), [vec!["0", "1", "2"]]);
+    }
+
+    #[tokio::test]
+    async fn stream_error_flushes() {
+        let sink = FlakySink::default();
+        let messages = stream::iter([Ok(message(0)), Ok(message(1)), Err("disconnected")]);
+
+        let err = persist_chat(messages, sink.clone(), config())
+            .run()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PersistError::Stream { source: "disconnected", ref progress } if progress.stored == 2
+        ));
+        assert_eq!(*sink.batches.lock().unwrap(), [vec!["0", "1"]]);
+    }
+
+    #[tokio::test]
+    async fn flush_interval() {
+        let clock = MockClock::new();
+        let sink = FlakySink::default();
+        let (sender, receiver) = mpsc::unbounded();
+        sender
+            .unbounded_send(Ok::<_, Infallible>(message(0)))
+            .unwrap();
+        sender.unbounded_send(Ok(message(1))).unwrap();
+
+        let config = PersistConfig {
+            batch_size: 10,
+            flush_interval: Duration::from_secs(5),
+            clock: Arc::new(clock.clone()),
+            ..PersistConfig::default()
+        };
+        let task = tokio::spawn(persist_chat(receiver, sink.clone(), config).run());
+
+        while clock.sleepers() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(sink.batches.lock().unwrap().is_empty());
+
+        clock.advance(Duration::from_secs(5));
+        while sink.batches.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(*sink.batches.lock().unwrap(), [vec!["0", "1"]]);
+
+        drop(sender);
+        assert_eq!(task.await.unwrap().unwrap().stored, 2);
+    }
+
+    #[cfg(feature = "jsonl-sink")]
+    #[tokio::test]
+    async fn jsonl_sink() {
+        let path = std::env::temp_dir().join(format!("trovo-persist-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let sink = JsonlSink::open(&path).await.unwrap();
+        let messages = stream::iter((0..5).map(message).map(Ok::<_, Infallible>));
+        persist_chat(messages, sink, config()).run().await.unwrap();
+
+        let stored: Vec<ChatMessage> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ids(&stored), ["0", "1", "2", "3", "4"]);
+    }
+}
@@ -1,27 +1,72 @@
 use crate::chat::{
-    ChatConnectError, ChatMessage, ChatMessageStreamError, ChatSocketMessage, ChatToken,
+    ChannelInfo, ChatConfig, ChatConnectError, ChatMessage, ChatMessageStreamError,
+    ChatSocketMessage, ChatToken,
 };
+use crate::{timestamp, Clock, TokioClock};
 use async_tungstenite::{
-    tokio::connect_async,
-    tungstenite::{self, Message},
+    tokio::connect_async_with_config,
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        error::CapacityError,
+        http::{header, HeaderValue},
+        protocol::WebSocketConfig,
+        Message,
+    },
 };
 use futures::prelude::*;
-use std::time::Duration;
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 use tokio::{
     select,
-    sync::{mpsc, oneshot},
-    time::sleep,
+    sync::{mpsc, watch},
+    task::JoinHandle,
 };
 use tokio_util::sync::CancellationToken;
 
 const CHAT_MESSAGES_BUFFER: usize = 32;
 const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+const AUTH_NONCE: &str = "authenticate"; // TODO randomly generate?
 
 /// A stream of chat messages
+///
+/// The connection is driven by a spawned task, see [`ChatConnection`] for driving it yourself.
 #[derive(Debug)]
 pub struct ChatMessageStream {
     cancellation_token: CancellationToken,
     messages: mpsc::Receiver<Result<ChatMessage, ChatMessageStreamError>>,
+    stats: Arc<StatsCounters>,
+    channel_info: watch::Receiver<Option<ChannelInfo>>,
+}
+
+/// Counters for a chat connection, see [`ChatMessageStream::stats`] and [`ChatConnection::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ChatStats {
+    /// Messages discarded for being older than
+    /// [`ChatConfig::max_message_age`](crate::chat::ChatConfig::max_message_age)
+    pub stale_discarded: u64,
+}
+
+/// Counters shared between the connection and the stream
+#[derive(Debug, Default)]
+struct StatsCounters {
+    stale_discarded: AtomicU64,
+}
+
+impl StatsCounters {
+    fn snapshot(&self) -> ChatStats {
+        ChatStats {
+            stale_discarded: self.stale_discarded.load(Ordering::Relaxed),
+        }
+    }
 }
 
 impl ChatMessageStream {
@@ -31,47 +76,56 @@ impl ChatMessageStream {
     /// [`Client::chat_messages_for_user`](crate::Client::chat_messages_for_user) for fetching
     /// the token and connecting in one.
     pub async fn connect(chat_token: ChatToken) -> Result<ChatMessageStream, ChatConnectError> {
-        let cancellation_token = CancellationToken::new();
-        let (ws_stream, _) = connect_async("wss://open-chat.trovo.live/chat").await?;
-        let (mut writer, reader) = ws_stream.split();
-        let (socket_messages_sender, socket_messages_receiver) = mpsc::channel(1);
-        let (chat_messages_sender, chat_messages_receiver) = mpsc::channel(CHAT_MESSAGES_BUFFER);
-        let (auth_response_sender, auth_response_receiver) = oneshot::channel();
-
-        let auth_nonce = "authenticate".to_string(); // TODO randomly generate?
-
-        let reader = SocketMessagesReader {
-            reader,
-            cancellation_token: cancellation_token.clone(),
-            auth: (auth_nonce.clone(), Some(auth_response_sender)),
-            chat_messages_sender: chat_messages_sender.clone(),
-            socket_messages_sender,
-            ping: Default::default(),
-        };
-        reader.spawn();
+        Self::connect_with_config(chat_token, ChatConfig::default()).await
+    }
 
-        let msg = serde_json::to_string(&ChatSocketMessage::Auth {
-            nonce: auth_nonce,
-            data: chat_token,
-        })?;
-        writer.send(msg.into()).await?;
+    /// Connect to trovo chat using the given chat token and connection config.
+    pub async fn connect_with_config(
+        chat_token: ChatToken,
+        config: ChatConfig,
+    ) -> Result<ChatMessageStream, ChatConnectError> {
+        let blocking_parse_threshold = config.blocking_parse_threshold;
+        let mut connection = ChatConnection::connect_with_config(chat_token, config).await?;
+        connection.blocking_parse_threshold = Some(blocking_parse_threshold);
+        Ok(Self::spawn(connection))
+    }
 
-        auth_response_receiver
-            .await
-            .map_err(|_| ChatConnectError::SocketClosed)??;
+    /// Drives the connection on a spawned task, forwarding its messages to the stream.
+    fn spawn(mut connection: ChatConnection) -> Self {
+        let cancellation_token = CancellationToken::new();
+        let (chat_messages_sender, chat_messages_receiver) = mpsc::channel(CHAT_MESSAGES_BUFFER);
+        let stats = connection.stats.clone();
+        let channel_info = connection.channel_info.subscribe();
 
-        let writer = SocketMessagesWriter {
-            writer,
-            cancellation_token: cancellation_token.clone(),
-            socket_messages_receiver,
-            chat_messages_sender,
-        };
-        writer.spawn();
+        let cancelled = cancellation_token.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = select! {
+                    _ = cancelled.cancelled() => break,
+                    event = connection.next_event() => event,
+                };
+                let item = match event {
+                    Ok(ChatEvent::Message(message)) => Ok(message),
+                    Err(err) => {
+                        error!(?err, "chat connection errored");
+                        chat_messages_sender.send(Err(err)).await.ok();
+                        break;
+                    }
+                };
+                if chat_messages_sender.send(item).await.is_err() {
+                    // Messages receiver must have been dropped and so we just need to cleanup
+                    break;
+                }
+            }
+            trace!("chat connection task exited");
+        });
 
-        Ok(ChatMessageStream {
+        ChatMessageStream {
             cancellation_token,
             messages: chat_messages_receiver,
-        })
+            stats,
+            channel_info,
+        }
     }
 
     /// Close the chat socket, causing any further calls to `next()` to return `None`.
@@ -80,6 +134,142 @@ impl ChatMessageStream {
     pub fn close(&self) {
         self.cancellation_token.cancel()
     }
+
+    /// Current counters for the connection
+    pub fn stats(&self) -> ChatStats {
+        self.stats.snapshot()
+    }
+
+    /// The channel the connection is receiving messages for, once known.
+    ///
+    /// Trovo only sends the channel along with chat messages, so this is `None` until the first
+    /// message arrives, and stays `None` if only historic messages have arrived. Comparing it to
+    /// the expected channel detects a chat token issued for the wrong channel.
+    pub fn channel_info(&self) -> Option<ChannelInfo> {
+        self.channel_info.borrow().clone()
+    }
+
+    /// Creates a stream without a socket, whose messages are fed by the returned
+    /// [`ChatStreamController`], for testing code that consumes a `ChatMessageStream`.
+    ///
+    /// The controller feeds the same channel as a real connection, so the stream behaves the
+    /// same: it ends after an error, or once closed from either side.
+    ///
+    /// ```
+    /// use futures::prelude::*;
+    /// use trovo::chat::{ChatMessage, ChatMessageStream, ChatMessageStreamError};
+    ///
+    /// /// The bot under test, counting `!ping` commands until the stream ends
+    /// async fn count_pings(mut messages: ChatMessageStream) -> Result<usize, ChatMessageStreamError> {
+    ///     let mut pings = 0;
+    ///     while let Some(message) = messages.next().await {
+    ///         if message?.content == "!ping" {
+    ///             pings += 1;
+    ///         }
+    ///     }
+    ///     Ok(pings)
+    /// }
+    ///
+    /// fn chat(content: &str) -> ChatMessage {
+    ///     serde_json::from_value(serde_json::json!({
+    ///         "type": 0,
+    ///         "content": content,
+    ///         "nick_name": "viewer",
+    ///         "message_id": "1",
+    ///         "send_time": 1700000000,
+    ///     }))
+    ///     .unwrap()
+    /// }
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let (messages, mut controller) = ChatMessageStream::test_pair();
+    /// let bot = tokio::spawn(count_pings(messages));
+    ///
+    /// controller.push(chat("!ping")).await;
+    /// controller.push(chat("hello")).await;
+    /// controller.push(chat("!ping")).await;
+    /// controller.close();
+    ///
+    /// assert_eq!(bot.await.unwrap().unwrap(), 2);
+    /// # }
+    /// ```
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn test_pair() -> (ChatMessageStream, ChatStreamController) {
+        let cancellation_token = CancellationToken::new();
+        let (chat_messages_sender, chat_messages_receiver) = mpsc::channel(CHAT_MESSAGES_BUFFER);
+        let (channel_info_sender, channel_info) = watch::channel(None);
+        let stream = ChatMessageStream {
+            cancellation_token: cancellation_token.clone(),
+            messages: chat_messages_receiver,
+            stats: Default::default(),
+            channel_info,
+        };
+        let controller = ChatStreamController {
+            cancellation_token,
+            chat_messages_sender: Some(chat_messages_sender),
+            channel_info: channel_info_sender,
+        };
+        (stream, controller)
+    }
+}
+
+/// Feeds messages to a stream created by [`ChatMessageStream::test_pair`], in place of a socket.
+///
+/// Dropping the controller ends the stream, like the server closing the connection.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug)]
+pub struct ChatStreamController {
+    cancellation_token: CancellationToken,
+    chat_messages_sender: Option<mpsc::Sender<Result<ChatMessage, ChatMessageStreamError>>>,
+    channel_info: watch::Sender<Option<ChannelInfo>>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl ChatStreamController {
+    /// Sends a message to the stream, waiting while its buffer is full like a real connection.
+    ///
+    /// Returns `false` once the stream was closed or dropped, or the controller closed, in which
+    /// case the message is discarded.
+    pub async fn push(&mut self, message: ChatMessage) -> bool {
+        self.send(Ok(message)).await
+    }
+
+    /// Sends an error to the stream, which then ends like a real connection does after an error.
+    ///
+    /// Returns `false` if the stream was already closed, see [`push`](Self::push).
+    pub async fn push_error(&mut self, error: ChatMessageStreamError) -> bool {
+        let sent = self.send(Err(error)).await;
+        self.close();
+        sent
+    }
+
+    /// Sets the stream's [`channel_info`](ChatMessageStream::channel_info), as if a message from
+    /// the channel had arrived.
+    pub fn set_channel_info(&mut self, channel_info: ChannelInfo) {
+        self.channel_info.send_replace(Some(channel_info));
+    }
+
+    /// Ends the stream once the messages already pushed have been received, like the connection
+    /// closing.
+    pub fn close(&mut self) {
+        self.chat_messages_sender = None;
+    }
+
+    /// Whether the stream was closed, or dropped, by its consumer
+    pub fn is_closed(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+    }
+
+    async fn send(&mut self, item: Result<ChatMessage, ChatMessageStreamError>) -> bool {
+        if self.is_closed() {
+            self.close();
+        }
+        match &self.chat_messages_sender {
+            Some(sender) => sender.send(item).await.is_ok(),
+            None => false,
+        }
+    }
 }
 
 impl Stream for ChatMessageStream {
@@ -89,6 +279,9 @@ impl Stream for ChatMessageStream {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
+        if self.cancellation_token.is_cancelled() {
+            return std::task::Poll::Ready(None);
+        }
         self.messages.poll_recv(cx)
     }
 }
@@ -99,72 +292,184 @@ impl Drop for ChatMessageStream {
     }
 }
 
-#[derive(Debug, PartialEq)]
-enum Continuation {
-    Continue,
-    Stop,
+/// An event received by a [`ChatConnection`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ChatEvent {
+    /// A chat message, including events such as follows and spells
+    Message(ChatMessage),
 }
 
-#[derive(Debug)]
-struct Ping {
-    interval: Duration,
-    iteration: u64,
+/// The websocket behind a [`ChatConnection`], boxed so tests can swap in a fake one
+trait Socket:
+    Stream<Item = Result<Message, tungstenite::Error>>
+    + Sink<Message, Error = tungstenite::Error>
+    + Send
+    + Unpin
+{
+}
 
-    /// The last iteration that we got a Pong response to
-    acknowledged: u64,
+impl<T> Socket for T where
+    T: Stream<Item = Result<Message, tungstenite::Error>>
+        + Sink<Message, Error = tungstenite::Error>
+        + Send
+        + Unpin
+{
 }
 
-impl Default for Ping {
-    fn default() -> Self {
+/// A chat connection driven by the caller, without spawning any tasks.
+///
+/// Each call to [`next_event`](Self::next_event) reads the socket until the next event arrives,
+/// sending pings along the way, so the connection only makes progress while it is being awaited.
+/// Call it regularly, or the server will drop the connection for missing pings.
+///
+/// Unlike [`ChatMessageStream`], frames are always parsed inline, ignoring
+/// [`ChatConfig::blocking_parse_threshold`]. The socket and the default [`TokioClock`] still rely
+/// on tokio's io and timer drivers, e.g. from a current thread runtime.
+///
+/// ```no_run
+/// # async fn example(client: trovo::Client<trovo::ClientId>) -> Result<(), Box<dyn std::error::Error>> {
+/// use trovo::chat::{ChatConnection, ChatEvent};
+///
+/// let token = client.chat_token_for_channel("100000").await?;
+/// let mut connection = ChatConnection::connect(token).await?;
+/// loop {
+///     match connection.next_event().await? {
+///         ChatEvent::Message(message) => println!("{}: {}", message.nick_name, message.content),
+///         _ => {}
+///     }
+/// }
+/// # }
+/// ```
+pub struct ChatConnection {
+    socket: Box<dyn Socket>,
+    ping: Ping,
+
+    /// Frames larger than this are parsed on a blocking thread, only set by [`ChatMessageStream`]
+    blocking_parse_threshold: Option<usize>,
+
+    /// The large frame being parsed on a blocking thread, if any
+    parsing: Option<JoinHandle<serde_json::Result<ChatSocketMessage>>>,
+
+    /// Socket items read while a frame is being parsed, handled once it is to keep their order
+    queued: VecDeque<Option<Result<Message, tungstenite::Error>>>,
+
+    /// Messages sent longer ago than this are discarded
+    max_message_age: Option<Duration>,
+    stats: Arc<StatsCounters>,
+
+    /// Messages from frames already read, frames can contain multiple messages
+    pending: VecDeque<ChatMessage>,
+
+    /// The channel from the latest chat frame that had one, shared with [`ChatMessageStream`]
+    channel_info: watch::Sender<Option<ChannelInfo>>,
+}
+
+impl ChatConnection {
+    /// Connect to trovo chat using the given chat token.
+    pub async fn connect(chat_token: ChatToken) -> Result<ChatConnection, ChatConnectError> {
+        Self::connect_with_config(chat_token, ChatConfig::default()).await
+    }
+
+    /// Connect to trovo chat using the given chat token and connection config.
+    pub async fn connect_with_config(
+        chat_token: ChatToken,
+        config: ChatConfig,
+    ) -> Result<ChatConnection, ChatConnectError> {
+        let mut request = config.url.as_str().into_client_request()?;
+        request.headers_mut().insert(
+            header::USER_AGENT,
+            HeaderValue::from_str(&config.user_agent)
+                .map_err(|err| tungstenite::Error::HttpFormat(err.into()))?,
+        );
+        let ws_config = WebSocketConfig {
+            max_message_size: Some(config.max_message_size),
+            max_frame_size: Some(config.max_message_size),
+            ..Default::default()
+        };
+        let (ws_stream, _) = connect_async_with_config(request, Some(ws_config)).await?;
+        let mut connection = Self::new(ws_stream, &config);
+        connection.authenticate(chat_token).await?;
+        Ok(connection)
+    }
+
+    fn new(socket: impl Socket + 'static, config: &ChatConfig) -> Self {
         Self {
-            interval: DEFAULT_PING_INTERVAL,
-            iteration: 0,
-            acknowledged: 0,
+            socket: Box::new(socket),
+            ping: Ping::new(config.clock.clone()),
+            blocking_parse_threshold: None,
+            parsing: None,
+            queued: VecDeque::new(),
+            max_message_age: config.max_message_age,
+            stats: Default::default(),
+            pending: VecDeque::new(),
+            channel_info: watch::channel(None).0,
         }
     }
-}
 
-struct SocketMessagesReader<R> {
-    cancellation_token: CancellationToken,
-    reader: R,
-    chat_messages_sender: mpsc::Sender<Result<ChatMessage, ChatMessageStreamError>>,
-    socket_messages_sender: mpsc::Sender<ChatSocketMessage>,
-    auth: (
-        String,
-        Option<oneshot::Sender<Result<(), ChatConnectError>>>,
-    ),
-    ping: Ping,
-}
+    /// Current counters for the connection
+    pub fn stats(&self) -> ChatStats {
+        self.stats.snapshot()
+    }
 
-impl<R> SocketMessagesReader<R>
-where
-    R: 'static + Stream<Item = Result<Message, tungstenite::Error>> + Send + Unpin,
-{
-    fn spawn(mut self) {
-        tokio::spawn(async move {
-            loop {
-                match self.next().await {
-                    Ok(Continuation::Stop) => {
-                        trace!("socket reader exited gracefully");
-                        break;
-                    }
-                    Err(err) => {
-                        error!(?err, "socket reader errored");
-                        self.chat_messages_sender.send(Err(err)).await.ok();
-                        break;
-                    }
-                    _ => {}
-                }
+    /// The channel the connection is receiving messages for, once known, see
+    /// [`ChatMessageStream::channel_info`].
+    pub fn channel_info(&self) -> Option<ChannelInfo> {
+        self.channel_info.borrow().clone()
+    }
+
+    /// Waits for the next event, sending pings while waiting.
+    ///
+    /// Once an error is returned, e.g. the socket closing or the server not answering pings, the
+    /// connection is finished and should be dropped.
+    pub async fn next_event(&mut self) -> Result<ChatEvent, ChatMessageStreamError> {
+        loop {
+            if let Some(message) = self.pending.pop_front() {
+                return Ok(ChatEvent::Message(message));
             }
-        });
+            self.step().await?;
+        }
     }
 
-    async fn next(&mut self) -> Result<Continuation, ChatMessageStreamError> {
-        select! {
-            _ = self.cancellation_token.cancelled() => {
-                Ok(Continuation::Stop)
+    /// Sends the auth message and waits for the server to acknowledge it.
+    async fn authenticate(&mut self, chat_token: ChatToken) -> Result<(), ChatConnectError> {
+        self.send(&ChatSocketMessage::Auth {
+            nonce: AUTH_NONCE.to_string(),
+            data: chat_token,
+        })
+        .await
+        .map_err(connect_error)?;
+
+        loop {
+            let msg = match self.socket.next().await {
+                Some(msg) => msg?,
+                None => return Err(ChatConnectError::SocketClosed),
+            };
+            match self.read_message(msg).map_err(connect_error)? {
+                Some(ChatSocketMessage::Response { nonce }) if nonce == AUTH_NONCE => return Ok(()),
+                Some(msg) => self.handle_socket_message(msg),
+                None => {}
             }
-            _ = sleep(self.ping.interval) => {
+        }
+    }
+
+    /// Waits for either the ping interval to pass, the next websocket message to arrive, or a
+    /// large frame to finish parsing.
+    ///
+    /// Pings keep being sent and messages keep being read while a frame is parsed on a blocking
+    /// thread, the messages are queued and handled after it.
+    async fn step(&mut self) -> Result<(), ChatMessageStreamError> {
+        if self.parsing.is_none() {
+            if let Some(item) = self.queued.pop_front() {
+                return self.handle_socket_item(item);
+            }
+        }
+
+        // Nothing more can be read once the socket ended or errored
+        let socket_ended = matches!(self.queued.back(), Some(None | Some(Err(_))));
+        let clock = self.ping.clock.clone();
+        select! {
+            _ = clock.sleep(self.ping.interval) => {
                 self.ping.iteration += 1;
 
                 // Are we missing 2 pongs?
@@ -174,58 +479,83 @@ where
 
                 let msg = ChatSocketMessage::Ping { nonce: self.ping.iteration.to_string() };
                 trace!(?msg, "sending ping");
-                match self.socket_messages_sender.send(msg).await {
-                    Ok(_) => Ok(Continuation::Continue),
-                    Err(_) => Ok(Continuation::Stop),
-                }
+                self.send(&msg).await
             }
-            Some(msg) = self.reader.next() => {
-                self.handle_message(msg?).await
+            msg = parsed(&mut self.parsing) => {
+                self.parsing = None;
+                self.handle_socket_message(msg?);
+                Ok(())
             }
-            else => {
-                Ok(Continuation::Stop)
+            item = self.socket.next(), if !socket_ended => {
+                if self.parsing.is_some() {
+                    self.queued.push_back(item);
+                    Ok(())
+                } else {
+                    self.handle_socket_item(item)
+                }
             }
         }
     }
 
-    async fn handle_message(
+    /// Handles an item read from the socket, the socket ending counts as it closing.
+    fn handle_socket_item(
+        &mut self,
+        item: Option<Result<Message, tungstenite::Error>>,
+    ) -> Result<(), ChatMessageStreamError> {
+        let msg = item.ok_or(ChatMessageStreamError::SocketClosed(None))??;
+        if let Some(msg) = self.read_message(msg)? {
+            self.handle_socket_message(msg);
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, msg: &ChatSocketMessage) -> Result<(), ChatMessageStreamError> {
+        trace!(?msg, "outgoing websocket message");
+        let msg = serde_json::to_string(msg)?;
+        self.socket.send(msg.into()).await?;
+        Ok(())
+    }
+
+    /// Parses the chat socket message in a websocket message, if any
+    fn read_message(
         &mut self,
         msg: Message,
-    ) -> Result<Continuation, ChatMessageStreamError> {
+    ) -> Result<Option<ChatSocketMessage>, ChatMessageStreamError> {
         trace!(?msg, "incoming websocket message");
         match msg {
-            Message::Text(text) => {
-                let msg = serde_json::from_str(&text)?;
-                Ok(self.handle_socket_message(msg).await)
-            }
-            Message::Binary(bytes) => {
-                let msg = serde_json::from_slice(bytes.as_slice())?;
-                Ok(self.handle_socket_message(msg).await)
-            }
-            Message::Ping(_) => Ok(Continuation::Continue),
-            Message::Pong(_) => Ok(Continuation::Continue),
+            Message::Text(text) => self.parse_frame(text.into_bytes()),
+            Message::Binary(bytes) => self.parse_frame(bytes),
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => Ok(None),
             Message::Close(reason) => Err(ChatMessageStreamError::SocketClosed(reason)),
-            Message::Frame(_) => Ok(Continuation::Continue),
         }
     }
 
-    async fn handle_socket_message(&mut self, msg: ChatSocketMessage) -> Continuation {
+    /// Parses the frame, or hands frames above the threshold to a blocking thread, returning
+    /// `None` until [`parsed`] picks up the result.
+    fn parse_frame(
+        &mut self,
+        bytes: Vec<u8>,
+    ) -> Result<Option<ChatSocketMessage>, ChatMessageStreamError> {
+        match self.blocking_parse_threshold {
+            Some(threshold) if bytes.len() > threshold => {
+                self.parsing = Some(tokio::task::spawn_blocking(move || {
+                    serde_json::from_slice(&bytes)
+                }));
+                Ok(None)
+            }
+            _ => Ok(Some(serde_json::from_slice(&bytes)?)),
+        }
+    }
+
+    fn handle_socket_message(&mut self, msg: ChatSocketMessage) {
         debug!(?msg, "incoming chat socket message");
         match msg {
-            ChatSocketMessage::Response { nonce } => {
-                if self.auth.0 == nonce {
-                    if let Some(auth) = self.auth.1.take() {
-                        auth.send(Ok(())).ok();
-                    }
-                }
-                Continuation::Continue
-            }
             ChatSocketMessage::Pong { nonce, data } => {
                 let iteration: u64 = match nonce.parse() {
                     Ok(v) => v,
                     Err(err) => {
                         warn!(?err, "failed to parse pong nonce as u64, ignoring...");
-                        return Continuation::Continue;
+                        return;
                     }
                 };
                 debug!(?iteration, "received pong");
@@ -234,168 +564,624 @@ where
                     self.ping.acknowledged = iteration;
                     self.ping.interval = Duration::from_secs(data.gap);
                 }
-                Continuation::Continue
             }
-            ChatSocketMessage::Chat {
-                channel_info: _,
-                data,
-            } => {
+            ChatSocketMessage::Chat { channel_info, data } => {
+                if let Some(channel_info) = channel_info {
+                    self.channel_info.send_if_modified(|current| {
+                        if current.as_ref() == Some(&channel_info) {
+                            return false;
+                        }
+                        if let Some(previous) = current.as_ref() {
+                            let previous = &previous.channel_id;
+                            let channel_id = &channel_info.channel_id;
+                            warn!(?previous, ?channel_id, "chat channel changed");
+                        }
+                        *current = Some(channel_info);
+                        true
+                    });
+                }
                 for chat in data.chats {
-                    if self.chat_messages_sender.send(Ok(chat)).await.is_err() {
-                        // Messages receiver must have been dropped and so we just need to cleanup
-                        return Continuation::Stop;
+                    if self.is_stale(&chat) {
+                        self.stats.stale_discarded.fetch_add(1, Ordering::Relaxed);
+                        let message_id = &chat.message_id;
+                        debug!(?message_id, "discarding stale chat message");
+                        continue;
                     }
+                    self.pending.push_back(chat);
                 }
-                Continuation::Continue
             }
-            _ => unreachable!(),
+            // Late auth responses, or messages only the client sends
+            ChatSocketMessage::Response { .. }
+            | ChatSocketMessage::Auth { .. }
+            | ChatSocketMessage::Ping { .. } => {}
         }
     }
-}
 
-impl<R> Drop for SocketMessagesReader<R> {
-    fn drop(&mut self) {
-        self.cancellation_token.cancel();
+    /// Whether the message was sent longer ago than the max message age. Messages without a
+    /// timestamp, i.e. a `send_time` of `0`, are never stale.
+    fn is_stale(&self, chat: &ChatMessage) -> bool {
+        let Some(max_age) = self.max_message_age else {
+            return false;
+        };
+        let sent = timestamp::unix_seconds(&chat.send_time);
+        if sent == 0 {
+            return false;
+        }
+        let now = self
+            .ping
+            .clock
+            .system_time()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        now.saturating_sub(sent) > max_age.as_secs() as i64
     }
 }
 
-struct SocketMessagesWriter<W> {
-    cancellation_token: CancellationToken,
-    writer: W,
-    socket_messages_receiver: mpsc::Receiver<ChatSocketMessage>,
-    chat_messages_sender: mpsc::Sender<Result<ChatMessage, ChatMessageStreamError>>,
+impl fmt::Debug for ChatConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChatConnection")
+            .field("ping", &self.ping)
+            .field("blocking_parse_threshold", &self.blocking_parse_threshold)
+            .field("parsing", &self.parsing.is_some())
+            .field("queued", &self.queued.len())
+            .field("max_message_age", &self.max_message_age)
+            .field("pending", &self.pending.len())
+            .field("channel_info", &*self.channel_info.borrow())
+            .finish_non_exhaustive()
+    }
 }
 
-impl<W> SocketMessagesWriter<W>
-where
-    W: 'static + Sink<Message, Error = tungstenite::Error> + Send + Unpin,
-{
-    fn spawn(mut self) {
-        tokio::spawn(async move {
-            loop {
-                match self.next().await {
-                    Ok(Continuation::Stop) => {
-                        trace!("socket writer exited gracefully");
-                        break;
-                    }
-                    Err(err) => {
-                        error!(?err, "socket writer errored");
-                        self.chat_messages_sender.send(Err(err)).await.ok();
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-        });
+/// Waits for the frame being parsed on a blocking thread, never resolving if there is none.
+async fn parsed(
+    parsing: &mut Option<JoinHandle<serde_json::Result<ChatSocketMessage>>>,
+) -> Result<ChatSocketMessage, ChatMessageStreamError> {
+    let Some(handle) = parsing else {
+        return future::pending().await;
+    };
+    match handle.await {
+        Ok(msg) => Ok(msg?),
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        // The runtime is shutting down
+        Err(_) => Err(ChatMessageStreamError::SocketClosed(None)),
     }
+}
 
-    async fn next(&mut self) -> Result<Continuation, ChatMessageStreamError> {
-        select! {
-            _ = self.cancellation_token.cancelled() => {
-                Ok(Continuation::Stop)
-            }
-            Some(msg) = self.socket_messages_receiver.recv() => {
-                self.handle_message(msg).await?;
-                Ok(Continuation::Continue)
-            }
-            else => {
-                Ok(Continuation::Stop)
-            }
+/// Errors while authenticating are reported as connect errors
+fn connect_error(err: ChatMessageStreamError) -> ChatConnectError {
+    match err {
+        ChatMessageStreamError::WebSocket(err) => ChatConnectError::WebSocket(*err),
+        ChatMessageStreamError::Serde(err) => ChatConnectError::Serde(err),
+        ChatMessageStreamError::SocketClosed(_) | ChatMessageStreamError::PingTimeout => {
+            ChatConnectError::SocketClosed
         }
+        ChatMessageStreamError::MessageTooLarge { size, max_size } => ChatConnectError::WebSocket(
+            tungstenite::Error::Capacity(CapacityError::MessageTooLong { size, max_size }),
+        ),
     }
+}
 
-    async fn handle_message(
-        &mut self,
-        msg: ChatSocketMessage,
-    ) -> Result<(), ChatMessageStreamError> {
-        trace!(?msg, "outgoing websocket message");
-        let msg = serde_json::to_string(&msg)?;
-        self.writer.send(msg.into()).await?;
-        Ok(())
+#[derive(Debug)]
+struct Ping {
+    clock: Arc<dyn Clock>,
+    interval: Duration,
+    iteration: u64,
+
+    /// The last iteration that we got a Pong response to
+    acknowledged: u64,
+}
+
+impl Ping {
+    fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            interval: DEFAULT_PING_INTERVAL,
+            iteration: 0,
+            acknowledged: 0,
+        }
     }
 }
 
-impl<W> Drop for SocketMessagesWriter<W> {
-    fn drop(&mut self) {
-        self.cancellation_token.cancel();
+impl Default for Ping {
+    fn default() -> Self {
+        Self::new(Arc::new(TokioClock))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::chat::PongMessageData;
+    use crate::{chat::PongMessageData, ChannelId, MockClock};
+    use async_tungstenite::tokio::{accept_hdr_async, TokioAdapter};
+    use async_tungstenite::{tungstenite::handshake::server, WebSocketStream};
+    use tokio::net::{TcpListener, TcpStream};
+
+    type ServerSocket = WebSocketStream<TokioAdapter<TcpStream>>;
+
+    /// A local chat server that accepts one connection and acknowledges its auth message.
+    ///
+    /// Resolves to the handshake request and the accepted socket.
+    #[allow(clippy::result_large_err)]
+    async fn mock_chat_server() -> (
+        ChatConfig,
+        tokio::task::JoinHandle<(server::Request, ServerSocket)>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = ChatConfig::new().url(format!("ws://{}", listener.local_addr().unwrap()));
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut handshake = None;
+            let mut socket = accept_hdr_async(stream, |req: &server::Request, res| {
+                handshake = Some(req.clone());
+                Ok(res)
+            })
+            .await
+            .unwrap();
+
+            let auth = socket.next().await.unwrap().unwrap();
+            let nonce = match serde_json::from_str(auth.to_text().unwrap()).unwrap() {
+                ChatSocketMessage::Auth { nonce, .. } => nonce,
+                msg => panic!("expected auth message, got {:?}", msg),
+            };
+            let response = serde_json::to_string(&ChatSocketMessage::Response { nonce }).unwrap();
+            socket.send(response.into()).await.unwrap();
+
+            (handshake.unwrap(), socket)
+        });
+        (config, server)
+    }
+
+    fn chat_token() -> ChatToken {
+        ChatToken {
+            token: "chat-token".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn handshake_user_agent() {
+        let (config, server) = mock_chat_server().await;
+
+        let _stream =
+            ChatMessageStream::connect_with_config(chat_token(), config.user_agent("my-bot/1.0"))
+                .await
+                .unwrap();
+
+        let (handshake, _socket) = server.await.unwrap();
+        assert_eq!(handshake.headers()["User-Agent"], "my-bot/1.0");
+    }
+
+    #[tokio::test]
+    async fn oversized_message_rejected() {
+        let (config, server) = mock_chat_server().await;
+
+        let mut stream =
+            ChatMessageStream::connect_with_config(chat_token(), config.max_message_size(1024))
+                .await
+                .unwrap();
+        let (_, mut socket) = server.await.unwrap();
+        socket
+            .send(chat_frame("large", &"spell".repeat(1024)))
+            .await
+            .unwrap();
+
+        match stream.next().await.unwrap() {
+            Err(ChatMessageStreamError::MessageTooLarge { size, max_size }) => {
+                assert!(size > 1024, "{}", size);
+                assert_eq!(max_size, 1024);
+            }
+            other => panic!("expected message too large, got {:?}", other),
+        }
+        assert!(stream.next().await.is_none());
+    }
+
+    /// A socket fed by the test instead of a server
+    struct FakeSocket {
+        incoming: futures::channel::mpsc::UnboundedReceiver<Result<Message, tungstenite::Error>>,
+        outgoing: futures::channel::mpsc::UnboundedSender<Message>,
+    }
+
+    impl Stream for FakeSocket {
+        type Item = Result<Message, tungstenite::Error>;
+
+        fn poll_next(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Self::Item>> {
+            self.incoming.poll_next_unpin(cx)
+        }
+    }
+
+    impl Sink<Message> for FakeSocket {
+        type Error = tungstenite::Error;
+
+        fn poll_ready(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: std::pin::Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+            self.outgoing
+                .unbounded_send(item)
+                .map_err(|_| tungstenite::Error::ConnectionClosed)
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    type Incoming = futures::channel::mpsc::UnboundedSender<Result<Message, tungstenite::Error>>;
+    type Outgoing = futures::channel::mpsc::UnboundedReceiver<Message>;
+
+    /// A connection over a [`FakeSocket`], returning the ends for feeding and reading it
+    fn fake_connection(config: &ChatConfig) -> (ChatConnection, Incoming, Outgoing) {
+        let (incoming_sender, incoming) = futures::channel::mpsc::unbounded();
+        let (outgoing, outgoing_receiver) = futures::channel::mpsc::unbounded();
+        let connection = ChatConnection::new(FakeSocket { incoming, outgoing }, config);
+        (connection, incoming_sender, outgoing_receiver)
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn frame(msg: &ChatSocketMessage) -> Result<Message, tungstenite::Error> {
+        Ok(serde_json::to_string(msg).unwrap().into())
+    }
+
+    fn parse(msg: Message) -> ChatSocketMessage {
+        serde_json::from_str(msg.to_text().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn authenticate() {
+        let (mut connection, incoming, mut outgoing) = fake_connection(&ChatConfig::new());
+
+        // Messages arriving before the auth response are kept
+        incoming
+            .unbounded_send(Ok(chat_frame("early", "hi")))
+            .unwrap();
+        incoming
+            .unbounded_send(frame(&ChatSocketMessage::Response {
+                nonce: AUTH_NONCE.to_string(),
+            }))
+            .unwrap();
+        connection.authenticate(chat_token()).await.unwrap();
+
+        assert!(matches!(
+            parse(outgoing.next().await.unwrap()),
+            ChatSocketMessage::Auth { nonce, data } if nonce == AUTH_NONCE && data.token == "chat-token"
+        ));
+        let ChatEvent::Message(message) = connection.next_event().await.unwrap();
+        assert_eq!(message.message_id, "early");
+
+        // The socket ending before the response fails the connect
+        let (mut connection, incoming, _outgoing) = fake_connection(&ChatConfig::new());
+        drop(incoming);
+        assert!(matches!(
+            connection.authenticate(chat_token()).await,
+            Err(ChatConnectError::SocketClosed)
+        ));
+    }
 
     #[tokio::test]
     async fn ping_pong() {
-        let cancellation_token = CancellationToken::new();
-        let (socket_messages_sender, _) = mpsc::channel(1);
-        let (chat_messages_sender, _) = mpsc::channel(CHAT_MESSAGES_BUFFER);
-        let (mut fake_sender, fake_receiver) =
-            futures::channel::mpsc::channel::<Result<Message, tungstenite::Error>>(1);
-        let mut reader = SocketMessagesReader {
-            cancellation_token,
-            reader: fake_receiver,
-            chat_messages_sender,
-            socket_messages_sender,
-            auth: ("authenticate".to_string(), None),
-            ping: Ping {
-                interval: DEFAULT_PING_INTERVAL,
-                iteration: 1,
-                acknowledged: 0,
-            },
-        };
+        let (mut connection, incoming, _outgoing) = fake_connection(&ChatConfig::new());
+        connection.ping.iteration = 1;
 
         // Should acknowledge pongs
-        let msg = serde_json::to_string(&ChatSocketMessage::Pong {
+        let msg = ChatSocketMessage::Pong {
             nonce: 1.to_string(),
             data: PongMessageData { gap: 10 },
-        })
-        .unwrap();
-        fake_sender.send(Ok(msg.into())).await.unwrap();
-        assert_eq!(reader.ping.acknowledged, 0);
-        assert_eq!(reader.ping.interval, DEFAULT_PING_INTERVAL);
-        assert!(matches!(reader.next().await, Ok(Continuation::Continue)));
-        assert_eq!(reader.ping.acknowledged, 1);
-        assert_eq!(reader.ping.interval, Duration::from_secs(10));
-
-        // Invalid nonce shouldn't kill the reader
-        let msg = serde_json::to_string(&ChatSocketMessage::Pong {
+        };
+        incoming.unbounded_send(frame(&msg)).unwrap();
+        assert_eq!(connection.ping.acknowledged, 0);
+        assert_eq!(connection.ping.interval, DEFAULT_PING_INTERVAL);
+        connection.step().await.unwrap();
+        assert_eq!(connection.ping.acknowledged, 1);
+        assert_eq!(connection.ping.interval, Duration::from_secs(10));
+
+        // Invalid nonce shouldn't kill the connection
+        let msg = ChatSocketMessage::Pong {
             nonce: (-2).to_string(),
             data: PongMessageData { gap: 20 },
-        })
-        .unwrap();
-        reader.ping.interval = DEFAULT_PING_INTERVAL;
-        fake_sender.send(Ok(msg.into())).await.unwrap();
-        assert!(matches!(reader.next().await, Ok(Continuation::Continue)));
-        assert_eq!(reader.ping.acknowledged, 1);
-        assert_eq!(reader.ping.interval, DEFAULT_PING_INTERVAL);
+        };
+        connection.ping.interval = DEFAULT_PING_INTERVAL;
+        incoming.unbounded_send(frame(&msg)).unwrap();
+        connection.step().await.unwrap();
+        assert_eq!(connection.ping.acknowledged, 1);
+        assert_eq!(connection.ping.interval, DEFAULT_PING_INTERVAL);
 
         // Should ignore backwards nonces
-        let msg = serde_json::to_string(&ChatSocketMessage::Pong {
+        let msg = ChatSocketMessage::Pong {
             nonce: 2.to_string(),
             data: PongMessageData { gap: 20 },
+        };
+        incoming.unbounded_send(frame(&msg)).unwrap();
+        connection.ping.interval = DEFAULT_PING_INTERVAL;
+        connection.ping.acknowledged = 5;
+        connection.ping.iteration = 6;
+        connection.step().await.unwrap();
+        assert_eq!(connection.ping.acknowledged, 5);
+        assert_eq!(connection.ping.interval, DEFAULT_PING_INTERVAL);
+    }
+
+    #[tokio::test]
+    async fn ping_timeout() {
+        let clock = MockClock::new();
+        let (mut connection, _incoming, mut outgoing) =
+            fake_connection(&ChatConfig::new().clock(clock.clone()));
+
+        // Two pings go unanswered, the third interval times out
+        let server = async {
+            for iteration in 1..=3 {
+                while clock.sleepers() == 0 {
+                    tokio::task::yield_now().await;
+                }
+                clock.advance(DEFAULT_PING_INTERVAL);
+                if iteration < 3 {
+                    let msg = parse(outgoing.next().await.unwrap());
+                    assert!(
+                        matches!(msg, ChatSocketMessage::Ping { nonce } if nonce == iteration.to_string())
+                    );
+                }
+            }
+        };
+        let (event, ()) = futures::join!(connection.next_event(), server);
+
+        assert!(matches!(event, Err(ChatMessageStreamError::PingTimeout)));
+    }
+
+    fn chat_frame(eid: &str, content: &str) -> Message {
+        serde_json::json!({
+            "type": "CHAT",
+            "data": {
+                "eid": eid,
+                "chats": [{
+                    "type": 0,
+                    "content": content,
+                    "nick_name": "someone",
+                    "message_id": eid,
+                    "send_time": 1700000000,
+                }],
+            },
         })
-        .unwrap();
-        fake_sender.send(Ok(msg.into())).await.unwrap();
-        reader.ping.interval = DEFAULT_PING_INTERVAL;
-        reader.ping.acknowledged = 5;
-        reader.ping.iteration = 6;
-        assert!(matches!(reader.next().await, Ok(Continuation::Continue)));
-        assert_eq!(reader.ping.acknowledged, 5);
-        assert_eq!(reader.ping.interval, DEFAULT_PING_INTERVAL);
-    }
-
-    #[test]
-    fn cancel_on_drop() {
-        let cancellation_token = CancellationToken::new();
-        let (_, messages) = mpsc::channel(CHAT_MESSAGES_BUFFER);
+        .to_string()
+        .into()
+    }
 
-        assert!(!cancellation_token.is_cancelled());
-        drop(ChatMessageStream {
-            cancellation_token: cancellation_token.clone(),
-            messages,
+    fn chat_message(message_id: &str) -> ChatMessage {
+        serde_json::from_value(serde_json::json!({
+            "type": 0,
+            "content": "hello",
+            "nick_name": "someone",
+            "message_id": message_id,
+            "send_time": 1700000000,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn channel_info() {
+        let (connection, incoming, _outgoing) = fake_connection(&ChatConfig::new());
+        let mut stream = ChatMessageStream::spawn(connection);
+
+        // Historic messages don't say which channel they're from
+        incoming
+            .unbounded_send(Ok(chat_frame("historic", "hi")))
+            .unwrap();
+        stream.next().await.unwrap().unwrap();
+        assert_eq!(stream.channel_info(), None);
+
+        let live = ChatSocketMessage::Chat {
+            channel_info: Some(ChannelInfo {
+                channel_id: "100000".to_string(),
+            }),
+            data: serde_json::from_value(serde_json::json!({"eid": "live", "chats": []})).unwrap(),
+        };
+        incoming.unbounded_send(frame(&live)).unwrap();
+        incoming
+            .unbounded_send(Ok(chat_frame("later", "hi")))
+            .unwrap();
+        stream.next().await.unwrap().unwrap();
+        let channel_info = stream.channel_info().unwrap();
+        assert_eq!(channel_info.channel_id(), ChannelId::new("100000"));
+
+        let (stream, mut controller) = ChatMessageStream::test_pair();
+        controller.set_channel_info(ChannelInfo {
+            channel_id: "100000".to_string(),
         });
-        assert!(cancellation_token.is_cancelled());
+        assert_eq!(stream.channel_info().unwrap().channel_id, "100000");
+    }
+
+    #[tokio::test]
+    async fn large_frames_parsed_in_order() {
+        let (mut connection, incoming, _outgoing) = fake_connection(&ChatConfig::new());
+        connection.blocking_parse_threshold = Some(1024);
+
+        let large = "spell".repeat(1024);
+        incoming
+            .unbounded_send(Ok(chat_frame("large", &large)))
+            .unwrap();
+        incoming
+            .unbounded_send(Ok(chat_frame("small", "hi")))
+            .unwrap();
+
+        let ChatEvent::Message(first) = connection.next_event().await.unwrap();
+        assert_eq!(first.message_id, "large");
+        assert_eq!(first.content, large);
+        let ChatEvent::Message(second) = connection.next_event().await.unwrap();
+        assert_eq!(second.message_id, "small");
+    }
+
+    #[tokio::test]
+    async fn reads_while_parsing_large_frame() {
+        let (mut connection, incoming, _outgoing) = fake_connection(&ChatConfig::new());
+        connection.blocking_parse_threshold = Some(1024);
+        connection.ping.iteration = 1;
+
+        incoming
+            .unbounded_send(Ok(chat_frame("large", &"spell".repeat(1024))))
+            .unwrap();
+        connection.step().await.unwrap();
+        assert!(connection.parsing.is_some());
+
+        // The connection keeps reading while the frame is parsed, without reordering messages
+        let pong = ChatSocketMessage::Pong {
+            nonce: 1.to_string(),
+            data: PongMessageData { gap: 10 },
+        };
+        incoming.unbounded_send(frame(&pong)).unwrap();
+        incoming
+            .unbounded_send(Ok(chat_frame("small", "hi")))
+            .unwrap();
+        drop(incoming);
+
+        let ChatEvent::Message(first) = connection.next_event().await.unwrap();
+        assert_eq!(first.message_id, "large");
+        let ChatEvent::Message(second) = connection.next_event().await.unwrap();
+        assert_eq!(second.message_id, "small");
+        assert_eq!(connection.ping.acknowledged, 1);
+        assert!(matches!(
+            connection.next_event().await,
+            Err(ChatMessageStreamError::SocketClosed(None))
+        ));
+    }
+
+    #[tokio::test]
+    async fn stale_messages_discarded() {
+        let clock = MockClock::new();
+        clock.set_system_time(SystemTime::UNIX_EPOCH + Duration::from_secs(1700000100));
+        let config = ChatConfig::new()
+            .clock(clock.clone())
+            .max_message_age(Duration::from_secs(10));
+        let (mut connection, incoming, _outgoing) = fake_connection(&config);
+
+        let chat = |message_id: &str, send_time: i64| ChatSocketMessage::Chat {
+            channel_info: None,
+            data: serde_json::from_value(serde_json::json!({
+                "eid": message_id,
+                "chats": [{
+                    "type": 0,
+                    "content": "hi",
+                    "nick_name": "someone",
+                    "message_id": message_id,
+                    "send_time": send_time,
+                }],
+            }))
+            .unwrap(),
+        };
+        let frames = [
+            chat("fresh", 1700000095),
+            chat("stale", 1700000000),
+            chat("missing", 0),
+            chat("edge", 1700000090),
+        ];
+        for msg in &frames {
+            incoming.unbounded_send(frame(msg)).unwrap();
+        }
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let ChatEvent::Message(message) = connection.next_event().await.unwrap();
+            received.push(message.message_id);
+        }
+
+        // Advancing the clock ages the messages still to arrive
+        clock.advance(Duration::from_secs(10));
+        incoming
+            .unbounded_send(frame(&chat("aged", 1700000095)))
+            .unwrap();
+        drop(incoming);
+        assert!(matches!(
+            connection.next_event().await,
+            Err(ChatMessageStreamError::SocketClosed(None))
+        ));
+
+        assert_eq!(received, ["fresh", "missing", "edge"]);
+        assert_eq!(connection.stats().stale_discarded, 2);
+    }
+
+    #[tokio::test]
+    async fn cancel_on_drop() {
+        let (messages, mut controller) = ChatMessageStream::test_pair();
+
+        assert!(!controller.is_closed());
+        drop(messages);
+        assert!(controller.is_closed());
+        assert!(!controller.push(chat_message("1")).await);
+    }
+
+    #[tokio::test]
+    async fn test_pair_semantics() {
+        let (mut messages, mut controller) = ChatMessageStream::test_pair();
+        assert!(controller.push(chat_message("1")).await);
+        assert!(
+            controller
+                .push_error(ChatMessageStreamError::PingTimeout)
+                .await
+        );
+        assert!(!controller.push(chat_message("2")).await);
+
+        assert_eq!(messages.next().await.unwrap().unwrap().message_id, "1");
+        assert!(matches!(
+            messages.next().await,
+            Some(Err(ChatMessageStreamError::PingTimeout))
+        ));
+        assert!(messages.next().await.is_none());
+
+        // Closing the stream ends it immediately, even with messages buffered
+        let (mut messages, mut controller) = ChatMessageStream::test_pair();
+        assert!(controller.push(chat_message("1")).await);
+        messages.close();
+        assert!(messages.next().await.is_none());
+        assert!(controller.is_closed());
+    }
+
+    #[cfg(feature = "log")]
+    #[tokio::test]
+    async fn log_facade() {
+        use std::sync::Mutex;
+
+        struct TestLogger(Mutex<Vec<(String, String)>>);
+
+        impl log::Log for TestLogger {
+            fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record<'_>) {
+                let entry = (record.target().to_string(), record.args().to_string());
+                self.0.lock().unwrap().push(entry);
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: TestLogger = TestLogger(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let (mut connection, incoming, _outgoing) = fake_connection(&ChatConfig::new());
+        let msg = ChatSocketMessage::Pong {
+            nonce: "not-a-number".to_string(),
+            data: PongMessageData { gap: 20 },
+        };
+        incoming.unbounded_send(frame(&msg)).unwrap();
+        connection.step().await.unwrap();
+
+        let records = LOGGER.0.lock().unwrap();
+        assert!(records.iter().any(|(target, message)| {
+            target == "trovo::chat::socket"
+                && message.starts_with("failed to parse pong nonce as u64, ignoring... err=")
+        }));
     }
 }
@@ -0,0 +1,348 @@
+//! # Summary
+//!
+//! Aggregate statistics of a chat session, e.g. for an end of stream report.
+//!
+//! ```no_run
+//! use trovo::chat::summary::ChatSessionSummary;
+//!
+//! # async fn run(client: trovo::Client<trovo::ClientId>) -> Result<(), Box<dyn std::error::Error>> {
+//! let messages = client.chat_messages_for_channel("100000").await?;
+//! let report = ChatSessionSummary::new().summarize(messages).await?;
+//! println!("{}", serde_json::to_string_pretty(&report)?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    chat::{
+        monetization::{Currency, MonetizationEvent, MonetizationKind},
+        ChatMessage, ChatMessageType,
+    },
+    timestamp, Timestamp, UserId,
+};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Default number of chatters tracked for [`SummaryReport::top_chatters`].
+pub const DEFAULT_TRACKED_CHATTERS: usize = 1000;
+
+/// Default number of chatters included in [`SummaryReport::top_chatters`].
+pub const DEFAULT_TOP_CHATTERS: usize = 10;
+
+/// Summary of a chat session produced by [`ChatSessionSummary::finish`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SummaryReport {
+    /// Number of messages of any type
+    pub total_messages: u64,
+
+    /// Number of distinct senders of chat messages, see [`ChatSessionSummary`]
+    pub unique_chatters: u64,
+
+    /// Number of messages of each type
+    pub messages_per_type: BTreeMap<ChatMessageType, u64>,
+
+    /// Senders of the most chat messages, most first
+    pub top_chatters: Vec<TopChatter>,
+
+    /// Earliest send time of any message. Serialized as unix seconds, with `0` for none.
+    #[serde(with = "timestamp::optional_seconds")]
+    pub first_message_at: Option<Timestamp>,
+
+    /// Latest send time of any message. Serialized as unix seconds, with `0` for none.
+    #[serde(with = "timestamp::optional_seconds")]
+    pub last_message_at: Option<Timestamp>,
+
+    /// Total value of spells and custom spells per currency, see
+    /// [`monetization`](crate::chat::monetization)
+    pub spell_value: BTreeMap<Currency, u64>,
+
+    /// Number of subscription messages
+    pub subscriptions: u64,
+
+    /// Number of subscriptions gifted, counted from the summary gift sub messages
+    pub gifted_subscriptions: u64,
+
+    /// Number of follow messages
+    pub follows: u64,
+}
+
+/// A frequent chatter in a [`SummaryReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopChatter {
+    /// User id of the chatter
+    pub sender_id: UserId,
+
+    /// Display name from the chatter's latest message
+    pub nick_name: String,
+
+    /// Number of chat messages sent. May be too high by up to `max_overcount`.
+    pub messages: u64,
+
+    /// How much `messages` may be overcounted by, `0` when the count is exact
+    pub max_overcount: u64,
+}
+
+#[derive(Debug)]
+struct ChatterCount {
+    nick_name: String,
+    messages: u64,
+    max_overcount: u64,
+}
+
+/// Accumulates a [`SummaryReport`] from chat messages.
+///
+/// Chat messages are the ones typed by viewers: [`Normal`](ChatMessageType::Normal) and the
+/// magic chat types. Only their senders count towards unique and top chatters.
+///
+/// Unique chatters are counted exactly, keeping every sender id seen. Top chatters are tracked
+/// with bounded memory: at most [`track_chatters`](Self::track_chatters) senders are counted at
+/// once. When a new sender arrives while full, the sender with the fewest messages is evicted
+/// and the new sender takes over its count plus one, which is recorded as a possible overcount.
+/// Counts are exact as long as there are fewer distinct chatters than tracked, and any chatter
+/// with more messages than the least counted one is guaranteed to be tracked.
+#[derive(Debug)]
+pub struct ChatSessionSummary {
+    total_messages: u64,
+    chatters: HashSet<i64>,
+    messages_per_type: BTreeMap<ChatMessageType, u64>,
+    tracked: HashMap<i64, ChatterCount>,
+    tracked_capacity: usize,
+    top_chatters: usize,
+    first_message_at: Option<Timestamp>,
+    last_message_at: Option<Timestamp>,
+    spell_value: BTreeMap<Currency, u64>,
+    subscriptions: u64,
+    gifted_subscriptions: u64,
+    follows: u64,
+}
+
+impl Default for ChatSessionSummary {
+    fn default() -> Self {
+        Self {
+            total_messages: 0,
+            chatters: HashSet::new(),
+            messages_per_type: BTreeMap::new(),
+            tracked: HashMap::new(),
+            tracked_capacity: DEFAULT_TRACKED_CHATTERS,
+            top_chatters: DEFAULT_TOP_CHATTERS,
+            first_message_at: None,
+            last_message_at: None,
+            spell_value: BTreeMap::new(),
+            subscriptions: 0,
+            gifted_subscriptions: 0,
+            follows: 0,
+        }
+    }
+}
+
+impl ChatSessionSummary {
+    /// Creates an empty summary, tracking [`DEFAULT_TRACKED_CHATTERS`] chatters and reporting
+    /// the top [`DEFAULT_TOP_CHATTERS`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many chatters are counted at once for the top chatters, at least one.
+    pub fn track_chatters(mut self, capacity: usize) -> Self {
+        self.tracked_capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets how many chatters are included in the report's top chatters.
+    pub fn top_chatters(mut self, count: usize) -> Self {
+        self.top_chatters = count;
+        self
+    }
+
+    /// Adds a message to the summary.
+    pub fn observe(&mut self, message: &ChatMessage) {
+        self.total_messages += 1;
+        *self.messages_per_type.entry(message.type_).or_default() += 1;
+
+        let sent = &message.send_time;
+        if self
+            .first_message_at
+            .as_ref()
+            .is_none_or(|first| sent < first)
+        {
+            self.first_message_at = Some(*sent);
+        }
+        if self.last_message_at.as_ref().is_none_or(|last| sent > last) {
+            self.last_message_at = Some(*sent);
+        }
+
+        match message.type_ {
+            ChatMessageType::Normal
+            | ChatMessageType::MagicSuperCap
+            | ChatMessageType::MagicColorful
+            | ChatMessageType::MagicSpell
+            | ChatMessageType::MagicBulletScreen => {
+                if let Some(sender_id) = message.sender_id {
+                    self.chatters.insert(sender_id);
+                    self.count_chatter(sender_id, &message.nick_name);
+                }
+            }
+            ChatMessageType::Subscription => self.subscriptions += 1,
+            ChatMessageType::Follow => self.follows += 1,
+            _ => {}
+        }
+
+        if let Some(Some(event)) = MonetizationEvent::parse(message) {
+            match event.kind {
+                MonetizationKind::Spell { .. } => {
+                    let total = self.spell_value.entry(event.value.currency).or_default();
+                    *total = total.saturating_add(event.value.amount);
+                }
+                MonetizationKind::GiftSub { count } => {
+                    self.gifted_subscriptions = self.gifted_subscriptions.saturating_add(count);
+                }
+            }
+        }
+    }
+
+    fn count_chatter(&mut self, sender_id: i64, nick_name: &str) {
+        if let Some(count) = self.tracked.get_mut(&sender_id) {
+            count.messages += 1;
+            if count.nick_name != nick_name {
+                count.nick_name = nick_name.to_string();
+            }
+            return;
+        }
+
+        let mut count = ChatterCount {
+            nick_name: nick_name.to_string(),
+            messages: 1,
+            max_overcount: 0,
+        };
+        if self.tracked.len() >= self.tracked_capacity {
+            let evicted = self
+                .tracked
+                .iter()
+                .min_by_key(|(id, count)| (count.messages, **id))
+                .map(|(id, _)| *id);
+            if let Some(evicted) = evicted.and_then(|id| self.tracked.remove(&id)) {
+                count.messages += evicted.messages;
+                count.max_overcount = evicted.messages;
+            }
+        }
+        self.tracked.insert(sender_id, count);
+    }
+
+    /// Builds the report of the messages observed so far.
+    pub fn finish(&self) -> SummaryReport {
+        let mut top_chatters: Vec<_> = self
+            .tracked
+            .iter()
+            .map(|(id, count)| TopChatter {
+                sender_id: UserId::new(id.to_string()),
+                nick_name: count.nick_name.clone(),
+                messages: count.messages,
+                max_overcount: count.max_overcount,
+            })
+            .collect();
+        top_chatters.sort_by(|a, b| {
+            b.messages
+                .cmp(&a.messages)
+                .then_with(|| a.sender_id.cmp(&b.sender_id))
+        });
+        top_chatters.truncate(self.top_chatters);
+
+        SummaryReport {
+            total_messages: self.total_messages,
+            unique_chatters: self.chatters.len() as u64,
+            messages_per_type: self.messages_per_type.clone(),
+            top_chatters,
+            first_message_at: self.first_message_at,
+            last_message_at: self.last_message_at,
+            spell_value: self.spell_value.clone(),
+            subscriptions: self.subscriptions,
+            gifted_subscriptions: self.gifted_subscriptions,
+            follows: self.follows,
+        }
+    }
+
+    /// Observes every message of a stream until it ends, then returns the report.
+    ///
+    /// Stops at the first error, which is returned instead of the report.
+    pub async fn summarize<S, E>(mut self, messages: S) -> Result<SummaryReport, E>
+    where
+        S: Stream<Item = Result<ChatMessage, E>>,
+    {
+        let mut messages = std::pin::pin!(messages);
+        while let Some(message) = messages.next().await {
+            self.observe(&message?);
+        }
+        Ok(self.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use std::convert::Infallible;
+
+    fn chat(sender_id: i64, nick_name: &str) -> ChatMessage {
+        serde_json::from_value(serde_json::json!({
+            "type": 0,
+            "content": "hi",
+            "nick_name": nick_name,
+            "message_id": "0",
+            "sender_id": sender_id,
+            "send_time": 1700000000,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn session_report() {
+        let mut messages: Vec<ChatMessage> =
+            serde_json::from_str(include_str!("../../fixtures/monetization_session.json")).unwrap();
+        messages.extend([chat(101, "alice"), chat(102, "bob"), chat(101, "alice2")]);
+
+        let report = ChatSessionSummary::new()
+            .top_chatters(2)
+            .summarize(stream::iter(messages.into_iter().map(Ok::<_, Infallible>)))
+            .await
+            .unwrap();
+
+        let expected: serde_json::Value =
+            serde_json::from_str(include_str!("../../fixtures/session_summary.json")).unwrap();
+        assert_eq!(serde_json::to_value(&report).unwrap(), expected);
+
+        let roundtrip: SummaryReport = serde_json::from_value(expected).unwrap();
+        assert_eq!(roundtrip, report);
+    }
+
+    #[test]
+    fn bounded_top_chatters() {
+        let mut summary = ChatSessionSummary::new().track_chatters(2).top_chatters(5);
+        for _ in 0..5 {
+            summary.observe(&chat(1, "regular"));
+        }
+        summary.observe(&chat(2, "once"));
+        summary.observe(&chat(3, "late"));
+        summary.observe(&chat(3, "late"));
+
+        let report = summary.finish();
+        assert_eq!(report.unique_chatters, 3);
+        assert_eq!(
+            report.top_chatters,
+            [
+                TopChatter {
+                    sender_id: UserId::new("1"),
+                    nick_name: "regular".to_string(),
+                    messages: 5,
+                    max_overcount: 0,
+                },
+                TopChatter {
+                    sender_id: UserId::new("3"),
+                    nick_name: "late".to_string(),
+                    messages: 3,
+                    max_overcount: 1,
+                },
+            ]
+        );
+    }
+}
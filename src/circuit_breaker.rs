@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+/// Configuration for the client's circuit breaker, see
+/// [`ClientBuilder::circuit_breaker`](crate::ClientBuilder::circuit_breaker).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CircuitBreakerConfig {
+    pub(crate) threshold: u32,
+    pub(crate) window: Duration,
+    pub(crate) cooldown: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Requests flow normally, counting consecutive failures
+    Closed {
+        failures: u32,
+        first_failure: Option<Instant>,
+    },
+
+    /// Requests fail fast until the cool-down has elapsed
+    Open { until: Instant },
+
+    /// A single probe request is in flight, everything else fails fast until it completes
+    HalfOpen { probe_deadline: Instant },
+}
+
+/// Stops sending requests after repeated failures, giving the api a chance to recover.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: State,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: State::Closed {
+                failures: 0,
+                first_failure: None,
+            },
+        }
+    }
+
+    /// Checks whether a request may be sent, returning the time to retry at if not.
+    pub(crate) fn before_request(&mut self, now: Instant) -> Result<(), Instant> {
+        match self.state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until }
+            | State::HalfOpen {
+                probe_deadline: until,
+            } if now < until => Err(until),
+            State::Open { .. } | State::HalfOpen { .. } => {
+                // Let a probe through. If it never reports back, another probe is allowed once
+                // the cool-down elapses again.
+                self.state = State::HalfOpen {
+                    probe_deadline: now + self.config.cooldown,
+                };
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the outcome of a request.
+    pub(crate) fn record(&mut self, now: Instant, success: bool) {
+        self.state = match (self.state, success) {
+            (_, true) => State::Closed {
+                failures: 0,
+                first_failure: None,
+            },
+            (
+                State::Closed {
+                    failures,
+                    first_failure: Some(first_failure),
+                },
+                false,
+            ) if now.duration_since(first_failure) <= self.config.window => {
+                self.failed(failures + 1, first_failure, now)
+            }
+            (State::Closed { .. }, false) => self.failed(1, now, now),
+            (State::Open { .. } | State::HalfOpen { .. }, false) => State::Open {
+                until: now + self.config.cooldown,
+            },
+        };
+    }
+
+    fn failed(&self, failures: u32, first_failure: Instant, now: Instant) -> State {
+        if failures >= self.config.threshold {
+            State::Open {
+                until: now + self.config.cooldown,
+            }
+        } else {
+            State::Closed {
+                failures,
+                first_failure: Some(first_failure),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle() {
+        let mut breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            threshold: 3,
+            window: Duration::from_secs(10),
+            cooldown: Duration::from_secs(30),
+        });
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // Failures outside of the window don't accumulate
+        breaker.record(at(0), false);
+        breaker.record(at(1), false);
+        breaker.record(at(20), false);
+        assert_eq!(breaker.before_request(at(20)), Ok(()));
+
+        // Closed -> Open
+        breaker.record(at(21), false);
+        breaker.record(at(22), false);
+        assert_eq!(breaker.before_request(at(23)), Err(at(52)));
+
+        // Open -> HalfOpen, only the probe is let through
+        assert_eq!(breaker.before_request(at(52)), Ok(()));
+        assert_eq!(breaker.before_request(at(53)), Err(at(82)));
+
+        // Failed probe re-opens
+        breaker.record(at(54), false);
+        assert_eq!(breaker.before_request(at(55)), Err(at(84)));
+
+        // HalfOpen -> Closed
+        assert_eq!(breaker.before_request(at(84)), Ok(()));
+        breaker.record(at(85), true);
+        assert_eq!(breaker.before_request(at(85)), Ok(()));
+        breaker.record(at(86), false);
+        assert_eq!(breaker.before_request(at(86)), Ok(()));
+    }
+}
@@ -1,19 +1,69 @@
 use crate::{
-    access_token, AccessTokenProvider, ApiError, AuthenticatedRequestError, ChannelInfo,
-    ChannelUpdate, ChannelUpdatePayload, ClientIdProvider, EmoteChannels, EmoteFetchType,
-    ErrorStatus, GetChannelByIdPayload, GetEmotesPayload, GetEmotesResponse, GetUsersPayload,
-    GetUsersResponse, RequestError, User,
+    access_token, AccessTokenProvider, ApiResponse, AuthenticatedRequestError, Category,
+    CategoryId, ChannelCommandPayload, ChannelId, ChannelInfo, ChannelUpdate, ChannelUpdatePayload,
+    ClientBuilder, ClientIdProvider, Clip, ClipsQuery, CommandResult, DropsFulfillmentStatus,
+    DropsUpdateResult, EmoteChannels, EmoteFetchType, ErrorStatus, FollowersPage,
+    GetCategoriesResponse, GetChannelByIdPayload, GetChannelByUsernamePayload, GetClipsPayload,
+    GetClipsResponse, GetEmotesPayload, GetEmotesResponse, GetFollowersPayload,
+    GetPastStreamsPayload, GetPastStreamsResponse, GetStreamUrlsPayload, GetStreamUrlsResponse,
+    GetTopChannelsPayload, GetUsersPayload, GetUsersResponse, GetViewersPayload, PastStream,
+    PastStreamsQuery, Pipeline, RateLimitStatus, RequestError, ResponseMeta, SearchCategoryPayload,
+    SendError, StreamUrl, SubscribersPage, SubscriptionInfo, TopChannelsResponse,
+    UpdateDropsEntitlementsPayload, UpdateDropsEntitlementsResponse, User, UserId, UserInfo,
+    Username, UsersLookup, ViewersResponse,
 };
-use reqwest::header;
-use std::time::Duration;
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue},
+    Method, RequestBuilder,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::Arc;
+
+const API_BASE_URL: &str = "https://open-api.trovo.live/openplatform";
+
+/// Default cap on the extra requests [`Client::users_detailed`] makes while splitting lookups
+pub const DEFAULT_USERS_SPLIT_REQUESTS: usize = 20;
+
+/// Percent-encodes a value for use as a single segment of an api path, leaving only unreserved
+/// characters as is.
+pub(crate) fn path_segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Trovo api returns a nulled out channel if it can't be found, a real channel always has a
+/// username and url
+fn found_channel(channel: ChannelInfo) -> Option<ChannelInfo> {
+    if channel.username.is_empty() && channel.channel_url.is_empty() {
+        None
+    } else {
+        Some(channel)
+    }
+}
 
 /// Entrypoint for making requests to the Trovo api.
 #[derive(Debug, Clone)]
 pub struct Client<A> {
     pub(crate) http: reqwest::Client,
+    pub(crate) pipeline: Arc<Pipeline>,
+    pub(crate) default_headers: HeaderMap,
     pub(crate) auth_provider: A,
 }
 
+impl Client<()> {
+    /// Creates a [`ClientBuilder`] to configure a client, e.g. to route requests through a proxy.
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+}
+
 impl<A> Client<A> {
     /// Creates a new default trovo client.
     /// If you are already using reqwest in your program, it is advisable
@@ -23,63 +73,246 @@ impl<A> Client<A> {
     /// # Panics
     ///
     /// This method panics if a TLS backend cannot be initialized, or the resolver cannot load the system configuration.
+    /// See [`Client::try_new`] for a non-panicking alternative.
     pub fn new(auth_provider: A) -> Self {
-        Self {
-            http: reqwest::Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .unwrap(),
-            auth_provider,
-        }
+        Self::try_new(auth_provider).expect("failed to construct trovo client")
+    }
+
+    /// Creates a new default trovo client, returning an error instead of panicking if the TLS
+    /// backend or resolver cannot be initialized.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), reqwest::Error> {
+    /// let client = trovo::Client::try_new(trovo::ClientId::new("client-id"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_new(auth_provider: A) -> Result<Self, reqwest::Error> {
+        ClientBuilder::new().build(auth_provider)
     }
 
     /// Creates a new trovo client using the provided reqwest client.
     ///
     /// This can be useful to allow sharing of a reqwest client's connection pool
     /// across your program
+    ///
+    /// The reqwest client is used as is, so it's up to you to enable response compression via
+    /// reqwest's `gzip`/`brotli` features if desired.
     pub fn from_reqwest(http: reqwest::Client, auth_provider: A) -> Self {
         Self {
+            pipeline: Arc::new(Pipeline::new(Arc::new(http.clone()))),
             http,
+            default_headers: HeaderMap::new(),
             auth_provider,
         }
     }
+
+    /// Creates a new trovo client that executes all requests via the given transport.
+    ///
+    /// Mostly useful for testing with a [`ScriptedTransport`](crate::ScriptedTransport).
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn with_transport(
+        transport: impl crate::HttpTransport + 'static,
+        auth_provider: A,
+    ) -> Self {
+        ClientBuilder::new()
+            .transport(transport)
+            .build(auth_provider)
+            .expect("failed to construct trovo client")
+    }
+
+    /// Creates a new client that sends all requests through the given tower service, e.g. to
+    /// apply timeouts, concurrency limits or metrics via a `tower::ServiceBuilder` stack.
+    ///
+    /// See [`TrovoService`](crate::TrovoService) for a service to wrap.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`Client::new`].
+    #[cfg(feature = "tower")]
+    pub fn with_service<S>(service: S, auth_provider: A) -> Self
+    where
+        S: tower::Service<crate::TrovoRequest, Response = crate::TrovoResponse>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Error: Into<crate::BoxError>,
+        S::Future: Send + 'static,
+    {
+        ClientBuilder::new()
+            .service(service)
+            .build(auth_provider)
+            .expect("failed to construct trovo client")
+    }
+
+    /// Latest known rate limit status, updated after every request. Shared between clones of the
+    /// client.
+    pub fn rate_limit_status(&self) -> RateLimitStatus {
+        *self.pipeline.rate_limit.lock().unwrap()
+    }
+
+    /// Builds the request, executes it via the client's transport, and downloads the response.
+    pub(crate) async fn send(&self, request: RequestBuilder) -> Result<ApiResponse, SendError> {
+        self.pipeline.execute(request.build()?).await
+    }
+
+    /// Same as [`Client::send`], but coalesces identical in-flight requests if enabled via
+    /// [`ClientBuilder::coalesce_requests`]. Only use this for idempotent lookups.
+    pub(crate) async fn send_coalesced(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<ApiResponse, RequestError> {
+        let request = request.build().map_err(SendError::from)?;
+        Ok(self.pipeline.execute_coalesced(request).await?)
+    }
+
+    /// Starts a request to the given api path with the given Client-ID, and the Accept and any
+    /// default headers already attached.
+    pub(crate) fn request_as(&self, method: Method, path: &str, client_id: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}/{}", API_BASE_URL, path))
+            .header(header::ACCEPT, HeaderValue::from_static("application/json"))
+            .headers(self.default_headers.clone())
+            .header("Client-ID", client_id)
+    }
 }
 
 impl<A> Client<A>
 where
     A: ClientIdProvider,
 {
+    /// Starts a request to the given api path, with the Client-ID, Accept, and any default headers
+    /// already attached.
+    pub(crate) fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.request_as(method, path, self.auth_provider.client_id())
+    }
+
     /// Gets a list of user’s channel id, user id, and nickname, by usernames.
     ///
     /// Note: Even if just one of the usernames doesn't exist, the result will be
-    /// an empty vec due to api limitations.
-    pub async fn users(&self, usernames: Vec<String>) -> Result<Vec<User>, RequestError> {
-        let res = self
-            .http
-            .post("https://open-api.trovo.live/openplatform/getusers")
-            .header("Client-ID", self.auth_provider.client_id())
-            .json(&GetUsersPayload { user: usernames })
-            .send()
-            .await?;
+    /// an empty vec due to api limitations, see [`Client::users_detailed`] for a workaround.
+    ///
+    /// Takes either a `Vec<String>` of usernames, normalized with [`Username::parse`], or a payload
+    /// validated with [`GetUsersPayload::builder`].
+    pub async fn users(
+        &self,
+        usernames: impl Into<GetUsersPayload>,
+    ) -> Result<Vec<User>, RequestError> {
+        let (users, _) = self.users_with_meta(usernames).await?;
+        Ok(users)
+    }
 
-        if ApiError::can_handle_code(res.status()) {
-            let err: ApiError = res.json().await.unwrap_or_default();
+    /// Same as [`Client::users`], but also returns the response metadata.
+    pub async fn users_with_meta(
+        &self,
+        usernames: impl Into<GetUsersPayload>,
+    ) -> Result<(Vec<User>, ResponseMeta), RequestError> {
+        let payload = usernames.into();
+        let count = payload.user.len();
+        let res = self
+            .send_coalesced(self.request(Method::POST, "getusers").json(&payload))
+            .await?
+            .summarize(format_args!("{} names", count));
 
+        if let Some(err) = res.api_error() {
             if err.status == ErrorStatus::InvalidParameters {
-                return Ok(vec![]);
+                return Ok((vec![], res.meta));
             } else {
                 return Err(RequestError::ApiError(err));
             }
         }
 
-        let response: GetUsersResponse = res.error_for_status()?.json().await?;
-        Ok(response.users)
+        res.error_for_status()?;
+        let response: GetUsersResponse = res.json()?;
+        Ok((response.users, res.meta))
+    }
+
+    /// Same as [`Client::users`], but works around the api returning no users when any of the
+    /// usernames don't exist, and reports which usernames weren't found.
+    ///
+    /// Usernames are looked up in chunks of [`GetUsersPayload::MAX_USERS`]. When a lookup comes
+    /// back empty, the usernames are split in half and each half looked up again, so an unknown
+    /// name costs a few extra requests instead of hiding its whole chunk. Usernames are matched
+    /// case-insensitively, and found users are returned in the order they were requested, see
+    /// [`UsersLookup::sort_by_request`]. Usernames are normalized with [`Username::parse`] first,
+    /// and reported back in their normalized form.
+    ///
+    /// Splitting makes at most [`DEFAULT_USERS_SPLIT_REQUESTS`] extra requests, see
+    /// [`Client::users_detailed_with_limit`].
+    pub async fn users_detailed(
+        &self,
+        usernames: Vec<String>,
+    ) -> Result<UsersLookup, RequestError> {
+        self.users_detailed_with_limit(usernames, DEFAULT_USERS_SPLIT_REQUESTS)
+            .await
+    }
+
+    /// Same as [`Client::users_detailed`], but makes at most `max_extra_requests` requests beyond
+    /// the one per chunk while splitting. Usernames left unsplit once the limit is reached are
+    /// reported in [`UsersLookup::unresolved`] instead of [`UsersLookup::missing`].
+    pub async fn users_detailed_with_limit(
+        &self,
+        usernames: Vec<String>,
+        max_extra_requests: usize,
+    ) -> Result<UsersLookup, RequestError> {
+        let usernames: Vec<String> = usernames
+            .iter()
+            .map(|name| Username::parse(name).into())
+            .collect();
+        let mut found = Vec::new();
+        let mut unresolved = Vec::new();
+        // Reversed, so chunks are looked up in the requested order
+        let mut chunks: Vec<Vec<String>> = usernames
+            .chunks(GetUsersPayload::MAX_USERS)
+            .rev()
+            .map(<[String]>::to_vec)
+            .collect();
+        let mut requests_left = max_extra_requests + chunks.len();
+        while let Some(mut chunk) = chunks.pop() {
+            if chunk.is_empty() {
+                continue;
+            }
+            if requests_left == 0 {
+                unresolved.append(&mut chunk);
+                continue;
+            }
+            requests_left -= 1;
+            let users = self.users(chunk.clone()).await?;
+            if users.is_empty() && chunk.len() > 1 {
+                let rest = chunk.split_off(chunk.len() / 2);
+                chunks.push(rest);
+                chunks.push(chunk);
+            } else {
+                found.extend(users);
+            }
+        }
+
+        let (unresolved, missing) = usernames
+            .iter()
+            .filter(|name| {
+                !found
+                    .iter()
+                    .any(|user| user.username.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+            .partition(|name| unresolved.contains(name));
+        let mut lookup = UsersLookup {
+            found,
+            missing,
+            unresolved,
+        };
+        lookup.sort_by_request(&usernames);
+        Ok(lookup)
     }
 
     /// Gets a user’s channel id, user id, and nickname, by username.
     ///
+    /// Accepts pasted channel urls and names with a leading `@`, see [`Username::parse`]; pass a
+    /// [`Username::exact`] to look up the string as is.
+    ///
     /// Returns None if the user was not found
-    pub async fn user(&self, username: impl Into<String>) -> Result<Option<User>, RequestError> {
+    pub async fn user(&self, username: impl Into<Username>) -> Result<Option<User>, RequestError> {
         let mut users = self.users(vec![username.into()]).await?;
 
         if !users.is_empty() {
@@ -89,36 +322,336 @@ where
         }
     }
 
+    /// Gets a page of the live channels with the most viewers, optionally filtered by category or
+    /// channel ids. Use [`GetTopChannelsPayload::next_page`] to fetch the following pages.
+    ///
+    /// Payloads outside the api's limits fail with a [`RequestError::Validation`] without making a
+    /// request, see [`GetTopChannelsPayload::validate`].
+    ///
+    /// ```no_run
+    /// # async fn example(client: trovo::Client<trovo::ClientId>) -> Result<(), trovo::RequestError> {
+    /// use trovo::GetTopChannelsPayload;
+    ///
+    /// let mut payload = Some(GetTopChannelsPayload {
+    ///     limit: Some(50),
+    ///     ..Default::default()
+    /// });
+    /// while let Some(page) = payload {
+    ///     let response = client.top_channels(page.clone()).await?;
+    ///     for channel in &response.channels {
+    ///         println!("{}: {} viewers", channel.username, channel.current_viewers);
+    ///     }
+    ///     payload = page.next_page(&response);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn top_channels(
+        &self,
+        payload: GetTopChannelsPayload,
+    ) -> Result<TopChannelsResponse, RequestError> {
+        payload.validate()?;
+        let res = self
+            .send(self.request(Method::POST, "gettopchannels").json(&payload))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        Ok(res.json()?)
+    }
+
     /// Gets channel information for the given id
     ///
     /// Returns None if the channel was not found
     pub async fn channel_by_id(
         &self,
-        channel_id: impl Into<String>,
+        channel_id: impl Into<ChannelId>,
     ) -> Result<Option<ChannelInfo>, RequestError> {
+        let (channel, _) = self.channel_by_id_with_meta(channel_id).await?;
+        Ok(channel)
+    }
+
+    /// Same as [`Client::channel_by_id`], but also returns the response metadata.
+    pub async fn channel_by_id_with_meta(
+        &self,
+        channel_id: impl Into<ChannelId>,
+    ) -> Result<(Option<ChannelInfo>, ResponseMeta), RequestError> {
+        let channel_id: ChannelId = channel_id.into();
+        let summary = format!("channel {}", channel_id);
         let res = self
-            .http
-            .post("https://open-api.trovo.live/openplatform/channels/id")
-            .header("Client-ID", self.auth_provider.client_id())
-            .json(&GetChannelByIdPayload {
-                channel_id: channel_id.into(),
-            })
-            .send()
+            .send_coalesced(self.request(Method::POST, "channels/id").json(
+                &GetChannelByIdPayload {
+                    channel_id: channel_id.0,
+                },
+            ))
+            .await?
+            .summarize(summary);
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        Ok((found_channel(res.json()?), res.meta))
+    }
+
+    /// Gets channel information for each of the given ids, with at most `concurrency` requests
+    /// in flight, see [`Client::batch`].
+    ///
+    /// Each id is returned alongside its own result in the order given, so a failed lookup
+    /// doesn't lose the others.
+    pub async fn channels_by_ids(
+        &self,
+        ids: Vec<String>,
+        concurrency: usize,
+    ) -> Vec<(String, Result<Option<ChannelInfo>, RequestError>)> {
+        self.batch(ids, concurrency, |client, id| client.channel_by_id(id))
+            .run()
+            .await
+    }
+
+    /// Gets channel information for the given username, in one request rather than looking up
+    /// the user first.
+    ///
+    /// Accepts pasted channel urls and names with a leading `@`, see [`Username::parse`]; pass a
+    /// [`Username::exact`] to look up the string as is.
+    ///
+    /// Returns None if the channel was not found
+    pub async fn channel_by_username(
+        &self,
+        username: impl Into<Username>,
+    ) -> Result<Option<ChannelInfo>, RequestError> {
+        let username: Username = username.into();
+        let summary = format!("username {}", username);
+        let res = self
+            .send_coalesced(self.request(Method::POST, "channels/id").json(
+                &GetChannelByUsernamePayload {
+                    username: username.into(),
+                },
+            ))
+            .await?
+            .summarize(summary);
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        Ok(found_channel(res.json()?))
+    }
+
+    /// Gets a page of a channel's followers, most recent first.
+    ///
+    /// `limit` is the number of followers per page, between 1 and 100, and `cursor` the page to
+    /// fetch, starting from 0. The api pages by number rather than with an opaque cursor, so
+    /// [`FollowersPage::next_cursor`] is simply the next page number, and can be stored to resume
+    /// paging later.
+    ///
+    /// ```no_run
+    /// # async fn example(client: trovo::Client<trovo::ClientId>) -> Result<(), trovo::RequestError> {
+    /// let mut cursor = None;
+    /// loop {
+    ///     let page = client.channel_followers("100000", Some(100), cursor).await?;
+    ///     for follower in &page.followers {
+    ///         println!("{}", follower.nickname);
+    ///     }
+    ///     match page.next_cursor() {
+    ///         Some(next) => cursor = Some(next),
+    ///         None => break,
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn channel_followers(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        limit: Option<u32>,
+        cursor: Option<u64>,
+    ) -> Result<FollowersPage, RequestError> {
+        let path = format!(
+            "channels/{}/followers",
+            path_segment(channel_id.into().as_str())
+        );
+        let payload = GetFollowersPayload { limit, cursor };
+        let res = self
+            .send_coalesced(self.request(Method::POST, &path).json(&payload))
             .await?;
 
-        if ApiError::can_handle_code(res.status()) {
-            let err: ApiError = res.json().await.unwrap_or_default();
+        if let Some(err) = res.api_error() {
             return Err(RequestError::ApiError(err));
         }
 
-        let channel: ChannelInfo = res.error_for_status()?.json().await?;
-        Ok(if channel.username.is_empty() {
-            // Trovo api returns a nulled out channel if it can't be found, username is probably
-            // never legitimately blank
-            None
-        } else {
-            Some(channel)
-        })
+        res.error_for_status()?;
+        Ok(res.json()?)
+    }
+
+    /// Gets a page of the viewers of a live channel, grouped by role.
+    ///
+    /// `limit` is the number of viewers per page, between 1 and 100000, and `cursor` the page to
+    /// fetch, starting from 0. Like [`Client::channel_followers`], the api pages by number, so
+    /// [`ViewersResponse::next_cursor`] is the `cursor` of the following page.
+    pub async fn channel_viewers(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        limit: Option<u32>,
+        cursor: Option<u64>,
+    ) -> Result<ViewersResponse, RequestError> {
+        let path = format!(
+            "channels/{}/viewers",
+            path_segment(channel_id.into().as_str())
+        );
+        let payload = GetViewersPayload { limit, cursor };
+        let res = self
+            .send_coalesced(self.request(Method::POST, &path).json(&payload))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        Ok(res.json()?)
+    }
+
+    /// Gets a page of a channel's clips.
+    ///
+    /// Use [`ClipsQuery::next_page`] to fetch the following pages, an empty list means there are
+    /// no more clips.
+    pub async fn clips(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        options: ClipsQuery,
+    ) -> Result<Vec<Clip>, RequestError> {
+        let channel_id: ChannelId = channel_id.into();
+        let summary = format!("channel {}", channel_id);
+        let res = self
+            .send(self.request(Method::POST, "clips").json(&GetClipsPayload {
+                channel_id: channel_id.0,
+                query: options,
+            }))
+            .await?
+            .summarize(summary);
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        let response: GetClipsResponse = res.json()?;
+        Ok(response.clips_info)
+    }
+
+    /// Gets a page of a channel's past broadcasts, e.g. alongside [`Client::channel_by_id`] for a
+    /// channel's archive.
+    ///
+    /// Use [`PastStreamsQuery::next_page`] to fetch the following pages, an empty list means
+    /// there are no more past streams.
+    pub async fn past_streams(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        query: PastStreamsQuery,
+    ) -> Result<Vec<PastStream>, RequestError> {
+        let channel_id: ChannelId = channel_id.into();
+        let summary = format!("channel {}", channel_id);
+        let res = self
+            .send(
+                self.request(Method::POST, "paststreams")
+                    .json(&GetPastStreamsPayload {
+                        channel_id: channel_id.0,
+                        query,
+                    }),
+            )
+            .await?
+            .summarize(summary);
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        let response: GetPastStreamsResponse = res.json()?;
+        Ok(response.video_info)
+    }
+
+    /// Gets the m3u8 playback urls of a live channel, one per quality.
+    ///
+    /// Returns an empty list if the channel is offline, use [`Client::channel_by_id`] to check
+    /// whether it's live.
+    pub async fn stream_urls(
+        &self,
+        channel_id: impl Into<ChannelId>,
+    ) -> Result<Vec<StreamUrl>, RequestError> {
+        let channel_id: ChannelId = channel_id.into();
+        let summary = format!("channel {}", channel_id);
+        let res = self
+            .send(
+                self.request(Method::POST, "livestreamurl")
+                    .json(&GetStreamUrlsPayload {
+                        channel_id: channel_id.0,
+                    }),
+            )
+            .await?
+            .summarize(summary);
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        let response: GetStreamUrlsResponse = res.json()?;
+        Ok(response.stream_urls)
+    }
+
+    /// Gets the most popular game categories, e.g. for picking a
+    /// [`ChannelUpdate::category_id`].
+    ///
+    /// The api returns every category in one response, so there is no paging.
+    pub async fn categories(&self) -> Result<Vec<Category>, RequestError> {
+        let res = self
+            .send_coalesced(self.request(Method::GET, "categorys/top"))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        let response: GetCategoriesResponse = res.json()?;
+        Ok(response.category_info)
+    }
+
+    /// Searches game categories by name, e.g. for picking a [`ChannelUpdate::category_id`] from
+    /// what a user typed.
+    ///
+    /// Returns an empty vec if no categories match.
+    pub async fn search_categories(
+        &self,
+        query: impl Into<String>,
+        limit: Option<u32>,
+    ) -> Result<Vec<Category>, RequestError> {
+        let payload = SearchCategoryPayload {
+            query: query.into(),
+            limit,
+        };
+        let res = self
+            .send_coalesced(self.request(Method::POST, "searchcategory").json(&payload))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            if err.status == ErrorStatus::InvalidCategory {
+                return Ok(vec![]);
+            } else {
+                return Err(RequestError::ApiError(err));
+            }
+        }
+
+        res.error_for_status()?;
+        let response: GetCategoriesResponse = res.json()?;
+        Ok(response.category_info)
     }
 
     /// Gets list of emotes for each channel and global/event emotes if requested.
@@ -127,62 +660,1934 @@ where
         emote_type: EmoteFetchType,
         channel_ids: Vec<String>,
     ) -> Result<EmoteChannels, RequestError> {
+        self.emotes_with_payload(GetEmotesPayload {
+            emote_type,
+            channel_id: channel_ids,
+        })
+        .await
+    }
+
+    /// Same as [`Client::emotes`], but takes a payload validated with
+    /// [`GetEmotesPayload::builder`].
+    pub async fn emotes_with_payload(
+        &self,
+        payload: GetEmotesPayload,
+    ) -> Result<EmoteChannels, RequestError> {
+        let count = payload.channel_id.len();
         let res = self
-            .http
-            .post("https://open-api.trovo.live/openplatform/getemotes")
-            .header("Client-ID", self.auth_provider.client_id())
-            .json(&GetEmotesPayload {
-                emote_type,
-                channel_id: channel_ids,
-            })
-            .send()
-            .await?;
+            .send(self.request(Method::POST, "getemotes").json(&payload))
+            .await?
+            .summarize(format_args!("{} channels", count));
 
-        if ApiError::can_handle_code(res.status()) {
-            let err: ApiError = res.json().await.unwrap_or_default();
+        if let Some(err) = res.api_error() {
             return Err(RequestError::ApiError(err));
         }
 
-        let response: GetEmotesResponse = res.error_for_status()?.json().await?;
+        res.error_for_status()?;
+        let response: GetEmotesResponse = res.json()?;
         Ok(response.channels)
     }
+
+    /// Sends a request to an api path this crate doesn't wrap yet, decoding the json response.
+    ///
+    /// The path is relative to the api base, e.g. `"getusers"`, and the body is sent as json.
+    /// Requests go through the same pipeline as the wrapped endpoints: the Client-ID and default
+    /// headers are attached, retries and middleware apply, and error responses are mapped to
+    /// [`RequestError::ApiError`]. See [`Client::request_raw_authenticated`] for endpoints that
+    /// need an access token.
+    ///
+    /// **Unstable:** this is an escape hatch, not part of the crate's stable api; it may change
+    /// in minor releases, and the endpoint's shape is up to you.
+    ///
+    /// ```no_run
+    /// # async fn example(client: trovo::Client<trovo::ClientId>) -> Result<(), trovo::RequestError> {
+    /// use reqwest::Method;
+    ///
+    /// let response: serde_json::Value = client
+    ///     .request_raw(Method::POST, "getusers", Some(&serde_json::json!({"user": ["someone"]})))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request_raw<T, B>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<T, RequestError>
+    where
+        T: DeserializeOwned,
+        B: Serialize + ?Sized,
+    {
+        let mut request = self.request(method, path);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let res = self.send(request).await?;
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        Ok(res.json()?)
+    }
 }
 
 impl<A> Client<A>
 where
     A: AccessTokenProvider,
 {
-    /// Allows you to update the user’s channel settings, including title, category, language,
-    /// audience type. You may update only part of the info.
-    pub async fn update_channel(
+    /// Gets the authenticated user, e.g. for a bot to find its own username and channel id.
+    ///
+    /// Invalid, expired or under-scoped tokens fail with an
+    /// [`AuthenticatedRequestError::ApiError`], see [`ErrorStatus::is_auth_error`].
+    pub async fn me(&self) -> Result<UserInfo, AuthenticatedRequestError<A::Error>> {
+        let res = self
+            .send(self.request(Method::GET, "getuserinfo").header(
+                header::AUTHORIZATION,
+                format!(
+                    "OAuth {}",
+                    access_token!(self.auth_provider, AuthenticatedRequestError)
+                ),
+            ))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            Err(AuthenticatedRequestError::ApiError(err))
+        } else {
+            res.error_for_status()?;
+            Ok(res.json()?)
+        }
+    }
+
+    /// Gets a page of the subscribers of the authenticated user's channel.
+    ///
+    /// Requires the `channel_subscriptions` scope, a token without it fails with an
+    /// [`AuthenticatedRequestError::ApiError`] of [`ErrorStatus::UnauthorizedScope`]. `offset` is
+    /// the number of subscribers to skip, and `limit` defaults to 25 on the api's side.
+    pub async fn channel_subscribers(
         &self,
-        channel_id: impl Into<String>,
-        update: ChannelUpdate,
+        channel_id: impl Into<ChannelId>,
+        limit: Option<u32>,
+        offset: Option<u64>,
+    ) -> Result<SubscribersPage, AuthenticatedRequestError<A::Error>> {
+        let path = format!(
+            "channels/{}/subscriptions",
+            path_segment(channel_id.into().as_str())
+        );
+        let mut request = self.request(Method::GET, &path).header(
+            header::AUTHORIZATION,
+            format!(
+                "OAuth {}",
+                access_token!(self.auth_provider, AuthenticatedRequestError)
+            ),
+        );
+        if let Some(limit) = limit {
+            request = request.query(&[("limit", limit)]);
+        }
+        if let Some(offset) = offset {
+            request = request.query(&[("offset", offset)]);
+        }
+        let res = self.send(request).await?;
+
+        if let Some(err) = res.api_error() {
+            return Err(AuthenticatedRequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        Ok(res.json()?)
+    }
+
+    /// Sets only the title of the user's channel, leaving the other settings as they are, see
+    /// [`Client::update_channel`].
+    pub async fn set_live_title(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        title: impl Into<String>,
+    ) -> Result<(), AuthenticatedRequestError<A::Error>> {
+        let update = ChannelUpdate {
+            live_title: Some(title.into()),
+            ..Default::default()
+        };
+        self.update_channel(channel_id, update).await
+    }
+
+    /// Sets only the category of the user's channel, leaving the other settings as they are, see
+    /// [`Client::update_channel`].
+    pub async fn set_category(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        category_id: impl Into<CategoryId>,
     ) -> Result<(), AuthenticatedRequestError<A::Error>> {
+        let update = ChannelUpdate {
+            category_id: Some(category_id.into()),
+            ..Default::default()
+        };
+        self.update_channel(channel_id, update).await
+    }
+
+    /// Gets a user's subscription to a channel, e.g. to gate sub-only bot commands.
+    ///
+    /// Returns None if the user isn't subscribed. Requires the `channel_subscriptions` scope of
+    /// the channel's streamer, a token without it fails with an
+    /// [`AuthenticatedRequestError::ApiError`] of [`ErrorStatus::UnauthorizedScope`].
+    pub async fn subscription_status(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        user_id: impl Into<UserId>,
+    ) -> Result<Option<SubscriptionInfo>, AuthenticatedRequestError<A::Error>> {
+        let path = format!(
+            "channels/{}/subscriptions/{}",
+            path_segment(channel_id.into().as_str()),
+            path_segment(user_id.into().as_str()),
+        );
         let res = self
-            .http
-            .post("https://open-api.trovo.live/openplatform/channels/update")
-            .header("Client-ID", self.auth_provider.client_id())
-            .header(
+            .send(self.request(Method::GET, &path).header(
                 header::AUTHORIZATION,
                 format!(
                     "OAuth {}",
                     access_token!(self.auth_provider, AuthenticatedRequestError)
                 ),
+            ))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            if err.status == ErrorStatus::NotSubscribed {
+                return Ok(None);
+            } else {
+                return Err(AuthenticatedRequestError::ApiError(err));
+            }
+        }
+
+        res.error_for_status()?;
+        Ok(Some(res.json()?))
+    }
+
+    /// Allows you to update the user’s channel settings, including title, category, language,
+    /// audience type. You may update only part of the info, fields left as `None` are unchanged.
+    ///
+    /// Requires the `channel_update_scope` scope. Titles rejected by moderation come back as an
+    /// [`AuthenticatedRequestError::ApiError`], see [`ErrorStatus::is_moderation`]. Updates
+    /// outside the api's limits, e.g. titles longer than [`ChannelUpdate::MAX_TITLE_CHARS`], fail
+    /// with an [`AuthenticatedRequestError::Validation`] without making a request.
+    pub async fn update_channel(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        update: ChannelUpdate,
+    ) -> Result<(), AuthenticatedRequestError<A::Error>> {
+        update.validate()?;
+        let channel_id: ChannelId = channel_id.into();
+        let res = self
+            .send(
+                self.request(Method::POST, "channels/update")
+                    .header(
+                        header::AUTHORIZATION,
+                        format!(
+                            "OAuth {}",
+                            access_token!(self.auth_provider, AuthenticatedRequestError)
+                        ),
+                    )
+                    .json(&ChannelUpdatePayload {
+                        channel_id: channel_id.0,
+                        update,
+                    }),
             )
-            .json(&ChannelUpdatePayload {
-                channel_id: channel_id.into(),
-                update,
-            })
-            .send()
             .await?;
 
-        if ApiError::can_handle_code(res.status()) {
-            let err: ApiError = res.json().await.unwrap_or_default();
+        if let Some(err) = res.api_error() {
             Err(AuthenticatedRequestError::ApiError(err))
         } else {
             res.error_for_status()?;
             Ok(())
         }
     }
+
+    /// Performs a chat command in a channel as the authenticated user, e.g. `/slow 30` or
+    /// `/settitle Speedruns`.
+    ///
+    /// Commands the api rejects, such as banning a user that doesn't exist, still return `Ok` with
+    /// [`CommandResult::is_success`] set to `false` and the reason in
+    /// [`CommandResult::display_msg`]. Requires the `manage_messages` scope.
+    pub async fn perform_channel_command(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        command: impl Into<String>,
+    ) -> Result<CommandResult, AuthenticatedRequestError<A::Error>> {
+        let channel_id: ChannelId = channel_id.into();
+        let res = self
+            .send(
+                self.request(Method::POST, "channels/command")
+                    .header(
+                        header::AUTHORIZATION,
+                        format!(
+                            "OAuth {}",
+                            access_token!(self.auth_provider, AuthenticatedRequestError)
+                        ),
+                    )
+                    .json(&ChannelCommandPayload {
+                        command: command.into(),
+                        channel_id: channel_id.0,
+                    }),
+            )
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            return Err(AuthenticatedRequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        Ok(res.json()?)
+    }
+
+    /// Sets the fulfillment status of a batch of drops entitlements, e.g. to
+    /// [`DropsFulfillmentStatus::Fulfilled`] once the in-game item has been granted.
+    ///
+    /// Returns the outcome for each id, so ids that failed can be retried or reported while the
+    /// rest are updated. Between 1 and [`UpdateDropsEntitlementsPayload::MAX_ENTITLEMENTS`] ids are
+    /// accepted, other counts fail with an [`AuthenticatedRequestError::Validation`] without
+    /// making a request.
+    pub async fn update_drops_entitlements(
+        &self,
+        entitlement_ids: Vec<String>,
+        status: DropsFulfillmentStatus,
+    ) -> Result<Vec<DropsUpdateResult>, AuthenticatedRequestError<A::Error>> {
+        let payload = UpdateDropsEntitlementsPayload {
+            entitlement_ids,
+            fulfillment_status: status,
+        };
+        payload.validate()?;
+        let count = payload.entitlement_ids.len();
+        let res = self
+            .send(
+                self.request(Method::POST, "drops/entitlements/update")
+                    .header(
+                        header::AUTHORIZATION,
+                        format!(
+                            "OAuth {}",
+                            access_token!(self.auth_provider, AuthenticatedRequestError)
+                        ),
+                    )
+                    .json(&payload),
+            )
+            .await?
+            .summarize(format_args!("{} entitlements", count));
+
+        if let Some(err) = res.api_error() {
+            return Err(AuthenticatedRequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        let response: UpdateDropsEntitlementsResponse = res.json()?;
+        Ok(response.results)
+    }
+
+    /// Same as [`Client::request_raw`], but sends the access token too, refreshing it first if
+    /// needed.
+    ///
+    /// **Unstable:** see [`Client::request_raw`].
+    pub async fn request_raw_authenticated<T, B>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<T, AuthenticatedRequestError<A::Error>>
+    where
+        T: DeserializeOwned,
+        B: Serialize + ?Sized,
+    {
+        let mut request = self.request(method, path).header(
+            header::AUTHORIZATION,
+            format!(
+                "OAuth {}",
+                access_token!(self.auth_provider, AuthenticatedRequestError)
+            ),
+        );
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let res = self.send(request).await?;
+
+        if let Some(err) = res.api_error() {
+            return Err(AuthenticatedRequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        Ok(res.json()?)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        AccessTokenOnly, ApiError, ClientId, ClipSort, ScriptedTransport, SortDirection,
+        ValidationError, RATE_LIMIT_REMAINING_HEADER, RATE_LIMIT_RESET_HEADER, TRACE_ID_HEADER,
+    };
+    use reqwest::StatusCode;
+    use std::time::Duration;
+
+    pub(crate) const CHANNEL_FIXTURE: &str = r#"{
+        "is_live": true,
+        "category_id": "10013",
+        "category_name": "Minecraft",
+        "live_title": "Building things",
+        "audi_type": "CHANNEL_AUDIENCE_TYPE_FAMILYFRIENDLY",
+        "language_code": "en",
+        "thumbnail": "https://example.com/thumb.jpg",
+        "current_viewers": 12,
+        "followers": 345,
+        "streamer_info": "Just a streamer",
+        "profile_pic": "https://example.com/pic.jpg",
+        "channel_url": "https://trovo.live/someone",
+        "created_at": "1573829033",
+        "subscriber_num": 6,
+        "username": "someone",
+        "social_links": [{ "type": "twitter", "url": "https://twitter.com/someone" }],
+        "started_at": "1625486400",
+        "ended_at": "1625490000"
+    }"#;
+
+    pub(crate) const EMPTY_CHANNEL_FIXTURE: &str = r#"{
+        "is_live": false,
+        "category_id": "",
+        "category_name": "",
+        "live_title": "",
+        "audi_type": "CHANNEL_AUDIENCE_TYPE_FAMILYFRIENDLY",
+        "language_code": "",
+        "thumbnail": "",
+        "current_viewers": 0,
+        "followers": 0,
+        "streamer_info": "",
+        "profile_pic": "",
+        "channel_url": "",
+        "created_at": "0",
+        "subscriber_num": 0,
+        "username": "",
+        "social_links": [],
+        "started_at": "0",
+        "ended_at": "0"
+    }"#;
+
+    /// Error the api returns when any of the requested usernames don't exist
+    const INVALID: &str = r#"{"status": 1002, "message": "invalid params"}"#;
+
+    pub(crate) fn scripted_client() -> (Client<ClientId>, ScriptedTransport) {
+        let transport = ScriptedTransport::new();
+        let client = Client::with_transport(transport.clone(), ClientId::new("client-id"));
+        (client, transport)
+    }
+
+    pub(crate) fn authenticated_client() -> (Client<AccessTokenOnly>, ScriptedTransport) {
+        let transport = ScriptedTransport::new();
+        let client = Client::with_transport(
+            transport.clone(),
+            AccessTokenOnly::new("client-id", "access-token"),
+        );
+        (client, transport)
+    }
+
+    #[tokio::test]
+    async fn users_invalid_parameters_is_empty() {
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::BAD_REQUEST,
+            r#"{"status": 1002, "message": "invalid params"}"#,
+        );
+
+        let users = client.users(vec!["nobody".to_string()]).await.unwrap();
+        assert!(users.is_empty());
+    }
+
+    #[tokio::test]
+    async fn users_api_error() {
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::UNAUTHORIZED,
+            r#"{"status": 11706, "message": "rate limited"}"#,
+        );
+
+        let err = client.users(vec!["someone".to_string()]).await.unwrap_err();
+        match err {
+            RequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::RateLimitExceeded);
+                assert_eq!(err.message, "rate limited");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_error_code_preserved() {
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::BAD_REQUEST,
+            r#"{"status": 99999, "message": "new moderation rule"}"#,
+        );
+
+        let err = client.channel_by_id("1").await.unwrap_err();
+        let message = err.to_string();
+        match err {
+            RequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::Other(99999));
+                assert_eq!(err.status.code(), 99999);
+                assert_eq!(err.message, "new moderation rule");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(message.contains("99999"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn json_errors_for_any_status() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(
+                StatusCode::FORBIDDEN,
+                r#"{"status": 11707, "message": "no permission"}"#,
+            )
+            .push(
+                StatusCode::TOO_MANY_REQUESTS,
+                r#"{"status": 11706, "message": "rate limited"}"#,
+            );
+
+        match client.channel_by_id("1").await.unwrap_err() {
+            RequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::MissingChatPermission);
+                assert_eq!(err.message, "no permission");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        match client.channel_by_id("1").await.unwrap_err() {
+            RequestError::ApiError(err) => assert!(err.is_rate_limited()),
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn endpoint_in_errors() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 11706, "message": "rate limited"}"#,
+            )
+            .push(StatusCode::OK, "not json")
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 11706, "message": "rate limited"}"#,
+            );
+
+        let err = client
+            .users(vec![
+                "one".to_string(),
+                "two".to_string(),
+                "three".to_string(),
+            ])
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("bad request to getusers (3 names): "),
+            "{}",
+            err
+        );
+
+        let err = client.channel_by_id("100000").await.unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("failed to decode response from channels/id (channel 100000)"),
+            "{}",
+            err
+        );
+
+        let err = client
+            .emotes(EmoteFetchType::All, vec!["100000".to_string()])
+            .await
+            .unwrap_err();
+        match err {
+            RequestError::ApiError(err) => {
+                assert_eq!(err.endpoint.as_deref(), Some("getemotes (1 channels)"))
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn emotes_custom() {
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::OK,
+            r#"{
+                "channels": {
+                    "customizedEmotes": {
+                        "channel": [
+                            {
+                                "channel_id": "100000",
+                                "emotes": [{
+                                    "name": "wave",
+                                    "description": "",
+                                    "url": "https://example.com/wave.png",
+                                    "status": "1",
+                                    "gifp": "",
+                                    "webp": "https://example.com/wave.webp",
+                                    "update_time": "1625486400"
+                                }]
+                            },
+                            { "channel_id": "200000", "emotes": [] }
+                        ]
+                    },
+                    "eventEmotes": [],
+                    "globalEmotes": []
+                }
+            }"#,
+        );
+
+        let channels = client
+            .emotes(
+                EmoteFetchType::Custom,
+                vec!["100000".to_string(), "200000".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            transport.requests()[0].json(),
+            serde_json::json!({"emote_type": 1, "channel_id": ["100000", "200000"]})
+        );
+        let channel = &channels.customized_emotes.channel;
+        assert_eq!(channel.len(), 2);
+        assert_eq!(channel[0].channel_id, "100000");
+        assert_eq!(channel[0].emotes[0].name, "wave");
+        assert!(channel[1].emotes.is_empty());
+        assert!(channels.global_emotes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn top_channels() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{
+                    "top_channels_lists": [{
+                        "channel_id": "100000",
+                        "is_live": true,
+                        "category_id": "10013",
+                        "category_name": "Minecraft",
+                        "live_title": "Building things",
+                        "audi_type": "CHANNEL_AUDIENCE_TYPE_FAMILYFRIENDLY",
+                        "language_code": "en",
+                        "thumbnail": "https://example.com/thumb.jpg",
+                        "current_viewers": "12",
+                        "num_followers": 345,
+                        "streamer_info": "",
+                        "profile_pic": "https://example.com/pic.jpg",
+                        "channel_url": "https://trovo.live/someone",
+                        "username": "someone",
+                        "nick_name": "Someone"
+                    }],
+                    "total_page": 2,
+                    "token": "page-token",
+                    "cursor": 0
+                }"#,
+            )
+            .push(
+                StatusCode::OK,
+                r#"{"top_channels_lists": [], "total_page": 2, "cursor": 2}"#,
+            )
+            .push(StatusCode::OK, r#"{"top_channels_lists": null}"#);
+
+        let payload = GetTopChannelsPayload {
+            limit: Some(1),
+            category_id: Some("10013".into()),
+            ..Default::default()
+        };
+        let response = client.top_channels(payload.clone()).await.unwrap();
+        let channel = &response.channels[0];
+        assert_eq!(channel.channel_id(), ChannelId::new("100000"));
+        assert_eq!(channel.current_viewers, 12);
+        assert_eq!(channel.streamer_info, None);
+        assert_eq!(
+            channel.category(),
+            Some((CategoryId::new("10013"), "Minecraft"))
+        );
+
+        // The next page keeps the filters
+        let next = payload.next_page(&response).unwrap();
+        assert_eq!(next.token.as_deref(), Some("page-token"));
+        assert_eq!(next.cursor, Some(0));
+        assert_eq!(next.after, Some(true));
+
+        // Past the last page there are no channels, however the api says so
+        for _ in 0..2 {
+            let response = client.top_channels(next.clone()).await.unwrap();
+            assert!(response.channels.is_empty());
+            assert_eq!(next.next_page(&response), None);
+        }
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/gettopchannels"
+        );
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"limit": 1, "category_id": "10013"})
+        );
+        assert_eq!(
+            requests[1].json(),
+            serde_json::json!({
+                "limit": 1,
+                "after": true,
+                "token": "page-token",
+                "cursor": 0,
+                "category_id": "10013"
+            })
+        );
+
+        let err = client
+            .top_channels(GetTopChannelsPayload {
+                limit: Some(GetTopChannelsPayload::MAX_LIMIT + 1),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RequestError::Validation(ValidationError::TooLarge {
+                field: "limit",
+                value: 101,
+                max: 100,
+            })
+        ));
+        assert!(!err.is_retryable());
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn categories() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{"category_info": [{
+                    "id": "10013",
+                    "name": "Minecraft",
+                    "short_name": "MC",
+                    "icon_url": "https://example.com/minecraft.png",
+                    "desc": "Blocks"
+                }]}"#,
+            )
+            .push(StatusCode::OK, r#"{"category_info": []}"#);
+
+        let categories = client.categories().await.unwrap();
+        assert_eq!(categories[0].id, CategoryId::new("10013"));
+        assert_eq!(categories[0].short_name, "MC");
+        assert!(client.categories().await.unwrap().is_empty());
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/categorys/top"
+        );
+    }
+
+    #[tokio::test]
+    async fn search_categories() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{"category_info": [
+                    {
+                        "id": "10017",
+                        "name": "原神",
+                        "short_name": "Genshin",
+                        "icon_url": "https://example.com/genshin.png",
+                        "desc": "オープンワールド"
+                    },
+                    {
+                        "id": "10045",
+                        "name": "Pokémon Unite",
+                        "short_name": "PU",
+                        "icon_url": "https://example.com/unite.png",
+                        "desc": ""
+                    }
+                ]}"#,
+            )
+            .push(StatusCode::OK, r#"{"category_info": []}"#)
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 11000, "message": "invalid category"}"#,
+            )
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 1002, "message": "invalid params"}"#,
+            );
+
+        let categories = client.search_categories("原", Some(5)).await.unwrap();
+        assert_eq!(categories[0].name, "原神");
+        assert_eq!(categories[0].desc, "オープンワールド");
+        assert_eq!(categories[1].name, "Pokémon Unite");
+
+        assert!(client
+            .search_categories("nothing", None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(client
+            .search_categories("nothing", None)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            client.search_categories("", None).await,
+            Err(RequestError::ApiError(_))
+        ));
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/searchcategory"
+        );
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"query": "原", "limit": 5})
+        );
+        assert_eq!(requests[1].json(), serde_json::json!({"query": "nothing"}));
+    }
+
+    #[tokio::test]
+    async fn update_channel_moderated() {
+        let (client, transport) = authenticated_client();
+        transport.push(
+            StatusCode::BAD_REQUEST,
+            r#"{"status": 11101, "message": "title rejected"}"#,
+        );
+
+        let update = ChannelUpdate {
+            live_title: Some("Something rude".to_string()),
+            ..Default::default()
+        };
+        match client.update_channel("100000", update).await.unwrap_err() {
+            AuthenticatedRequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::Moderated1);
+                assert!(err.is_moderation());
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(
+            transport.requests()[0].json(),
+            serde_json::json!({"channel_id": "100000", "live_title": "Something rude"})
+        );
+    }
+
+    #[tokio::test]
+    async fn set_live_title_and_category() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(StatusCode::OK, "{}")
+            .push(StatusCode::OK, "{}");
+
+        client.set_live_title("100000", "Speedruns").await.unwrap();
+        client.set_category("100000", "10013").await.unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"channel_id": "100000", "live_title": "Speedruns"})
+        );
+        assert_eq!(
+            requests[1].json(),
+            serde_json::json!({"channel_id": "100000", "category_id": "10013"})
+        );
+    }
+
+    #[tokio::test]
+    async fn set_live_title_too_long() {
+        let (client, transport) = authenticated_client();
+        transport.push(StatusCode::OK, "{}");
+
+        // Counted in characters rather than bytes
+        let longest = "é".repeat(ChannelUpdate::MAX_TITLE_CHARS);
+        client.set_live_title("100000", longest).await.unwrap();
+
+        let too_long = "a".repeat(ChannelUpdate::MAX_TITLE_CHARS + 1);
+        match client.set_live_title("100000", too_long).await.unwrap_err() {
+            AuthenticatedRequestError::Validation(err) => assert_eq!(
+                err,
+                ValidationError::TooLong {
+                    field: "live_title",
+                    len: ChannelUpdate::MAX_TITLE_CHARS + 1,
+                    max: ChannelUpdate::MAX_TITLE_CHARS,
+                }
+            ),
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let update = ChannelUpdate {
+            live_title: Some("a".repeat(ChannelUpdate::MAX_TITLE_CHARS + 1)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            client.update_channel("100000", update).await,
+            Err(AuthenticatedRequestError::Validation(
+                ValidationError::TooLong { .. }
+            ))
+        ));
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn channel_followers() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{
+                    "total": "3",
+                    "follower": [
+                        {
+                            "user_id": "200000",
+                            "nickname": "Follower",
+                            "profile_pic": "https://example.com/pic.jpg",
+                            "followed_at": "1625486400"
+                        },
+                        {
+                            "user_id": "200001",
+                            "nickname": "Old follower",
+                            "profile_pic": "",
+                            "followed_at": "0"
+                        }
+                    ],
+                    "total_page": 2,
+                    "cur_page": 0
+                }"#,
+            )
+            .push(
+                StatusCode::OK,
+                r#"{"total": 3, "follower": null, "total_page": 2, "cur_page": 1}"#,
+            );
+
+        let page = client
+            .channel_followers("100000", Some(2), None)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.followers[0].user_id(), UserId::new("200000"));
+        assert_eq!(
+            page.followers[0]
+                .followed_at
+                .as_ref()
+                .map(crate::timestamp::unix_seconds),
+            Some(1625486400)
+        );
+        assert_eq!(page.followers[1].profile_pic, None);
+        assert_eq!(page.followers[1].followed_at, None);
+        assert_eq!(page.next_cursor(), Some(1));
+
+        let page = client
+            .channel_followers("100000", Some(2), page.next_cursor())
+            .await
+            .unwrap();
+        assert!(page.followers.is_empty());
+        assert_eq!(page.next_cursor(), None);
+
+        // Ids are a single path segment, so they can't reach other endpoints
+        transport.push(StatusCode::OK, r#"{"follower": []}"#);
+        client
+            .channel_followers("1/../../x?y", None, None)
+            .await
+            .unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/channels/100000/followers"
+        );
+        assert_eq!(
+            requests[2].url.path(),
+            "/openplatform/channels/1%2F..%2F..%2Fx%3Fy/followers"
+        );
+        assert_eq!(requests[0].json(), serde_json::json!({"limit": 2}));
+        assert_eq!(
+            requests[1].json(),
+            serde_json::json!({"limit": 2, "cursor": 1})
+        );
+    }
+
+    #[tokio::test]
+    async fn me() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{"userId": "100000", "userName": "bot", "nickName": "Bot", "channelId": "100001"}"#,
+            )
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11714, "message": "access token expired"}"#,
+            );
+
+        let me = client.me().await.unwrap();
+        assert_eq!(me.username, "bot");
+        assert_eq!(me.channel_id(), ChannelId::new("100001"));
+
+        match client.me().await.unwrap_err() {
+            AuthenticatedRequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::AccessTokenExpired);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/getuserinfo"
+        );
+        assert_eq!(
+            requests[0].headers[header::AUTHORIZATION],
+            "OAuth access-token"
+        );
+    }
+
+    #[tokio::test]
+    async fn channel_subscribers() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{"total": 1, "subscriptions": [{
+                    "user": {"user_id": "200000", "username": "fan", "display_name": "Fan"},
+                    "sub_created_at": 1625486400,
+                    "sub_lv": "sub_L1",
+                    "sub_tier": "1"
+                }]}"#,
+            )
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11730, "message": "unauthorized scope"}"#,
+            );
+
+        let page = client
+            .channel_subscribers("100000", Some(10), Some(20))
+            .await
+            .unwrap();
+        assert_eq!(page.subscribers[0].user.username, "fan");
+
+        match client
+            .channel_subscribers("100000", None, None)
+            .await
+            .unwrap_err()
+        {
+            AuthenticatedRequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::UnauthorizedScope);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/channels/100000/subscriptions?limit=10&offset=20"
+        );
+        assert_eq!(
+            requests[0].headers[header::AUTHORIZATION],
+            "OAuth access-token"
+        );
+        assert_eq!(requests[1].url.query(), None);
+    }
+
+    #[tokio::test]
+    async fn channel_subscribers_encodes_channel_id() {
+        let (client, transport) = authenticated_client();
+        transport.push(StatusCode::OK, r#"{"total": 0, "subscriptions": []}"#);
+
+        client
+            .channel_subscribers("1/../x?y", None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            transport.requests()[0].url.path(),
+            "/openplatform/channels/1%2F..%2Fx%3Fy/subscriptions"
+        );
+    }
+
+    #[tokio::test]
+    async fn subscription_status() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{"sub_tier": "2", "sub_created_at": 1625486400, "is_gift": true}"#,
+            )
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 12410, "message": "not subscribed"}"#,
+            )
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11730, "message": "unauthorized scope"}"#,
+            );
+
+        let info = client
+            .subscription_status("100000", "200000")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.sub_tier, 2);
+        assert!(info.is_gift);
+        assert!(info.started_at.is_some());
+
+        assert!(client
+            .subscription_status("100000", "200001")
+            .await
+            .unwrap()
+            .is_none());
+
+        match client
+            .subscription_status("100000", "200002")
+            .await
+            .unwrap_err()
+        {
+            AuthenticatedRequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::UnauthorizedScope);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/channels/100000/subscriptions/200000"
+        );
+        assert_eq!(
+            requests[0].headers[header::AUTHORIZATION],
+            "OAuth access-token"
+        );
+    }
+
+    #[tokio::test]
+    async fn channel_viewers() {
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::OK,
+            r#"{"total": 1, "chatters": {"all": {"viewers": ["lurker"]}}}"#,
+        );
+
+        let viewers = client
+            .channel_viewers("100000", Some(50), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(viewers.chatters.all.viewers, ["lurker"]);
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/channels/100000/viewers"
+        );
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"limit": 50, "cursor": 1})
+        );
+
+        transport.push(
+            StatusCode::OK,
+            r#"{"total": 0, "chatters": {"all": {"viewers": []}}}"#,
+        );
+        client
+            .channel_viewers("1/../x?y", None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            transport.requests()[1].url.path(),
+            "/openplatform/channels/1%2F..%2Fx%3Fy/viewers"
+        );
+    }
+
+    #[tokio::test]
+    async fn clips() {
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::OK,
+            r#"{"clips_info": [{"clip_id": "ltv_1", "duration": 0, "views": "3"}]}"#,
+        );
+
+        let clips = client
+            .clips(
+                "100000",
+                ClipsQuery {
+                    limit: Some(10),
+                    sort_by: Some(ClipSort::Views),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].clip_id, "ltv_1");
+        assert_eq!(clips[0].views, 3);
+        let next = ClipsQuery::default().next_page(&clips).unwrap();
+        assert_eq!(next.cursor, Some(1));
+        assert_eq!(next.next_page(&[]), None);
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/clips"
+        );
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"channel_id": "100000", "limit": 10, "sort_by": "views"})
+        );
+    }
+
+    #[tokio::test]
+    async fn past_streams() {
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::OK,
+            r#"{"video_info": [{"vid": "ltv_1", "duration": "3600"}]}"#,
+        );
+
+        let streams = client
+            .past_streams(
+                "100000",
+                PastStreamsQuery {
+                    cursor: Some(2),
+                    direction: Some(SortDirection::Asc),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].duration, 3600);
+        let query = PastStreamsQuery {
+            cursor: Some(2),
+            direction: Some(SortDirection::Asc),
+            ..Default::default()
+        };
+        let next = query.next_page(&streams).unwrap();
+        assert_eq!(next.cursor, Some(3));
+        assert_eq!(next.direction, Some(SortDirection::Asc));
+        assert_eq!(next.next_page(&[]), None);
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/paststreams"
+        );
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"channel_id": "100000", "cursor": 2, "direction": "asc"})
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_urls() {
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::OK,
+            r#"{"stream_urls": [
+                {"play_url": "https://example.com/1080p.m3u8", "desc": "1080P", "bitrate": 4000},
+                {"play_url": "https://example.com/720p.m3u8", "desc": "720P", "bitrate": 2500}
+            ]}"#,
+        );
+        transport.push(StatusCode::OK, r#"{"stream_urls": []}"#);
+
+        let live = client.stream_urls("100000").await.unwrap();
+        assert_eq!(live.len(), 2);
+        assert_eq!(live[1].play_url, "https://example.com/720p.m3u8");
+        let offline = client.stream_urls("100001").await.unwrap();
+        assert!(offline.is_empty());
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/livestreamurl"
+        );
+        assert_eq!(
+            requests[1].json(),
+            serde_json::json!({"channel_id": "100001"})
+        );
+    }
+
+    #[tokio::test]
+    async fn perform_channel_command() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{"is_success": true, "display_msg": "Slow mode is on"}"#,
+            )
+            .push(
+                StatusCode::OK,
+                r#"{"is_success": false, "display_msg": "User does not exist"}"#,
+            );
+
+        let result = client
+            .perform_channel_command("100000", "/slow 30")
+            .await
+            .unwrap();
+        assert!(result.is_success);
+        let result = client
+            .perform_channel_command("100000", "/ban nobody")
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            CommandResult {
+                is_success: false,
+                display_msg: "User does not exist".to_string(),
+            }
+        );
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/channels/command"
+        );
+        assert_eq!(
+            requests[0].headers[header::AUTHORIZATION],
+            "OAuth access-token"
+        );
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"command": "/slow 30", "channel_id": "100000"})
+        );
+    }
+
+    #[tokio::test]
+    async fn update_drops_entitlements() {
+        let (client, transport) = authenticated_client();
+        transport.push(
+            StatusCode::OK,
+            r#"{"results": [
+                {"entitlement_id": "e1", "status": "SUCCESS"},
+                {"entitlement_id": "bad", "status": "INVALID_ID", "err_msg": "invalid id"}
+            ]}"#,
+        );
+
+        let results = client
+            .update_drops_entitlements(
+                vec!["e1".to_string(), "bad".to_string()],
+                DropsFulfillmentStatus::Fulfilled,
+            )
+            .await
+            .unwrap();
+        let failed: Vec<_> = results
+            .iter()
+            .filter(|result| !result.is_success())
+            .map(|result| result.entitlement_id.as_str())
+            .collect();
+        assert_eq!(failed, ["bad"]);
+
+        match client
+            .update_drops_entitlements(Vec::new(), DropsFulfillmentStatus::Fulfilled)
+            .await
+            .unwrap_err()
+        {
+            AuthenticatedRequestError::Validation(err) => assert_eq!(
+                err,
+                ValidationError::Empty {
+                    field: "entitlement_ids"
+                }
+            ),
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/drops/entitlements/update"
+        );
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({
+                "entitlement_ids": ["e1", "bad"],
+                "fulfillment_status": "FULFILLED"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn html_error_pages() {
+        let (client, transport) = scripted_client();
+        let html = |status: StatusCode, body: &str| {
+            http::Response::builder()
+                .status(status)
+                .header("content-type", "text/html; charset=UTF-8")
+                .body(body.to_string())
+                .unwrap()
+        };
+        transport
+            .push_response(html(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "<!DOCTYPE html>\n<html>\n  <head><title>503 Service Temporarily Unavailable</title></head>\n</html>",
+            ))
+            .push_response(html(
+                StatusCode::OK,
+                "<html><head><title>Just a moment...</title></head></html>",
+            ))
+            .push_response(
+                http::Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .header("content-type", "application/json")
+                    .body("  <html>cloudflare</html>".to_string())
+                    .unwrap(),
+            );
+
+        let err = client.channel_by_id("1").await.unwrap_err();
+        assert!(err.is_retryable());
+        match &err {
+            RequestError::UnexpectedContentType {
+                status,
+                content_type,
+                body_excerpt,
+            } => {
+                assert_eq!(*status, StatusCode::SERVICE_UNAVAILABLE);
+                assert_eq!(content_type.as_deref(), Some("text/html; charset=UTF-8"));
+                assert!(body_excerpt.starts_with("<!DOCTYPE html> <html> <head><title>503"));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(err
+            .to_string()
+            .starts_with("unexpected text/html; charset=UTF-8 response with status 503"));
+
+        let err = client.channel_by_id("1").await.unwrap_err();
+        assert!(!err.is_retryable());
+        assert!(matches!(
+            err,
+            RequestError::UnexpectedContentType {
+                status: StatusCode::OK,
+                ..
+            }
+        ));
+
+        // Html with a json content type is still caught by sniffing the body
+        let err = client.channel_by_id("1").await.unwrap_err();
+        assert!(matches!(
+            err,
+            RequestError::UnexpectedContentType {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn unparseable_api_error_is_unknown() {
+        let (client, transport) = scripted_client();
+        transport.push(StatusCode::INTERNAL_SERVER_ERROR, "not json");
+
+        let err = client.channel_by_id("1").await.unwrap_err();
+        assert!(matches!(
+            err,
+            RequestError::ApiError(ApiError {
+                status: ErrorStatus::Unknown,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn channel_by_id_found() {
+        let (client, transport) = scripted_client();
+        transport.push(StatusCode::OK, CHANNEL_FIXTURE);
+
+        let channel = client.channel_by_id("100000").await.unwrap().unwrap();
+        assert_eq!(channel.username, "someone");
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].json()["channel_id"], "100000");
+    }
+
+    #[tokio::test]
+    async fn channel_by_id_empty_channel_is_none() {
+        let (client, transport) = scripted_client();
+        transport.push(StatusCode::OK, EMPTY_CHANNEL_FIXTURE);
+
+        assert!(client.channel_by_id("100000").await.unwrap().is_none());
+
+        // Only a fully blank channel is missing
+        transport.push(
+            StatusCode::OK,
+            EMPTY_CHANNEL_FIXTURE.replace(
+                r#""channel_url": """#,
+                r#""channel_url": "https://trovo.live/someone""#,
+            ),
+        );
+        let channel = client.channel_by_id("100000").await.unwrap().unwrap();
+        assert_eq!(channel.profile_pic, None);
+    }
+
+    #[tokio::test]
+    async fn channels_by_ids() {
+        let transport = ScriptedTransport::new().with_delay(Duration::from_millis(20));
+        for i in 0..8 {
+            if i == 5 {
+                transport.push(StatusCode::SERVICE_UNAVAILABLE, "");
+            } else {
+                transport.push(StatusCode::OK, CHANNEL_FIXTURE);
+            }
+        }
+        let client = Client::with_transport(transport.clone(), ClientId::new("client-id"));
+
+        let ids: Vec<String> = (0..8).map(|i| format!("10000{}", i)).collect();
+        let results = client.channels_by_ids(ids.clone(), 2).await;
+
+        let returned: Vec<_> = results.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(returned, ids);
+        assert_eq!(results.iter().filter(|(_, res)| res.is_err()).count(), 1);
+        assert_eq!(
+            results
+                .iter()
+                .filter(|(_, res)| matches!(res, Ok(Some(_))))
+                .count(),
+            7
+        );
+        assert_eq!(transport.requests().len(), 8);
+        assert_eq!(transport.max_in_flight(), 2);
+    }
+
+    #[tokio::test]
+    async fn channel_by_username() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(StatusCode::OK, CHANNEL_FIXTURE)
+            .push(StatusCode::OK, EMPTY_CHANNEL_FIXTURE);
+
+        let channel = client
+            .channel_by_username("SomeOne")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(channel.username, "someone");
+        assert!(client
+            .channel_by_username("nobody")
+            .await
+            .unwrap()
+            .is_none());
+
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/channels/id"
+        );
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"username": "someone"})
+        );
+    }
+
+    #[tokio::test]
+    async fn trace_id_in_errors() {
+        let (client, transport) = scripted_client();
+        transport.push_response(
+            http::Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(TRACE_ID_HEADER, "trace-123")
+                .body(r#"{"status": 11706, "message": "rate limited"}"#.to_string())
+                .unwrap(),
+        );
+        transport.push_response(
+            http::Response::builder()
+                .status(StatusCode::OK)
+                .header(TRACE_ID_HEADER, "trace-456")
+                .body("{}".to_string())
+                .unwrap(),
+        );
+
+        let err = client.channel_by_id("1").await.unwrap_err();
+        assert!(err.to_string().contains("trace-123"), "{}", err);
+
+        let err = client.channel_by_id("1").await.unwrap_err();
+        assert!(matches!(err, RequestError::Decode(_)));
+        assert!(err.to_string().contains("trace-456"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn response_meta() {
+        let (client, transport) = scripted_client();
+        transport.push_response(
+            http::Response::builder()
+                .status(StatusCode::OK)
+                .header(TRACE_ID_HEADER, "trace-123")
+                .header("x-ratelimit-remaining", "42")
+                .header("set-cookie", "session=secret")
+                .body(CHANNEL_FIXTURE.to_string())
+                .unwrap(),
+        );
+
+        let (channel, meta) = client.channel_by_id_with_meta("100000").await.unwrap();
+        assert!(channel.is_some());
+        assert_eq!(meta.status, StatusCode::OK);
+        assert_eq!(meta.request_id.as_deref(), Some("trace-123"));
+        assert_eq!(meta.headers["x-ratelimit-remaining"], "42");
+        assert!(!meta.headers.contains_key("set-cookie"));
+        assert!(meta.latency > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_status() {
+        let (client, transport) = scripted_client();
+        let response = |remaining: Option<&str>, reset: &str| {
+            let mut builder = http::Response::builder()
+                .status(StatusCode::OK)
+                .header(RATE_LIMIT_RESET_HEADER, reset);
+            if let Some(remaining) = remaining {
+                builder = builder.header(RATE_LIMIT_REMAINING_HEADER, remaining);
+            }
+            builder.body(CHANNEL_FIXTURE.to_string()).unwrap()
+        };
+        transport
+            .push_response(response(Some("42"), "1700000000"))
+            .push_response(response(None, "1700000060"));
+        assert_eq!(client.rate_limit_status(), RateLimitStatus::default());
+
+        client.channel_by_id("100000").await.unwrap();
+        let status = client.rate_limit_status();
+        assert_eq!(status.remaining, Some(42));
+        assert_eq!(
+            crate::timestamp::unix_seconds(&status.reset_at.unwrap()),
+            1700000000
+        );
+
+        // Missing headers keep the previous value
+        client.clone().channel_by_id("100000").await.unwrap();
+        let status = client.rate_limit_status();
+        assert_eq!(status.remaining, Some(42));
+        assert_eq!(
+            crate::timestamp::unix_seconds(&status.reset_at.unwrap()),
+            1700000060
+        );
+    }
+
+    #[tokio::test]
+    async fn secrets_redacted() {
+        const SENTINEL: &str = "SENTINEL-TOKEN-123";
+        let transport = ScriptedTransport::new();
+        transport.push(StatusCode::SERVICE_UNAVAILABLE, "").push(
+            StatusCode::UNAUTHORIZED,
+            r#"{"status": 11704, "message": "invalid token"}"#,
+        );
+        let auth = AccessTokenOnly::new("client-id", SENTINEL);
+        assert!(!format!("{:?}", auth).contains(SENTINEL));
+        assert!(!format!("{:?}", auth.access_token()).contains(SENTINEL));
+        let client = Client::with_transport(transport.clone(), auth);
+        assert!(!format!("{:?}", client).contains(SENTINEL));
+
+        for _ in 0..2 {
+            let err = client
+                .update_channel("100000", ChannelUpdate::default())
+                .await
+                .unwrap_err();
+            assert!(
+                !format!("{} {:?}", err, err).contains(SENTINEL),
+                "{:?}",
+                err
+            );
+        }
+
+        // The header is still sent, but hidden from debug output
+        let requests = transport.requests();
+        assert_eq!(
+            requests[0].headers[header::AUTHORIZATION],
+            format!("OAuth {}", SENTINEL)
+        );
+        assert!(!format!("{:?}", requests).contains(SENTINEL));
+
+        // Query strings are scrubbed from http errors
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = Client::new(ClientId::new("client-id"));
+        let err: RequestError = client
+            .send(client.http.get(format!(
+                "http://user:{}@{}/?token={}",
+                SENTINEL, addr, SENTINEL
+            )))
+            .await
+            .unwrap_err()
+            .into();
+        assert!(matches!(err, RequestError::Other(_)));
+        assert!(
+            !format!("{} {:?}", err, err).contains(SENTINEL),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn request_raw() {
+        let (client, transport) = scripted_client();
+        transport.push(StatusCode::OK, r#"{"total": 3}"#).push(
+            StatusCode::BAD_REQUEST,
+            r#"{"status": 99999, "message": "new endpoint rule"}"#,
+        );
+
+        let response: serde_json::Value = client
+            .request_raw(
+                Method::POST,
+                "newendpoint",
+                Some(&serde_json::json!({"limit": 3})),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response, serde_json::json!({"total": 3}));
+        let err = client
+            .request_raw::<serde_json::Value, ()>(Method::GET, "newendpoint", None)
+            .await
+            .unwrap_err();
+        match err {
+            RequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::Other(99999));
+                assert_eq!(err.message, "new endpoint rule");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::POST);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/newendpoint"
+        );
+        assert_eq!(requests[0].headers["Client-ID"], "client-id");
+        assert!(!requests[0].headers.contains_key(header::AUTHORIZATION));
+        assert_eq!(requests[0].json(), serde_json::json!({"limit": 3}));
+        assert_eq!(requests[1].method, Method::GET);
+    }
+
+    #[tokio::test]
+    async fn request_raw_authenticated() {
+        let (client, transport) = authenticated_client();
+        transport.push(StatusCode::OK, r#"{"total": 3}"#).push(
+            StatusCode::UNAUTHORIZED,
+            r#"{"status": 11704, "message": "invalid token"}"#,
+        );
+
+        let response: serde_json::Value = client
+            .request_raw_authenticated(Method::POST, "newendpoint", Some(&[1, 2, 3]))
+            .await
+            .unwrap();
+        assert_eq!(response, serde_json::json!({"total": 3}));
+        let err = client
+            .request_raw_authenticated::<serde_json::Value, ()>(Method::GET, "newendpoint", None)
+            .await
+            .unwrap_err();
+        match err {
+            AuthenticatedRequestError::ApiError(err) => {
+                assert_eq!(err.status.code(), 11704);
+                assert_eq!(err.message, "invalid token");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].headers["Client-ID"], "client-id");
+        assert_eq!(
+            requests[0].headers[header::AUTHORIZATION],
+            "OAuth access-token"
+        );
+        assert_eq!(requests[0].json(), serde_json::json!([1, 2, 3]));
+    }
+
+    #[tokio::test]
+    async fn usernames_normalized() {
+        let (client, transport) = scripted_client();
+        transport
+            .push(StatusCode::OK, r#"{"users": []}"#)
+            .push(StatusCode::OK, r#"{"users": []}"#)
+            .push(StatusCode::OK, r#"{"users": []}"#)
+            .push(StatusCode::BAD_REQUEST, INVALID);
+
+        client.user("https://trovo.live/s/SomeName").await.unwrap();
+        client.user(Username::exact("SomeName")).await.unwrap();
+        client
+            .users(vec![" @SomeName ".to_string(), "Other".to_string()])
+            .await
+            .unwrap();
+        let lookup = client
+            .users_detailed(vec!["@Nobody".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(lookup.missing, ["nobody"]);
+
+        let sent: Vec<_> = transport.requests().iter().map(|r| r.json()).collect();
+        assert_eq!(
+            sent,
+            [
+                serde_json::json!({"user": ["somename"]}),
+                serde_json::json!({"user": ["SomeName"]}),
+                serde_json::json!({"user": ["somename", "other"]}),
+                serde_json::json!({"user": ["nobody"]}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn users_detailed() {
+        let user = |name: &str| {
+            format!(
+                r#"{{"user_id": "1", "channel_id": "1", "username": "{}", "nickname": "{}"}}"#,
+                name, name
+            )
+        };
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        // All found
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::OK,
+            format!(r#"{{"users": [{}, {}]}}"#, user("alice"), user("Bob")),
+        );
+        let lookup = client
+            .users_detailed(names(&["Alice", "bob"]))
+            .await
+            .unwrap();
+        assert_eq!(lookup.found.len(), 2);
+        assert!(lookup.missing.is_empty());
+        assert_eq!(transport.requests().len(), 1);
+
+        // Some missing, the unknown name is isolated by splitting
+        let (client, transport) = scripted_client();
+        transport
+            .push(StatusCode::BAD_REQUEST, INVALID)
+            .push(
+                StatusCode::OK,
+                format!(r#"{{"users": [{}]}}"#, user("alice")),
+            )
+            .push(StatusCode::BAD_REQUEST, INVALID);
+        let lookup = client
+            .users_detailed(names(&["alice", "nobody"]))
+            .await
+            .unwrap();
+        assert_eq!(lookup.found[0].username, "alice");
+        assert_eq!(lookup.missing, names(&["nobody"]));
+        assert_eq!(transport.requests().len(), 3);
+
+        // All missing
+        let (client, transport) = scripted_client();
+        transport
+            .push(StatusCode::BAD_REQUEST, INVALID)
+            .push(StatusCode::BAD_REQUEST, INVALID)
+            .push(StatusCode::BAD_REQUEST, INVALID);
+        let lookup = client
+            .users_detailed(names(&["nobody", "noone"]))
+            .await
+            .unwrap();
+        assert!(lookup.found.is_empty());
+        assert_eq!(lookup.missing, names(&["nobody", "noone"]));
+
+        // Found users follow the requested order, extras last
+        let (client, transport) = scripted_client();
+        transport.push(
+            StatusCode::OK,
+            format!(
+                r#"{{"total": 4, "users": [{}, {}, {}, {}]}}"#,
+                user("dave"),
+                user("carol"),
+                user("alice"),
+                user("Bob")
+            ),
+        );
+        let lookup = client
+            .users_detailed(names(&["BOB", "Alice", "carol"]))
+            .await
+            .unwrap();
+        let found: Vec<_> = lookup.found.iter().map(|u| u.username.as_str()).collect();
+        assert_eq!(found, ["Bob", "alice", "carol", "dave"]);
+
+        // One bad name in a batch of four is isolated, the other users are still returned
+        let (client, transport) = scripted_client();
+        transport
+            .push(StatusCode::BAD_REQUEST, INVALID)
+            .push(StatusCode::BAD_REQUEST, INVALID)
+            .push(
+                StatusCode::OK,
+                format!(r#"{{"users": [{}]}}"#, user("alice")),
+            )
+            .push(StatusCode::BAD_REQUEST, INVALID)
+            .push(
+                StatusCode::OK,
+                format!(r#"{{"users": [{}, {}]}}"#, user("carol"), user("dave")),
+            );
+        let lookup = client
+            .users_detailed(names(&["alice", "typo", "carol", "dave"]))
+            .await
+            .unwrap();
+        let found: Vec<_> = lookup.found.iter().map(|u| u.username.as_str()).collect();
+        assert_eq!(found, ["alice", "carol", "dave"]);
+        assert_eq!(lookup.missing, names(&["typo"]));
+        assert!(lookup.unresolved.is_empty());
+        let requested: Vec<_> = transport
+            .requests()
+            .iter()
+            .map(|req| req.json()["user"].clone())
+            .collect();
+        assert_eq!(
+            requested,
+            [
+                serde_json::json!(["alice", "typo", "carol", "dave"]),
+                serde_json::json!(["alice", "typo"]),
+                serde_json::json!(["alice"]),
+                serde_json::json!(["typo"]),
+                serde_json::json!(["carol", "dave"]),
+            ]
+        );
+
+        // Splitting stops at the request limit
+        let (client, transport) = scripted_client();
+        transport
+            .push(StatusCode::BAD_REQUEST, INVALID)
+            .push(StatusCode::BAD_REQUEST, INVALID);
+        let lookup = client
+            .users_detailed_with_limit(names(&["alice", "typo", "carol", "dave"]), 1)
+            .await
+            .unwrap();
+        assert!(lookup.found.is_empty());
+        assert!(lookup.missing.is_empty());
+        assert_eq!(
+            lookup.unresolved,
+            names(&["alice", "typo", "carol", "dave"])
+        );
+        assert_eq!(transport.requests().len(), 2);
+
+        // More names than one request accepts are looked up in chunks
+        let (client, transport) = scripted_client();
+        let many: Vec<String> = (0..=GetUsersPayload::MAX_USERS)
+            .map(|i| format!("user{}", i))
+            .collect();
+        transport
+            .push(
+                StatusCode::OK,
+                format!(r#"{{"users": [{}]}}"#, user("user0")),
+            )
+            .push(
+                StatusCode::OK,
+                format!(r#"{{"users": [{}]}}"#, user("user100")),
+            );
+        let lookup = client.users_detailed(many).await.unwrap();
+        let found: Vec<_> = lookup.found.iter().map(|u| u.username.as_str()).collect();
+        assert_eq!(found, ["user0", "user100"]);
+        assert_eq!(lookup.missing.len(), GetUsersPayload::MAX_USERS - 1);
+        let sizes: Vec<_> = transport
+            .requests()
+            .iter()
+            .map(|req| req.json()["user"].as_array().unwrap().len())
+            .collect();
+        assert_eq!(sizes, [GetUsersPayload::MAX_USERS, 1]);
+    }
 }
@@ -0,0 +1,545 @@
+use crate::{
+    timestamp::unix_seconds, ChannelId, Client, ClientIdProvider, Clip, ClipSort, ClipsQuery,
+    RequestError,
+};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// Settings for [`Client::new_clips_stream_with`].
+#[derive(Debug, Clone)]
+pub struct NewClipsConfig {
+    /// Also return the clips found by the first poll, rather than only the clips made after it.
+    /// Defaults to `false`.
+    pub emit_existing: bool,
+
+    /// Number of clips fetched by each poll, at most 100. Defaults to 20.
+    pub limit: u32,
+
+    /// Number of clip ids remembered, so clips listed again aren't returned twice. Never less
+    /// than [`limit`](Self::limit). Defaults to 500.
+    pub seen_capacity: usize,
+
+    /// How much older than the newest clip seen a clip may be and still be returned, for clips
+    /// that show up in the listing out of order. Defaults to 10 minutes.
+    pub reorder_window: Duration,
+}
+
+impl Default for NewClipsConfig {
+    fn default() -> Self {
+        Self {
+            emit_existing: false,
+            limit: 20,
+            seen_capacity: 500,
+            reorder_window: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl<A> Client<A>
+where
+    A: ClientIdProvider + Clone + Send + Sync + 'static,
+{
+    /// Polls a channel's clips every `interval`, returning each clip made after the first poll
+    /// once, oldest first.
+    ///
+    /// A failed poll is returned as an error and retried on the next tick, the stream never ends
+    /// on its own. Waits use the client's clock. See [`Client::new_clips_stream_with`] to also
+    /// return the existing clips.
+    ///
+    /// ```no_run
+    /// use futures::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example(client: trovo::Client<trovo::ClientId>) {
+    /// let mut clips = client.new_clips_stream("100000", Duration::from_secs(60));
+    /// while let Some(clip) = clips.next().await {
+    ///     match clip {
+    ///         Ok(clip) => println!("New clip: {}", clip.url),
+    ///         Err(err) => eprintln!("Failed to check for clips: {}", err),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn new_clips_stream(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        interval: Duration,
+    ) -> BoxStream<'static, Result<Clip, RequestError>> {
+        self.new_clips_stream_with(channel_id, interval, NewClipsConfig::default())
+    }
+
+    /// Same as [`Client::new_clips_stream`], with the given settings.
+    pub fn new_clips_stream_with(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        interval: Duration,
+        config: NewClipsConfig,
+    ) -> BoxStream<'static, Result<Clip, RequestError>> {
+        let state = ClipPoller {
+            client: self.clone(),
+            channel_id: channel_id.into(),
+            interval,
+            seen: SeenClips::new(config.seen_capacity.max(config.limit as usize)),
+            newest: None,
+            polled: false,
+            next_poll: None,
+            ready: VecDeque::new(),
+            config,
+        };
+        stream::unfold(state, |mut state| async move {
+            let item = state.next().await;
+            Some((item, state))
+        })
+        .boxed()
+    }
+
+    /// Lists all of a channel's clips, fetching the pages as they're needed.
+    ///
+    /// The stream ends after the last page, or after returning an error. To carry on from where
+    /// a stream stopped, pass its [`ClipsStream::cursor`] as the [`ClipsQuery::cursor`] of a new
+    /// one.
+    ///
+    /// ```no_run
+    /// use futures::prelude::*;
+    /// use trovo::ClipsQuery;
+    ///
+    /// # async fn example(client: trovo::Client<trovo::ClientId>) -> Result<(), trovo::RequestError> {
+    /// let mut clips = client.clips_stream("100000", ClipsQuery::default());
+    /// while let Some(clip) = clips.try_next().await? {
+    ///     println!("{}: {}", clip.title, clip.url);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn clips_stream(&self, channel_id: impl Into<ChannelId>, query: ClipsQuery) -> ClipsStream {
+        let cursor = Arc::new(AtomicU64::new(query.cursor.unwrap_or(0)));
+        let state = ClipPages {
+            client: self.clone(),
+            channel_id: channel_id.into(),
+            query: Some(query),
+            ready: VecDeque::new(),
+            cursor: cursor.clone(),
+        };
+        let inner = stream::unfold(state, |mut state| async move {
+            let item = state.next().await?;
+            Some((item, state))
+        })
+        .boxed();
+        ClipsStream { inner, cursor }
+    }
+}
+
+struct ClipPoller<A> {
+    client: Client<A>,
+    channel_id: ChannelId,
+    interval: Duration,
+    config: NewClipsConfig,
+    seen: SeenClips,
+
+    /// Unix seconds of the newest clip seen so far
+    newest: Option<i64>,
+
+    /// Whether a poll has succeeded, clips before that are only remembered
+    polled: bool,
+    next_poll: Option<Instant>,
+
+    /// New clips waiting to be returned, oldest first
+    ready: VecDeque<Clip>,
+}
+
+impl<A> ClipPoller<A>
+where
+    A: ClientIdProvider,
+{
+    async fn next(&mut self) -> Result<Clip, RequestError> {
+        loop {
+            if let Some(clip) = self.ready.pop_front() {
+                return Ok(clip);
+            }
+
+            let clock = self.client.pipeline.clock.clone();
+            if let Some(deadline) = self.next_poll {
+                clock.sleep_until(deadline).await;
+            }
+            self.next_poll = Some(clock.now() + self.interval);
+
+            let query = ClipsQuery {
+                limit: Some(self.config.limit),
+                sort_by: Some(ClipSort::Date),
+                ..Default::default()
+            };
+            let clips = self.client.clips(self.channel_id.clone(), query).await?;
+            self.record(clips);
+        }
+    }
+
+    fn record(&mut self, clips: Vec<Clip>) {
+        let emit = self.polled || self.config.emit_existing;
+        let reorder_window = self.config.reorder_window.as_secs() as i64;
+        let newest = self.newest;
+        // Listed newest first
+        for clip in clips.into_iter().rev() {
+            if !self.seen.insert(&clip.clip_id) {
+                continue;
+            }
+            let created_at = clip.created_at.as_ref().map(unix_seconds);
+            let stale = matches!(
+                (created_at, newest),
+                (Some(created_at), Some(newest)) if created_at + reorder_window < newest
+            );
+            if let Some(created_at) = created_at {
+                self.newest = Some(self.newest.map_or(created_at, |n| n.max(created_at)));
+            }
+            if emit && !stale {
+                self.ready.push_back(clip);
+            }
+        }
+        self.polled = true;
+    }
+}
+
+/// The most recently seen clip ids, forgetting the oldest past capacity
+struct SeenClips {
+    capacity: usize,
+    order: VecDeque<String>,
+    ids: HashSet<String>,
+}
+
+impl SeenClips {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            order: VecDeque::new(),
+            ids: HashSet::new(),
+        }
+    }
+
+    /// Remembers the id, returning whether it wasn't seen before.
+    fn insert(&mut self, id: &str) -> bool {
+        if self.ids.contains(id) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.order.push_back(id.to_owned());
+        self.ids.insert(id.to_owned());
+        true
+    }
+}
+
+/// All of a channel's clips, created via [`Client::clips_stream`].
+pub struct ClipsStream {
+    inner: BoxStream<'static, Result<Clip, RequestError>>,
+    cursor: Arc<AtomicU64>,
+}
+
+impl ClipsStream {
+    /// Page of the next clip to be returned, for resuming the listing later.
+    ///
+    /// Resuming from a page that was partly read returns its clips again.
+    pub fn cursor(&self) -> u64 {
+        self.cursor.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for ClipsStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClipsStream")
+            .field("cursor", &self.cursor())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for ClipsStream {
+    type Item = Result<Clip, RequestError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+struct ClipPages<A> {
+    client: Client<A>,
+    channel_id: ChannelId,
+
+    /// Query for the next page, `None` once the listing ended
+    query: Option<ClipsQuery>,
+
+    /// Clips of the current page waiting to be returned
+    ready: VecDeque<Clip>,
+    cursor: Arc<AtomicU64>,
+}
+
+impl<A> ClipPages<A>
+where
+    A: ClientIdProvider,
+{
+    async fn next(&mut self) -> Option<Result<Clip, RequestError>> {
+        loop {
+            if let Some(clip) = self.ready.pop_front() {
+                if self.ready.is_empty() {
+                    if let Some(cursor) = self.query.as_ref().and_then(|query| query.cursor) {
+                        self.cursor.store(cursor, Ordering::Relaxed);
+                    }
+                }
+                return Some(Ok(clip));
+            }
+
+            // An error ends the stream, it can be resumed from the cursor
+            let query = self.query.take()?;
+            let clips = match self
+                .client
+                .clips(self.channel_id.clone(), query.clone())
+                .await
+            {
+                Ok(clips) => clips,
+                Err(err) => return Some(Err(err)),
+            };
+            self.query = query.next_page(&clips);
+            self.ready = clips.into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientId, ClipPeriod, MockClock, ScriptedTransport};
+    use reqwest::StatusCode;
+
+    fn mock_client() -> (Client<ClientId>, ScriptedTransport, MockClock) {
+        let transport = ScriptedTransport::new();
+        let clock = MockClock::new();
+        let client = Client::builder()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build(ClientId::new("client-id"))
+            .unwrap();
+        (client, transport, clock)
+    }
+
+    fn clips(clips: &[(&str, i64)]) -> String {
+        let clips: Vec<_> = clips
+            .iter()
+            .map(|(id, created_at)| serde_json::json!({"clip_id": id, "created_at": created_at}))
+            .collect();
+        serde_json::json!({ "clips_info": clips }).to_string()
+    }
+
+    /// Takes the next item, moving the clock on whenever the stream waits for its next poll.
+    async fn next_item(
+        stream: &mut BoxStream<'static, Result<Clip, RequestError>>,
+        clock: &MockClock,
+        interval: Duration,
+    ) -> Result<Clip, RequestError> {
+        let mut next = stream.next();
+        loop {
+            tokio::select! {
+                item = &mut next => return item.unwrap(),
+                _ = tokio::task::yield_now() => {
+                    if clock.sleepers() > 0 {
+                        clock.advance(interval);
+                    }
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn clips_stream() {
+        let (client, transport, _) = mock_client();
+        transport
+            .push(
+                StatusCode::OK,
+                clips(&[("c1", 1_700_000_100), ("c2", 1_700_000_200)]),
+            )
+            .push(StatusCode::OK, clips(&[("c3", 1_700_000_300)]))
+            .push(StatusCode::OK, clips(&[]));
+
+        let mut stream = client.clips_stream(
+            "100000",
+            ClipsQuery {
+                limit: Some(2),
+                period: Some(ClipPeriod::Week),
+                ..Default::default()
+            },
+        );
+        let mut listed = Vec::new();
+        while let Some(clip) = stream.next().await {
+            listed.push((clip.unwrap().clip_id, stream.cursor()));
+        }
+        assert_eq!(
+            listed,
+            [
+                ("c1".to_string(), 0),
+                ("c2".to_string(), 1),
+                ("c3".to_string(), 2)
+            ]
+        );
+        assert_eq!(stream.cursor(), 2);
+
+        let bodies: Vec<_> = transport.requests().iter().map(|r| r.json()).collect();
+        assert_eq!(
+            bodies,
+            [
+                serde_json::json!({"channel_id": "100000", "limit": 2, "period": "week"}),
+                serde_json::json!({"channel_id": "100000", "limit": 2, "period": "week", "cursor": 1}),
+                serde_json::json!({"channel_id": "100000", "limit": 2, "period": "week", "cursor": 2}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn clips_stream_resume() {
+        let (client, transport, _) = mock_client();
+        transport
+            .push(StatusCode::OK, clips(&[("c6", 1_700_000_600)]))
+            .push(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                r#"{"status": 500, "message": "internal error"}"#,
+            )
+            .push(StatusCode::OK, clips(&[("c7", 1_700_000_700)]))
+            .push(StatusCode::OK, clips(&[]));
+
+        let mut stream = client.clips_stream(
+            "100000",
+            ClipsQuery {
+                cursor: Some(5),
+                ..Default::default()
+            },
+        );
+        assert_eq!(stream.cursor(), 5);
+        assert_eq!(stream.next().await.unwrap().unwrap().clip_id, "c6");
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+        assert_eq!(stream.cursor(), 6);
+
+        let mut stream = client.clips_stream(
+            "100000",
+            ClipsQuery {
+                cursor: Some(stream.cursor()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(stream.next().await.unwrap().unwrap().clip_id, "c7");
+        assert!(stream.next().await.is_none());
+
+        let cursors: Vec<_> = transport
+            .requests()
+            .iter()
+            .map(|r| r.json()["cursor"].clone())
+            .collect();
+        assert_eq!(cursors, [5, 6, 6, 7]);
+    }
+
+    #[tokio::test]
+    async fn new_clips_stream() {
+        let (client, transport, clock) = mock_client();
+        let interval = Duration::from_secs(60);
+        transport
+            .push(
+                StatusCode::OK,
+                clips(&[("c2", 1_700_000_200), ("c1", 1_700_000_100)]),
+            )
+            .push(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                r#"{"status": 500, "message": "internal error"}"#,
+            )
+            .push(
+                StatusCode::OK,
+                clips(&[
+                    ("c4", 1_700_000_400),
+                    ("c3", 1_700_000_300),
+                    ("c2", 1_700_000_200),
+                    ("c1", 1_700_000_100),
+                ]),
+            )
+            .push(
+                StatusCode::OK,
+                clips(&[
+                    ("c4", 1_700_000_400),
+                    ("late", 1_700_000_350),
+                    ("c3", 1_700_000_300),
+                ]),
+            )
+            .push(
+                StatusCode::OK,
+                clips(&[("c4", 1_700_000_400), ("old", 1_600_000_000)]),
+            )
+            .push(
+                StatusCode::OK,
+                clips(&[("c5", 1_700_000_500), ("c4", 1_700_000_400)]),
+            );
+
+        let mut stream = client.new_clips_stream("100000", interval);
+
+        // The first poll only remembers the existing clips
+        assert!(next_item(&mut stream, &clock, interval).await.is_err());
+        let mut ids = Vec::new();
+        for _ in 0..4 {
+            ids.push(
+                next_item(&mut stream, &clock, interval)
+                    .await
+                    .unwrap()
+                    .clip_id,
+            );
+        }
+        assert_eq!(ids, ["c3", "c4", "late", "c5"]);
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 6);
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({"channel_id": "100000", "limit": 20, "sort_by": "date"})
+        );
+    }
+
+    #[tokio::test]
+    async fn new_clips_stream_emit_existing() {
+        let (client, transport, clock) = mock_client();
+        let interval = Duration::from_secs(60);
+        transport
+            .push(
+                StatusCode::OK,
+                clips(&[("c2", 1_700_000_200), ("c1", 1_700_000_100)]),
+            )
+            .push(
+                StatusCode::OK,
+                clips(&[("c2", 1_700_000_200), ("c1", 1_700_000_100)]),
+            )
+            .push(StatusCode::OK, clips(&[("c3", 1_700_000_300)]));
+
+        let mut stream = client.new_clips_stream_with(
+            "100000",
+            interval,
+            NewClipsConfig {
+                emit_existing: true,
+                ..NewClipsConfig::default()
+            },
+        );
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            ids.push(
+                next_item(&mut stream, &clock, interval)
+                    .await
+                    .unwrap()
+                    .clip_id,
+            );
+        }
+        assert_eq!(ids, ["c1", "c2", "c3"]);
+        assert_eq!(transport.requests().len(), 3);
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Source of time used by the client, e.g. for the circuit breaker and chat pings.
+///
+/// The default implementation is [`TokioClock`]. Swapping it out is mostly useful for testing
+/// time-dependent behaviour, see `MockClock` under the `test-util` feature.
+#[async_trait::async_trait]
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+
+    /// Returns the current wall clock time, e.g. for comparing against message timestamps.
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    /// Waits until the given deadline has been reached.
+    async fn sleep_until(&self, deadline: Instant);
+
+    /// Waits for the given duration to elapse.
+    async fn sleep(&self, duration: Duration) {
+        self.sleep_until(self.now() + duration).await
+    }
+}
+
+/// A [`Clock`] backed by real time, sleeping via the tokio timer.
+///
+/// Tokio is only a dependency with the `chat` feature, without it sleeps are served by a single
+/// helper thread, started by the first sleep and shared by every clock, so the clock works on any
+/// executor.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+#[async_trait::async_trait]
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    async fn sleep_until(&self, deadline: Instant) {
+        sleep_until(deadline).await
+    }
+}
+
+/// Waits until the deadline using the tokio timer, or the timer thread when tokio isn't available.
+pub(crate) async fn sleep_until(deadline: Instant) {
+    #[cfg(feature = "chat")]
+    tokio::time::sleep_until(deadline.into()).await;
+
+    #[cfg(not(feature = "chat"))]
+    timer::sleep_until(deadline).await;
+}
+
+#[cfg(not(feature = "chat"))]
+mod timer {
+    use futures::channel::oneshot;
+    use std::{
+        sync::{mpsc, Mutex, OnceLock},
+        thread,
+        time::Instant,
+    };
+
+    type Sleeper = (Instant, oneshot::Sender<()>);
+
+    /// Queue of the one thread serving every sleep
+    static TIMER: OnceLock<Mutex<mpsc::Sender<Sleeper>>> = OnceLock::new();
+
+    /// Waits until the deadline on the timer thread, starting it on first use.
+    pub(super) async fn sleep_until(deadline: Instant) {
+        let timer = TIMER.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::Builder::new()
+                .name("trovo-timer".to_string())
+                .spawn(move || run(receiver))
+                .expect("failed to spawn timer thread");
+            Mutex::new(sender)
+        });
+        let (sender, receiver) = oneshot::channel();
+        if timer.lock().unwrap().send((deadline, sender)).is_ok() {
+            receiver.await.ok();
+        }
+    }
+
+    /// Wakes sleepers as their deadlines pass, dropping any whose sleep was cancelled.
+    fn run(receiver: mpsc::Receiver<Sleeper>) {
+        let mut sleepers: Vec<Sleeper> = Vec::new();
+        loop {
+            let now = Instant::now();
+            let (woken, sleeping): (Vec<_>, Vec<_>) = sleepers
+                .drain(..)
+                .partition(|(deadline, sender)| *deadline <= now || sender.is_canceled());
+            sleepers = sleeping;
+            for (_, sender) in woken {
+                sender.send(()).ok();
+            }
+
+            let next = match sleepers.iter().map(|(deadline, _)| *deadline).min() {
+                Some(deadline) => receiver.recv_timeout(deadline - now),
+                None => receiver
+                    .recv()
+                    .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match next {
+                Ok(sleeper) => sleepers.push(sleeper),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::time::{Duration, Instant};
+
+        #[test]
+        fn concurrent_sleeps() {
+            let start = Instant::now();
+            let deadlines: Vec<_> = [30, 10, 20]
+                .map(|ms| start + Duration::from_millis(ms))
+                .into();
+            let sleeps = deadlines.iter().map(|deadline| async move {
+                super::sleep_until(*deadline).await;
+                assert!(Instant::now() >= *deadline);
+            });
+            futures::executor::block_on(futures::future::join_all(sleeps));
+            assert!(start.elapsed() >= Duration::from_millis(30));
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::*;
+
+#[cfg(any(test, feature = "test-util"))]
+mod mock {
+    use super::Clock;
+    use futures::channel::oneshot;
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant, SystemTime},
+    };
+
+    /// A [`Clock`] that only moves forward when told to, for fast deterministic tests.
+    ///
+    /// Clones share the same time, so one can be handed to the client while the test keeps
+    /// another to [`advance`](MockClock::advance) it.
+    #[derive(Debug, Clone)]
+    pub struct MockClock {
+        state: Arc<Mutex<State>>,
+    }
+
+    #[derive(Debug)]
+    struct State {
+        now: Instant,
+        system_time: SystemTime,
+        sleepers: Vec<(Instant, oneshot::Sender<()>)>,
+    }
+
+    impl Default for MockClock {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl MockClock {
+        /// Creates a new mock clock starting at the current instant.
+        pub fn new() -> Self {
+            Self {
+                state: Arc::new(Mutex::new(State {
+                    now: Instant::now(),
+                    system_time: SystemTime::now(),
+                    sleepers: Vec::new(),
+                })),
+            }
+        }
+
+        /// Moves time forward, waking any sleepers whose deadline has been reached.
+        pub fn advance(&self, duration: Duration) {
+            let mut state = self.state.lock().unwrap();
+            state.now += duration;
+            state.system_time += duration;
+            let now = state.now;
+            let (woken, sleeping) = state
+                .sleepers
+                .drain(..)
+                .partition(|(deadline, _)| *deadline <= now);
+            state.sleepers = sleeping;
+            for (_, sender) in woken {
+                sender.send(()).ok();
+            }
+        }
+
+        /// Sets the wall clock time returned by [`Clock::system_time`], which otherwise starts at
+        /// the real time and moves forward with [`advance`](MockClock::advance).
+        pub fn set_system_time(&self, time: SystemTime) {
+            self.state.lock().unwrap().system_time = time;
+        }
+
+        /// Number of sleepers currently waiting on the clock.
+        pub fn sleepers(&self) -> usize {
+            self.state.lock().unwrap().sleepers.len()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Clock for MockClock {
+        fn now(&self) -> Instant {
+            self.state.lock().unwrap().now
+        }
+
+        fn system_time(&self) -> SystemTime {
+            self.state.lock().unwrap().system_time
+        }
+
+        async fn sleep_until(&self, deadline: Instant) {
+            let receiver = {
+                let mut state = self.state.lock().unwrap();
+                if deadline <= state.now {
+                    return;
+                }
+                let (sender, receiver) = oneshot::channel();
+                state.sleepers.push((deadline, sender));
+                receiver
+            };
+            receiver.await.ok();
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use crate::{
+    access_token, client::path_segment, AccessTokenProvider, AuthenticatedRequestError,
+    ChannelCommandError, ChannelId, Client, ErrorStatus, UserId, Username, ValidationError,
+};
+use reqwest::{header, Method};
+use std::time::Duration;
+
+/// Longest timed ban accepted by [`Client::ban_user`], 7 days.
+pub const MAX_BAN_DURATION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+impl<A> Client<A>
+where
+    A: AccessTokenProvider,
+{
+    /// Hosts another channel in the given channel, with the `/host` command.
+    ///
+    /// The target is normalized with [`Username::parse`], so pasted channel urls work, and must
+    /// not be blank or contain whitespace, otherwise this fails with a
+    /// [`ChannelCommandError::Validation`] without making a request.
+    pub async fn host_channel(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        target_username: impl Into<Username>,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        let target = command_username("target_username", target_username.into())?;
+        self.channel_command(channel_id, format!("/host {}", target))
+            .await
+    }
+
+    /// Stops hosting in the given channel, with the `/unhost` command.
+    pub async fn unhost_channel(
+        &self,
+        channel_id: impl Into<ChannelId>,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        self.channel_command(channel_id, "/unhost").await
+    }
+
+    /// Bans a user from chatting in the given channel, with the `/ban` command.
+    ///
+    /// `None` bans permanently, a duration bans for that many whole seconds, which must be at
+    /// least 1 and at most [`MAX_BAN_DURATION`]. The username is normalized with
+    /// [`Username::parse`] and must not be blank or contain whitespace. Invalid arguments fail
+    /// with a [`ChannelCommandError::Validation`] without making a request, and usernames that
+    /// don't exist fail with a [`ChannelCommandError::UnknownUser`].
+    pub async fn ban_user(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        username: impl Into<Username>,
+        duration: Option<Duration>,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        let seconds = ban_seconds(duration)?;
+        self.user_command(channel_id, "/ban", username.into(), seconds)
+            .await
+    }
+
+    /// Lifts a ban from a user in the given channel, with the `/unban` command.
+    ///
+    /// The username is handled the same way as for [`Client::ban_user`].
+    pub async fn unban_user(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        username: impl Into<Username>,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        self.user_command(channel_id, "/unban", username.into(), None)
+            .await
+    }
+
+    /// Makes a user a moderator of the given channel, with the `/mod` command.
+    ///
+    /// The username is handled the same way as for [`Client::ban_user`].
+    pub async fn add_moderator(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        username: impl Into<Username>,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        self.user_command(channel_id, "/mod", username.into(), None)
+            .await
+    }
+
+    /// Removes a user's moderator role in the given channel, with the `/unmod` command.
+    ///
+    /// Same as [`Client::add_moderator`] otherwise.
+    pub async fn remove_moderator(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        username: impl Into<Username>,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        self.user_command(channel_id, "/unmod", username.into(), None)
+            .await
+    }
+
+    /// Deletes a message from a channel's chat, e.g. a chat message flagged as spam, by its
+    /// `message_id` and the `sender_id` of whoever sent it.
+    ///
+    /// ```no_run
+    /// # async fn example(client: trovo::Client<trovo::AccessTokenOnly>) -> Result<(), Box<dyn std::error::Error>> {
+    /// client
+    ///     .delete_chat_message("100000", "1625486400_100000_abc", "200000")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Requires the `manage_messages` scope, and the user to be a moderator of the channel. Without
+    /// permission this fails with an [`AuthenticatedRequestError::ApiError`] of
+    /// [`ErrorStatus::MissingChatPermission`](crate::ErrorStatus::MissingChatPermission).
+    pub async fn delete_chat_message(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        message_id: &str,
+        sender_id: impl Into<UserId>,
+    ) -> Result<(), AuthenticatedRequestError<A::Error>> {
+        let path = format!(
+            "channels/{}/messages/{}/users/{}",
+            path_segment(channel_id.into().as_str()),
+            path_segment(message_id),
+            path_segment(sender_id.into().as_str()),
+        );
+        let res = self
+            .send(self.request(Method::DELETE, &path).header(
+                header::AUTHORIZATION,
+                format!(
+                    "OAuth {}",
+                    access_token!(self.auth_provider, AuthenticatedRequestError)
+                ),
+            ))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            Err(AuthenticatedRequestError::ApiError(err))
+        } else {
+            res.error_for_status()?;
+            Ok(())
+        }
+    }
+
+    /// Performs a command taking a username and an optional number after it, turning
+    /// [`ErrorStatus::InvalidUser`] into [`ChannelCommandError::UnknownUser`].
+    async fn user_command(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        command: &str,
+        username: Username,
+        argument: Option<u64>,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        let username = command_username("username", username)?;
+        let command = match argument {
+            Some(argument) => format!("{} {} {}", command, username, argument),
+            None => format!("{} {}", command, username),
+        };
+        match self.channel_command(channel_id, command).await {
+            Err(ChannelCommandError::Request(AuthenticatedRequestError::ApiError(err)))
+                if err.status == ErrorStatus::InvalidUser =>
+            {
+                Err(ChannelCommandError::UnknownUser { username })
+            }
+            result => result,
+        }
+    }
+
+    /// Performs the command, turning a failed [`CommandResult`](crate::CommandResult) into
+    /// [`ChannelCommandError::Rejected`].
+    async fn channel_command(
+        &self,
+        channel_id: impl Into<ChannelId>,
+        command: impl Into<String>,
+    ) -> Result<(), ChannelCommandError<A::Error>> {
+        let result = self.perform_channel_command(channel_id, command).await?;
+        if result.is_success {
+            Ok(())
+        } else {
+            Err(ChannelCommandError::Rejected {
+                display_msg: result.display_msg,
+            })
+        }
+    }
+}
+
+/// Whole seconds of a timed ban, `None` for a permanent one.
+fn ban_seconds(duration: Option<Duration>) -> Result<Option<u64>, ValidationError> {
+    match duration {
+        None => Ok(None),
+        Some(duration) if duration.as_secs() == 0 || duration > MAX_BAN_DURATION => {
+            Err(ValidationError::DurationOutOfRange {
+                field: "duration",
+                value: duration,
+                max: MAX_BAN_DURATION,
+            })
+        }
+        Some(duration) => Ok(Some(duration.as_secs())),
+    }
+}
+
+/// Username as a command argument, which must be a single non-blank word.
+fn command_username(field: &'static str, username: Username) -> Result<String, ValidationError> {
+    let username: String = username.into();
+    if username.is_empty() || username.chars().any(char::is_whitespace) {
+        Err(ValidationError::InvalidUsername {
+            field,
+            value: username,
+        })
+    } else {
+        Ok(username)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        client::tests::authenticated_client, AuthenticatedRequestError, ChannelCommandError,
+        ErrorStatus, ScriptedTransport, ValidationError, MAX_BAN_DURATION,
+    };
+    use reqwest::{header, Method, StatusCode};
+    use std::time::Duration;
+
+    const SUCCESS: &str = r#"{"is_success": true, "display_msg": ""}"#;
+
+    fn commands(transport: &ScriptedTransport) -> Vec<String> {
+        transport
+            .requests()
+            .iter()
+            .map(|request| request.json()["command"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn host_and_unhost() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(StatusCode::OK, SUCCESS)
+            .push(StatusCode::OK, SUCCESS)
+            .push(StatusCode::OK, SUCCESS);
+
+        client.host_channel("100000", "Friend").await.unwrap();
+        client
+            .host_channel("100000", "https://trovo.live/s/friend")
+            .await
+            .unwrap();
+        client.unhost_channel("100000").await.unwrap();
+
+        assert_eq!(
+            commands(&transport),
+            ["/host friend", "/host friend", "/unhost"]
+        );
+        assert_eq!(transport.requests()[0].json()["channel_id"], "100000");
+    }
+
+    #[tokio::test]
+    async fn host_invalid_username() {
+        let (client, transport) = authenticated_client();
+
+        for username in ["", "  ", "two words"] {
+            match client.host_channel("100000", username).await.unwrap_err() {
+                ChannelCommandError::Validation(ValidationError::InvalidUsername {
+                    field, ..
+                }) => assert_eq!(field, "target_username"),
+                err => panic!("unexpected error {:?}", err),
+            }
+        }
+        assert!(transport.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ban_and_unban() {
+        let (client, transport) = authenticated_client();
+        for _ in 0..4 {
+            transport.push(StatusCode::OK, SUCCESS);
+        }
+
+        client.ban_user("100000", "spammer", None).await.unwrap();
+        client
+            .ban_user("100000", "spammer", Some(Duration::from_millis(600_900)))
+            .await
+            .unwrap();
+        client
+            .ban_user("100000", "@ÜberFan", Some(MAX_BAN_DURATION))
+            .await
+            .unwrap();
+        client.unban_user("100000", "Ünïcödé_名前").await.unwrap();
+
+        assert_eq!(
+            commands(&transport),
+            [
+                "/ban spammer",
+                "/ban spammer 600",
+                "/ban überfan 604800",
+                "/unban ünïcödé_名前",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn ban_invalid_duration() {
+        let (client, transport) = authenticated_client();
+
+        for duration in [
+            Duration::ZERO,
+            Duration::from_millis(999),
+            MAX_BAN_DURATION + Duration::from_secs(1),
+        ] {
+            match client
+                .ban_user("100000", "spammer", Some(duration))
+                .await
+                .unwrap_err()
+            {
+                ChannelCommandError::Validation(err) => assert_eq!(
+                    err,
+                    ValidationError::DurationOutOfRange {
+                        field: "duration",
+                        value: duration,
+                        max: MAX_BAN_DURATION,
+                    }
+                ),
+                err => panic!("unexpected error {:?}", err),
+            }
+        }
+        assert!(client.unban_user("100000", "").await.is_err());
+        assert!(transport.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_moderator() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(StatusCode::OK, SUCCESS)
+            .push(StatusCode::OK, SUCCESS);
+
+        client.add_moderator("100000", "Helper").await.unwrap();
+        client.remove_moderator("100000", "@helper").await.unwrap();
+
+        assert_eq!(commands(&transport), ["/mod helper", "/unmod helper"]);
+    }
+
+    #[tokio::test]
+    async fn moderator_unknown_user() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 10505, "message": "user does not exist"}"#,
+            )
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11730, "message": "unauthorized scope"}"#,
+            );
+
+        match client.add_moderator("100000", "helpr").await.unwrap_err() {
+            ChannelCommandError::UnknownUser { username } => assert_eq!(username, "helpr"),
+            err => panic!("unexpected error {:?}", err),
+        }
+        match client
+            .remove_moderator("100000", "helper")
+            .await
+            .unwrap_err()
+        {
+            ChannelCommandError::Request(err) => assert!(err.is_auth_error()),
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert!(matches!(
+            client.add_moderator("100000", "two words").await,
+            Err(ChannelCommandError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn ban_unknown_user() {
+        let (client, transport) = authenticated_client();
+        transport
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 10505, "message": "user does not exist"}"#,
+            )
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 10505, "message": "user does not exist"}"#,
+            );
+
+        match client
+            .ban_user("100000", "spamer", Some(Duration::from_secs(60)))
+            .await
+            .unwrap_err()
+        {
+            ChannelCommandError::UnknownUser { username } => assert_eq!(username, "spamer"),
+            err => panic!("unexpected error {:?}", err),
+        }
+        match client.unban_user("100000", "spamer").await.unwrap_err() {
+            ChannelCommandError::UnknownUser { username } => assert_eq!(username, "spamer"),
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(commands(&transport), ["/ban spamer 60", "/unban spamer"]);
+    }
+
+    #[tokio::test]
+    async fn rejected_command() {
+        let (client, transport) = authenticated_client();
+        transport.push(
+            StatusCode::OK,
+            r#"{"is_success": false, "display_msg": "Channel does not exist"}"#,
+        );
+
+        match client.host_channel("100000", "nobody").await.unwrap_err() {
+            ChannelCommandError::Rejected { display_msg } => {
+                assert_eq!(display_msg, "Channel does not exist");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_chat_message() {
+        let (client, transport) = authenticated_client();
+        transport.push(StatusCode::OK, "{}").push(
+            StatusCode::FORBIDDEN,
+            r#"{"status": 11707, "message": "no permission"}"#,
+        );
+
+        client
+            .delete_chat_message("100000", "1625486400_100000_abc", "200000")
+            .await
+            .unwrap();
+        match client
+            .delete_chat_message("100000", "a/b c?d", "200000")
+            .await
+            .unwrap_err()
+        {
+            AuthenticatedRequestError::ApiError(err) => {
+                assert_eq!(err.status, ErrorStatus::MissingChatPermission);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::DELETE);
+        assert_eq!(
+            requests[0].url.as_str(),
+            "https://open-api.trovo.live/openplatform/channels/100000/messages/1625486400_100000_abc/users/200000"
+        );
+        assert_eq!(
+            requests[0].headers[header::AUTHORIZATION],
+            "OAuth access-token"
+        );
+        assert_eq!(
+            requests[1].url.path(),
+            "/openplatform/channels/100000/messages/a%2Fb%20c%3Fd/users/200000"
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use crate::{
+    ChannelId, Client, ClientIdProvider, Emote, EmoteCacheError, EmoteChannels, EmoteFetchType,
+    GetEmotesResponse,
+};
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Default time emotes stay cached in an [`EmoteCache`]
+pub const DEFAULT_EMOTE_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Where an emote in an [`EmoteMap`] came from
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum EmoteSource {
+    /// Platform-level emote available everywhere
+    Global,
+
+    /// Limited time event emote
+    Event,
+
+    /// Custom emote of the given channel
+    Channel(ChannelId),
+}
+
+/// An emote in an [`EmoteMap`] along with where it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct EmoteEntry {
+    /// The emote
+    pub emote: Emote,
+
+    /// Where the emote came from
+    pub source: EmoteSource,
+}
+
+/// Emotes from an emotes response, keyed by name.
+///
+/// When names collide, channel emotes win over event emotes, which win over global emotes. Between
+/// channels, the first channel in the response wins.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct EmoteMap {
+    emotes: BTreeMap<String, EmoteEntry>,
+}
+
+impl EmoteMap {
+    /// Builds a map from a get emotes response.
+    pub fn from_response(response: GetEmotesResponse) -> Self {
+        Self::from_channels(response.channels)
+    }
+
+    /// Builds a map from the emotes returned by [`Client::emotes`](crate::Client::emotes).
+    pub fn from_channels(channels: EmoteChannels) -> Self {
+        let mut map = Self::default();
+        // Inserted lowest priority first so later sources overwrite earlier ones
+        map.extend(channels.global_emotes, EmoteSource::Global);
+        map.extend(channels.event_emotes, EmoteSource::Event);
+        for channel in channels.customized_emotes.channel.into_iter().rev() {
+            map.extend(
+                channel.emotes,
+                EmoteSource::Channel(ChannelId::new(channel.channel_id)),
+            );
+        }
+        map
+    }
+
+    fn extend(&mut self, emotes: Vec<Emote>, source: EmoteSource) {
+        for emote in emotes {
+            self.emotes.insert(
+                emote.name.clone(),
+                EmoteEntry {
+                    emote,
+                    source: source.clone(),
+                },
+            );
+        }
+    }
+
+    /// Gets an emote by name.
+    pub fn get(&self, name: &str) -> Option<&EmoteEntry> {
+        self.emotes.get(name)
+    }
+
+    /// Iterates over all emotes, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = &EmoteEntry> {
+        self.emotes.values()
+    }
+
+    /// Number of emotes in the map.
+    pub fn len(&self) -> usize {
+        self.emotes.len()
+    }
+
+    /// Whether the map has no emotes.
+    pub fn is_empty(&self) -> bool {
+        self.emotes.is_empty()
+    }
+}
+
+impl From<EmoteChannels> for EmoteMap {
+    fn from(channels: EmoteChannels) -> Self {
+        Self::from_channels(channels)
+    }
+}
+
+impl From<GetEmotesResponse> for EmoteMap {
+    fn from(response: GetEmotesResponse) -> Self {
+        Self::from_response(response)
+    }
+}
+
+/// Caches the [`EmoteMap`] of each channel, fetched with [`Client::emotes`].
+///
+/// Emote sets rarely change, so maps are kept for a TTL, see [`EmoteCache::ttl`]. Concurrent
+/// lookups of the same channel share a single fetch, and clones of the cache share its entries.
+/// Expiry uses the client's [`Clock`](crate::Clock).
+#[derive(Clone)]
+pub struct EmoteCache<A> {
+    client: Client<A>,
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<ChannelId, CacheEntry>>>,
+}
+
+type SharedFetch = Shared<BoxFuture<'static, Result<Arc<EmoteMap>, EmoteCacheError>>>;
+
+#[derive(Default)]
+struct CacheEntry {
+    /// The last fetched map and when it was fetched
+    cached: Option<(Arc<EmoteMap>, Instant)>,
+    fetch: Option<SharedFetch>,
+}
+
+impl<A> EmoteCache<A>
+where
+    A: ClientIdProvider + Clone + Send + Sync + 'static,
+{
+    /// Creates an empty cache fetching with the given client.
+    pub fn new(client: Client<A>) -> Self {
+        Self {
+            client,
+            ttl: DEFAULT_EMOTE_CACHE_TTL,
+            entries: Default::default(),
+        }
+    }
+
+    /// How long a fetched map is used before being fetched again, defaults to
+    /// [`DEFAULT_EMOTE_CACHE_TTL`].
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Gets the global, event and custom emotes of the channel, fetching them if they aren't
+    /// cached or have expired.
+    pub async fn get(
+        &self,
+        channel_id: impl Into<ChannelId>,
+    ) -> Result<Arc<EmoteMap>, EmoteCacheError> {
+        let channel_id = channel_id.into();
+        let fetch = {
+            let mut entries = self.entries.lock().unwrap();
+            let entry = entries.entry(channel_id.clone()).or_default();
+            if let Some((map, fetched_at)) = &entry.cached {
+                let now = self.client.pipeline.clock.now();
+                if now.saturating_duration_since(*fetched_at) < self.ttl {
+                    return Ok(map.clone());
+                }
+            }
+            self.fetch(channel_id, entry)
+        };
+        fetch.await
+    }
+
+    /// Fetches the emotes of the channel, even if cached. Joins a fetch already in flight.
+    ///
+    /// The previous map keeps being served until the fetch succeeds.
+    pub async fn refresh(
+        &self,
+        channel_id: impl Into<ChannelId>,
+    ) -> Result<Arc<EmoteMap>, EmoteCacheError> {
+        let channel_id = channel_id.into();
+        let fetch = {
+            let mut entries = self.entries.lock().unwrap();
+            let entry = entries.entry(channel_id.clone()).or_default();
+            self.fetch(channel_id, entry)
+        };
+        fetch.await
+    }
+
+    /// Starts fetching the channel's emotes, unless a fetch is already in flight.
+    fn fetch(&self, channel_id: ChannelId, entry: &mut CacheEntry) -> SharedFetch {
+        entry
+            .fetch
+            .get_or_insert_with(|| {
+                let client = self.client.clone();
+                let entries = self.entries.clone();
+                async move {
+                    let res = client
+                        .emotes(EmoteFetchType::All, vec![channel_id.to_string()])
+                        .await
+                        .map(|channels| Arc::new(EmoteMap::from_channels(channels)))
+                        .map_err(|err| EmoteCacheError(Arc::new(err)));
+                    let fetched_at = client.pipeline.clock.now();
+                    if let Some(entry) = entries.lock().unwrap().get_mut(&channel_id) {
+                        entry.fetch = None;
+                        if let Ok(map) = &res {
+                            entry.cached = Some((map.clone(), fetched_at));
+                        }
+                    }
+                    res
+                }
+                .boxed()
+                .shared()
+            })
+            .clone()
+    }
+}
+
+impl<A> fmt::Debug for EmoteCache<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmoteCache")
+            .field("ttl", &self.ttl)
+            .field("channels", &self.entries.lock().unwrap().len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientId, MockClock, ScriptedTransport};
+    use reqwest::StatusCode;
+
+    fn emote(name: &str, url: &str) -> String {
+        format!(
+            r#"{{"name": "{}", "description": "", "url": "{}", "status": "1"}}"#,
+            name, url
+        )
+    }
+
+    #[test]
+    fn collisions_and_provenance() {
+        let response: GetEmotesResponse = serde_json::from_str(&format!(
+            r#"{{"channels": {{
+                "customizedEmotes": {{"channel": [
+                    {{"channel_id": "1", "emotes": [{}, {}]}},
+                    {{"channel_id": "2", "emotes": [{}]}}
+                ]}},
+                "eventEmotes": [{}, {}],
+                "globalEmotes": [{}, {}, {}]
+            }}}}"#,
+            emote("wave", "channel-1"),
+            emote("hype", "channel-1"),
+            emote("wave", "channel-2"),
+            emote("party", "event"),
+            emote("smile", "event"),
+            emote("wave", "global"),
+            emote("smile", "global"),
+            emote("cry", "global"),
+        ))
+        .unwrap();
+        let map = EmoteMap::from_response(response);
+
+        let source = |name: &str| map.get(name).map(|e| (e.emote.url.as_str(), &e.source));
+        assert_eq!(
+            source("wave"),
+            Some(("channel-1", &EmoteSource::Channel(ChannelId::new("1"))))
+        );
+        assert_eq!(source("smile"), Some(("event", &EmoteSource::Event)));
+        assert_eq!(source("cry"), Some(("global", &EmoteSource::Global)));
+        assert_eq!(source("nope"), None);
+
+        assert_eq!(map.len(), 5);
+        let names: Vec<_> = map.iter().map(|e| e.emote.name.as_str()).collect();
+        assert_eq!(names, ["cry", "hype", "party", "smile", "wave"]);
+
+        let cached: EmoteMap = serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+        assert_eq!(cached.len(), 5);
+        assert_eq!(
+            cached.get("wave").unwrap().source,
+            map.get("wave").unwrap().source
+        );
+    }
+
+    fn emotes_response(url: &str) -> String {
+        format!(
+            r#"{{"channels": {{
+                "customizedEmotes": {{"channel": [{{"channel_id": "1", "emotes": [{}]}}]}},
+                "eventEmotes": [],
+                "globalEmotes": []
+            }}}}"#,
+            emote("wave", url)
+        )
+    }
+
+    fn cache(transport: &ScriptedTransport, clock: &MockClock) -> EmoteCache<ClientId> {
+        let client = Client::builder()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build(ClientId::new("client-id"))
+            .unwrap();
+        EmoteCache::new(client).ttl(Duration::from_secs(60))
+    }
+
+    #[tokio::test]
+    async fn cache_single_fetch() {
+        let transport = ScriptedTransport::new().with_delay(Duration::from_millis(50));
+        transport.push(StatusCode::OK, emotes_response("first"));
+        let cache = cache(&transport, &MockClock::new());
+
+        let gets = (0..10).map(|_| cache.get("1"));
+        let maps = futures::future::join_all(gets).await;
+
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(
+            transport.requests()[0].json(),
+            serde_json::json!({"emote_type": 0, "channel_id": ["1"]})
+        );
+        let first = maps[0].as_ref().unwrap();
+        assert_eq!(first.get("wave").unwrap().emote.url, "first");
+        for map in &maps {
+            assert!(Arc::ptr_eq(first, map.as_ref().unwrap()));
+        }
+    }
+
+    #[tokio::test]
+    async fn cache_expiry_and_refresh() {
+        let transport = ScriptedTransport::new();
+        let clock = MockClock::new();
+        let cache = cache(&transport, &clock);
+        let url = |map: Arc<EmoteMap>| map.get("wave").unwrap().emote.url.clone();
+
+        transport.push(StatusCode::OK, emotes_response("first"));
+        assert_eq!(url(cache.get("1").await.unwrap()), "first");
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(url(cache.get("1").await.unwrap()), "first");
+        assert_eq!(transport.requests().len(), 1);
+
+        // Expired entries are fetched again, once for all callers
+        transport.push(StatusCode::OK, emotes_response("second"));
+        clock.advance(Duration::from_secs(1));
+        let (a, b) = futures::join!(cache.get("1"), cache.get("1"));
+        assert_eq!(url(a.unwrap()), "second");
+        assert_eq!(url(b.unwrap()), "second");
+        assert_eq!(transport.requests().len(), 2);
+
+        // Failed refreshes keep serving the cached map
+        transport.push(StatusCode::BAD_GATEWAY, "");
+        assert!(cache.refresh("1").await.is_err());
+        assert_eq!(url(cache.get("1").await.unwrap()), "second");
+
+        transport.push(StatusCode::OK, emotes_response("third"));
+        assert_eq!(url(cache.refresh("1").await.unwrap()), "third");
+        assert_eq!(url(cache.get("1").await.unwrap()), "third");
+        assert_eq!(transport.requests().len(), 4);
+    }
+}
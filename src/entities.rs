@@ -1,10 +1,18 @@
-use chrono::{DateTime, Utc};
+use crate::{
+    timestamp::unix_seconds, CategoryId, ChannelId, InvalidLanguageCode, LanguageCode, Timestamp,
+    UserId, Username, ValidationError,
+};
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use serde_repr::*;
-use serde_with::{serde_as, NoneAsEmptyString};
+use serde_with::{serde_as, DefaultOnNull, DisplayFromStr, NoneAsEmptyString, PickFirst};
+use std::collections::HashMap;
 
 /// User details returned by [`Client::users`](crate::Client::users)
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Equality and hashing are structural over all fields.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct User {
     /// Unique id of a user.
     pub user_id: String,
@@ -19,30 +27,1216 @@ pub struct User {
     pub nickname: String,
 }
 
+impl User {
+    /// Typed id of the user
+    pub fn user_id(&self) -> UserId {
+        UserId::new(&self.user_id)
+    }
+
+    /// Typed id of the user's channel
+    pub fn channel_id(&self) -> ChannelId {
+        ChannelId::new(&self.channel_id)
+    }
+}
+
+/// The authenticated user, returned by [`Client::me`](crate::Client::me)
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct UserInfo {
+    /// Unique id of the user.
+    #[serde(rename = "userId")]
+    pub user_id: String,
+
+    /// The username of the user, also the last part of their channel url.
+    #[serde(rename = "userName")]
+    pub username: String,
+
+    /// The display name of the user.
+    #[serde(rename = "nickName", default)]
+    pub nickname: String,
+
+    /// Email of the user, `None` unless the token has the `user_details_self` scope.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub email: Option<String>,
+
+    /// Url of the user's profile picture, `None` if not set.
+    #[serde(rename = "profilePic", default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub profile_pic: Option<String>,
+
+    /// Unique id of the user's channel.
+    #[serde(rename = "channelId")]
+    pub channel_id: String,
+}
+
+impl UserInfo {
+    /// Typed id of the user
+    pub fn user_id(&self) -> UserId {
+        UserId::new(&self.user_id)
+    }
+
+    /// Typed id of the user's channel
+    pub fn channel_id(&self) -> ChannelId {
+        ChannelId::new(&self.channel_id)
+    }
+}
+
 /// Payload for the get users api
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Usernames added through the builder or converted from a `Vec<String>` are normalized with
+/// [`Username::parse`]; set [`user`](Self::user) directly, or convert from a `Vec<Username>` of
+/// [`Username::exact`] names, to send them as is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GetUsersPayload {
     /// A list of valid usernames that you want to request for. Not case sensitive.
     pub user: Vec<String>,
 }
 
-/// Response for the get users api
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GetUsersResponse {
-    /// The list of user info for each username requested.
-    pub users: Vec<User>,
+impl GetUsersPayload {
+    /// Maximum number of usernames the api accepts in one request.
+    pub const MAX_USERS: usize = 100;
+
+    /// Starts building a validated payload.
+    pub fn builder() -> GetUsersPayloadBuilder {
+        GetUsersPayloadBuilder::default()
+    }
+
+    /// Checks the payload against the api's limits: between 1 and [`Self::MAX_USERS`] non-blank
+    /// usernames.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_list("user", &self.user, Self::MAX_USERS, true)
+    }
+}
+
+impl From<Vec<String>> for GetUsersPayload {
+    fn from(user: Vec<String>) -> Self {
+        user.into_iter()
+            .map(Username::from)
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+impl From<Vec<Username>> for GetUsersPayload {
+    fn from(user: Vec<Username>) -> Self {
+        Self {
+            user: user.into_iter().map(String::from).collect(),
+        }
+    }
+}
+
+/// Builder for [`GetUsersPayload`], see [`GetUsersPayload::builder`]
+#[derive(Debug, Clone, Default)]
+pub struct GetUsersPayloadBuilder {
+    user: Vec<String>,
+}
+
+impl GetUsersPayloadBuilder {
+    /// Adds a username to look up, normalized unless it's a [`Username::exact`].
+    pub fn user(mut self, username: impl Into<Username>) -> Self {
+        self.user.push(username.into().into());
+        self
+    }
+
+    /// Adds several usernames to look up, see [`user`](Self::user).
+    pub fn users(mut self, usernames: impl IntoIterator<Item = impl Into<Username>>) -> Self {
+        self.user
+            .extend(usernames.into_iter().map(|name| name.into().into()));
+        self
+    }
+
+    /// Validates and builds the payload.
+    pub fn build(self) -> Result<GetUsersPayload, ValidationError> {
+        let payload = GetUsersPayload { user: self.user };
+        payload.validate()?;
+        Ok(payload)
+    }
+}
+
+/// Checks a list of strings is within `max` entries, has no blank entries, and is non-empty if
+/// `required`.
+fn validate_list(
+    field: &'static str,
+    list: &[String],
+    max: usize,
+    required: bool,
+) -> Result<(), ValidationError> {
+    if required && list.is_empty() {
+        return Err(ValidationError::Empty { field });
+    }
+    if list.len() > max {
+        return Err(ValidationError::TooMany {
+            field,
+            len: list.len(),
+            max,
+        });
+    }
+    if list.iter().any(|s| s.trim().is_empty()) {
+        return Err(ValidationError::BlankString { field });
+    }
+    Ok(())
+}
+
+/// Response for the get users api
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GetUsersResponse {
+    /// Number of users found, `0` if missing.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub total: u64,
+
+    /// The list of user info for each username requested.
+    pub users: Vec<User>,
+}
+
+/// Result of [`Client::users_detailed`](crate::Client::users_detailed)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsersLookup {
+    /// Users that were found.
+    pub found: Vec<User>,
+
+    /// Requested usernames that were not found, in the order they were requested.
+    pub missing: Vec<String>,
+
+    /// Requested usernames that weren't looked up on their own before the split limit was
+    /// reached, so may or may not exist, in the order they were requested.
+    pub unresolved: Vec<String>,
+}
+
+impl UsersLookup {
+    /// Sorts the found users into the order of the given usernames, matched case-insensitively.
+    /// Users not matching any of the usernames keep their order at the end.
+    pub fn sort_by_request<S: AsRef<str>>(&mut self, usernames: &[S]) {
+        self.found.sort_by_key(|user| {
+            usernames
+                .iter()
+                .position(|name| user.username.eq_ignore_ascii_case(name.as_ref()))
+                .unwrap_or(usize::MAX)
+        });
+    }
+}
+
+/// Payload for the get channel info by id api
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetChannelByIdPayload {
+    /// Channel id indicating which channel you are requesting.
+    pub channel_id: String,
+}
+
+/// Payload for the get channel info by username api
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetChannelByUsernamePayload {
+    /// Username of the channel's streamer. Not case sensitive.
+    pub username: String,
+}
+
+/// Response for the get users api
+///
+/// The counters accept both numbers and numeric strings, with `null` treated as zero.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ChannelInfo {
+    /// If the channel is currently live streaming.
+    pub is_live: bool,
+
+    /// The id of the game category.
+    pub category_id: String,
+
+    /// Text name of the category.
+    pub category_name: String,
+
+    /// Current title of the channel.
+    pub live_title: String,
+
+    /// Audience type.
+    pub audi_type: AudienceType,
+
+    /// Language of the channel in in ISO 2 (2 letter language code)
+    pub language_code: String,
+
+    /// URL of the thumbnail, `None` if the thumbnail from the previous stream has expired.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub thumbnail: Option<String>,
+
+    /// Number of current viewers
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub current_viewers: u64,
+
+    /// Number of followers
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub followers: u64,
+
+    /// Profile information of the streamer, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub streamer_info: Option<String>,
+
+    /// Url of the streamer’s profile picture, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub profile_pic: Option<String>,
+
+    /// URL of the channel
+    pub channel_url: String,
+
+    /// Timestamp of the streamer creation time, `None` if not set.
+    #[serde(with = "crate::timestamp::optional_seconds")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub created_at: Option<Timestamp>,
+
+    /// Count of subscribers
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub subscriber_num: u64,
+
+    /// Username of the channel’s streamer. Also the last part of the channel url.
+    pub username: String,
+
+    /// Social media links of the streamer.
+    pub social_links: Vec<SocialLink>,
+
+    /// The latest streaming start time of a given channel, `None` if the channel has never
+    /// streamed.
+    #[serde(with = "crate::timestamp::optional_seconds")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub started_at: Option<Timestamp>,
+
+    /// The latest streaming end time of a given channel, `None` if the channel has never
+    /// streamed.
+    #[serde(with = "crate::timestamp::optional_seconds")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub ended_at: Option<Timestamp>,
+}
+
+impl ChannelInfo {
+    /// Id and name of the channel's current category, `None` if no category is set.
+    ///
+    /// The id can be passed straight on to other calls, e.g. to move another channel into the
+    /// same category:
+    ///
+    /// ```no_run
+    /// # async fn example(client: trovo::Client<trovo::AccessTokenOnly>) -> Result<(), Box<dyn std::error::Error>> {
+    /// use trovo::ChannelUpdate;
+    ///
+    /// let channel = client.channel_by_id("100000").await?.expect("channel exists");
+    /// if let Some((category_id, name)) = channel.category() {
+    ///     println!("switching to {}", name);
+    ///     let update = ChannelUpdate {
+    ///         category_id: Some(category_id),
+    ///         ..Default::default()
+    ///     };
+    ///     client.update_channel("100001", update).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn category(&self) -> Option<(CategoryId, &str)> {
+        if self.category_id.is_empty() {
+            None
+        } else {
+            Some((CategoryId::new(&self.category_id), &self.category_name))
+        }
+    }
+
+    /// Parsed [`language_code`](Self::language_code) of the channel.
+    pub fn language(&self) -> Result<LanguageCode, InvalidLanguageCode> {
+        self.language_code.parse()
+    }
+}
+
+/// Game category of a channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Category {
+    /// Unique id of the category.
+    pub id: CategoryId,
+
+    /// Name of the category.
+    pub name: String,
+
+    /// Short name of the category.
+    #[serde(default)]
+    pub short_name: String,
+
+    /// Url of the category icon.
+    #[serde(default)]
+    pub icon_url: String,
+
+    /// Description of the category.
+    #[serde(default)]
+    pub desc: String,
+}
+
+/// Payload for the get channel followers api
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetFollowersPayload {
+    /// Number of followers per page, between 1 and 100. The api defaults to 20.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// Page to fetch, starting from 0, see [`FollowersPage::next_cursor`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<u64>,
+}
+
+/// A page of a channel's followers, see [`Client::channel_followers`](crate::Client::channel_followers)
+///
+/// The counters accept both numbers and numeric strings, with `null` treated as zero.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct FollowersPage {
+    /// Total number of followers of the channel.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub total: u64,
+
+    /// Followers on this page. Empty past the last page.
+    #[serde(rename = "follower", default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub followers: Vec<Follower>,
+
+    /// Total number of pages.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub total_page: u64,
+
+    /// This page, starting from 0.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub cur_page: u64,
+}
+
+impl FollowersPage {
+    /// The cursor of the page after this one, or `None` if this was the last page.
+    ///
+    /// The api pages by number, so this is `cur_page + 1`, and can be stored to resume paging
+    /// later.
+    pub fn next_cursor(&self) -> Option<u64> {
+        if self.followers.is_empty() || self.cur_page + 1 >= self.total_page {
+            return None;
+        }
+        Some(self.cur_page + 1)
+    }
+}
+
+/// A follower of a channel
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Follower {
+    /// Unique id of the user.
+    pub user_id: String,
+
+    /// Display name of the user.
+    pub nickname: String,
+
+    /// Url of the user's profile picture, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub profile_pic: Option<String>,
+
+    /// When the user followed the channel, `None` if unknown.
+    #[serde(default, with = "crate::timestamp::optional_seconds")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub followed_at: Option<Timestamp>,
+}
+
+impl Follower {
+    /// Typed id of the user
+    pub fn user_id(&self) -> UserId {
+        UserId::new(&self.user_id)
+    }
+}
+
+/// Options for [`Client::clips`](crate::Client::clips), every field is optional
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipsQuery {
+    /// Number of clips to return, between 1 and 100. The api defaults to 20.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// Page to fetch, starting from 0, see [`Self::next_page`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<u64>,
+
+    /// Only list clips made within this period. The api defaults to [`ClipPeriod::All`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<ClipPeriod>,
+
+    /// Order of the clips. The api defaults to [`ClipSort::Date`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<ClipSort>,
+}
+
+impl ClipsQuery {
+    /// The query for the page after `clips`, keeping the filters, or `None` if `clips` was empty
+    /// and there are no more pages.
+    pub fn next_page(&self, clips: &[Clip]) -> Option<Self> {
+        if clips.is_empty() {
+            return None;
+        }
+        Some(Self {
+            cursor: Some(self.cursor.unwrap_or(0) + 1),
+            ..self.clone()
+        })
+    }
+}
+
+/// Period to list clips from, see [`ClipsQuery::period`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipPeriod {
+    /// The last day
+    Day,
+
+    /// The last week
+    Week,
+
+    /// The last month
+    Month,
+
+    /// Any time
+    All,
+}
+
+/// Order to list clips in, see [`ClipsQuery::sort_by`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipSort {
+    /// Most viewed first
+    Views,
+
+    /// Most recent first
+    Date,
+}
+
+/// Payload for the get clips api
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GetClipsPayload {
+    /// Id of the channel
+    pub channel_id: String,
+
+    /// Filters and paging
+    #[serde(flatten)]
+    pub query: ClipsQuery,
+}
+
+/// Response for the get clips api
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GetClipsResponse {
+    /// Clips on this page. Empty past the last page.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub clips_info: Vec<Clip>,
+}
+
+/// A clip of a channel, see [`Client::clips`](crate::Client::clips)
+///
+/// The counters accept both numbers and numeric strings, with `null` treated as zero.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Clip {
+    /// Unique id of the clip.
+    pub clip_id: String,
+
+    /// Title of the clip.
+    #[serde(default)]
+    pub title: String,
+
+    /// Url to watch the clip.
+    #[serde(default)]
+    pub url: String,
+
+    /// Url of the thumbnail, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub thumbnail: Option<String>,
+
+    /// Length of the clip in seconds, `0` if unknown.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub duration: u64,
+
+    /// Number of views.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub views: u64,
+
+    /// Display name of the user who made the clip.
+    #[serde(default)]
+    pub maker_nickname: String,
+
+    /// When the clip was made, `None` if unknown.
+    #[serde(
+        default,
+        alias = "made_at",
+        with = "crate::timestamp::optional_seconds"
+    )]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub created_at: Option<Timestamp>,
+}
+
+/// Options for [`Client::past_streams`](crate::Client::past_streams), every field is optional
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PastStreamsQuery {
+    /// Number of past streams to return, between 1 and 100. The api defaults to 20.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// Page to fetch, starting from 0, see [`Self::next_page`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<u64>,
+
+    /// Order of the past streams by start time. The api defaults to [`SortDirection::Desc`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<SortDirection>,
+}
+
+impl PastStreamsQuery {
+    /// The query for the page after `streams`, keeping the direction, or `None` if `streams` was
+    /// empty and there are no more pages.
+    pub fn next_page(&self, streams: &[PastStream]) -> Option<Self> {
+        if streams.is_empty() {
+            return None;
+        }
+        Some(Self {
+            cursor: Some(self.cursor.unwrap_or(0) + 1),
+            ..self.clone()
+        })
+    }
+}
+
+/// Direction to sort a listing in, see [`PastStreamsQuery::direction`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    /// Oldest first
+    Asc,
+
+    /// Newest first
+    Desc,
+}
+
+/// Payload for the get past streams api
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GetPastStreamsPayload {
+    /// Id of the channel
+    pub channel_id: String,
+
+    /// Direction and paging
+    #[serde(flatten)]
+    pub query: PastStreamsQuery,
+}
+
+/// Response for the get past streams api
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GetPastStreamsResponse {
+    /// Past streams on this page. Empty past the last page.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub video_info: Vec<PastStream>,
+}
+
+/// A past broadcast of a channel, see [`Client::past_streams`](crate::Client::past_streams)
+///
+/// The duration and counters accept both numbers and numeric strings, with `null` treated as
+/// zero.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PastStream {
+    /// Unique id of the past stream.
+    pub vid: String,
+
+    /// Title of the stream.
+    #[serde(default)]
+    pub title: String,
+
+    /// Url to watch the past stream.
+    #[serde(default)]
+    pub url: String,
+
+    /// Url of the thumbnail, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub thumbnail: Option<String>,
+
+    /// Length of the stream in seconds, `0` if unknown.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub duration: u64,
+
+    /// Number of times the past stream was played.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub views: u64,
+
+    /// Number of likes.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub likes: u64,
+
+    /// The id of the game category streamed.
+    #[serde(default)]
+    pub category_id: String,
+
+    /// Text name of the category.
+    #[serde(default)]
+    pub category_name: String,
+
+    /// When the stream started, `None` if unknown.
+    #[serde(default, with = "crate::timestamp::optional_seconds")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub start_at: Option<Timestamp>,
+
+    /// When the stream ended, `None` if unknown.
+    #[serde(default, with = "crate::timestamp::optional_seconds")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub end_at: Option<Timestamp>,
+}
+
+impl PastStream {
+    /// Id and name of the category streamed, `None` if no category was set.
+    pub fn category(&self) -> Option<(CategoryId, &str)> {
+        if self.category_id.is_empty() {
+            None
+        } else {
+            Some((CategoryId::new(&self.category_id), &self.category_name))
+        }
+    }
+}
+
+/// Payload for the get live stream urls api
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetStreamUrlsPayload {
+    /// Id of the channel
+    pub channel_id: String,
+}
+
+/// Response for the get live stream urls api
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GetStreamUrlsResponse {
+    /// Playback urls, one per quality. Empty if the channel is offline.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub stream_urls: Vec<StreamUrl>,
+}
+
+/// Playback url of a live channel at one quality, see
+/// [`Client::stream_urls`](crate::Client::stream_urls)
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct StreamUrl {
+    /// Url of the m3u8 playlist.
+    pub play_url: String,
+
+    /// Quality label, e.g. `1080P` or `Original`.
+    #[serde(default)]
+    pub desc: String,
+
+    /// Bitrate in kbps, `None` if not reported.
+    #[serde(default)]
+    #[serde_as(as = "Option<PickFirst<(_, DisplayFromStr)>>")]
+    pub bitrate: Option<u64>,
+}
+
+/// Payload for the get channel viewers api
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetViewersPayload {
+    /// Number of viewers per page, between 1 and 100000. The api defaults to 20.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// Page to fetch, starting from 0, see [`ViewersResponse::next_cursor`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<u64>,
+}
+
+/// A page of the viewers of a live channel, grouped by role, see
+/// [`Client::channel_viewers`](crate::Client::channel_viewers)
+///
+/// Roles nobody is watching in are left out by the api, and read as empty groups.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ViewersResponse {
+    /// Current title of the channel.
+    #[serde(default)]
+    pub live_title: String,
+
+    /// Display name of the streamer.
+    #[serde(default)]
+    pub nickname: String,
+
+    /// Total number of viewers.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub total: u64,
+
+    /// Viewers grouped by the platform's roles.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub chatters: ViewerGroups,
+
+    /// Viewers grouped by the channel's custom roles, by role name.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub custom_roles: HashMap<String, ViewerGroup>,
+
+    /// Total number of pages.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub total_page: u64,
+
+    /// This page, starting from 0.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub cursor: u64,
+}
+
+impl ViewersResponse {
+    /// The cursor of the page after this one, or `None` if this was the last page.
+    ///
+    /// The api pages by number, so this is `cursor + 1`, and can be stored to resume paging later.
+    pub fn next_cursor(&self) -> Option<u64> {
+        if self.chatters.all.viewers.is_empty() || self.cursor + 1 >= self.total_page {
+            return None;
+        }
+        Some(self.cursor + 1)
+    }
+}
+
+/// Viewers of a channel by role, see [`ViewersResponse`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ViewerGroups {
+    /// Every viewer, whatever their role.
+    #[serde(default)]
+    pub all: ViewerGroup,
+
+    /// VIPs of the channel.
+    #[serde(rename = "VIPS", default)]
+    pub vips: ViewerGroup,
+
+    /// Ace subscribers.
+    #[serde(default)]
+    pub ace: ViewerGroup,
+
+    /// Ace+ subscribers.
+    #[serde(default)]
+    pub aceplus: ViewerGroup,
+
+    /// Trovo admins.
+    #[serde(default)]
+    pub admins: ViewerGroup,
+
+    /// Trovo creators.
+    #[serde(default)]
+    pub creators: ViewerGroup,
+
+    /// Editors of the channel.
+    #[serde(default)]
+    pub editors: ViewerGroup,
+
+    /// Followers of the channel.
+    #[serde(default)]
+    pub followers: ViewerGroup,
+
+    /// Moderators of the channel.
+    #[serde(default)]
+    pub moderators: ViewerGroup,
+
+    /// Subscribers of the channel.
+    #[serde(default)]
+    pub subscribers: ViewerGroup,
+
+    /// Trovo super moderators.
+    #[serde(default)]
+    pub supermods: ViewerGroup,
+
+    /// Trovo wardens.
+    #[serde(default)]
+    pub wardens: ViewerGroup,
+}
+
+/// Names of the viewers with a role
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ViewerGroup {
+    /// Display names of the viewers.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub viewers: Vec<String>,
+}
+
+/// A page of a channel's subscribers, see
+/// [`Client::channel_subscribers`](crate::Client::channel_subscribers)
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SubscribersPage {
+    /// Total number of subscribers of the channel.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub total: u64,
+
+    /// Subscribers on this page. Empty past the last page.
+    #[serde(rename = "subscriptions", default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub subscribers: Vec<Subscriber>,
+}
+
+/// A subscriber of a channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Subscriber {
+    /// The subscribed user.
+    pub user: SubscriberUser,
+
+    /// Subscription level, e.g. `"sub_L1"`. Also read from `sub_level`.
+    #[serde(default, alias = "sub_level")]
+    pub sub_lv: String,
+
+    /// Tier and start of the subscription, read from the same object.
+    #[serde(flatten)]
+    pub subscription: SubscriptionInfo,
+}
+
+/// User details of a [`Subscriber`]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SubscriberUser {
+    /// Unique id of the user.
+    pub user_id: String,
+
+    /// The username of the user.
+    pub username: String,
+
+    /// Display name of the user. Also read from `display_name`.
+    #[serde(default, alias = "display_name")]
+    pub nickname: String,
+
+    /// Url of the user's profile picture, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub profile_pic: Option<String>,
+}
+
+impl SubscriberUser {
+    /// Typed id of the user
+    pub fn user_id(&self) -> UserId {
+        UserId::new(&self.user_id)
+    }
+}
+
+/// A user's subscription to a channel, see
+/// [`Client::subscription_status`](crate::Client::subscription_status) and
+/// [`Subscriber::subscription`]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SubscriptionInfo {
+    /// Subscription tier. Accepts both numbers and numeric strings.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub sub_tier: u32,
+
+    /// When the subscription started, `None` if unknown.
+    #[serde(
+        rename = "sub_created_at",
+        alias = "started_at",
+        default,
+        with = "crate::timestamp::optional_seconds"
+    )]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub started_at: Option<Timestamp>,
+
+    /// Whether the subscription was gifted by another user, `false` if not given.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub is_gift: bool,
+}
+
+/// Payload for the search categories api
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchCategoryPayload {
+    /// Text to search category names for.
+    pub query: String,
+
+    /// Maximum number of categories to return. The api defaults to 20.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Response for the get top categories and search categories apis
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GetCategoriesResponse {
+    /// Categories, most popular first. Empty if missing or `null`.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub category_info: Vec<Category>,
+}
+
+/// Audience type of a channel
+///
+/// Values not known to this crate are kept as [`AudienceType::Other`] and serialised back
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+#[non_exhaustive]
+pub enum AudienceType {
+    /// Family friendly
+    FamilyFriendly,
+
+    /// Teen
+    Teen,
+
+    /// 18+
+    EighteenPlus,
+
+    /// An audience type not known to this crate, including an empty string for unset channels
+    Other(String),
+}
+
+impl AudienceType {
+    /// The raw string used by the api.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::FamilyFriendly => "CHANNEL_AUDIENCE_TYPE_FAMILYFRIENDLY",
+            Self::Teen => "CHANNEL_AUDIENCE_TYPE_TEEN",
+            Self::EighteenPlus => "CHANNEL_AUDIENCE_TYPE_EIGHTEENPLUS",
+            Self::Other(s) => s,
+        }
+    }
+}
+
+impl From<String> for AudienceType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "CHANNEL_AUDIENCE_TYPE_FAMILYFRIENDLY" => Self::FamilyFriendly,
+            "CHANNEL_AUDIENCE_TYPE_TEEN" => Self::Teen,
+            "CHANNEL_AUDIENCE_TYPE_EIGHTEENPLUS" => Self::EighteenPlus,
+            _ => Self::Other(s),
+        }
+    }
+}
+
+impl From<AudienceType> for String {
+    fn from(audi_type: AudienceType) -> Self {
+        match audi_type {
+            AudienceType::Other(s) => s,
+            known => known.as_str().to_owned(),
+        }
+    }
+}
+
+/// Social media link for a channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SocialLink {
+    /// Social media platform as returned by the api, see [`SocialLink::platform`] for a typed
+    /// version
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    /// Url to the account on the given platform
+    pub url: String,
+}
+
+impl SocialLink {
+    /// Social media platform of the link.
+    pub fn platform(&self) -> SocialPlatform {
+        SocialPlatform::from(self.type_.as_str())
+    }
+
+    /// Parsed url of the link.
+    pub fn link(&self) -> Result<Url, url::ParseError> {
+        Url::parse(&self.url)
+    }
+}
+
+/// Social media platform of a [`SocialLink`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SocialPlatform {
+    /// Facebook
+    Facebook,
+
+    /// Twitter
+    Twitter,
+
+    /// YouTube
+    YouTube,
+
+    /// Discord
+    Discord,
+
+    /// Instagram
+    Instagram,
+
+    /// Twitch
+    Twitch,
+
+    /// TikTok
+    TikTok,
+
+    /// A platform not known to this crate, with the original value
+    Other(String),
+}
+
+impl SocialPlatform {
+    /// The lowercase name used by the api.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Facebook => "facebook",
+            Self::Twitter => "twitter",
+            Self::YouTube => "youtube",
+            Self::Discord => "discord",
+            Self::Instagram => "instagram",
+            Self::Twitch => "twitch",
+            Self::TikTok => "tiktok",
+            Self::Other(s) => s,
+        }
+    }
 }
 
-/// Payload for the get channel info by id api
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GetChannelByIdPayload {
-    /// Channel id indicating which channel you are requesting.
-    pub channel_id: String,
+impl From<&str> for SocialPlatform {
+    /// Parses a platform name, ignoring case.
+    fn from(s: &str) -> Self {
+        match s.to_ascii_lowercase().as_str() {
+            "facebook" => Self::Facebook,
+            "twitter" => Self::Twitter,
+            "youtube" => Self::YouTube,
+            "discord" => Self::Discord,
+            "instagram" => Self::Instagram,
+            "twitch" => Self::Twitch,
+            "tiktok" => Self::TikTok,
+            _ => Self::Other(s.to_owned()),
+        }
+    }
 }
 
-/// Response for the get users api
-#[derive(Debug, Deserialize)]
-pub struct ChannelInfo {
+/// Payload for the get top channels api, see [`Client::top_channels`](crate::Client::top_channels)
+///
+/// Every field is optional, the default payload fetches the first page of all live channels.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GetTopChannelsPayload {
+    /// Number of channels per page, at most [`Self::MAX_LIMIT`]. The api defaults to 20.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// Fetch the page after [`cursor`](Self::cursor) if `true`, or before it if `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<bool>,
+
+    /// Pagination token from the previous page's [`TopChannelsResponse::token`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    /// Current page from the previous page's [`TopChannelsResponse::cursor`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<u64>,
+
+    /// Only list channels streaming in this category.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<CategoryId>,
+
+    /// Only list these channels.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channel_ids: Vec<String>,
+}
+
+impl GetTopChannelsPayload {
+    /// Maximum number of channels per page the api returns.
+    pub const MAX_LIMIT: u32 = 100;
+
+    /// Maximum number of channel ids to filter by.
+    pub const MAX_CHANNELS: usize = 100;
+
+    /// Checks the payload against the api's limits: a limit of at most [`Self::MAX_LIMIT`], and
+    /// at most [`Self::MAX_CHANNELS`] non-blank channel ids.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(limit) = self.limit.filter(|limit| *limit > Self::MAX_LIMIT) {
+            return Err(ValidationError::TooLarge {
+                field: "limit",
+                value: limit.into(),
+                max: Self::MAX_LIMIT.into(),
+            });
+        }
+        validate_list("channel_ids", &self.channel_ids, Self::MAX_CHANNELS, false)
+    }
+
+    /// The payload for the page after `response`, keeping the filters, or `None` if `response`
+    /// was the last page.
+    pub fn next_page(&self, response: &TopChannelsResponse) -> Option<Self> {
+        if response.channels.is_empty() || response.cursor + 1 >= response.total_page {
+            return None;
+        }
+        Some(Self {
+            after: Some(true),
+            token: response.token.clone(),
+            cursor: Some(response.cursor),
+            ..self.clone()
+        })
+    }
+}
+
+/// Response for the get top channels api
+///
+/// A cursor past the last page returns no channels rather than an error.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TopChannelsResponse {
+    /// Channels on this page, most viewers first.
+    #[serde(rename = "top_channels_lists", default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub channels: Vec<TopChannel>,
+
+    /// Total number of pages.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub total_page: u64,
+
+    /// Pagination token to pass back when fetching the next page, `None` if not sent.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub token: Option<String>,
+
+    /// Current page, starting from 0.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub cursor: u64,
+}
+
+/// A channel listed by [`Client::top_channels`](crate::Client::top_channels)
+///
+/// The counters accept both numbers and numeric strings, with `null` treated as zero.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TopChannel {
+    /// Unique id of the channel.
+    pub channel_id: String,
+
     /// If the channel is currently live streaming.
     pub is_live: bool,
 
@@ -55,85 +1249,68 @@ pub struct ChannelInfo {
     /// Current title of the channel.
     pub live_title: String,
 
-    /// Audience type.
-    pub audi_type: AudienceType,
-
-    /// Language of the channel in in ISO 2 (2 letter language code)
-    pub language_code: String,
-
-    /// URL of the thumbnail. Empty thumbnail means the thumbnail from the previous stream has
-    /// expired.
-    pub thumbnail: String,
-
     /// Number of current viewers
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
     pub current_viewers: u64,
 
     /// Number of followers
-    pub followers: u64,
-
-    /// Profile information of the streamer
-    pub streamer_info: String,
-
-    /// Url of the streamer’s profile picture
-    pub profile_pic: String,
-
-    /// URL of the channel
-    pub channel_url: String,
-
-    /// Timestamp of the streamer creation time
-    #[serde(with = "serde_with::chrono_0_4::datetime_utc_ts_seconds_from_any")]
-    pub created_at: DateTime<Utc>,
-
-    /// Count of subscribers
-    pub subscriber_num: u64,
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull<PickFirst<(_, DisplayFromStr)>>")]
+    pub num_followers: u64,
 
     /// Username of the channel’s streamer. Also the last part of the channel url.
     pub username: String,
 
-    /// Social media links of the streamer.
-    pub social_links: Vec<SocialLink>,
+    /// Display name of the channel’s streamer.
+    #[serde(default)]
+    pub nick_name: String,
 
-    /// The latest streaming start time of a given channel.
-    #[serde(with = "serde_with::chrono_0_4::datetime_utc_ts_seconds_from_any")]
-    pub started_at: DateTime<Utc>,
+    /// Language of the channel in in ISO 2 (2 letter language code)
+    #[serde(default)]
+    pub language_code: String,
 
-    /// The latest streaming end time of a given channel.
-    #[serde(with = "serde_with::chrono_0_4::datetime_utc_ts_seconds_from_any")]
-    pub ended_at: DateTime<Utc>,
-}
+    /// URL of the channel
+    #[serde(default)]
+    pub channel_url: String,
 
-/// Audience type of a channel
-#[derive(Debug, Serialize, Deserialize)]
-pub enum AudienceType {
-    /// Family friendly
-    #[serde(rename = "CHANNEL_AUDIENCE_TYPE_FAMILYFRIENDLY")]
-    FamilyFriendly,
+    /// URL of the thumbnail, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub thumbnail: Option<String>,
 
-    /// Teen
-    #[serde(rename = "CHANNEL_AUDIENCE_TYPE_TEEN")]
-    Teen,
+    /// Url of the streamer’s profile picture, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub profile_pic: Option<String>,
 
-    /// 18+
-    #[serde(rename = "CHANNEL_AUDIENCE_TYPE_EIGHTEENPLUS")]
-    EighteenPlus,
+    /// Profile information of the streamer, `None` if not set.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub streamer_info: Option<String>,
 }
 
-/// Social media link for a channel
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SocialLink {
-    /// Social media platform
-    #[serde(rename = "type")]
-    type_: String,
+impl TopChannel {
+    /// Typed id of the channel
+    pub fn channel_id(&self) -> ChannelId {
+        ChannelId::new(&self.channel_id)
+    }
 
-    /// Url to the account on the given platform
-    url: String,
+    /// Id and name of the channel's current category, `None` if no category is set.
+    pub fn category(&self) -> Option<(CategoryId, &str)> {
+        if self.category_id.is_empty() {
+            None
+        } else {
+            Some((CategoryId::new(&self.category_id), &self.category_name))
+        }
+    }
 }
 
 /// Types of emotes to fetch
-#[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, Clone, Copy, PartialEq, Eq, Debug, Default)]
 #[repr(i16)]
 pub enum EmoteFetchType {
     /// Get platform-level emoticons and custom emoticons corresponding to channel IDs
+    #[default]
     All = 0,
 
     /// Get the custom emoji corresponding to the channel IDs
@@ -143,14 +1320,8 @@ pub enum EmoteFetchType {
     Platform = 2,
 }
 
-impl Default for EmoteFetchType {
-    fn default() -> Self {
-        Self::All
-    }
-}
-
 /// Payload for the get emotes api call
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GetEmotesPayload {
     /// Get the kind of emotes you want
     pub emote_type: EmoteFetchType,
@@ -159,9 +1330,69 @@ pub struct GetEmotesPayload {
     pub channel_id: Vec<String>,
 }
 
+impl GetEmotesPayload {
+    /// Maximum number of channels the api accepts in one request.
+    pub const MAX_CHANNELS: usize = 100;
+
+    /// Starts building a validated payload.
+    pub fn builder() -> GetEmotesPayloadBuilder {
+        GetEmotesPayloadBuilder::default()
+    }
+
+    /// Checks the payload against the api's limits: at most [`Self::MAX_CHANNELS`] non-blank
+    /// channel ids, and at least one unless only fetching [`EmoteFetchType::Platform`] emotes.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_list(
+            "channel_id",
+            &self.channel_id,
+            Self::MAX_CHANNELS,
+            self.emote_type != EmoteFetchType::Platform,
+        )
+    }
+}
+
+/// Builder for [`GetEmotesPayload`], see [`GetEmotesPayload::builder`]
+#[derive(Debug, Clone, Default)]
+pub struct GetEmotesPayloadBuilder {
+    emote_type: EmoteFetchType,
+    channel_id: Vec<String>,
+}
+
+impl GetEmotesPayloadBuilder {
+    /// Sets the kind of emotes to fetch, defaults to [`EmoteFetchType::All`].
+    pub fn emote_type(mut self, emote_type: EmoteFetchType) -> Self {
+        self.emote_type = emote_type;
+        self
+    }
+
+    /// Adds a channel to fetch emotes for.
+    pub fn channel(mut self, channel_id: impl Into<ChannelId>) -> Self {
+        self.channel_id.push(channel_id.into().0);
+        self
+    }
+
+    /// Adds several channels to fetch emotes for.
+    pub fn channels(mut self, channel_ids: impl IntoIterator<Item = impl Into<ChannelId>>) -> Self {
+        self.channel_id
+            .extend(channel_ids.into_iter().map(|id| id.into().0));
+        self
+    }
+
+    /// Validates and builds the payload.
+    pub fn build(self) -> Result<GetEmotesPayload, ValidationError> {
+        let payload = GetEmotesPayload {
+            emote_type: self.emote_type,
+            channel_id: self.channel_id,
+        };
+        payload.validate()?;
+        Ok(payload)
+    }
+}
+
 /// Common emote payload across types
 #[serde_as]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Emote {
     /// Name of emote.
     pub name: String,
@@ -188,40 +1419,57 @@ pub struct Emote {
     #[serde_as(as = "NoneAsEmptyString")]
     pub webp: Option<String>,
 
-    /// Update time of emote in seconds.
-    pub update_time: Option<String>,
+    /// Update time of emote, `None` if missing or unset.
+    #[serde(default, with = "crate::timestamp::optional_seconds")]
+    #[cfg_attr(feature = "schemars", schemars(with = "crate::schema::EpochSeconds"))]
+    pub update_time: Option<Timestamp>,
+}
+
+impl Emote {
+    /// Update time of emote as epoch seconds, as returned by the api.
+    pub fn update_time_secs(&self) -> Option<i64> {
+        self.update_time.as_ref().map(unix_seconds)
+    }
 }
 
 /// Response for the get emotes api
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct GetEmotesResponse {
     /// Map of different emote types
     pub channels: EmoteChannels,
 }
 
 /// Map of different emote types
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct EmoteChannels {
-    /// Container for customized/channel emotes
+    /// Container for customized/channel emotes, empty if not requested
+    #[serde(default)]
     pub customized_emotes: CustomizedEmotes,
 
-    /// List of event emotes
+    /// List of event emotes, empty if not requested
+    #[serde(default)]
     pub event_emotes: Vec<Emote>,
 
-    /// List of global emotes
+    /// List of global emotes, empty if not requested
+    #[serde(default)]
     pub global_emotes: Vec<Emote>,
 }
 
 /// Container for customized emotes
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CustomizedEmotes {
     /// List of channels and their emotes
+    #[serde(default)]
     pub channel: Vec<ChannelEmotes>,
 }
 
 /// Channel id and its emotes
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ChannelEmotes {
     /// Id of the channel
     pub channel_id: String,
@@ -230,8 +1478,168 @@ pub struct ChannelEmotes {
     pub emotes: Vec<Emote>,
 }
 
+/// Payload for the update drops entitlements api
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateDropsEntitlementsPayload {
+    /// Ids of the entitlements to update
+    pub entitlement_ids: Vec<String>,
+
+    /// Status to set on every entitlement
+    pub fulfillment_status: DropsFulfillmentStatus,
+}
+
+impl UpdateDropsEntitlementsPayload {
+    /// Maximum number of entitlement ids the api accepts in one request.
+    pub const MAX_ENTITLEMENTS: usize = 100;
+
+    /// Checks the payload against the api's limits: between 1 and [`Self::MAX_ENTITLEMENTS`]
+    /// non-blank ids.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_list(
+            "entitlement_ids",
+            &self.entitlement_ids,
+            Self::MAX_ENTITLEMENTS,
+            true,
+        )
+    }
+}
+
+/// Fulfillment status of a drops entitlement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DropsFulfillmentStatus {
+    /// The viewer has claimed the drop, but the item hasn't been granted yet
+    Claimed,
+
+    /// The item has been granted in game
+    Fulfilled,
+}
+
+/// Response for the update drops entitlements api
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct UpdateDropsEntitlementsResponse {
+    /// Outcome for each requested entitlement id.
+    #[serde(default)]
+    #[serde_as(as = "DefaultOnNull")]
+    pub results: Vec<DropsUpdateResult>,
+}
+
+/// Outcome of updating one drops entitlement, see
+/// [`Client::update_drops_entitlements`](crate::Client::update_drops_entitlements)
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DropsUpdateResult {
+    /// Id of the entitlement.
+    pub entitlement_id: String,
+
+    /// Whether the entitlement was updated, or why not.
+    pub status: DropsUpdateStatus,
+
+    /// Message describing why the update failed, `None` if it succeeded or no reason was given.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub err_msg: Option<String>,
+}
+
+impl DropsUpdateResult {
+    /// Whether the entitlement was updated.
+    pub fn is_success(&self) -> bool {
+        self.status == DropsUpdateStatus::Success
+    }
+}
+
+/// Status of a [`DropsUpdateResult`]
+///
+/// Values not known to this crate are kept as [`DropsUpdateStatus::Other`] and serialised back
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+#[non_exhaustive]
+pub enum DropsUpdateStatus {
+    /// The entitlement was updated
+    Success,
+
+    /// The id isn't a valid entitlement id
+    InvalidId,
+
+    /// No entitlement exists with the id
+    NotFound,
+
+    /// The entitlement belongs to another game or organization
+    Unauthorized,
+
+    /// The update failed on Trovo's side, and may succeed if retried
+    UpdateFailed,
+
+    /// A status not known to this crate
+    Other(String),
+}
+
+impl DropsUpdateStatus {
+    /// The raw string used by the api.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Success => "SUCCESS",
+            Self::InvalidId => "INVALID_ID",
+            Self::NotFound => "NOT_FOUND",
+            Self::Unauthorized => "UNAUTHORIZED",
+            Self::UpdateFailed => "UPDATE_FAILED",
+            Self::Other(s) => s,
+        }
+    }
+}
+
+impl From<String> for DropsUpdateStatus {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "SUCCESS" => Self::Success,
+            "INVALID_ID" => Self::InvalidId,
+            "NOT_FOUND" => Self::NotFound,
+            "UNAUTHORIZED" => Self::Unauthorized,
+            "UPDATE_FAILED" => Self::UpdateFailed,
+            _ => Self::Other(s),
+        }
+    }
+}
+
+impl From<DropsUpdateStatus> for String {
+    fn from(status: DropsUpdateStatus) -> Self {
+        match status {
+            DropsUpdateStatus::Other(s) => s,
+            known => known.as_str().to_owned(),
+        }
+    }
+}
+
+/// Payload for the channel command endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelCommandPayload {
+    /// Command to perform, e.g. `/slow 30`
+    pub command: String,
+
+    /// Id of the channel to perform the command in
+    pub channel_id: String,
+}
+
+/// Outcome of a channel command, see
+/// [`Client::perform_channel_command`](crate::Client::perform_channel_command)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CommandResult {
+    /// Whether the command was performed.
+    #[serde(default)]
+    pub is_success: bool,
+
+    /// Message describing the outcome, e.g. why the command was rejected.
+    #[serde(default)]
+    pub display_msg: String,
+}
+
 /// Payload for the channel update endpoint
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct ChannelUpdatePayload {
     /// Id of the channel
     pub channel_id: String,
@@ -242,17 +1650,811 @@ pub struct ChannelUpdatePayload {
 }
 
 /// Fields to update on a channel
-#[derive(Debug, Default, Serialize)]
+///
+/// Fields left as `None` are omitted from the request and keep their current value, unlike empty
+/// strings which Trovo applies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct ChannelUpdate {
     /// Name of user’s channel
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub live_title: Option<String>,
 
     /// Represent which game is the user playing in their channel.
-    pub category_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<CategoryId>,
 
     /// 2 character language ISO 2 code, see standard: https://www.sitepoint.com/iso-2-letter-language-codes/
-    pub language_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_code: Option<LanguageCode>,
 
     /// 3 options representing age range
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub audi_type: Option<AudienceType>,
 }
+
+impl ChannelUpdate {
+    /// Maximum number of characters the api accepts in a title.
+    pub const MAX_TITLE_CHARS: usize = 140;
+
+    /// Checks the update against the api's limits: a title of at most
+    /// [`Self::MAX_TITLE_CHARS`] characters.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(title) = &self.live_title {
+            let len = title.chars().count();
+            if len > Self::MAX_TITLE_CHARS {
+                return Err(ValidationError::TooLong {
+                    field: "live_title",
+                    len,
+                    max: Self::MAX_TITLE_CHARS,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audience_type_round_trips() {
+        for (raw, expected) in [
+            ("CHANNEL_AUDIENCE_TYPE_TEEN", AudienceType::Teen),
+            (
+                "CHANNEL_AUDIENCE_TYPE_SOMETHING_NEW",
+                AudienceType::Other("CHANNEL_AUDIENCE_TYPE_SOMETHING_NEW".to_owned()),
+            ),
+            ("", AudienceType::Other(String::new())),
+        ] {
+            let json = serde_json::to_string(raw).unwrap();
+            let parsed: AudienceType = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, expected);
+            assert_eq!(parsed.as_str(), raw);
+            assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn channel_with_unknown_audience_type() {
+        let json = crate::client::tests::CHANNEL_FIXTURE.replace(
+            "CHANNEL_AUDIENCE_TYPE_FAMILYFRIENDLY",
+            "CHANNEL_AUDIENCE_TYPE_SOMETHING_NEW",
+        );
+        let channel: ChannelInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            channel.audi_type.as_str(),
+            "CHANNEL_AUDIENCE_TYPE_SOMETHING_NEW"
+        );
+    }
+
+    #[test]
+    fn channel_timestamps() {
+        let live: ChannelInfo =
+            serde_json::from_str(crate::client::tests::CHANNEL_FIXTURE).unwrap();
+        assert_eq!(unix_seconds(&live.created_at.unwrap()), 1573829033);
+        assert_eq!(unix_seconds(&live.started_at.unwrap()), 1625486400);
+        assert_eq!(unix_seconds(&live.ended_at.unwrap()), 1625490000);
+
+        let offline: ChannelInfo = serde_json::from_str(
+            &crate::client::tests::CHANNEL_FIXTURE
+                .replace(r#""is_live": true"#, r#""is_live": false"#)
+                .replace(
+                    r#""started_at": "1625486400""#,
+                    r#""started_at": 1625486400"#,
+                ),
+        )
+        .unwrap();
+        assert!(!offline.is_live);
+        assert_eq!(unix_seconds(&offline.started_at.unwrap()), 1625486400);
+        assert_eq!(unix_seconds(&offline.ended_at.unwrap()), 1625490000);
+
+        let never_streamed: ChannelInfo = serde_json::from_str(
+            &crate::client::tests::CHANNEL_FIXTURE
+                .replace(r#""is_live": true"#, r#""is_live": false"#)
+                .replace(r#""started_at": "1625486400""#, r#""started_at": 0"#)
+                .replace(r#""ended_at": "1625490000""#, r#""ended_at": """#),
+        )
+        .unwrap();
+        assert_eq!(never_streamed.started_at, None);
+        assert_eq!(never_streamed.ended_at, None);
+        assert!(never_streamed.created_at.is_some());
+
+        let empty: ChannelInfo =
+            serde_json::from_str(crate::client::tests::EMPTY_CHANNEL_FIXTURE).unwrap();
+        assert_eq!(empty.created_at, None);
+    }
+
+    #[test]
+    fn users_response_total() {
+        let users = r#"[{"user_id": "1", "channel_id": "1", "username": "a", "nickname": "a"}]"#;
+        let response: GetUsersResponse =
+            serde_json::from_str(&format!(r#"{{"total": 1, "users": {}}}"#, users)).unwrap();
+        assert_eq!(response.total, 1);
+        let response: GetUsersResponse =
+            serde_json::from_str(&format!(r#"{{"total": "1", "users": {}}}"#, users)).unwrap();
+        assert_eq!(response.total, 1);
+        let response: GetUsersResponse =
+            serde_json::from_str(&format!(r#"{{"users": {}}}"#, users)).unwrap();
+        assert_eq!(response.total, 0);
+        assert_eq!(response.users.len(), 1);
+    }
+
+    #[test]
+    fn channel_empty_strings_as_none() {
+        let live: ChannelInfo =
+            serde_json::from_str(crate::client::tests::CHANNEL_FIXTURE).unwrap();
+        assert_eq!(
+            live.thumbnail.as_deref(),
+            Some("https://example.com/thumb.jpg")
+        );
+        assert_eq!(live.streamer_info.as_deref(), Some("Just a streamer"));
+        assert_eq!(
+            live.profile_pic.as_deref(),
+            Some("https://example.com/pic.jpg")
+        );
+        let json = serde_json::to_value(&live).unwrap();
+        assert_eq!(json["thumbnail"], "https://example.com/thumb.jpg");
+        assert_eq!(json["profile_pic"], "https://example.com/pic.jpg");
+
+        let empty: ChannelInfo =
+            serde_json::from_str(crate::client::tests::EMPTY_CHANNEL_FIXTURE).unwrap();
+        assert_eq!(empty.thumbnail, None);
+        assert_eq!(empty.streamer_info, None);
+        assert_eq!(empty.profile_pic, None);
+        let json = serde_json::to_value(&empty).unwrap();
+        assert_eq!(json["thumbnail"], "");
+        assert_eq!(json["streamer_info"], "");
+        assert_eq!(json["profile_pic"], "");
+        assert_round_trips::<ChannelInfo>(crate::client::tests::EMPTY_CHANNEL_FIXTURE);
+    }
+
+    fn assert_round_trips<T: Serialize + serde::de::DeserializeOwned>(json: &str) {
+        let first: T = serde_json::from_str(json).unwrap();
+        let serialized = serde_json::to_value(&first).unwrap();
+        let second: T = serde_json::from_value(serialized.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&second).unwrap(), serialized);
+    }
+
+    #[test]
+    fn round_trips() {
+        assert_round_trips::<ChannelInfo>(crate::client::tests::CHANNEL_FIXTURE);
+        assert_round_trips::<ChannelInfo>(crate::client::tests::EMPTY_CHANNEL_FIXTURE);
+        assert_round_trips::<GetEmotesResponse>(
+            r#"{
+                "channels": {
+                    "customizedEmotes": {
+                        "channel": [{
+                            "channel_id": "100000",
+                            "emotes": [{
+                                "name": "wave",
+                                "description": "",
+                                "url": "https://example.com/wave.png",
+                                "status": "1",
+                                "gifp": "",
+                                "webp": "https://example.com/wave.webp",
+                                "update_time": "1625486400"
+                            }]
+                        }]
+                    },
+                    "eventEmotes": [],
+                    "globalEmotes": [{
+                        "name": "smile",
+                        "description": "Smile",
+                        "url": "https://example.com/smile.png",
+                        "status": "1",
+                        "activity_name": "launch"
+                    }]
+                }
+            }"#,
+        );
+
+        let channel: ChannelInfo =
+            serde_json::from_str(crate::client::tests::CHANNEL_FIXTURE).unwrap();
+        assert_eq!(channel.social_links[0].type_, "twitter");
+    }
+
+    #[test]
+    fn emote_update_time() {
+        let emote = |update_time: &str| -> Emote {
+            serde_json::from_str(&format!(
+                r#"{{
+                    "name": "wave",
+                    "description": "",
+                    "url": "https://example.com/wave.png",
+                    "status": "1"
+                    {}
+                }}"#,
+                update_time
+            ))
+            .unwrap()
+        };
+
+        let updated = emote(r#", "update_time": "1625486400""#);
+        assert_eq!(unix_seconds(&updated.update_time.unwrap()), 1625486400);
+        assert_eq!(updated.update_time_secs(), Some(1625486400));
+        assert_eq!(emote(r#", "update_time": """#).update_time, None);
+        assert_eq!(emote(r#", "update_time": "0""#).update_time, None);
+        assert_eq!(emote("").update_time, None);
+
+        let serialized = serde_json::to_value(&updated).unwrap();
+        assert_eq!(serialized["update_time"], 1625486400);
+        let reparsed: Emote = serde_json::from_value(serialized).unwrap();
+        assert_eq!(reparsed.update_time, updated.update_time);
+    }
+
+    #[test]
+    fn emotes_partial_sections() {
+        const EMOTE: &str = r#"{
+            "name": "wave",
+            "description": "",
+            "url": "https://example.com/wave.png",
+            "status": "1"
+        }"#;
+
+        // EmoteFetchType::All
+        let all: GetEmotesResponse = serde_json::from_str(&format!(
+            r#"{{"channels": {{
+                "customizedEmotes": {{"channel": [{{"channel_id": "100000", "emotes": [{e}]}}]}},
+                "eventEmotes": [{e}],
+                "globalEmotes": [{e}]
+            }}}}"#,
+            e = EMOTE
+        ))
+        .unwrap();
+        assert_eq!(all.channels.customized_emotes.channel[0].emotes.len(), 1);
+        assert_eq!(all.channels.event_emotes.len(), 1);
+        assert_eq!(all.channels.global_emotes.len(), 1);
+
+        // EmoteFetchType::Custom
+        let custom: GetEmotesResponse = serde_json::from_str(&format!(
+            r#"{{"channels": {{
+                "customizedEmotes": {{"channel": [{{"channel_id": "100000", "emotes": [{e}]}}]}}
+            }}}}"#,
+            e = EMOTE
+        ))
+        .unwrap();
+        assert_eq!(custom.channels.customized_emotes.channel.len(), 1);
+        assert!(custom.channels.event_emotes.is_empty());
+        assert!(custom.channels.global_emotes.is_empty());
+
+        // EmoteFetchType::Platform
+        let platform: GetEmotesResponse = serde_json::from_str(&format!(
+            r#"{{"channels": {{"eventEmotes": [{e}], "globalEmotes": [{e}]}}}}"#,
+            e = EMOTE
+        ))
+        .unwrap();
+        assert!(platform.channels.customized_emotes.channel.is_empty());
+        assert_eq!(platform.channels.global_emotes.len(), 1);
+
+        let platform: GetEmotesResponse =
+            serde_json::from_str(r#"{"channels": {"globalEmotes": [], "customizedEmotes": {}}}"#)
+                .unwrap();
+        assert!(platform.channels.event_emotes.is_empty());
+    }
+
+    #[test]
+    fn user_info() {
+        let info: UserInfo = serde_json::from_str(
+            r#"{
+                "userId": "100000",
+                "userName": "bot",
+                "nickName": "Bot",
+                "email": "",
+                "profilePic": "https://example.com/pic.jpg",
+                "info": "",
+                "channelId": "100001"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(info.user_id(), UserId::new("100000"));
+        assert_eq!(info.username, "bot");
+        assert_eq!(info.email, None);
+        assert_eq!(
+            info.profile_pic.as_deref(),
+            Some("https://example.com/pic.jpg")
+        );
+        assert_eq!(info.channel_id(), ChannelId::new("100001"));
+    }
+
+    #[test]
+    fn user_equality() {
+        let user = User {
+            user_id: "1".to_owned(),
+            channel_id: "2".to_owned(),
+            username: "someone".to_owned(),
+            nickname: "Someone".to_owned(),
+        };
+        let renamed = User {
+            nickname: "Someone Else".to_owned(),
+            ..user.clone()
+        };
+        assert_eq!(user.clone(), user);
+        assert_ne!(user, renamed);
+
+        let users: std::collections::HashSet<_> =
+            [user.clone(), user.clone(), renamed].into_iter().collect();
+        assert_eq!(users.len(), 2);
+        assert!(users.contains(&user));
+    }
+
+    #[test]
+    fn channel_counters_as_strings() {
+        let channel: ChannelInfo = serde_json::from_str(
+            &crate::client::tests::CHANNEL_FIXTURE
+                .replace(r#""current_viewers": 12"#, r#""current_viewers": "12""#)
+                .replace(r#""followers": 345"#, r#""followers": null"#),
+        )
+        .unwrap();
+        assert_eq!(channel.current_viewers, 12);
+        assert_eq!(channel.followers, 0);
+        assert_eq!(channel.subscriber_num, 6);
+
+        let json = serde_json::to_value(&channel).unwrap();
+        assert_eq!(json["current_viewers"], 12);
+        assert_eq!(json["followers"], 0);
+
+        let invalid = crate::client::tests::CHANNEL_FIXTURE
+            .replace(r#""followers": 345"#, r#""followers": "lots""#);
+        assert!(serde_json::from_str::<ChannelInfo>(&invalid).is_err());
+    }
+
+    #[test]
+    fn social_links() {
+        let link = |type_: &str, url: &str| SocialLink {
+            type_: type_.to_owned(),
+            url: url.to_owned(),
+        };
+
+        let twitter = link("Twitter", "https://twitter.com/someone");
+        assert_eq!(twitter.platform(), SocialPlatform::Twitter);
+        assert_eq!(twitter.link().unwrap().host_str(), Some("twitter.com"));
+        assert_eq!(link("youtube", "").platform(), SocialPlatform::YouTube);
+        assert_eq!(link("DISCORD", "").platform(), SocialPlatform::Discord);
+
+        let unknown = link("MySpace", "https://myspace.com/someone");
+        assert_eq!(
+            unknown.platform(),
+            SocialPlatform::Other("MySpace".to_owned())
+        );
+        assert_eq!(unknown.platform().as_str(), "MySpace");
+
+        assert!(link("twitter", "not a url").link().is_err());
+    }
+
+    #[test]
+    fn channel_language() {
+        let channel: ChannelInfo =
+            serde_json::from_str(crate::client::tests::CHANNEL_FIXTURE).unwrap();
+        assert_eq!(channel.language(), Ok(LanguageCode::EN));
+
+        let empty: ChannelInfo =
+            serde_json::from_str(crate::client::tests::EMPTY_CHANNEL_FIXTURE).unwrap();
+        assert!(empty.language().is_err());
+    }
+
+    #[test]
+    fn users_payload_validation() {
+        let payload = GetUsersPayload::builder()
+            .user("someone")
+            .users(["a", "b"])
+            .user("@SomeOne")
+            .user(Username::exact("@SomeOne"))
+            .build()
+            .unwrap();
+        assert_eq!(payload.user, ["someone", "a", "b", "someone", "@SomeOne"]);
+        assert_eq!(
+            GetUsersPayload::from(vec![" https://trovo.live/s/A ".to_string()]).user,
+            ["a"]
+        );
+
+        assert_eq!(
+            GetUsersPayload::builder().build(),
+            Err(ValidationError::Empty { field: "user" })
+        );
+        assert_eq!(
+            GetUsersPayload::builder().user(" ").build(),
+            Err(ValidationError::BlankString { field: "user" })
+        );
+        let names = (0..=GetUsersPayload::MAX_USERS).map(|i| i.to_string());
+        assert_eq!(
+            GetUsersPayload::builder().users(names).build(),
+            Err(ValidationError::TooMany {
+                field: "user",
+                len: 101,
+                max: 100
+            })
+        );
+    }
+
+    #[test]
+    fn emotes_payload_validation() {
+        let payload = GetEmotesPayload::builder()
+            .channel("123")
+            .emote_type(EmoteFetchType::Custom)
+            .build()
+            .unwrap();
+        assert_eq!(payload.channel_id, ["123"]);
+        assert_eq!(payload.emote_type, EmoteFetchType::Custom);
+
+        assert!(GetEmotesPayload::builder()
+            .emote_type(EmoteFetchType::Platform)
+            .build()
+            .is_ok());
+        assert_eq!(
+            GetEmotesPayload::builder().build(),
+            Err(ValidationError::Empty {
+                field: "channel_id"
+            })
+        );
+        assert_eq!(
+            GetEmotesPayload::builder().channel("").build(),
+            Err(ValidationError::BlankString {
+                field: "channel_id"
+            })
+        );
+        let ids = (0..=GetEmotesPayload::MAX_CHANNELS).map(|i| i.to_string());
+        assert!(matches!(
+            GetEmotesPayload::builder().channels(ids).build(),
+            Err(ValidationError::TooMany { len: 101, .. })
+        ));
+    }
+
+    #[test]
+    fn category() {
+        let category: Category = serde_json::from_str(
+            r#"{
+                "id": "10013",
+                "name": "Minecraft",
+                "short_name": "MC",
+                "icon_url": "https://example.com/minecraft.png",
+                "desc": "Blocks"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(category.id, CategoryId::new("10013"));
+        assert_eq!(
+            serde_json::from_value::<Category>(serde_json::to_value(&category).unwrap()).unwrap(),
+            category
+        );
+
+        let minimal: Category =
+            serde_json::from_str(r#"{"id": "10013", "name": "Minecraft"}"#).unwrap();
+        assert_eq!(minimal.short_name, "");
+
+        let response: GetCategoriesResponse = serde_json::from_str(
+            r#"{"category_info": [{"id": "10013", "name": "Minecraft"}, {"id": "10014", "name": "Fortnite"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(response.category_info.len(), 2);
+        assert_eq!(response.category_info[1].id, CategoryId::new("10014"));
+        for empty in [
+            r#"{"category_info": []}"#,
+            r#"{"category_info": null}"#,
+            "{}",
+        ] {
+            let response: GetCategoriesResponse = serde_json::from_str(empty).unwrap();
+            assert!(response.category_info.is_empty(), "{}", empty);
+        }
+
+        let channel: ChannelInfo =
+            serde_json::from_str(crate::client::tests::CHANNEL_FIXTURE).unwrap();
+        assert_eq!(
+            channel.category(),
+            Some((CategoryId::new("10013"), "Minecraft"))
+        );
+        let empty: ChannelInfo =
+            serde_json::from_str(crate::client::tests::EMPTY_CHANNEL_FIXTURE).unwrap();
+        assert_eq!(empty.category(), None);
+
+        let update = ChannelUpdate {
+            category_id: Some("10013".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&update).unwrap()["category_id"],
+            "10013"
+        );
+    }
+
+    #[test]
+    fn clips_response() {
+        let response: GetClipsResponse = serde_json::from_str(
+            r#"{"clips_info": [
+                {
+                    "clip_id": "ltv_100000_100000_abc",
+                    "title": "Nice shot",
+                    "url": "https://trovo.live/clip/ltv_100000_100000_abc",
+                    "thumbnail": "https://example.com/clip.jpg",
+                    "duration": "30",
+                    "views": 120,
+                    "maker_nickname": "Fan",
+                    "created_at": "1625486400"
+                },
+                {
+                    "clip_id": "ltv_100000_100000_def",
+                    "title": "Processing",
+                    "url": "https://trovo.live/clip/ltv_100000_100000_def",
+                    "thumbnail": "",
+                    "duration": 0,
+                    "views": null,
+                    "made_at": 0
+                },
+                {"clip_id": "ltv_100000_100000_ghi"}
+            ]}"#,
+        )
+        .unwrap();
+        let [clip, processing, bare] = &response.clips_info[..] else {
+            panic!("expected three clips");
+        };
+        assert_eq!(clip.duration, 30);
+        assert_eq!(clip.views, 120);
+        assert_eq!(clip.created_at.as_ref().map(unix_seconds), Some(1625486400));
+        assert_eq!(processing.thumbnail, None);
+        assert_eq!(processing.duration, 0);
+        assert_eq!(processing.views, 0);
+        assert_eq!(processing.created_at, None);
+        assert_eq!(bare.thumbnail, None);
+        assert_eq!(bare.maker_nickname, "");
+
+        let empty: GetClipsResponse = serde_json::from_str("{}").unwrap();
+        assert!(empty.clips_info.is_empty());
+    }
+
+    #[test]
+    fn past_streams_response() {
+        let response: GetPastStreamsResponse = serde_json::from_str(
+            r#"{"total_videos": 2, "video_info": [
+                {
+                    "vid": "ltv_100000_100000_xyz",
+                    "title": "Ranked grind",
+                    "url": "https://trovo.live/video/ltv_100000_100000_xyz",
+                    "thumbnail": "https://example.com/vod.jpg",
+                    "duration": "7265",
+                    "views": "1024",
+                    "likes": 12,
+                    "category_id": "10233",
+                    "category_name": "Valorant",
+                    "start_at": "1625486400",
+                    "end_at": 1625493665
+                },
+                {
+                    "vid": "ltv_100000_100000_uvw",
+                    "thumbnail": "",
+                    "duration": null,
+                    "category_id": "",
+                    "start_at": 0
+                }
+            ]}"#,
+        )
+        .unwrap();
+        let [vod, bare] = &response.video_info[..] else {
+            panic!("expected two past streams");
+        };
+        assert_eq!(vod.duration, 7265);
+        assert_eq!(vod.views, 1024);
+        assert_eq!(vod.likes, 12);
+        assert_eq!(vod.category(), Some((CategoryId::new("10233"), "Valorant")));
+        assert_eq!(vod.start_at.as_ref().map(unix_seconds), Some(1625486400));
+        assert_eq!(vod.end_at.as_ref().map(unix_seconds), Some(1625493665));
+        assert_eq!(bare.thumbnail, None);
+        assert_eq!(bare.duration, 0);
+        assert_eq!(bare.category(), None);
+        assert_eq!(bare.start_at, None);
+        assert_eq!(bare.end_at, None);
+    }
+
+    #[test]
+    fn stream_urls_response() {
+        let live: GetStreamUrlsResponse = serde_json::from_str(
+            r#"{"stream_urls": [
+                {"play_url": "https://example.com/live/original.m3u8", "desc": "Original", "bitrate": "6000"},
+                {"play_url": "https://example.com/live/720p.m3u8", "desc": "720P", "bitrate": 2500},
+                {"play_url": "https://example.com/live/480p.m3u8", "desc": "480P"}
+            ]}"#,
+        )
+        .unwrap();
+        let qualities: Vec<_> = live
+            .stream_urls
+            .iter()
+            .map(|url| (url.desc.as_str(), url.bitrate))
+            .collect();
+        assert_eq!(
+            qualities,
+            [
+                ("Original", Some(6000)),
+                ("720P", Some(2500)),
+                ("480P", None)
+            ]
+        );
+
+        let offline: GetStreamUrlsResponse =
+            serde_json::from_str(r#"{"stream_urls": []}"#).unwrap();
+        assert!(offline.stream_urls.is_empty());
+        let offline: GetStreamUrlsResponse =
+            serde_json::from_str(r#"{"stream_urls": null}"#).unwrap();
+        assert!(offline.stream_urls.is_empty());
+    }
+
+    #[test]
+    fn drops_update_response() {
+        let response: UpdateDropsEntitlementsResponse = serde_json::from_str(
+            r#"{"results": [
+                {"entitlement_id": "e1", "status": "SUCCESS", "err_msg": ""},
+                {"entitlement_id": "e2", "status": "INVALID_ID", "err_msg": "invalid entitlement id"},
+                {"entitlement_id": "e3", "status": "NOT_FOUND"},
+                {"entitlement_id": "e4", "status": "SOMETHING_NEW"}
+            ]}"#,
+        )
+        .unwrap();
+        let statuses: Vec<_> = response
+            .results
+            .iter()
+            .map(|result| (result.entitlement_id.as_str(), result.status.clone()))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("e1", DropsUpdateStatus::Success),
+                ("e2", DropsUpdateStatus::InvalidId),
+                ("e3", DropsUpdateStatus::NotFound),
+                ("e4", DropsUpdateStatus::Other("SOMETHING_NEW".to_string())),
+            ]
+        );
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["results"][1]["status"], "INVALID_ID");
+        assert_eq!(json["results"][3]["status"], "SOMETHING_NEW");
+        assert!(response.results[0].is_success());
+        assert_eq!(response.results[0].err_msg, None);
+        assert!(!response.results[1].is_success());
+        assert_eq!(
+            response.results[1].err_msg.as_deref(),
+            Some("invalid entitlement id")
+        );
+    }
+
+    #[test]
+    fn viewers_response() {
+        let minimal: ViewersResponse = serde_json::from_str(r#"{ "total": 0 }"#).unwrap();
+        assert_eq!(minimal.total, 0);
+        assert!(minimal.chatters.all.viewers.is_empty());
+        assert!(minimal.chatters.vips.viewers.is_empty());
+        assert!(minimal.custom_roles.is_empty());
+        assert_eq!(minimal.next_cursor(), None);
+
+        let viewers: ViewersResponse = serde_json::from_str(
+            r#"{
+                "live_title": "Giveaway!",
+                "nickname": "Streamer",
+                "total": "3",
+                "chatters": {
+                    "VIPS": {"viewers": ["vip"]},
+                    "moderators": {"viewers": null},
+                    "all": {"viewers": ["vip", "mod", "lurker"]}
+                },
+                "custom_roles": {"Regulars": {"viewers": ["lurker"]}},
+                "total_page": 2,
+                "cursor": 0
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(viewers.total, 3);
+        assert_eq!(viewers.chatters.vips.viewers, ["vip"]);
+        assert!(viewers.chatters.moderators.viewers.is_empty());
+        assert!(viewers.chatters.followers.viewers.is_empty());
+        assert_eq!(viewers.chatters.all.viewers.len(), 3);
+        assert_eq!(viewers.custom_roles["Regulars"].viewers, ["lurker"]);
+        assert_eq!(viewers.next_cursor(), Some(1));
+
+        // A short last page ends the paging even if the page count says otherwise
+        let empty: ViewersResponse =
+            serde_json::from_str(r#"{"total": 3, "total_page": 5, "cursor": 1}"#).unwrap();
+        assert_eq!(empty.next_cursor(), None);
+    }
+
+    #[test]
+    fn subscribers_page() {
+        let page: SubscribersPage = serde_json::from_str(
+            r#"{
+                "total": 2,
+                "subscriptions": [
+                    {
+                        "user": {
+                            "user_id": "200000",
+                            "username": "fan",
+                            "display_name": "Fan",
+                            "profile_pic": "https://example.com/fan.jpg",
+                            "created_at": 1573829033
+                        },
+                        "sub_created_at": 1625486400,
+                        "sub_lv": "sub_L2",
+                        "sub_tier": "1"
+                    },
+                    {
+                        "user": {
+                            "user_id": "200001",
+                            "username": "newfan",
+                            "nickname": "New fan",
+                            "profile_pic": ""
+                        },
+                        "started_at": "0",
+                        "sub_level": "sub_L1",
+                        "sub_tier": 2
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(page.total, 2);
+        let [fan, new_fan] = &page.subscribers[..] else {
+            panic!("expected two subscribers");
+        };
+        assert_eq!(fan.user.user_id(), UserId::new("200000"));
+        assert_eq!(fan.user.nickname, "Fan");
+        assert_eq!(fan.sub_lv, "sub_L2");
+        assert_eq!(fan.subscription.sub_tier, 1);
+        assert_eq!(
+            fan.subscription.started_at.as_ref().map(unix_seconds),
+            Some(1625486400)
+        );
+        assert_eq!(new_fan.user.nickname, "New fan");
+        assert_eq!(new_fan.user.profile_pic, None);
+        assert_eq!(new_fan.sub_lv, "sub_L1");
+        assert_eq!(new_fan.subscription.sub_tier, 2);
+        assert_eq!(new_fan.subscription.started_at, None);
+
+        let empty: SubscribersPage =
+            serde_json::from_str(r#"{"total": 2, "subscriptions": null}"#).unwrap();
+        assert!(empty.subscribers.is_empty());
+    }
+
+    #[test]
+    fn channel_update_omits_unset_fields() {
+        let payload = |update| {
+            serde_json::to_value(ChannelUpdatePayload {
+                channel_id: "100000".to_string(),
+                update,
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            payload(ChannelUpdate::default()),
+            serde_json::json!({"channel_id": "100000"})
+        );
+        assert_eq!(
+            payload(ChannelUpdate {
+                live_title: Some("Building things".to_string()),
+                ..Default::default()
+            }),
+            serde_json::json!({"channel_id": "100000", "live_title": "Building things"})
+        );
+        assert_eq!(
+            payload(ChannelUpdate {
+                live_title: Some("Building things".to_string()),
+                category_id: Some("10013".into()),
+                language_code: Some(LanguageCode::EN),
+                audi_type: Some(AudienceType::Teen),
+            }),
+            serde_json::json!({
+                "channel_id": "100000",
+                "live_title": "Building things",
+                "category_id": "10013",
+                "language_code": "en",
+                "audi_type": "CHANNEL_AUDIENCE_TYPE_TEEN"
+            })
+        );
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn channel_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(ChannelInfo)).unwrap();
+        for fixture in [
+            crate::client::tests::CHANNEL_FIXTURE,
+            crate::client::tests::EMPTY_CHANNEL_FIXTURE,
+        ] {
+            let json: serde_json::Value = serde_json::from_str(fixture).unwrap();
+            crate::schema::tests::validate(&schema, &json).unwrap();
+        }
+    }
+}
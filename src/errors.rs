@@ -1,13 +1,62 @@
+use crate::{SendError, SharedSendError, StatusError};
 use reqwest::StatusCode;
-use serde::{Deserialize, Serialize};
-use serde_repr::*;
-use std::fmt::{Debug, Display};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt::{self, Debug, Display},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 
-/// Error codes returned by the Trovo api
-#[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug)]
-#[repr(i16)]
-pub enum ErrorStatus {
+/// Response header Trovo uses to identify a request, useful when contacting Trovo support.
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+macro_rules! error_status {
+    ($($(#[doc = $doc:literal])* $variant:ident = $code:literal,)*) => {
+        /// Error codes returned by the Trovo api
+        ///
+        /// Codes that aren't known to this crate are preserved as [`ErrorStatus::Other`].
+        #[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+        pub enum ErrorStatus {
+            $($(#[doc = $doc])* $variant,)*
+
+            /// An error code that isn't known to this crate.
+            Other(i32),
+        }
+
+        impl ErrorStatus {
+            /// Numeric error code as returned by the api
+            pub fn code(&self) -> i32 {
+                match self {
+                    $(Self::$variant => $code,)*
+                    Self::Other(code) => *code,
+                }
+            }
+
+            /// Maps a numeric error code to its status, falling back to [`ErrorStatus::Other`]
+            pub fn from_code(code: i32) -> Self {
+                match code {
+                    $($code => Self::$variant,)*
+                    code => Self::Other(code),
+                }
+            }
+
+            /// Full documentation of the status, the first sentence of which is used for display
+            fn docs(&self) -> Option<&'static str> {
+                match self {
+                    $(Self::$variant => Some(concat!($($doc),*)),)*
+                    Self::Other(_) => None,
+                }
+            }
+
+            /// Every known status
+            #[cfg(test)]
+            const ALL: &'static [Self] = &[$(Self::$variant,)*];
+        }
+    };
+}
+
+error_status! {
     /// Internal service failed to fetch data. Please try again.
     InternalFetch = -1201,
 
@@ -115,6 +164,9 @@ pub enum ErrorStatus {
     /// chat.
     FollowerOnly = 12402,
 
+    /// The user is not subscribed to this channel.
+    NotSubscribed = 12410,
+
     /// The user does not have permission to send hyperlinks in this channel. The channel is in
     /// block hyperlink mode. Please check the hyperlink mode rules.
     UnauthorizedHyperlink = 12905,
@@ -126,17 +178,203 @@ pub enum ErrorStatus {
     Unknown = 20000,
 }
 
+impl ErrorStatus {
+    /// The api rate limit was exceeded: `11706`.
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, Self::RateLimitExceeded)
+    }
+
+    /// Something is wrong with the token, scopes, or grant: `10703`, `10710`, `11703`, `11704`,
+    /// `11710`-`11718`, and `11730`.
+    pub fn is_auth_error(&self) -> bool {
+        matches!(
+            self,
+            Self::AuthorizationFailed
+                | Self::InvalidAuthCode1
+                | Self::InvalidScope
+                | Self::InvalidAccessToken
+                | Self::InvalidAuthCode2
+                | Self::UsedAuthCode
+                | Self::RefreshTokenExpired
+                | Self::InvalidRefreshToken
+                | Self::AccessTokenExpired
+                | Self::InvalidGrantType
+                | Self::InvalidRedirectUri
+                | Self::InvalidClientSecret
+                | Self::AccessTokenLimit
+                | Self::UnauthorizedScope
+        )
+    }
+
+    /// The content was rejected by moderation: `11101`, `11103`, and `12906`.
+    pub fn is_moderation(&self) -> bool {
+        matches!(
+            self,
+            Self::Moderated1 | Self::Moderated2 | Self::ModeratedMessage
+        )
+    }
+
+    /// The user isn't allowed to do this in the channel: `11707`, `12400`-`12402`, and `12905`.
+    pub fn is_permission(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingChatPermission
+                | Self::BannedInChannel
+                | Self::SlowMode
+                | Self::FollowerOnly
+                | Self::UnauthorizedHyperlink
+        )
+    }
+
+    /// A temporary internal error, retrying may succeed: `-1201`, `-1000`, `1111`, and `1203`.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::InternalFetch | Self::InternalTimeout | Self::InternalUnknown | Self::Conflict
+        )
+    }
+}
+
+impl ErrorStatus {
+    /// Short human readable description of the status, e.g. "API rate limit exceeded".
+    ///
+    /// `None` for [`ErrorStatus::Other`].
+    pub fn description(&self) -> Option<&'static str> {
+        self.docs().map(|docs| {
+            let docs = docs.trim();
+            let end = docs.find(". ").unwrap_or(docs.len());
+            docs[..end].trim_end_matches('.')
+        })
+    }
+}
+
+impl Display for ErrorStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.description() {
+            Some(description) => write!(f, "{} ({})", description, self.code()),
+            None => write!(f, "unknown error code {}", self.code()),
+        }
+    }
+}
+
+impl Serialize for ErrorStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i32(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        i32::deserialize(deserializer).map(Self::from_code)
+    }
+}
+
 /// Standard errors that can occur on most api calls
 #[derive(Debug, Error)]
 pub enum RequestError {
     /// The api returned an error response. Can inspect the stats to found out what specifically
     /// went wrong.
-    #[error("bad request ({:?}): {}", .0.status, .0.message)]
+    #[error(
+        "bad request{}: {}: {}{}",
+        EndpointSuffix(" to", &.0.endpoint),
+        .0.status,
+        .0.message,
+        TraceIdSuffix(&.0.trace_id)
+    )]
     ApiError(ApiError),
 
+    /// The api responded successfully but the body couldn't be decoded.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    /// The response wasn't json, e.g. a Cloudflare error or challenge page served during an
+    /// outage, whatever the status.
+    #[error(
+        "unexpected {} response with status {status}: {body_excerpt}",
+        .content_type.as_deref().unwrap_or("non-json")
+    )]
+    UnexpectedContentType {
+        /// Http status code of the response
+        status: StatusCode,
+        /// Content type of the response, if it had one
+        content_type: Option<String>,
+        /// Start of the body, with whitespace collapsed
+        body_excerpt: String,
+    },
+
     /// Some other request error happened, could be status code, or network.
     #[error(transparent)]
     Other(#[from] reqwest::Error),
+
+    /// Same as [`RequestError::Other`], but the failed request was shared between multiple
+    /// coalesced callers. See [`ClientBuilder::coalesce_requests`](crate::ClientBuilder::coalesce_requests).
+    #[error(transparent)]
+    Shared(Arc<reqwest::Error>),
+
+    /// The circuit breaker is open after repeated failures, so the request wasn't sent. See
+    /// [`ClientBuilder::circuit_breaker`](crate::ClientBuilder::circuit_breaker).
+    #[error("circuit breaker open after repeated failures")]
+    CircuitOpen {
+        /// When the next request will be let through to probe whether the api has recovered.
+        retry_at: Instant,
+    },
+
+    /// A middleware of the tower service given to `Client::with_service` failed, e.g. a buffer
+    /// or load shedding layer. Only returned with the `tower` feature.
+    #[error(transparent)]
+    Service(Arc<dyn std::error::Error + Send + Sync>),
+
+    /// The arguments were rejected locally, so the request wasn't sent.
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+}
+
+impl From<SendError> for RequestError {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::Http(err) => Self::Other(err),
+            SendError::CircuitOpen { retry_at } => Self::CircuitOpen { retry_at },
+            #[cfg(feature = "tower")]
+            SendError::Service(err) => Self::Service(err),
+        }
+    }
+}
+
+impl From<StatusError> for RequestError {
+    fn from(err: StatusError) -> Self {
+        match err {
+            StatusError::Http(err) => Self::Other(err),
+            StatusError::UnexpectedContentType {
+                status,
+                content_type,
+                body_excerpt,
+            } => Self::UnexpectedContentType {
+                status,
+                content_type,
+                body_excerpt,
+            },
+        }
+    }
+}
+
+impl From<SharedSendError> for RequestError {
+    fn from(err: SharedSendError) -> Self {
+        match err {
+            SharedSendError::Http(err) => match Arc::try_unwrap(err) {
+                Ok(err) => Self::Other(err),
+                Err(err) => Self::Shared(err),
+            },
+            SharedSendError::CircuitOpen { retry_at } => Self::CircuitOpen { retry_at },
+            #[cfg(feature = "tower")]
+            SharedSendError::Service(err) => Self::Service(err),
+        }
+    }
 }
 
 /// Standard errors that can occur on most api calls
@@ -151,22 +389,109 @@ where
 
     /// The api returned an error response. Can inspect the stats to found out what specifically
     /// went wrong.
-    #[error("bad request ({:?}): {}", .0.status, .0.message)]
+    #[error(
+        "bad request{}: {}: {}{}",
+        EndpointSuffix(" to", &.0.endpoint),
+        .0.status,
+        .0.message,
+        TraceIdSuffix(&.0.trace_id)
+    )]
     ApiError(ApiError),
 
+    /// The api responded successfully but the body couldn't be decoded.
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    /// The response wasn't json, e.g. a Cloudflare error or challenge page served during an
+    /// outage, whatever the status.
+    #[error(
+        "unexpected {} response with status {status}: {body_excerpt}",
+        .content_type.as_deref().unwrap_or("non-json")
+    )]
+    UnexpectedContentType {
+        /// Http status code of the response
+        status: StatusCode,
+        /// Content type of the response, if it had one
+        content_type: Option<String>,
+        /// Start of the body, with whitespace collapsed
+        body_excerpt: String,
+    },
+
     /// Some other request error happened, could be status code, or network.
     #[error(transparent)]
     Other(#[from] reqwest::Error),
+
+    /// The circuit breaker is open after repeated failures, so the request wasn't sent. See
+    /// [`ClientBuilder::circuit_breaker`](crate::ClientBuilder::circuit_breaker).
+    #[error("circuit breaker open after repeated failures")]
+    CircuitOpen {
+        /// When the next request will be let through to probe whether the api has recovered.
+        retry_at: Instant,
+    },
+
+    /// A middleware of the tower service given to `Client::with_service` failed, e.g. a buffer
+    /// or load shedding layer. Only returned with the `tower` feature.
+    #[error(transparent)]
+    Service(Arc<dyn std::error::Error + Send + Sync>),
+
+    /// The arguments were rejected locally, so the request wasn't sent.
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+}
+
+impl<E> From<SendError> for AuthenticatedRequestError<E>
+where
+    E: Display + Debug,
+{
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::Http(err) => Self::Other(err),
+            SendError::CircuitOpen { retry_at } => Self::CircuitOpen { retry_at },
+            #[cfg(feature = "tower")]
+            SendError::Service(err) => Self::Service(err),
+        }
+    }
+}
+
+impl<E> From<StatusError> for AuthenticatedRequestError<E>
+where
+    E: Display + Debug,
+{
+    fn from(err: StatusError) -> Self {
+        match err {
+            StatusError::Http(err) => Self::Other(err),
+            StatusError::UnexpectedContentType {
+                status,
+                content_type,
+                body_excerpt,
+            } => Self::UnexpectedContentType {
+                status,
+                content_type,
+                body_excerpt,
+            },
+        }
+    }
 }
 
 /// Struct representing errors that trovo api responds with.
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ApiError {
     /// Trovo error code indicating what went wrong.
     pub status: ErrorStatus,
 
     /// Trovo provided message describing the error
     pub message: String,
+
+    /// Trovo request id taken from the [`TRACE_ID_HEADER`] response header, if present.
+    #[serde(skip)]
+    pub trace_id: Option<String>,
+
+    /// Api endpoint that returned the error, with a short summary of the request where
+    /// available, e.g. `getusers (3 names)`.
+    #[serde(skip)]
+    pub endpoint: Option<String>,
 }
 
 impl ApiError {
@@ -178,11 +503,356 @@ impl ApiError {
     }
 }
 
+macro_rules! forward_predicates {
+    ($ty:ident$(<$generic:ident>)?, $($(#[$meta:meta])* $name:ident,)*) => {
+        $(
+            $(#[$meta])*
+            pub fn $name(&self) -> bool {
+                match self {
+                    $ty::ApiError(err) => err.$name(),
+                    _ => false,
+                }
+            }
+        )*
+    };
+}
+
+impl ApiError {
+    /// See [`ErrorStatus::is_rate_limited`].
+    pub fn is_rate_limited(&self) -> bool {
+        self.status.is_rate_limited()
+    }
+
+    /// See [`ErrorStatus::is_auth_error`].
+    pub fn is_auth_error(&self) -> bool {
+        self.status.is_auth_error()
+    }
+
+    /// See [`ErrorStatus::is_moderation`].
+    pub fn is_moderation(&self) -> bool {
+        self.status.is_moderation()
+    }
+
+    /// See [`ErrorStatus::is_permission`].
+    pub fn is_permission(&self) -> bool {
+        self.status.is_permission()
+    }
+
+    /// See [`ErrorStatus::is_transient`].
+    pub fn is_transient(&self) -> bool {
+        self.status.is_transient()
+    }
+}
+
+impl RequestError {
+    forward_predicates! {
+        RequestError,
+        /// Api error that is rate limited, see [`ErrorStatus::is_rate_limited`].
+        is_rate_limited,
+        /// Api error caused by auth, see [`ErrorStatus::is_auth_error`].
+        is_auth_error,
+        /// Api error caused by moderation, see [`ErrorStatus::is_moderation`].
+        is_moderation,
+        /// Api error caused by missing permissions, see [`ErrorStatus::is_permission`].
+        is_permission,
+        /// Transient api error, see [`ErrorStatus::is_transient`]. Network errors aren't included.
+        is_transient,
+    }
+
+    /// Whether retrying the whole operation later may succeed.
+    ///
+    /// - `ApiError`: transient or rate limited statuses
+    /// - `Decode`: never, the response will most likely be the same
+    /// - `UnexpectedContentType`: 5xx statuses, as error pages are served during outages
+    /// - `Other`/`Shared`: timeouts, connection failures, and 429 or 5xx statuses
+    /// - `CircuitOpen`: always, once `retry_at` has passed
+    /// - `Service`: never, as the middleware's error can't be inspected
+    /// - `Validation`: never, as the arguments are the problem
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::ApiError(err) => err.is_transient() || err.is_rate_limited(),
+            Self::Decode(_) => false,
+            Self::UnexpectedContentType { status, .. } => status.is_server_error(),
+            Self::Other(err) => is_retryable_http(err),
+            Self::Shared(err) => is_retryable_http(err),
+            Self::CircuitOpen { .. } => true,
+            Self::Service(_) => false,
+            Self::Validation(_) => false,
+        }
+    }
+
+    /// Whether the request timed out, only ever true for `Other`/`Shared`.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Self::Other(err) => err.is_timeout(),
+            Self::Shared(err) => err.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// Whether connecting to the api failed, only ever true for `Other`/`Shared`.
+    pub fn is_connection(&self) -> bool {
+        match self {
+            Self::Other(err) => err.is_connect(),
+            Self::Shared(err) => err.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+impl<E> AuthenticatedRequestError<E>
+where
+    E: Display + Debug,
+{
+    forward_predicates! {
+        AuthenticatedRequestError<E>,
+        /// Api error that is rate limited, see [`ErrorStatus::is_rate_limited`].
+        is_rate_limited,
+        /// Api error caused by auth, see [`ErrorStatus::is_auth_error`]. Failing to refresh the
+        /// token isn't included.
+        is_auth_error,
+        /// Api error caused by moderation, see [`ErrorStatus::is_moderation`].
+        is_moderation,
+        /// Api error caused by missing permissions, see [`ErrorStatus::is_permission`].
+        is_permission,
+        /// Transient api error, see [`ErrorStatus::is_transient`]. Network errors aren't included.
+        is_transient,
+    }
+
+    /// Whether retrying the whole operation later may succeed.
+    ///
+    /// Same as [`RequestError::is_retryable`], with `RefreshToken` never being retryable as the
+    /// auth provider's error can't be inspected, and `Validation` never being retryable as the
+    /// arguments are the problem.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RefreshToken(_) => false,
+            Self::ApiError(err) => err.is_transient() || err.is_rate_limited(),
+            Self::Decode(_) => false,
+            Self::UnexpectedContentType { status, .. } => status.is_server_error(),
+            Self::Other(err) => is_retryable_http(err),
+            Self::CircuitOpen { .. } => true,
+            Self::Service(_) => false,
+            Self::Validation(_) => false,
+        }
+    }
+
+    /// Whether the request timed out, only ever true for `Other`.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Other(err) if err.is_timeout())
+    }
+
+    /// Whether connecting to the api failed, only ever true for `Other`.
+    pub fn is_connection(&self) -> bool {
+        matches!(self, Self::Other(err) if err.is_connect())
+    }
+}
+
+/// Timeouts, connection failures, and 429 or 5xx statuses may succeed when retried
+fn is_retryable_http(err: &reqwest::Error) -> bool {
+    err.is_timeout()
+        || err.is_connect()
+        || err.status().is_some_and(|status| {
+            status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+        })
+}
+
 impl Default for ApiError {
     fn default() -> Self {
         Self {
             status: ErrorStatus::Unknown,
             message: "Unknown or uncategorized error".to_string(),
+            trace_id: None,
+            endpoint: None,
+        }
+    }
+}
+
+/// The body of an otherwise successful response couldn't be decoded.
+#[derive(Debug, Error)]
+#[error(
+    "failed to decode response{}{}: {source}",
+    EndpointSuffix(" from", .endpoint),
+    TraceIdSuffix(.trace_id)
+)]
+pub struct DecodeError {
+    /// The underlying decoding error
+    pub source: serde_json::Error,
+
+    /// Trovo request id taken from the [`TRACE_ID_HEADER`] response header, if present.
+    pub trace_id: Option<String>,
+
+    /// Api endpoint that returned the response, see [`ApiError::endpoint`].
+    pub endpoint: Option<String>,
+}
+
+/// Formats an optional trace id for appending to error messages
+struct TraceIdSuffix<'a>(&'a Option<String>);
+
+impl Display for TraceIdSuffix<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(trace_id) => write!(f, " (trace id: {})", trace_id),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Formats an optional endpoint for inclusion in error messages
+struct EndpointSuffix<'a>(&'static str, &'a Option<String>);
+
+impl Display for EndpointSuffix<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(endpoint) => write!(f, "{} {}", self.0, endpoint),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Errors that can occur obtaining a token from the oauth token endpoint, e.g. via
+/// [`Client::exchange_client_credentials`](crate::Client::exchange_client_credentials), or
+/// revoking one with [`Client::revoke_token`](crate::Client::revoke_token).
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum OAuthError {
+    /// The request to the token endpoint failed, including the api rejecting the credentials.
+    #[error(transparent)]
+    Request(#[from] RequestError),
+
+    /// The token given to [`Client::revoke_token`](crate::Client::revoke_token) is unknown,
+    /// expired, or was already revoked.
+    #[error(
+        "token is invalid or already revoked: {}: {}{}",
+        .0.status,
+        .0.message,
+        TraceIdSuffix(&.0.trace_id)
+    )]
+    InvalidToken(ApiError),
+}
+
+impl OAuthError {
+    /// Whether the token endpoint rejected the client credentials.
+    pub fn is_invalid_credentials(&self) -> bool {
+        matches!(
+            self,
+            Self::Request(RequestError::ApiError(ApiError {
+                status: ErrorStatus::AuthorizationFailed
+                    | ErrorStatus::InvalidParameters
+                    | ErrorStatus::InvalidClientSecret,
+                ..
+            }))
+        )
+    }
+
+    /// Whether the token endpoint rejected the client secret as
+    /// [`ErrorStatus::InvalidClientSecret`].
+    pub fn is_invalid_client_secret(&self) -> bool {
+        matches!(
+            self,
+            Self::Request(RequestError::ApiError(ApiError {
+                status: ErrorStatus::InvalidClientSecret,
+                ..
+            }))
+        )
+    }
+}
+
+/// Errors that can occur during [`interactive_setup`](crate::auth::interactive_setup), or when a
+/// [`FileTokenProvider`](crate::auth::FileTokenProvider) renews its token.
+#[cfg(feature = "auth-flow")]
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SetupError {
+    /// The token file couldn't be read or written.
+    #[error("failed to access token file: {0}")]
+    TokenFile(#[source] std::io::Error),
+
+    /// The token file doesn't contain a valid token.
+    #[error("invalid token file: {0}")]
+    InvalidTokenFile(#[source] serde_json::Error),
+
+    /// The redirect uri isn't a valid url with a port to listen on.
+    #[error("invalid redirect uri {0:?}")]
+    InvalidRedirectUri(String),
+
+    /// The local server capturing the redirect failed, e.g. the port is already in use.
+    #[error("redirect capture server failed: {0}")]
+    CaptureServer(#[source] std::io::Error),
+
+    /// The redirect's state didn't match the one sent, so it doesn't answer our request.
+    #[error("redirect state doesn't match the authorize request")]
+    StateMismatch,
+
+    /// The redirect had no authorization code.
+    #[error("redirect is missing the authorization code")]
+    MissingCode,
+
+    /// The user declined, or Trovo rejected the authorize request.
+    #[error("authorization failed: {error}{}", .description.as_deref().map(|d| format!(": {}", d)).unwrap_or_default())]
+    Denied {
+        /// Error code from the redirect
+        error: String,
+        /// Description of the error, if Trovo gave one
+        description: Option<String>,
+    },
+
+    /// The user didn't authorize within [`SetupConfig::timeout`](crate::auth::SetupConfig::timeout).
+    #[error("timed out waiting for authorization")]
+    Timeout,
+
+    /// Exchanging the code or refreshing the token failed.
+    #[error(transparent)]
+    OAuth(#[from] OAuthError),
+
+    /// The token expired and has no refresh token to renew it with.
+    #[error("access token expired and no refresh token is stored")]
+    MissingRefreshToken,
+}
+
+/// Errors that can occur when an [`Oauth2Provider`](crate::Oauth2Provider) renews its token.
+#[cfg(feature = "oauth2")]
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Oauth2ProviderError {
+    /// There is no token, or it has no refresh token to renew it with.
+    #[error("access token expired and no refresh token is stored")]
+    MissingRefreshToken,
+
+    /// The request to the token endpoint failed, including the api rejecting the refresh.
+    #[error(transparent)]
+    Request(RequestError),
+
+    /// The token endpoint returned a standard oauth2 error response.
+    #[error("token refresh failed: {error}{}", .description.as_deref().map(|d| format!(": {}", d)).unwrap_or_default())]
+    Rejected {
+        /// Error code from the response
+        error: String,
+        /// Description of the error, if given
+        description: Option<String>,
+    },
+
+    /// The token endpoint's response couldn't be parsed as a token.
+    #[error("invalid token response: {0}")]
+    InvalidResponse(String),
+}
+
+#[cfg(feature = "oauth2")]
+impl From<oauth2::RequestTokenError<RequestError, oauth2::basic::BasicErrorResponse>>
+    for Oauth2ProviderError
+{
+    fn from(
+        err: oauth2::RequestTokenError<RequestError, oauth2::basic::BasicErrorResponse>,
+    ) -> Self {
+        use oauth2::RequestTokenError;
+        match err {
+            RequestTokenError::ServerResponse(response) => Self::Rejected {
+                error: response.error().to_string(),
+                description: response.error_description().cloned(),
+            },
+            RequestTokenError::Request(err) => Self::Request(err),
+            RequestTokenError::Parse(err, _) => Self::InvalidResponse(err.to_string()),
+            RequestTokenError::Other(message) => Self::InvalidResponse(message),
         }
     }
 }
@@ -192,3 +862,607 @@ impl Default for ApiError {
 #[derive(Error, Debug)]
 #[error("access token expired and doesn't support refreshing")]
 pub struct AccessTokenExpired;
+
+/// Error returned when parsing a [`LanguageCode`](crate::LanguageCode) from a string that isn't a
+/// two letter ISO 639-1 code.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid language code {0:?}, expected a two letter ISO 639-1 code such as \"en\"")]
+pub struct InvalidLanguageCode(pub String);
+
+/// Error returned when building a request payload that the api would reject.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// A list that must have at least one entry was empty
+    #[error("{field} must not be empty")]
+    Empty {
+        /// Name of the payload field
+        field: &'static str,
+    },
+
+    /// A list had more entries than the api accepts in one request
+    #[error("{field} has {len} entries, at most {max} are allowed")]
+    TooMany {
+        /// Name of the payload field
+        field: &'static str,
+        /// Number of entries given
+        len: usize,
+        /// Maximum number of entries allowed
+        max: usize,
+    },
+
+    /// A string that must not be blank was blank
+    #[error("{field} must not contain blank strings")]
+    BlankString {
+        /// Name of the payload field
+        field: &'static str,
+    },
+
+    /// A string was longer than the api accepts
+    #[error("{field} has {len} characters, at most {max} are allowed")]
+    TooLong {
+        /// Name of the payload field
+        field: &'static str,
+        /// Number of characters given
+        len: usize,
+        /// Maximum number of characters allowed
+        max: usize,
+    },
+
+    /// A number was above the largest the api accepts
+    #[error("{field} is {value}, at most {max} is allowed")]
+    TooLarge {
+        /// Name of the payload field
+        field: &'static str,
+        /// The number given
+        value: u64,
+        /// Largest number allowed
+        max: u64,
+    },
+
+    /// A username was blank or contained whitespace
+    #[error("{field} {value:?} is not a valid username")]
+    InvalidUsername {
+        /// Name of the argument
+        field: &'static str,
+        /// The username given
+        value: String,
+    },
+
+    /// A duration was zero or above the longest the api accepts
+    #[error("{field} must be between 1 second and {max:?}, got {value:?}")]
+    DurationOutOfRange {
+        /// Name of the argument
+        field: &'static str,
+        /// The duration given
+        value: Duration,
+        /// Longest duration allowed
+        max: Duration,
+    },
+
+    /// A shard index wasn't below the total number of shards
+    #[error("current_shard {current_shard} must be less than total_shard {total_shard}")]
+    InvalidShard {
+        /// Index of the shard requested
+        current_shard: u32,
+        /// Total number of shards
+        total_shard: u32,
+    },
+}
+
+/// Error fetching emotes for an [`EmoteCache`](crate::EmoteCache), shared by every caller waiting
+/// on the fetch.
+#[derive(Debug, Clone, Error)]
+#[error(transparent)]
+pub struct EmoteCacheError(pub(crate) Arc<RequestError>);
+
+impl EmoteCacheError {
+    /// The error the fetch failed with
+    pub fn request_error(&self) -> &RequestError {
+        &self.0
+    }
+}
+
+/// Boxed error used for auth provider errors in [`Error`]
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Error returned by the typed channel command helpers, e.g.
+/// [`Client::host_channel`](crate::Client::host_channel)
+#[derive(Debug, Error)]
+pub enum ChannelCommandError<E>
+where
+    E: Display + Debug,
+{
+    /// The request failed, see [`AuthenticatedRequestError`]
+    #[error(transparent)]
+    Request(#[from] AuthenticatedRequestError<E>),
+
+    /// The arguments were rejected locally, so the command wasn't sent.
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+
+    /// The user the command was for doesn't exist, e.g. a typo'd username given to
+    /// [`Client::add_moderator`](crate::Client::add_moderator).
+    #[error("user {username:?} does not exist")]
+    UnknownUser {
+        /// The username given
+        username: String,
+    },
+
+    /// The api ran the command but it didn't succeed, e.g. hosting a channel that doesn't exist.
+    #[error("channel command failed: {display_msg}")]
+    Rejected {
+        /// Trovo provided message describing why the command failed
+        display_msg: String,
+    },
+}
+
+/// Any error this crate can return, for when you don't need to handle them precisely.
+///
+/// All of the specific error types convert into this via `?`.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// See [`RequestError`]
+    #[error(transparent)]
+    Request(#[from] RequestError),
+
+    /// See [`AuthenticatedRequestError`]
+    #[error(transparent)]
+    AuthenticatedRequest(AuthenticatedRequestError<BoxError>),
+
+    /// See [`ChatConnectError`](crate::chat::ChatConnectError)
+    #[cfg(feature = "chat")]
+    #[error(transparent)]
+    ChatConnect(#[from] crate::chat::ChatConnectError),
+
+    /// See [`ChatMessageStreamError`](crate::chat::ChatMessageStreamError)
+    #[cfg(feature = "chat")]
+    #[error(transparent)]
+    ChatMessageStream(#[from] crate::chat::ChatMessageStreamError),
+
+    /// See [`ValidationError`]
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+
+    /// See [`EmoteCacheError`]
+    #[error(transparent)]
+    EmoteCache(#[from] EmoteCacheError),
+
+    /// See [`ChannelCommandError::UnknownUser`]
+    #[error("user {username:?} does not exist")]
+    UnknownUser {
+        /// The username given
+        username: String,
+    },
+
+    /// See [`ChannelCommandError::Rejected`]
+    #[error("channel command failed: {display_msg}")]
+    ChannelCommandRejected {
+        /// Trovo provided message describing why the command failed
+        display_msg: String,
+    },
+}
+
+impl<E> From<AuthenticatedRequestError<E>> for Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn from(err: AuthenticatedRequestError<E>) -> Self {
+        Self::AuthenticatedRequest(match err {
+            AuthenticatedRequestError::RefreshToken(err) => {
+                AuthenticatedRequestError::RefreshToken(Box::new(err))
+            }
+            AuthenticatedRequestError::ApiError(err) => AuthenticatedRequestError::ApiError(err),
+            AuthenticatedRequestError::Decode(err) => AuthenticatedRequestError::Decode(err),
+            AuthenticatedRequestError::UnexpectedContentType {
+                status,
+                content_type,
+                body_excerpt,
+            } => AuthenticatedRequestError::UnexpectedContentType {
+                status,
+                content_type,
+                body_excerpt,
+            },
+            AuthenticatedRequestError::Other(err) => AuthenticatedRequestError::Other(err),
+            AuthenticatedRequestError::CircuitOpen { retry_at } => {
+                AuthenticatedRequestError::CircuitOpen { retry_at }
+            }
+            AuthenticatedRequestError::Service(err) => AuthenticatedRequestError::Service(err),
+            AuthenticatedRequestError::Validation(err) => {
+                AuthenticatedRequestError::Validation(err)
+            }
+        })
+    }
+}
+
+impl<E> From<ChannelCommandError<E>> for Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn from(err: ChannelCommandError<E>) -> Self {
+        match err {
+            ChannelCommandError::Request(err) => err.into(),
+            ChannelCommandError::Validation(err) => Self::Validation(err),
+            ChannelCommandError::UnknownUser { username } => Self::UnknownUser { username },
+            ChannelCommandError::Rejected { display_msg } => {
+                Self::ChannelCommandRejected { display_msg }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "chat")]
+impl From<crate::chat::ChatMessagesForChannelError> for Error {
+    fn from(err: crate::chat::ChatMessagesForChannelError) -> Self {
+        use crate::chat::ChatMessagesForChannelError;
+        match err {
+            ChatMessagesForChannelError::Request(err) => Self::Request(err),
+            ChatMessagesForChannelError::ChatConnect(err) => Self::ChatConnect(err),
+        }
+    }
+}
+
+#[cfg(feature = "chat")]
+impl<E> From<crate::chat::ChatMessagesForUserError<E>> for Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn from(err: crate::chat::ChatMessagesForUserError<E>) -> Self {
+        use crate::chat::ChatMessagesForUserError;
+        match err {
+            ChatMessagesForUserError::Request(err) => err.into(),
+            ChatMessagesForUserError::ChatConnect(err) => Self::ChatConnect(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessTokenExpired;
+    use super::*;
+    use ErrorStatus::*;
+
+    const RATE_LIMIT: u8 = 1;
+    const AUTH: u8 = 2;
+    const MODERATION: u8 = 4;
+    const PERMISSION: u8 = 8;
+    const TRANSIENT: u8 = 16;
+
+    /// Expected classification of every known status, to catch accidental drift
+    const CLASSIFICATION: &[(ErrorStatus, i32, u8)] = &[
+        (InternalFetch, -1201, TRANSIENT),
+        (InternalTimeout, -1000, TRANSIENT),
+        (InvalidParameters, 1002, 0),
+        (InternalUnknown, 1111, TRANSIENT),
+        (Conflict, 1203, TRANSIENT),
+        (InvalidUser, 10505, 0),
+        (AuthorizationFailed, 10703, AUTH),
+        (InvalidAuthCode1, 10710, AUTH),
+        (MessageSpam, 10908, 0),
+        (InvalidCategory, 11000, 0),
+        (Moderated1, 11101, MODERATION),
+        (Moderated2, 11103, MODERATION),
+        (AccountBlocked, 11400, 0),
+        (InvalidHeader, 11701, 0),
+        (InvalidScope, 11703, AUTH),
+        (InvalidAccessToken, 11704, AUTH),
+        (RateLimitExceeded, 11706, RATE_LIMIT),
+        (MissingChatPermission, 11707, PERMISSION),
+        (InvalidShardValue, 11708, 0),
+        (MissingShardTokenPermission, 11709, 0),
+        (InvalidAuthCode2, 11710, AUTH),
+        (UsedAuthCode, 11711, AUTH),
+        (RefreshTokenExpired, 11712, AUTH),
+        (InvalidRefreshToken, 11713, AUTH),
+        (ErrorStatus::AccessTokenExpired, 11714, AUTH),
+        (InvalidGrantType, 11715, AUTH),
+        (InvalidRedirectUri, 11716, AUTH),
+        (InvalidClientSecret, 11717, AUTH),
+        (AccessTokenLimit, 11718, AUTH),
+        (UnauthorizedScope, 11730, AUTH),
+        (BannedInChannel, 12400, PERMISSION),
+        (SlowMode, 12401, PERMISSION),
+        (FollowerOnly, 12402, PERMISSION),
+        (NotSubscribed, 12410, 0),
+        (UnauthorizedHyperlink, 12905, PERMISSION),
+        (ModeratedMessage, 12906, MODERATION),
+        (Unknown, 20000, 0),
+        (Other(99999), 99999, 0),
+    ];
+
+    #[test]
+    fn classification() {
+        for status in ErrorStatus::ALL {
+            assert!(
+                CLASSIFICATION.iter().any(|(s, _, _)| s == status),
+                "{:?} missing from classification table",
+                status
+            );
+        }
+
+        for &(status, code, flags) in CLASSIFICATION {
+            assert_eq!(status.code(), code);
+            assert_eq!(ErrorStatus::from_code(code), status);
+            assert_eq!(
+                status.is_rate_limited(),
+                flags & RATE_LIMIT != 0,
+                "{:?}",
+                status
+            );
+            assert_eq!(status.is_auth_error(), flags & AUTH != 0, "{:?}", status);
+            assert_eq!(
+                status.is_moderation(),
+                flags & MODERATION != 0,
+                "{:?}",
+                status
+            );
+            assert_eq!(
+                status.is_permission(),
+                flags & PERMISSION != 0,
+                "{:?}",
+                status
+            );
+            assert_eq!(
+                status.is_transient(),
+                flags & TRANSIENT != 0,
+                "{:?}",
+                status
+            );
+
+            let err = RequestError::ApiError(ApiError {
+                status,
+                ..Default::default()
+            });
+            assert_eq!(err.is_rate_limited(), status.is_rate_limited());
+            assert_eq!(err.is_auth_error(), status.is_auth_error());
+            assert_eq!(err.is_moderation(), status.is_moderation());
+            assert_eq!(err.is_permission(), status.is_permission());
+            assert_eq!(err.is_transient(), status.is_transient());
+        }
+    }
+
+    #[test]
+    fn display() {
+        let cases = [
+            (RateLimitExceeded, "API rate limit exceeded (11706)"),
+            (
+                MissingChatPermission,
+                "No permission to send chats to this channel (11707)",
+            ),
+            (InternalTimeout, "Internal server error (-1000)"),
+            (
+                InternalUnknown,
+                "Unknown or uncategorized internal server error (1111)",
+            ),
+            (
+                InvalidAuthCode1,
+                "Authorization Code doesn't exist or has expired (10710)",
+            ),
+            (Other(99999), "unknown error code 99999"),
+        ];
+        for (status, expected) in cases {
+            assert_eq!(status.to_string(), expected);
+        }
+
+        let err = RequestError::ApiError(ApiError {
+            status: SlowMode,
+            message: "slow down".to_string(),
+            trace_id: None,
+            endpoint: None,
+        });
+        assert_eq!(
+            err.to_string(),
+            "bad request: Channel is currently in slow mode (12401): slow down"
+        );
+    }
+
+    #[test]
+    fn into_error() {
+        use std::error::Error as _;
+
+        let decode = || DecodeError {
+            source: serde_json::from_str::<u8>("x").unwrap_err(),
+            trace_id: None,
+            endpoint: None,
+        };
+
+        let err: Error = RequestError::Decode(decode()).into();
+        assert!(matches!(err, Error::Request(RequestError::Decode(_))));
+        assert!(err.source().unwrap().is::<serde_json::Error>());
+
+        let err: Error = AuthenticatedRequestError::<AccessTokenExpired>::Decode(decode()).into();
+        assert!(matches!(
+            err,
+            Error::AuthenticatedRequest(AuthenticatedRequestError::Decode(_))
+        ));
+        assert!(err.source().unwrap().is::<serde_json::Error>());
+
+        let err: Error = AuthenticatedRequestError::RefreshToken(AccessTokenExpired).into();
+        match &err {
+            Error::AuthenticatedRequest(AuthenticatedRequestError::RefreshToken(inner)) => {
+                assert!(inner.is::<AccessTokenExpired>())
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(
+            err.to_string(),
+            "failed to refresh token: access token expired and doesn't support refreshing"
+        );
+    }
+
+    #[cfg(feature = "chat")]
+    #[test]
+    fn chat_into_error() {
+        use crate::chat::*;
+
+        let err: Error = ChatConnectError::SocketClosed.into();
+        assert!(matches!(
+            err,
+            Error::ChatConnect(ChatConnectError::SocketClosed)
+        ));
+
+        let err: Error = ChatMessageStreamError::PingTimeout.into();
+        assert!(matches!(
+            err,
+            Error::ChatMessageStream(ChatMessageStreamError::PingTimeout)
+        ));
+
+        let err: Error =
+            ChatMessagesForChannelError::ChatConnect(ChatConnectError::SocketClosed).into();
+        assert!(matches!(
+            err,
+            Error::ChatConnect(ChatConnectError::SocketClosed)
+        ));
+        let err: Error =
+            ChatMessagesForChannelError::Request(RequestError::ApiError(ApiError::default()))
+                .into();
+        assert!(matches!(err, Error::Request(RequestError::ApiError(_))));
+
+        let err: Error = ChatMessagesForUserError::<AccessTokenExpired>::ChatConnect(
+            ChatConnectError::SocketClosed,
+        )
+        .into();
+        assert!(matches!(
+            err,
+            Error::ChatConnect(ChatConnectError::SocketClosed)
+        ));
+        let err: Error = ChatMessagesForUserError::Request(
+            AuthenticatedRequestError::RefreshToken(AccessTokenExpired),
+        )
+        .into();
+        assert!(matches!(
+            err,
+            Error::AuthenticatedRequest(AuthenticatedRequestError::RefreshToken(_))
+        ));
+    }
+
+    fn status_error(status: StatusCode) -> reqwest::Error {
+        let res: reqwest::Response = http::Response::builder()
+            .status(status)
+            .body("")
+            .unwrap()
+            .into();
+        res.error_for_status().unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn retryable() {
+        // Nothing listening on the port
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let connect = reqwest::get(format!("http://{}", addr)).await.unwrap_err();
+        let err = RequestError::Other(connect);
+        assert!(err.is_connection() && err.is_retryable() && !err.is_timeout());
+
+        // Accepts the connection but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _server = tokio::spawn(async move {
+            let _socket = listener.accept().await;
+            std::future::pending::<()>().await
+        });
+        let timeout = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_millis(50))
+            .build()
+            .unwrap()
+            .get(format!("http://{}", addr))
+            .send()
+            .await
+            .unwrap_err();
+        let err = RequestError::Shared(Arc::new(timeout));
+        assert!(err.is_timeout() && err.is_retryable() && !err.is_connection());
+
+        let cases = [
+            (
+                RequestError::Other(status_error(StatusCode::BAD_GATEWAY)),
+                true,
+            ),
+            (
+                RequestError::Other(status_error(StatusCode::TOO_MANY_REQUESTS)),
+                true,
+            ),
+            (
+                RequestError::Other(status_error(StatusCode::NOT_FOUND)),
+                false,
+            ),
+            (
+                RequestError::ApiError(ApiError {
+                    status: RateLimitExceeded,
+                    ..Default::default()
+                }),
+                true,
+            ),
+            (
+                RequestError::ApiError(ApiError {
+                    status: InternalTimeout,
+                    ..Default::default()
+                }),
+                true,
+            ),
+            (
+                RequestError::ApiError(ApiError {
+                    status: InvalidParameters,
+                    ..Default::default()
+                }),
+                false,
+            ),
+            (
+                RequestError::Decode(DecodeError {
+                    source: serde_json::from_str::<u8>("x").unwrap_err(),
+                    trace_id: None,
+                    endpoint: None,
+                }),
+                false,
+            ),
+            (
+                RequestError::CircuitOpen {
+                    retry_at: Instant::now(),
+                },
+                true,
+            ),
+        ];
+        for (err, retryable) in cases {
+            assert_eq!(err.is_retryable(), retryable, "{:?}", err);
+            assert!(!err.is_timeout() && !err.is_connection(), "{:?}", err);
+        }
+
+        let err = AuthenticatedRequestError::RefreshToken(AccessTokenExpired);
+        assert!(!err.is_retryable());
+        let err = AuthenticatedRequestError::<AccessTokenExpired>::Other(status_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+        ));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn non_api_errors_unclassified() {
+        let err = RequestError::CircuitOpen {
+            retry_at: Instant::now(),
+        };
+        assert!(!err.is_rate_limited() && !err.is_transient() && !err.is_auth_error());
+
+        let err = AuthenticatedRequestError::RefreshToken("expired".to_string());
+        assert!(!err.is_auth_error());
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_api_errors_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        for seed in 0..=255u8 {
+            let bytes: Vec<u8> = (0..64)
+                .map(|i| seed.wrapping_mul(31).wrapping_add(i))
+                .collect();
+            let err = ApiError::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            assert_eq!(ErrorStatus::from_code(err.status.code()), err.status);
+            let parsed: ApiError =
+                serde_json::from_str(&serde_json::to_string(&err).unwrap()).unwrap();
+            assert_eq!(parsed.status, err.status);
+            assert_eq!(parsed.message, err.message);
+        }
+    }
+}
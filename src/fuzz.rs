@@ -0,0 +1,48 @@
+//! [`Arbitrary`] support for generating valid entities when fuzzing.
+//!
+//! Field level generators keep values within what the entities can round trip through serde, e.g.
+//! timestamps are whole seconds and nonces are ascii.
+
+use crate::{timestamp, ErrorStatus, Timestamp};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use std::collections::HashMap;
+
+impl<'a> Arbitrary<'a> for ErrorStatus {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // Goes through the code so known codes never end up as `Other`
+        Ok(Self::from_code(i32::arbitrary(u)?))
+    }
+}
+
+/// Printable ascii string, as used for nonces.
+#[cfg_attr(not(feature = "chat"), allow(dead_code))]
+pub(crate) fn ascii_string(u: &mut Unstructured<'_>) -> Result<String> {
+    let len = u.arbitrary_len::<u8>()?;
+    (0..len)
+        .map(|_| Ok(char::from(u.int_in_range(0x20..=0x7e)?)))
+        .collect()
+}
+
+/// Timestamp with whole seconds between 1970 and 9999.
+#[cfg_attr(not(feature = "chat"), allow(dead_code))]
+pub(crate) fn timestamp_seconds(u: &mut Unstructured<'_>) -> Result<Timestamp> {
+    let secs = u.int_in_range(0..=253_402_300_799)?;
+    Ok(timestamp::from_unix(secs, 0).expect("timestamp in range"))
+}
+
+/// Json object with string, integer, bool and null values.
+#[cfg_attr(not(feature = "chat"), allow(dead_code))]
+pub(crate) fn json_map(u: &mut Unstructured<'_>) -> Result<HashMap<String, serde_json::Value>> {
+    let len = u.arbitrary_len::<(String, i64)>()?;
+    (0..len)
+        .map(|_| {
+            let value = match u.int_in_range(0..=3)? {
+                0 => serde_json::Value::String(u.arbitrary()?),
+                1 => i64::arbitrary(u)?.into(),
+                2 => bool::arbitrary(u)?.into(),
+                _ => serde_json::Value::Null,
+            };
+            Ok((u.arbitrary()?, value))
+        })
+        .collect()
+}
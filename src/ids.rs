@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+macro_rules! string_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+        #[serde(transparent)]
+        pub struct $name(pub String);
+
+        impl $name {
+            /// Create a new id from the given string
+            pub fn new(id: impl Into<String>) -> Self {
+                Self(id.into())
+            }
+
+            /// Get the id as a string slice
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(id: &String) -> Self {
+                Self(id.clone())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+
+        impl From<&$name> for $name {
+            fn from(id: &$name) -> Self {
+                id.clone()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+    };
+}
+
+string_id! {
+    /// Unique id of a Trovo channel.
+    ///
+    /// Methods taking a channel id accept anything that converts into one, so plain strings still
+    /// work, but a [`UserId`] can't be passed by mistake:
+    ///
+    /// ```compile_fail
+    /// # async fn example(client: trovo::Client<trovo::ClientId>, user: trovo::User) {
+    /// client.channel_by_id(user.user_id()).await;
+    /// # }
+    /// ```
+    ChannelId
+}
+
+string_id! {
+    /// Unique id of a Trovo user.
+    ///
+    /// Distinct from [`ChannelId`] so the two can't be mixed up:
+    ///
+    /// ```compile_fail
+    /// let channel_id: trovo::ChannelId = trovo::UserId::new("100000").into();
+    /// ```
+    UserId
+}
+
+string_id! {
+    /// Unique id of a Trovo game category, see [`Category`](crate::Category).
+    CategoryId
+}
@@ -0,0 +1,145 @@
+use crate::InvalidLanguageCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Two letter ISO 639-1 language code, as used by Trovo for channel languages.
+///
+/// Parsing lowercases the code and drops any region suffix, so `"EN"` and `"en-US"` both become
+/// `"en"`. Anything else that isn't two ascii letters is rejected with [`InvalidLanguageCode`].
+///
+/// ```
+/// # use trovo::LanguageCode;
+/// assert_eq!("en-US".parse::<LanguageCode>().unwrap(), LanguageCode::EN);
+/// assert!("english".parse::<LanguageCode>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LanguageCode([u8; 2]);
+
+impl LanguageCode {
+    /// Arabic
+    pub const AR: Self = Self(*b"ar");
+    /// German
+    pub const DE: Self = Self(*b"de");
+    /// English
+    pub const EN: Self = Self(*b"en");
+    /// Spanish
+    pub const ES: Self = Self(*b"es");
+    /// French
+    pub const FR: Self = Self(*b"fr");
+    /// Hindi
+    pub const HI: Self = Self(*b"hi");
+    /// Indonesian
+    pub const ID: Self = Self(*b"id");
+    /// Italian
+    pub const IT: Self = Self(*b"it");
+    /// Japanese
+    pub const JA: Self = Self(*b"ja");
+    /// Korean
+    pub const KO: Self = Self(*b"ko");
+    /// Malay
+    pub const MS: Self = Self(*b"ms");
+    /// Polish
+    pub const PL: Self = Self(*b"pl");
+    /// Portuguese
+    pub const PT: Self = Self(*b"pt");
+    /// Russian
+    pub const RU: Self = Self(*b"ru");
+    /// Thai
+    pub const TH: Self = Self(*b"th");
+    /// Turkish
+    pub const TR: Self = Self(*b"tr");
+    /// Vietnamese
+    pub const VI: Self = Self(*b"vi");
+    /// Chinese
+    pub const ZH: Self = Self(*b"zh");
+
+    /// Get the code as a string slice
+    pub fn as_str(&self) -> &str {
+        // Only ever constructed from ascii letters
+        std::str::from_utf8(&self.0).expect("language code is ascii")
+    }
+}
+
+impl FromStr for LanguageCode {
+    type Err = InvalidLanguageCode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.trim().split(['-', '_']).next().unwrap_or_default();
+        match language.as_bytes() {
+            &[a, b] if a.is_ascii_alphabetic() && b.is_ascii_alphabetic() => {
+                Ok(Self([a.to_ascii_lowercase(), b.to_ascii_lowercase()]))
+            }
+            _ => Err(InvalidLanguageCode(s.to_owned())),
+        }
+    }
+}
+
+impl TryFrom<&str> for LanguageCode {
+    type Error = InvalidLanguageCode;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for LanguageCode {
+    type Error = InvalidLanguageCode;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<LanguageCode> for String {
+    fn from(code: LanguageCode) -> Self {
+        code.as_str().to_owned()
+    }
+}
+
+impl Display for LanguageCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AsRef<str> for LanguageCode {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("en".parse(), Ok(LanguageCode::EN));
+        assert_eq!("PT".parse(), Ok(LanguageCode::PT));
+        assert_eq!(" de ".parse(), Ok(LanguageCode::DE));
+        assert_eq!("en-US".parse(), Ok(LanguageCode::EN));
+        assert_eq!("zh_Hant".parse(), Ok(LanguageCode::ZH));
+        assert_eq!(LanguageCode::EN.to_string(), "en");
+
+        for garbage in ["", "e", "eng", "english", "12", "é", "-US"] {
+            assert_eq!(
+                garbage.parse::<LanguageCode>(),
+                Err(InvalidLanguageCode(garbage.to_owned())),
+            );
+        }
+    }
+
+    #[test]
+    fn serde() {
+        assert_eq!(serde_json::to_string(&LanguageCode::EN).unwrap(), r#""en""#);
+        assert_eq!(
+            serde_json::from_str::<LanguageCode>(r#""FR""#).unwrap(),
+            LanguageCode::FR
+        );
+        assert!(serde_json::from_str::<LanguageCode>(r#""french""#).is_err());
+    }
+}
@@ -41,18 +41,130 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ## Features
+//!
+//! | Feature             | Default | Description                                                                         |
+//! | ------------------- | ------- | ----------------------------------------------------------------------------------- |
+//! | `chat`              | yes     | Chat websocket support, see the `chat` module                                       |
+//! | `tracing`           | yes     | Diagnostics via `tracing`                                                           |
+//! | `log`               | no      | Diagnostics via `log` instead of `tracing`                                          |
+//! | `chrono`            | yes     | Timestamps as `chrono::DateTime<Utc>`                                               |
+//! | `time`              | no      | Timestamps as `time::OffsetDateTime`, when `chrono` is disabled                     |
+//! | `tls-rustls-webpki` | yes     | TLS via rustls, trusting the bundled Mozilla root certificates                      |
+//! | `tls-rustls-native` | no      | TLS via rustls, trusting the operating system's certificate store                   |
+//! | `tls-native`        | no      | TLS via the platform library (OpenSSL, Schannel or Secure Transport)                |
+//! | `gzip`              | yes     | Request and decode gzip compressed responses                                        |
+//! | `brotli`            | no      | Request and decode brotli compressed responses                                      |
+//! | `test-util`         | no      | `ScriptedTransport`, `ReplayTransport` and `MockClock` for testing with the client  |
+//! | `record`            | no      | `ClientBuilder::record_to` for capturing api exchanges to a file                    |
+//! | `tower`             | no      | `TrovoService` and `Client::with_service` for wrapping requests in tower middleware |
+//! | `jsonl-sink`        | no      | `chat::persist::JsonlSink` for archiving chat messages to a json lines file         |
+//! | `auth-flow`         | no      | `auth::interactive_setup` for authorizing a chat bot via oauth on its first run     |
+//! | `oauth2`            | no      | `Oauth2Provider` for renewing tokens managed with the `oauth2` crate                |
+//! | `schemars`          | no      | `JsonSchema` implementations for the api and chat entities                          |
+//! | `arbitrary`         | no      | `Arbitrary` implementations for the chat entities and `ApiError`, for fuzzing       |
+//!
+//! Only the `chat` feature depends on tokio directly. Without it the crate uses no tokio apis itself,
+//! though reqwest still relies on tokio internally, so api requests must run inside a tokio runtime.
+//!
+//! Diagnostics such as chat socket errors are emitted through `tracing` by default. With the `log`
+//! feature they go through the `log` macros instead, so `tracing` can be turned off along with the
+//! other default features.
+//!
+//! The `chrono` and `time` features decide the type behind the `Timestamp` alias used for timestamp
+//! fields. `chrono` takes precedence if both are enabled, so turn off the default features to use
+//! `time`:
+//!
+//! ```toml
+//! trovo = { version = "0.6", default-features = false, features = ["chat", "time", "tls-rustls-webpki", "gzip"] }
+//! ```
+//!
+//! With neither enabled, timestamps are unix seconds as an `i64` and neither crate is compiled.
+//!
+//! At most one `tls-*` feature can be enabled, otherwise the crate fails to compile. It is used for
+//! both api requests and the chat websocket, so they always trust the same certificates. To use
+//! another backend, e.g. to trust a corporate CA in the OS store, turn off the default features:
+//!
+//! ```toml
+//! trovo = { version = "0.6", default-features = false, features = ["chat", "chrono", "tls-rustls-native", "gzip"] }
+//! ```
+//!
+//! Clients created with `Client::from_reqwest` use the given reqwest client's TLS configuration
+//! instead, while the chat websocket still uses the selected feature. Without any `tls-*` feature,
+//! api requests only work through such a client and the chat websocket fails to connect.
+//!
+//! Compression only applies to clients built by the crate, e.g. via `Client::new` or
+//! `ClientBuilder`, and can be turned off with `ClientBuilder::compression`. Clients created with
+//! `Client::from_reqwest` use the given reqwest client as is, so enable reqwest's own `gzip`/`brotli`
+//! features and configure it yourself.
+
+#[cfg(any(
+    all(feature = "tls-rustls-webpki", feature = "tls-rustls-native"),
+    all(feature = "tls-rustls-webpki", feature = "tls-native"),
+    all(feature = "tls-rustls-native", feature = "tls-native"),
+))]
+compile_error!(
+    "only one of the `tls-rustls-webpki`, `tls-rustls-native` and `tls-native` features can be \
+     enabled, disable default features when choosing a different tls backend"
+);
 
-mod auth;
+#[macro_use]
+#[cfg_attr(not(feature = "chat"), allow(unused_macros))]
+mod macros;
+
+pub mod auth;
+mod batch;
+mod builder;
 #[cfg(feature = "chat")]
 pub mod chat;
+mod circuit_breaker;
 mod client;
+mod clips;
+mod clock;
+mod commands;
+mod emotes;
 mod entities;
 mod errors;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+mod ids;
+mod language;
+mod oauth;
+mod pipeline;
+mod rate_limit;
+#[cfg(any(test, feature = "record", feature = "test-util"))]
+mod record;
+mod response;
+#[cfg(feature = "schemars")]
+mod schema;
+#[cfg(feature = "tower")]
+mod service;
+mod timestamp;
+mod transport;
+mod username;
 
 pub use auth::*;
+pub use batch::*;
+pub use builder::*;
+use circuit_breaker::*;
 pub use client::*;
+pub use clips::*;
+pub use clock::*;
+pub use commands::*;
+pub use emotes::*;
 pub use entities::*;
 pub use errors::*;
-
-#[macro_use]
-extern crate tracing;
+pub use ids::*;
+pub use language::*;
+pub use oauth::*;
+use pipeline::*;
+pub use rate_limit::*;
+#[cfg(any(test, feature = "record", feature = "test-util"))]
+pub use record::*;
+pub use response::*;
+#[cfg(feature = "tower")]
+pub use service::*;
+pub use timestamp::Timestamp;
+pub use transport::*;
+pub use username::*;
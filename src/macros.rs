@@ -0,0 +1,39 @@
+//! Logging macros that forward to `log` when the `log` feature is enabled, otherwise to `tracing`.
+//!
+//! Only the subset of the `tracing` syntax used in the crate is supported: a message literal,
+//! optionally preceded by `?field` debug values.
+
+macro_rules! __log {
+    ($level:ident, $(?$field:ident,)* $msg:literal) => {{
+        #[cfg(feature = "log")]
+        ::log::$level!(concat!($msg $(, " ", stringify!($field), "={:?}")*) $(, $field)*);
+
+        #[cfg(all(feature = "tracing", not(feature = "log")))]
+        ::tracing::$level!($(?$field,)* $msg);
+
+        #[cfg(not(any(feature = "log", feature = "tracing")))]
+        {
+            $(let _ = &$field;)*
+        }
+    }};
+}
+
+macro_rules! trace {
+    ($($args:tt)*) => { __log!(trace, $($args)*) };
+}
+
+macro_rules! debug {
+    ($($args:tt)*) => { __log!(debug, $($args)*) };
+}
+
+macro_rules! info {
+    ($($args:tt)*) => { __log!(info, $($args)*) };
+}
+
+macro_rules! warn {
+    ($($args:tt)*) => { __log!(warn, $($args)*) };
+}
+
+macro_rules! error {
+    ($($args:tt)*) => { __log!(error, $($args)*) };
+}
@@ -0,0 +1,837 @@
+use crate::{
+    access_token, auth::REDACTED, AccessToken, AccessTokenProvider, AuthenticatedRequestError,
+    Client, ClientIdProvider, Clock, ErrorStatus, OAuthError, RequestError,
+};
+use reqwest::{header, Method};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, PickFirst};
+use std::{
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Tokens are considered expired this long before they actually expire, so a request doesn't
+/// start with a token that expires before it arrives.
+const DEFAULT_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Token issued by the oauth token endpoint.
+///
+/// The tokens are redacted from the `Debug` output.
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenResponse {
+    /// The access token
+    pub access_token: String,
+
+    /// Type of the token, `OAuth` for tokens sent in the `Authorization` header
+    #[serde(default)]
+    pub token_type: String,
+
+    /// Seconds until the access token expires. Accepts both numbers and numeric strings.
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub expires_in: u64,
+
+    /// Token to obtain a new access token with, not issued for client credentials
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+
+    /// Space separated scopes granted to the token, if sent
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl fmt::Debug for TokenResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenResponse")
+            .field("access_token", &REDACTED)
+            .field("token_type", &self.token_type)
+            .field("expires_in", &self.expires_in)
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| REDACTED),
+            )
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+/// Details of an access token, returned by [`Client::validate_token`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedToken {
+    /// Client id of the app the token was issued to
+    pub client_id: String,
+
+    /// Id of the user the token acts for
+    pub user_id: String,
+
+    /// Display name of the user the token acts for
+    pub nickname: String,
+
+    /// Scopes granted to the token, e.g. `chat_send_self`
+    pub scopes: Vec<String>,
+
+    /// Time left until the token expires, zero if it already has
+    pub expires_in: Duration,
+}
+
+impl ValidatedToken {
+    /// Whether the token was granted the given scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+}
+
+/// Response of the validate endpoint
+#[serde_as]
+#[derive(Deserialize)]
+struct ValidateResponse {
+    #[serde(default)]
+    client_id: String,
+    #[serde(default)]
+    uid: String,
+    #[serde(default)]
+    nick_name: String,
+    #[serde(default)]
+    scopes: Vec<String>,
+    /// Unix seconds at which the token expires
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    expire_ts: u64,
+}
+
+impl ValidateResponse {
+    fn into_validated(self, now: SystemTime) -> ValidatedToken {
+        let now = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        ValidatedToken {
+            client_id: self.client_id,
+            user_id: self.uid,
+            nickname: self.nick_name,
+            scopes: self.scopes,
+            expires_in: Duration::from_secs(self.expire_ts.saturating_sub(now)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ClientCredentialsPayload<'a> {
+    client_secret: &'a str,
+    grant_type: &'static str,
+}
+
+#[derive(Serialize)]
+struct ExchangeCodePayload<'a> {
+    client_secret: &'a str,
+    grant_type: &'static str,
+    code: &'a str,
+    redirect_uri: &'a str,
+}
+
+#[derive(Serialize)]
+struct RefreshTokenPayload<'a> {
+    client_secret: &'a str,
+    grant_type: &'static str,
+    refresh_token: &'a str,
+}
+
+/// Deliberately not `Debug`, so the token can't end up in logs.
+#[derive(Serialize)]
+struct RevokeTokenPayload<'a> {
+    access_token: &'a str,
+}
+
+impl<A> Client<A> {
+    /// Obtains an app access token with the client credentials grant, for server to server use
+    /// without a user.
+    ///
+    /// App tokens aren't tied to a user, so they carry no user scopes. They authenticate calls
+    /// made on behalf of the app itself, while endpoints acting for a user, such as
+    /// [`Client::update_channel`], fail with
+    /// [`ErrorStatus::UnauthorizedScope`](crate::ErrorStatus::UnauthorizedScope). See
+    /// [`AppTokenProvider`] for a provider that caches and renews the token.
+    pub async fn exchange_client_credentials(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<TokenResponse, OAuthError> {
+        self.token_request(
+            "exchangetoken",
+            client_id,
+            &ClientCredentialsPayload {
+                client_secret,
+                grant_type: "client_credentials",
+            },
+        )
+        .await
+    }
+
+    /// Exchanges the authorization code from the oauth redirect for a user access token.
+    ///
+    /// `redirect_uri` must be the same one the user was sent to authorize with.
+    pub async fn exchange_code(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        code: &str,
+        redirect_uri: &str,
+    ) -> Result<TokenResponse, OAuthError> {
+        self.token_request(
+            "exchangetoken",
+            client_id,
+            &ExchangeCodePayload {
+                client_secret,
+                grant_type: "authorization_code",
+                code,
+                redirect_uri,
+            },
+        )
+        .await
+    }
+
+    /// Obtains a new access token with a refresh token from an earlier exchange.
+    pub async fn refresh_access_token(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        refresh_token: &str,
+    ) -> Result<TokenResponse, OAuthError> {
+        self.token_request(
+            "refreshtoken",
+            client_id,
+            &RefreshTokenPayload {
+                client_secret,
+                grant_type: "refresh_token",
+                refresh_token,
+            },
+        )
+        .await
+    }
+
+    async fn token_request(
+        &self,
+        path: &str,
+        client_id: &str,
+        payload: &impl Serialize,
+    ) -> Result<TokenResponse, OAuthError> {
+        let res = self
+            .send(self.request_as(Method::POST, path, client_id).json(payload))
+            .await
+            .map_err(RequestError::from)?;
+
+        if let Some(err) = res.api_error() {
+            return Err(RequestError::ApiError(err).into());
+        }
+
+        res.error_for_status().map_err(RequestError::from)?;
+        Ok(res.json().map_err(RequestError::from)?)
+    }
+
+    /// A client sharing this one's settings and state, with a different auth provider.
+    pub(crate) fn with_auth_provider<B>(&self, auth_provider: B) -> Client<B> {
+        Client {
+            http: self.http.clone(),
+            pipeline: self.pipeline.clone(),
+            default_headers: self.default_headers.clone(),
+            auth_provider,
+        }
+    }
+}
+
+impl<A> Client<A>
+where
+    A: AccessTokenProvider,
+{
+    /// Checks the access token is still valid, and which user and scopes it is for, e.g. at
+    /// startup rather than finding out a scope is missing mid-stream.
+    ///
+    /// ```no_run
+    /// # async fn example(client: trovo::Client<trovo::AccessTokenOnly>) -> Result<(), trovo::Error> {
+    /// let token = client.validate_token().await?;
+    /// if !token.has_scope("chat_send_self") {
+    ///     eprintln!("{} can't send chat messages, please reauthorize", token.nickname);
+    /// }
+    /// println!("token expires in {:?}", token.expires_in);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Invalid or expired tokens fail with an [`AuthenticatedRequestError::ApiError`] of
+    /// [`ErrorStatus::InvalidAccessToken`](crate::ErrorStatus::InvalidAccessToken) or
+    /// [`ErrorStatus::AccessTokenExpired`](crate::ErrorStatus::AccessTokenExpired).
+    pub async fn validate_token(
+        &self,
+    ) -> Result<ValidatedToken, AuthenticatedRequestError<A::Error>> {
+        let res = self
+            .send(self.request(Method::GET, "validate").header(
+                header::AUTHORIZATION,
+                format!(
+                    "OAuth {}",
+                    access_token!(self.auth_provider, AuthenticatedRequestError)
+                ),
+            ))
+            .await?;
+
+        if let Some(err) = res.api_error() {
+            return Err(AuthenticatedRequestError::ApiError(err));
+        }
+
+        res.error_for_status()?;
+        let response: ValidateResponse = res.json()?;
+        Ok(response.into_validated(self.pipeline.clock.system_time()))
+    }
+}
+
+impl<A> Client<A>
+where
+    A: ClientIdProvider,
+{
+    /// Revokes a user access token, e.g. when the user disconnects your app.
+    ///
+    /// The token is passed explicitly, so this works with any auth provider, including a plain
+    /// [`ClientId`](crate::ClientId). It's only ever sent in the request body, which is never
+    /// logged and is redacted in recordings.
+    ///
+    /// Tokens that are unknown, expired, or already revoked fail with
+    /// [`OAuthError::InvalidToken`], so callers can treat them as already disconnected.
+    pub async fn revoke_token(&self, access_token: &str) -> Result<(), OAuthError> {
+        let res = self
+            .send(
+                self.request(Method::POST, "revoke")
+                    .json(&RevokeTokenPayload { access_token }),
+            )
+            .await
+            .map_err(RequestError::from)?;
+
+        if let Some(err) = res.api_error() {
+            return Err(match err.status {
+                ErrorStatus::InvalidAccessToken | ErrorStatus::AccessTokenExpired => {
+                    OAuthError::InvalidToken(err)
+                }
+                _ => RequestError::ApiError(err).into(),
+            });
+        }
+
+        res.error_for_status().map_err(RequestError::from)?;
+        Ok(())
+    }
+}
+
+/// A client secret, with an optional second secret tried while the secret is being rotated.
+#[derive(Clone)]
+pub(crate) struct ClientSecret {
+    primary: String,
+    fallback: Option<String>,
+}
+
+impl ClientSecret {
+    pub(crate) fn new(primary: impl Into<String>) -> Self {
+        Self {
+            primary: primary.into(),
+            fallback: None,
+        }
+    }
+
+    pub(crate) fn set_fallback(&mut self, fallback: impl Into<String>) {
+        self.fallback = Some(fallback.into());
+    }
+
+    /// Runs the exchange with the primary secret, retrying with the fallback secret only if the
+    /// primary one is rejected as [`ErrorStatus::InvalidClientSecret`](crate::ErrorStatus::InvalidClientSecret).
+    /// If the fallback fails too, the primary secret's error is returned.
+    pub(crate) async fn exchange<T, F, Fut>(&self, mut exchange: F) -> Result<T, OAuthError>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<T, OAuthError>>,
+    {
+        let err = match exchange(self.primary.clone()).await {
+            Err(err) if err.is_invalid_client_secret() => err,
+            result => return result,
+        };
+        let Some(fallback) = &self.fallback else {
+            return Err(err);
+        };
+
+        let result = exchange(fallback.clone()).await;
+        if result.is_ok() {
+            warn!(
+                "client secret rejected but the fallback secret was accepted, the primary is stale"
+            );
+        }
+        result.map_err(|_| err)
+    }
+}
+
+impl fmt::Debug for ClientSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientSecret")
+            .field("primary", &REDACTED)
+            .field("fallback", &self.fallback.as_ref().map(|_| REDACTED))
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+struct CachedToken {
+    token: String,
+    expires_at: Instant,
+}
+
+/// An [`AccessTokenProvider`] using app tokens from the client credentials grant, see
+/// [`Client::exchange_client_credentials`].
+///
+/// The token is obtained on first use and cached, shared between clones of the provider. Once
+/// it is within a minute of expiring (see [`expiry_margin`](Self::expiry_margin)), the next
+/// request obtains a new one. Concurrent requests wait for a single exchange.
+///
+/// App tokens have no user scopes, so only endpoints that don't act on behalf of a user work
+/// with this provider.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use trovo::{AppTokenProvider, Client};
+///
+/// let http = Client::new(());
+/// let client = Client::new(AppTokenProvider::new(&http, "client-id", "client-secret"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The client secret and tokens are redacted from the `Debug` output.
+#[derive(Clone)]
+pub struct AppTokenProvider {
+    client: Client<()>,
+    client_id: String,
+    client_secret: ClientSecret,
+    expiry_margin: Duration,
+    cached: Arc<Mutex<Option<CachedToken>>>,
+    exchange: Arc<futures::lock::Mutex<()>>,
+}
+
+impl AppTokenProvider {
+    /// Creates a provider that exchanges the credentials through the given client, sharing its
+    /// transport, clock and other settings.
+    pub fn new<A>(
+        client: &Client<A>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            client: client.with_auth_provider(()),
+            client_id: client_id.into(),
+            client_secret: ClientSecret::new(client_secret),
+            expiry_margin: DEFAULT_EXPIRY_MARGIN,
+            cached: Arc::default(),
+            exchange: Arc::default(),
+        }
+    }
+
+    /// Sets how long before expiry a token is renewed. Defaults to one minute.
+    pub fn expiry_margin(mut self, margin: Duration) -> Self {
+        self.expiry_margin = margin;
+        self
+    }
+
+    /// Sets a second client secret, tried when the token endpoint rejects the primary one as
+    /// [`ErrorStatus::InvalidClientSecret`](crate::ErrorStatus::InvalidClientSecret), e.g.
+    /// while rotating the secret. A warning is logged whenever the fallback is accepted.
+    pub fn with_fallback_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret.set_fallback(secret);
+        self
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        &*self.client.pipeline.clock
+    }
+
+    /// The cached token, if it isn't about to expire
+    fn valid_token(&self) -> Option<String> {
+        let cached = self.cached.lock().unwrap();
+        let cached = cached.as_ref()?;
+        (self.clock().now() + self.expiry_margin < cached.expires_at).then(|| cached.token.clone())
+    }
+}
+
+impl fmt::Debug for AppTokenProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppTokenProvider")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &self.client_secret)
+            .field("expiry_margin", &self.expiry_margin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClientIdProvider for AppTokenProvider {
+    fn client_id(&self) -> &str {
+        &self.client_id
+    }
+}
+
+#[async_trait::async_trait]
+impl AccessTokenProvider for AppTokenProvider {
+    type Error = OAuthError;
+
+    fn access_token(&self) -> AccessToken {
+        match self.valid_token() {
+            Some(token) => AccessToken::Token(token),
+            None => AccessToken::NeedsRefresh,
+        }
+    }
+
+    async fn refresh_token(&self) -> Result<String, Self::Error> {
+        let _exchange = self.exchange.lock().await;
+        // Another caller may have renewed the token while we waited
+        if let Some(token) = self.valid_token() {
+            return Ok(token);
+        }
+
+        let requested_at = self.clock().now();
+        let response = self
+            .client_secret
+            .exchange(|secret| async move {
+                self.client
+                    .exchange_client_credentials(&self.client_id, &secret)
+                    .await
+            })
+            .await?;
+        *self.cached.lock().unwrap() = Some(CachedToken {
+            token: response.access_token.clone(),
+            expires_at: requested_at + Duration::from_secs(response.expires_in),
+        });
+        Ok(response.access_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AccessTokenOnly, ChannelUpdate, ClientId, ErrorStatus, MockClock, ScriptedTransport,
+    };
+    use reqwest::{header, StatusCode};
+
+    fn app_client() -> (Client<AppTokenProvider>, ScriptedTransport, MockClock) {
+        let transport = ScriptedTransport::new();
+        let clock = MockClock::new();
+        let http = Client::builder()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build(())
+            .unwrap();
+        let provider = AppTokenProvider::new(&http, "client-id", "client-secret");
+        let client = Client::builder()
+            .transport(transport.clone())
+            .clock(clock.clone())
+            .build(provider)
+            .unwrap();
+        (client, transport, clock)
+    }
+
+    fn token(token: &str, expires_in: u64) -> String {
+        serde_json::json!({
+            "access_token": token,
+            "token_type": "OAuth",
+            "expires_in": expires_in.to_string(),
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn acquire_cache_and_renew() {
+        let (client, transport, clock) = app_client();
+        transport.push(StatusCode::OK, token("first", 3600));
+        for _ in 0..2 {
+            transport.push(StatusCode::OK, "{}");
+        }
+
+        // Acquired on first use, then cached
+        for _ in 0..2 {
+            client
+                .update_channel("100000", ChannelUpdate::default())
+                .await
+                .unwrap();
+        }
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].url.path().ends_with("/exchangetoken"));
+        assert_eq!(requests[0].headers["client-id"], "client-id");
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({
+                "client_secret": "client-secret",
+                "grant_type": "client_credentials",
+            })
+        );
+        for request in &requests[1..] {
+            assert_eq!(request.headers[header::AUTHORIZATION], "OAuth first");
+        }
+
+        // Renewed once within the expiry margin
+        clock.advance(Duration::from_secs(3600 - 30));
+        transport.push(StatusCode::OK, token("second", 3600));
+        transport.push(StatusCode::OK, "{}");
+        client
+            .update_channel("100000", ChannelUpdate::default())
+            .await
+            .unwrap();
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 5);
+        assert!(requests[3].url.path().ends_with("/exchangetoken"));
+        assert_eq!(requests[4].headers[header::AUTHORIZATION], "OAuth second");
+    }
+
+    #[tokio::test]
+    async fn concurrent_refresh_exchanges_once() {
+        let (client, transport, _clock) = app_client();
+        transport.push(StatusCode::OK, token("shared", 3600));
+
+        let provider = &client.auth_provider;
+        let (a, b) = futures::join!(provider.refresh_token(), provider.refresh_token());
+        assert_eq!(a.unwrap(), "shared");
+        assert_eq!(b.unwrap(), "shared");
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn invalid_credentials() {
+        let (client, transport, _clock) = app_client();
+        transport.push(
+            StatusCode::UNAUTHORIZED,
+            r#"{"status": 10703, "message": "Authorization failed"}"#,
+        );
+
+        let err = client.auth_provider.refresh_token().await.unwrap_err();
+        assert!(err.is_invalid_credentials(), "{:?}", err);
+        assert!(!format!("{:?}", client.auth_provider).contains("client-secret"));
+
+        // Nothing cached after a failure
+        assert!(matches!(
+            client.auth_provider.access_token(),
+            AccessToken::NeedsRefresh
+        ));
+    }
+
+    #[tokio::test]
+    async fn fallback_secret() {
+        let (client, transport, _clock) = app_client();
+        let provider = client
+            .auth_provider
+            .clone()
+            .with_fallback_secret("new-secret");
+        let invalid_secret =
+            |message: &str| serde_json::json!({"status": 11717, "message": message}).to_string();
+        transport
+            .push(StatusCode::BAD_REQUEST, invalid_secret("stale"))
+            .push(StatusCode::OK, token("fallback", 3600));
+
+        assert_eq!(provider.refresh_token().await.unwrap(), "fallback");
+        let secrets: Vec<_> = transport
+            .requests()
+            .iter()
+            .map(|request| request.json()["client_secret"].clone())
+            .collect();
+        assert_eq!(secrets, ["client-secret", "new-secret"]);
+        assert!(!format!("{:?}", provider).contains("new-secret"));
+
+        // Both rejected reports the primary secret's error
+        let provider = provider.expiry_margin(Duration::from_secs(3600));
+        transport
+            .push(StatusCode::BAD_REQUEST, invalid_secret("primary"))
+            .push(StatusCode::BAD_REQUEST, invalid_secret("fallback"));
+        let err = provider.refresh_token().await.unwrap_err();
+        assert!(err.is_invalid_client_secret());
+        assert!(err.to_string().contains("primary"), "{}", err);
+        assert_eq!(transport.requests().len(), 4);
+
+        // Other failures never consult the fallback
+        transport.push(StatusCode::SERVICE_UNAVAILABLE, "");
+        provider.refresh_token().await.unwrap_err();
+        transport.push(
+            StatusCode::BAD_REQUEST,
+            r#"{"status": 10703, "message": "failed"}"#,
+        );
+        provider.refresh_token().await.unwrap_err();
+        assert_eq!(transport.requests().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn exchange_code_and_refresh() {
+        let transport = ScriptedTransport::new();
+        let client = Client::with_transport(transport.clone(), ());
+        transport.push(
+            StatusCode::OK,
+            r#"{"access_token": "access", "token_type": "OAuth", "expires_in": 3600, "refresh_token": "refresh"}"#,
+        );
+        transport.push(
+            StatusCode::OK,
+            r#"{"access_token": "renewed", "token_type": "OAuth", "expires_in": "3600", "refresh_token": "refresh2"}"#,
+        );
+
+        let token = client
+            .exchange_code(
+                "client-id",
+                "secret",
+                "code",
+                "http://localhost:3000/callback",
+            )
+            .await
+            .unwrap();
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+        let token = client
+            .refresh_access_token("client-id", "secret", "refresh")
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, "renewed");
+
+        let requests = transport.requests();
+        assert!(requests[0].url.path().ends_with("/exchangetoken"));
+        assert_eq!(
+            requests[0].json(),
+            serde_json::json!({
+                "client_secret": "secret",
+                "grant_type": "authorization_code",
+                "code": "code",
+                "redirect_uri": "http://localhost:3000/callback",
+            })
+        );
+        assert!(requests[1].url.path().ends_with("/refreshtoken"));
+        assert_eq!(
+            requests[1].json(),
+            serde_json::json!({
+                "client_secret": "secret",
+                "grant_type": "refresh_token",
+                "refresh_token": "refresh",
+            })
+        );
+    }
+
+    #[test]
+    fn validate_response() {
+        let response: ValidateResponse = serde_json::from_str(
+            r#"{
+                "uid": "100000",
+                "client_id": "client-id",
+                "nick_name": "Bot",
+                "scopes": ["user_details_self", "chat_send_self"],
+                "expire_ts": "1700003600"
+            }"#,
+        )
+        .unwrap();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let token = response.into_validated(now);
+        assert_eq!(token.user_id, "100000");
+        assert_eq!(token.nickname, "Bot");
+        assert!(token.has_scope("chat_send_self"));
+        assert!(!token.has_scope("channel_update_self"));
+        assert_eq!(token.expires_in, Duration::from_secs(3600));
+
+        let expired: ValidateResponse =
+            serde_json::from_str(r#"{"expire_ts": 1600000000}"#).unwrap();
+        assert_eq!(expired.into_validated(now).expires_in, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn validate_token() {
+        let transport = ScriptedTransport::new();
+        let clock = MockClock::new();
+        clock.set_system_time(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let client = Client::builder()
+            .transport(transport.clone())
+            .clock(clock)
+            .build(AccessTokenOnly::new("client-id", "access-token"))
+            .unwrap();
+        transport
+            .push(
+                StatusCode::OK,
+                r#"{"uid": "100000", "client_id": "client-id", "nick_name": "Bot", "scopes": ["chat_send_self"], "expire_ts": 1700000060}"#,
+            )
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11714, "message": "access token expired"}"#,
+            )
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11704, "message": "invalid access token"}"#,
+            );
+
+        let token = client.validate_token().await.unwrap();
+        assert_eq!(token.client_id, "client-id");
+        assert_eq!(token.expires_in, Duration::from_secs(60));
+
+        for expected in [
+            ErrorStatus::AccessTokenExpired,
+            ErrorStatus::InvalidAccessToken,
+        ] {
+            match client.validate_token().await.unwrap_err() {
+                AuthenticatedRequestError::ApiError(err) => {
+                    assert_eq!(err.status, expected);
+                    assert!(err.is_auth_error());
+                }
+                err => panic!("unexpected error {:?}", err),
+            }
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::GET);
+        assert!(requests[0].url.path().ends_with("/validate"));
+        assert_eq!(
+            requests[0].headers[header::AUTHORIZATION],
+            "OAuth access-token"
+        );
+    }
+
+    #[tokio::test]
+    async fn revoke_token() {
+        let transport = ScriptedTransport::new();
+        let client = Client::with_transport(transport.clone(), ClientId::new("client-id"));
+        transport
+            .push(StatusCode::OK, "{}")
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11704, "message": "invalid access token"}"#,
+            )
+            .push(
+                StatusCode::UNAUTHORIZED,
+                r#"{"status": 11714, "message": "access token expired"}"#,
+            )
+            .push(
+                StatusCode::BAD_REQUEST,
+                r#"{"status": 11701, "message": "invalid header"}"#,
+            );
+
+        client.revoke_token("user-token").await.unwrap();
+        for _ in 0..2 {
+            match client.revoke_token("user-token").await.unwrap_err() {
+                OAuthError::InvalidToken(err) => assert!(err.is_auth_error()),
+                err => panic!("unexpected error {:?}", err),
+            }
+        }
+        match client.revoke_token("user-token").await.unwrap_err() {
+            OAuthError::Request(RequestError::ApiError(err)) => {
+                assert_eq!(err.status, ErrorStatus::InvalidHeader);
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        let requests = transport.requests();
+        assert_eq!(requests[0].method, Method::POST);
+        assert!(requests[0].url.path().ends_with("/revoke"));
+        assert!(!requests[0].url.as_str().contains("user-token"));
+        assert_eq!(requests[0].headers["client-id"], "client-id");
+        assert_eq!(requests[0].json()["access_token"], "user-token");
+    }
+
+    #[test]
+    fn token_response_redacted() {
+        let response: TokenResponse = serde_json::from_str(
+            r#"{"access_token": "secret-access", "token_type": "OAuth", "expires_in": 3600, "refresh_token": "secret-refresh"}"#,
+        )
+        .unwrap();
+        assert_eq!(response.expires_in, 3600);
+        let debug = format!("{:?}", response);
+        assert!(!debug.contains("secret"), "{}", debug);
+    }
+}
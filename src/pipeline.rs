@@ -0,0 +1,211 @@
+use crate::{
+    ApiResponse, CircuitBreaker, Clock, HttpTransport, RateLimitStatus, StrictDecoding, TokioClock,
+};
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use reqwest::{header, Method, Request, Url};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+/// Shared state used to execute requests, shared between clones of a
+/// [`Client`](crate::Client).
+#[derive(Debug)]
+pub(crate) struct Pipeline {
+    pub(crate) transport: Arc<dyn HttpTransport>,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) coalescer: Option<Coalescer>,
+    pub(crate) circuit_breaker: Option<Mutex<CircuitBreaker>>,
+    pub(crate) rate_limit: Mutex<RateLimitStatus>,
+    pub(crate) strict: Option<StrictDecoding>,
+    #[cfg(feature = "tower")]
+    pub(crate) service: Option<crate::ServiceStack>,
+}
+
+impl Pipeline {
+    pub(crate) fn new(transport: Arc<dyn HttpTransport>) -> Self {
+        Self {
+            transport,
+            clock: Arc::new(TokioClock),
+            coalescer: None,
+            circuit_breaker: None,
+            rate_limit: Mutex::default(),
+            strict: None,
+            #[cfg(feature = "tower")]
+            service: None,
+        }
+    }
+
+    /// Executes the request and downloads the response.
+    pub(crate) async fn execute(&self, mut request: Request) -> Result<ApiResponse, SendError> {
+        // Keep the token out of any debug output of the request, e.g. from custom transports
+        if let Some(value) = request.headers_mut().get_mut(header::AUTHORIZATION) {
+            value.set_sensitive(true);
+        }
+
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker
+                .lock()
+                .unwrap()
+                .before_request(self.clock.now())
+                .map_err(|retry_at| SendError::CircuitOpen { retry_at })?;
+        }
+
+        let started = self.clock.now();
+        let res = match self.send(request).await {
+            Ok(res) => ApiResponse::read(res, started, self.clock.as_ref())
+                .await
+                .map_err(SendError::from),
+            Err(err) => Err(err),
+        };
+
+        if let Some(breaker) = &self.circuit_breaker {
+            let success = match &res {
+                Ok(res) => !res.status().is_server_error(),
+                Err(_) => false,
+            };
+            breaker.lock().unwrap().record(self.clock.now(), success);
+        }
+        let mut res = res?;
+        self.rate_limit.lock().unwrap().update(&res.meta);
+        res.strict = self.strict.clone();
+        Ok(res)
+    }
+
+    /// Sends the request via the service if one was given, otherwise the transport.
+    async fn send(&self, request: Request) -> Result<reqwest::Response, SendError> {
+        #[cfg(feature = "tower")]
+        if let Some(service) = &self.service {
+            return service.call(request).await;
+        }
+        Ok(self.transport.execute(request).await?)
+    }
+
+    /// Executes the request, sharing the response with any identical requests that are already in
+    /// flight if coalescing is enabled.
+    pub(crate) async fn execute_coalesced(
+        self: &Arc<Self>,
+        request: Request,
+    ) -> Result<ApiResponse, SharedSendError> {
+        let coalescer = match &self.coalescer {
+            Some(coalescer) => coalescer,
+            None => return self.execute(request).await.map_err(SharedSendError::from),
+        };
+
+        let key = CoalesceKey {
+            method: request.method().clone(),
+            url: request.url().to_string(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(<[u8]>::to_vec),
+        };
+
+        let future = {
+            let mut in_flight = coalescer.in_flight.lock().unwrap();
+            in_flight
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    let pipeline = self.clone();
+                    async move {
+                        let res = pipeline
+                            .execute(request)
+                            .await
+                            .map_err(SharedSendError::from);
+                        if let Some(coalescer) = &pipeline.coalescer {
+                            coalescer.in_flight.lock().unwrap().remove(&key);
+                        }
+                        res
+                    }
+                    .boxed()
+                    .shared()
+                })
+                .clone()
+        };
+        future.await
+    }
+}
+
+/// Errors that can happen while sending a request
+#[derive(Debug)]
+pub(crate) enum SendError {
+    Http(reqwest::Error),
+    CircuitOpen {
+        retry_at: Instant,
+    },
+    #[cfg(feature = "tower")]
+    Service(Arc<dyn std::error::Error + Send + Sync>),
+}
+
+impl From<reqwest::Error> for SendError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(redact_error(err))
+    }
+}
+
+/// Strips anything potentially sensitive from the url embedded in the error
+pub(crate) fn redact_error(mut err: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = err.url_mut() {
+        redact_url(url);
+    }
+    err
+}
+
+/// Strips credentials, query string, and fragment from the url
+pub(crate) fn redact_url(url: &mut Url) {
+    url.set_query(None);
+    url.set_fragment(None);
+    url.set_username("").ok();
+    url.set_password(None).ok();
+}
+
+/// Same as [`SendError`], but clonable so it can be shared between coalesced requests
+#[derive(Debug, Clone)]
+pub(crate) enum SharedSendError {
+    Http(Arc<reqwest::Error>),
+    CircuitOpen {
+        retry_at: Instant,
+    },
+    #[cfg(feature = "tower")]
+    Service(Arc<dyn std::error::Error + Send + Sync>),
+}
+
+impl From<SendError> for SharedSendError {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::Http(err) => Self::Http(Arc::new(err)),
+            SendError::CircuitOpen { retry_at } => Self::CircuitOpen { retry_at },
+            #[cfg(feature = "tower")]
+            SendError::Service(err) => Self::Service(err),
+        }
+    }
+}
+
+type SharedResponse = Shared<BoxFuture<'static, Result<ApiResponse, SharedSendError>>>;
+
+/// Identifies requests that can share a response
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CoalesceKey {
+    method: Method,
+    url: String,
+    body: Option<Vec<u8>>,
+}
+
+/// Tracks in-flight requests so identical ones can be coalesced
+#[derive(Default)]
+pub(crate) struct Coalescer {
+    in_flight: Mutex<HashMap<CoalesceKey, SharedResponse>>,
+}
+
+impl Debug for Coalescer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coalescer")
+            .field("in_flight", &self.in_flight.lock().unwrap().len())
+            .finish()
+    }
+}
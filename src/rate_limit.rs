@@ -0,0 +1,53 @@
+use crate::{timestamp, ResponseMeta, Timestamp};
+
+/// Response header carrying the number of requests left in the current rate limit window.
+///
+/// Trovo doesn't document any rate limit headers, this is the conventional name, read if the api
+/// or a proxy in front of it sends it.
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+
+/// Response header carrying when the current rate limit window resets, as a unix timestamp in
+/// seconds.
+///
+/// Undocumented like [`RATE_LIMIT_REMAINING_HEADER`].
+pub const RATE_LIMIT_RESET_HEADER: &str = "x-ratelimit-reset";
+
+/// Snapshot of how close the client is to the api's rate limits, see
+/// [`Client::rate_limit_status`](crate::Client::rate_limit_status).
+///
+/// Both fields stay `None` as long as no response carried the headers, so don't rely on them
+/// alone, [`ErrorStatus::RateLimitExceeded`](crate::ErrorStatus::RateLimitExceeded) is the
+/// documented signal.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimitStatus {
+    /// Requests left in the current window, taken from the [`RATE_LIMIT_REMAINING_HEADER`] of the
+    /// latest response that carried it.
+    pub remaining: Option<u32>,
+
+    /// When the current window resets, taken from the [`RATE_LIMIT_RESET_HEADER`] of the latest
+    /// response that carried it.
+    pub reset_at: Option<Timestamp>,
+}
+
+impl RateLimitStatus {
+    /// Updates the status from the headers of a response, keeping previous values for any headers
+    /// that are missing or invalid.
+    pub(crate) fn update(&mut self, meta: &ResponseMeta) {
+        let header = |name| {
+            meta.headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+        };
+
+        if let Some(remaining) = header(RATE_LIMIT_REMAINING_HEADER).and_then(|v| v.parse().ok()) {
+            self.remaining = Some(remaining);
+        }
+        if let Some(reset_at) = header(RATE_LIMIT_RESET_HEADER)
+            .and_then(|v| v.parse().ok())
+            .and_then(|secs| timestamp::from_unix(secs, 0))
+        {
+            self.reset_at = Some(reset_at);
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// An api request and its response, as stored on each line of a recording.
+///
+/// See [`ClientBuilder::record_to`](crate::ClientBuilder::record_to) for creating recordings and
+/// `ReplayTransport` under the `test-util` feature for playing them back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    /// Http method of the request
+    pub method: String,
+
+    /// Path of the request url, without the query string
+    pub path: String,
+
+    /// Allow-listed request headers, with `Client-ID` and `Authorization` values redacted
+    #[serde(default)]
+    pub request_headers: BTreeMap<String, String>,
+
+    /// Body of the request, if any, with sensitive top level fields such as `client_secret`
+    /// redacted
+    #[serde(default)]
+    pub request_body: Option<String>,
+
+    /// Http status code of the response
+    pub status: u16,
+
+    /// Allow-listed response headers, e.g. the content type and rate limit counters
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Body of the response, with sensitive top level fields such as `token` and `access_token`
+    /// redacted
+    pub body: String,
+}
+
+#[cfg(any(test, feature = "record"))]
+pub(crate) use recording::*;
+
+#[cfg(any(test, feature = "record"))]
+mod recording {
+    use super::RecordedExchange;
+    use crate::HttpTransport;
+    use reqwest::{header::HeaderMap, ResponseBuilderExt};
+    use std::{
+        collections::BTreeMap,
+        fs::OpenOptions,
+        io::{self, Write},
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    /// Request headers kept in recordings, the values of sensitive ones are redacted.
+    const REQUEST_HEADERS: &[&str] = &["accept", "authorization", "client-id", "content-type"];
+
+    /// Request headers whose values are replaced with [`REDACTED`](crate::auth::REDACTED).
+    const SENSITIVE_HEADERS: &[&str] = &["authorization", "client-id"];
+
+    /// Top level json body fields whose values are replaced with
+    /// [`REDACTED`](crate::auth::REDACTED), in both requests and responses.
+    const SENSITIVE_FIELDS: &[&str] = &["token", "access_token", "refresh_token", "client_secret"];
+
+    /// Response headers kept in recordings.
+    const RESPONSE_HEADERS: &[&str] = &[
+        "content-type",
+        crate::TRACE_ID_HEADER,
+        crate::RATE_LIMIT_REMAINING_HEADER,
+        crate::RATE_LIMIT_RESET_HEADER,
+    ];
+
+    impl RecordedExchange {
+        /// Records the request half of the exchange.
+        fn request(request: &reqwest::Request) -> Self {
+            let request_headers = allowed_headers(request.headers(), REQUEST_HEADERS)
+                .map(|(name, value)| {
+                    if SENSITIVE_HEADERS.contains(&name.as_str()) {
+                        (name, crate::auth::REDACTED.to_string())
+                    } else {
+                        (name, value)
+                    }
+                })
+                .collect();
+            Self {
+                method: request.method().to_string(),
+                path: request.url().path().to_string(),
+                request_headers,
+                request_body: request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .map(redact_body),
+                status: 0,
+                headers: BTreeMap::new(),
+                body: String::new(),
+            }
+        }
+
+        /// Records the response half of the exchange.
+        fn response(&mut self, status: reqwest::StatusCode, headers: &HeaderMap, body: &[u8]) {
+            self.status = status.as_u16();
+            self.headers = allowed_headers(headers, RESPONSE_HEADERS).collect();
+            self.body = redact_body(body);
+        }
+    }
+
+    fn allowed_headers<'a>(
+        headers: &'a HeaderMap,
+        allowed: &'a [&str],
+    ) -> impl Iterator<Item = (String, String)> + 'a {
+        headers
+            .iter()
+            .filter(move |(name, _)| allowed.contains(&name.as_str()))
+            .map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                (name.to_string(), value)
+            })
+    }
+
+    /// Redacts the top level [`SENSITIVE_FIELDS`] of a json request or response body, leaving any
+    /// other body untouched.
+    fn redact_body(body: &[u8]) -> String {
+        if let Ok(serde_json::Value::Object(mut object)) = serde_json::from_slice(body) {
+            let mut redacted = false;
+            for field in SENSITIVE_FIELDS {
+                if let Some(value) = object.get_mut(*field).filter(|value| value.is_string()) {
+                    *value = crate::auth::REDACTED.into();
+                    redacted = true;
+                }
+            }
+            if redacted {
+                return serde_json::Value::Object(object).to_string();
+            }
+        }
+        String::from_utf8_lossy(body).into_owned()
+    }
+
+    /// Executes requests via another transport, appending each exchange to a json lines file.
+    #[derive(Debug)]
+    pub(crate) struct RecordingTransport {
+        inner: Arc<dyn HttpTransport>,
+        path: PathBuf,
+        lock: Mutex<()>,
+    }
+
+    impl RecordingTransport {
+        pub(crate) fn new(inner: Arc<dyn HttpTransport>, path: PathBuf) -> Self {
+            Self {
+                inner,
+                path,
+                lock: Mutex::new(()),
+            }
+        }
+
+        fn append(&self, exchange: &RecordedExchange) -> io::Result<()> {
+            let mut line = serde_json::to_vec(exchange)?;
+            line.push(b'\n');
+            let _guard = self.lock.lock().unwrap();
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?
+                .write_all(&line)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpTransport for RecordingTransport {
+        async fn execute(
+            &self,
+            request: reqwest::Request,
+        ) -> Result<reqwest::Response, reqwest::Error> {
+            let mut exchange = RecordedExchange::request(&request);
+            let res = self.inner.execute(request).await?;
+            let status = res.status();
+            let version = res.version();
+            let url = res.url().clone();
+            let headers = res.headers().clone();
+            let body = res.bytes().await?;
+            exchange.response(status, &headers, &body);
+
+            // Recording is best effort, it should never fail the request itself
+            if let Err(err) = self.append(&exchange) {
+                warn!(?err, "failed to record http exchange");
+            }
+
+            // Rebuild the response as its body has been consumed
+            let mut builder = http::Response::builder()
+                .status(status)
+                .version(version)
+                .url(url);
+            if let Some(builder_headers) = builder.headers_mut() {
+                *builder_headers = headers;
+            }
+            Ok(builder.body(body).unwrap().into())
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use replay::*;
+
+#[cfg(any(test, feature = "test-util"))]
+mod replay {
+    use super::RecordedExchange;
+    use crate::HttpTransport;
+    use reqwest::{header::HeaderName, ResponseBuilderExt, StatusCode};
+    use std::{
+        io,
+        path::Path,
+        sync::{Arc, Mutex},
+    };
+
+    /// A transport that plays back a recording made with
+    /// [`ClientBuilder::record_to`](crate::ClientBuilder::record_to), for reproducing issues
+    /// offline.
+    ///
+    /// Each request is answered with the first unused exchange with the same method and path.
+    /// Requests without one get a `501 Not Implemented` response. Clones share the same recording.
+    #[derive(Debug, Clone, Default)]
+    pub struct ReplayTransport {
+        exchanges: Arc<Mutex<Vec<RecordedExchange>>>,
+    }
+
+    impl ReplayTransport {
+        /// Plays back the given exchanges.
+        pub fn new(exchanges: impl IntoIterator<Item = RecordedExchange>) -> Self {
+            Self {
+                exchanges: Arc::new(Mutex::new(exchanges.into_iter().collect())),
+            }
+        }
+
+        /// Parses a recording, one json encoded [`RecordedExchange`] per line.
+        pub fn from_json_lines(recording: &str) -> Result<Self, serde_json::Error> {
+            let exchanges = recording
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Self::new(exchanges))
+        }
+
+        /// Reads a recording from the given file, see [`ReplayTransport::from_json_lines`].
+        pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+            let recording = std::fs::read_to_string(path)?;
+            Self::from_json_lines(&recording).map_err(io::Error::from)
+        }
+
+        /// Number of exchanges that haven't been played back yet.
+        pub fn remaining(&self) -> usize {
+            self.exchanges.lock().unwrap().len()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpTransport for ReplayTransport {
+        async fn execute(
+            &self,
+            request: reqwest::Request,
+        ) -> Result<reqwest::Response, reqwest::Error> {
+            let method = request.method().as_str();
+            let path = request.url().path();
+            let exchange = {
+                let mut exchanges = self.exchanges.lock().unwrap();
+                exchanges
+                    .iter()
+                    .position(|exchange| exchange.method == method && exchange.path == path)
+                    .map(|i| exchanges.remove(i))
+            };
+            let exchange = exchange.unwrap_or_else(|| RecordedExchange {
+                method: method.to_string(),
+                path: path.to_string(),
+                request_headers: Default::default(),
+                request_body: None,
+                status: StatusCode::NOT_IMPLEMENTED.as_u16(),
+                headers: Default::default(),
+                body: format!("no recorded exchange for {} {}", method, path),
+            });
+            Ok(into_response(exchange, request.url().clone()))
+        }
+    }
+
+    /// Builds a response from a recorded exchange.
+    fn into_response(exchange: RecordedExchange, url: reqwest::Url) -> reqwest::Response {
+        let mut builder = http::Response::builder().status(exchange.status).url(url);
+        for (name, value) in &exchange.headers {
+            if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+                builder = builder.header(name, value.as_str());
+            }
+        }
+        builder
+            .body(exchange.body)
+            .unwrap_or_else(|_| {
+                http::Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body("invalid recorded response".to_string())
+                    .unwrap()
+            })
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        auth::REDACTED, client::tests::CHANNEL_FIXTURE, AccessTokenOnly, ChannelUpdate, Client,
+        ClientId, ScriptedTransport, TRACE_ID_HEADER,
+    };
+    use reqwest::{header, StatusCode};
+
+    #[tokio::test]
+    async fn recording_redacted() {
+        const CLIENT_ID: &str = "SENTINEL-CLIENT-ID";
+        const TOKEN: &str = "SENTINEL-TOKEN";
+        const CHAT_TOKEN: &str = "SENTINEL-CHAT-TOKEN";
+        const COOKIE: &str = "session=SENTINEL-COOKIE";
+        const CLIENT_SECRET: &str = "SENTINEL-CLIENT-SECRET";
+        const APP_TOKEN: &str = "SENTINEL-APP-TOKEN";
+
+        let path = std::env::temp_dir().join(format!("trovo-record-{}.jsonl", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let transport = ScriptedTransport::new();
+        transport
+            .push_response(
+                http::Response::builder()
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::SET_COOKIE, COOKIE)
+                    .header(TRACE_ID_HEADER, "trace")
+                    .body(CHANNEL_FIXTURE.to_string())
+                    .unwrap(),
+            )
+            .push(
+                StatusCode::OK,
+                format!(r#"{{"token": "{}", "expires": 3600}}"#, CHAT_TOKEN),
+            )
+            .push(
+                StatusCode::OK,
+                format!(r#"{{"access_token": "{}", "expires_in": 3600}}"#, APP_TOKEN),
+            );
+        let client = Client::builder()
+            .transport(transport)
+            .record_to(&path)
+            .build(AccessTokenOnly::new(CLIENT_ID, TOKEN))
+            .unwrap();
+
+        // The client still sees the real response
+        let channel = client.channel_by_id("100000").await.unwrap().unwrap();
+        assert_eq!(channel.username, "someone");
+        client
+            .update_channel("100000", ChannelUpdate::default())
+            .await
+            .unwrap();
+        client
+            .exchange_client_credentials(CLIENT_ID, CLIENT_SECRET)
+            .await
+            .unwrap();
+
+        let recording = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        for secret in [
+            CLIENT_ID,
+            TOKEN,
+            CHAT_TOKEN,
+            COOKIE,
+            CLIENT_SECRET,
+            APP_TOKEN,
+        ] {
+            assert!(!recording.contains(secret), "{} in {}", secret, recording);
+        }
+
+        let exchanges: Vec<RecordedExchange> = recording
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(exchanges.len(), 3);
+        assert_eq!(exchanges[0].method, "POST");
+        assert_eq!(exchanges[0].path, "/openplatform/channels/id");
+        assert_eq!(exchanges[0].request_headers["client-id"], REDACTED);
+        assert_eq!(
+            exchanges[0].request_body.as_deref(),
+            Some(r#"{"channel_id":"100000"}"#)
+        );
+        assert_eq!(exchanges[0].status, 200);
+        assert_eq!(exchanges[0].headers[TRACE_ID_HEADER], "trace");
+        assert!(!exchanges[0].headers.contains_key("set-cookie"));
+        assert_eq!(exchanges[0].body, CHANNEL_FIXTURE);
+        assert_eq!(exchanges[1].request_headers["authorization"], REDACTED);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&exchanges[1].body).unwrap(),
+            serde_json::json!({ "token": REDACTED, "expires": 3600 })
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(exchanges[2].request_body.as_ref().unwrap())
+                .unwrap(),
+            serde_json::json!({ "client_secret": REDACTED, "grant_type": "client_credentials" })
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_fixture() {
+        let transport =
+            ReplayTransport::from_json_lines(include_str!("../fixtures/channel_by_id.jsonl"))
+                .unwrap();
+        let client = Client::with_transport(transport.clone(), ClientId::new("client-id"));
+
+        // Unrecorded requests aren't answered
+        assert!(client.users(vec!["someone".to_string()]).await.is_err());
+        assert_eq!(transport.remaining(), 1);
+
+        let (channel, meta) = client.channel_by_id_with_meta("100000").await.unwrap();
+        assert_eq!(channel.unwrap().username, "someone");
+        assert_eq!(meta.request_id.as_deref(), Some("0a1b2c3d4e5f"));
+        assert_eq!(transport.remaining(), 0);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{redact_url, ApiError, Clock, DecodeError, TRACE_ID_HEADER};
+use bytes::Bytes;
+use reqwest::{
+    header::{self, HeaderMap},
+    Response, ResponseBuilderExt, StatusCode, Url,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Metadata about a response from the Trovo api, returned by the `_with_meta` variants of the
+/// client methods.
+#[derive(Debug, Clone)]
+pub struct ResponseMeta {
+    /// Http status code of the response
+    pub status: StatusCode,
+
+    /// Response headers, such as rate limit counters. Cookies are filtered out.
+    pub headers: HeaderMap,
+
+    /// Time taken for the full request, including downloading the response body.
+    pub latency: Duration,
+
+    /// Trovo request id taken from the [`TRACE_ID_HEADER`] response header, if present.
+    pub request_id: Option<String>,
+}
+
+/// Fields in an api response that the crate's types don't know about, reported to the handler
+/// given to [`ClientBuilder::strict_decoding`](crate::ClientBuilder::strict_decoding).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnexpectedFields {
+    /// Endpoint the response came from, see [`ApiError::endpoint`]
+    pub endpoint: String,
+
+    /// Paths of the unknown fields, e.g. `social_links.0.icon`
+    pub paths: Vec<String>,
+}
+
+/// Handler for responses with unknown fields, see
+/// [`ClientBuilder::strict_decoding`](crate::ClientBuilder::strict_decoding).
+#[derive(Clone)]
+pub(crate) struct StrictDecoding(pub(crate) Arc<dyn Fn(&UnexpectedFields) + Send + Sync>);
+
+impl StrictDecoding {
+    /// Decodes the body again, only to report any fields ignored while decoding as `T`.
+    fn check<T>(&self, body: &[u8], endpoint: &str)
+    where
+        T: DeserializeOwned,
+    {
+        let mut paths = Vec::new();
+        let mut deserializer = serde_json::Deserializer::from_slice(body);
+        let _ = serde_ignored::deserialize::<_, _, T>(&mut deserializer, |path| {
+            paths.push(path.to_string())
+        });
+        if !paths.is_empty() {
+            (self.0)(&UnexpectedFields {
+                endpoint: endpoint.to_string(),
+                paths,
+            });
+        }
+    }
+}
+
+impl fmt::Debug for StrictDecoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StrictDecoding").finish_non_exhaustive()
+    }
+}
+
+/// Longest body excerpt included in [`RequestError::UnexpectedContentType`](crate::RequestError::UnexpectedContentType)
+const BODY_EXCERPT_LEN: usize = 200;
+
+/// Errors returned by [`ApiResponse::error_for_status`]
+#[derive(Debug)]
+pub(crate) enum StatusError {
+    Http(reqwest::Error),
+    UnexpectedContentType {
+        status: StatusCode,
+        content_type: Option<String>,
+        body_excerpt: String,
+    },
+}
+
+impl From<reqwest::Error> for StatusError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(err)
+    }
+}
+
+/// A fully downloaded api response
+#[derive(Debug, Clone)]
+pub(crate) struct ApiResponse {
+    url: Url,
+
+    /// Endpoint included in errors, see [`ApiError::endpoint`]
+    endpoint: String,
+    body: Bytes,
+    pub(crate) meta: ResponseMeta,
+
+    /// Reports unknown fields when decoding, if enabled
+    pub(crate) strict: Option<StrictDecoding>,
+}
+
+impl ApiResponse {
+    /// Downloads the response body, measuring latency from the given start time.
+    pub(crate) async fn read(
+        res: Response,
+        started: Instant,
+        clock: &dyn Clock,
+    ) -> Result<Self, reqwest::Error> {
+        let status = res.status();
+        let mut url = res.url().clone();
+        redact_url(&mut url);
+        let mut headers = res.headers().clone();
+        headers.remove(header::SET_COOKIE);
+        let request_id = headers
+            .get(TRACE_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(ToString::to_string);
+        let body = res.bytes().await?;
+
+        let endpoint = url
+            .path()
+            .trim_start_matches('/')
+            .trim_start_matches("openplatform/")
+            .to_string();
+
+        Ok(Self {
+            url,
+            endpoint,
+            body,
+            meta: ResponseMeta {
+                status,
+                headers,
+                latency: clock.now().saturating_duration_since(started),
+                request_id,
+            },
+            strict: None,
+        })
+    }
+
+    /// Adds a short summary of the request to the endpoint reported in errors, e.g.
+    /// `getusers (3 names)`. Avoid including anything sensitive.
+    pub(crate) fn summarize(mut self, summary: impl std::fmt::Display) -> Self {
+        self.endpoint = format!("{} ({})", self.endpoint, summary);
+        self
+    }
+
+    /// Http status code of the response
+    pub(crate) fn status(&self) -> StatusCode {
+        self.meta.status
+    }
+
+    /// Raw body of the response
+    #[cfg_attr(not(feature = "oauth2"), allow(dead_code))]
+    pub(crate) fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Reads the body of an unsuccessful response as an api error.
+    ///
+    /// Any non-2xx json response is attempted. If that fails, statuses known to return friendly
+    /// errors (see [`ApiError::can_handle_code`]) fall back to [`ApiError::default`], while
+    /// anything else returns `None` to be handled as a plain status error.
+    pub(crate) fn api_error(&self) -> Option<ApiError> {
+        if self.status().is_success() || self.is_unexpected_content() {
+            return None;
+        }
+
+        let parsed = if self.is_json() {
+            serde_json::from_slice(&self.body).ok()
+        } else {
+            None
+        };
+        let mut err = match parsed {
+            Some(err) => err,
+            None if ApiError::can_handle_code(self.status()) => ApiError::default(),
+            None => return None,
+        };
+        err.trace_id = self.meta.request_id.clone();
+        err.endpoint = Some(self.endpoint.clone());
+        Some(err)
+    }
+
+    /// Whether the response has a json content type
+    fn is_json(&self) -> bool {
+        self.content_type()
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| {
+                let mime = mime.trim();
+                mime.eq_ignore_ascii_case("application/json") || mime.ends_with("+json")
+            })
+    }
+
+    /// The response's content type, if it has a valid one
+    fn content_type(&self) -> Option<&str> {
+        self.meta
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+    }
+
+    /// Whether the body is something other than json, such as a Cloudflare error or challenge
+    /// page served during an outage.
+    ///
+    /// Bodies starting with `<` are always unexpected. Otherwise the body only counts as
+    /// unexpected when neither the content type nor its first byte suggest json, so json served
+    /// with the wrong content type is still decoded.
+    fn is_unexpected_content(&self) -> bool {
+        let body = self.body.trim_ascii_start();
+        match body.first() {
+            None => false,
+            Some(b'<') => true,
+            Some(b'{' | b'[' | b'"' | b'-' | b'0'..=b'9' | b't' | b'f' | b'n') => false,
+            Some(_) => !self.is_json(),
+        }
+    }
+
+    /// Returns an error if the body isn't json, see [`ApiResponse::is_unexpected_content`], or
+    /// the status code was a client or server error
+    pub(crate) fn error_for_status(&self) -> Result<(), StatusError> {
+        if self.is_unexpected_content() {
+            let excerpt = String::from_utf8_lossy(&self.body);
+            let excerpt = excerpt.split_whitespace().collect::<Vec<_>>().join(" ");
+            return Err(StatusError::UnexpectedContentType {
+                status: self.status(),
+                content_type: self.content_type().map(ToString::to_string),
+                body_excerpt: excerpt.chars().take(BODY_EXCERPT_LEN).collect(),
+            });
+        }
+
+        if !self.status().is_client_error() && !self.status().is_server_error() {
+            return Ok(());
+        }
+
+        // Rebuild a bodyless response so that reqwest can construct its usual status error
+        let res: Response = http::Response::builder()
+            .status(self.status())
+            .url(self.url.clone())
+            .body("")
+            .unwrap()
+            .into();
+        res.error_for_status()
+            .map(|_| ())
+            .map_err(StatusError::Http)
+    }
+
+    /// Decodes the body as json
+    pub(crate) fn json<T>(&self) -> Result<T, DecodeError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = serde_json::from_slice(&self.body).map_err(|source| DecodeError {
+            source,
+            trace_id: self.meta.request_id.clone(),
+            endpoint: Some(self.endpoint.clone()),
+        })?;
+        if let Some(strict) = &self.strict {
+            strict.check::<T>(&self.body, &self.endpoint);
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,252 @@
+//! Hand written [`JsonSchema`] impls for types with custom serde representations.
+
+use crate::{AudienceType, DropsUpdateStatus, ErrorStatus, LanguageCode};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use std::borrow::Cow;
+
+/// Schema for epoch second timestamps, which the api sends as numbers or numeric strings with
+/// `0` or `""` for no value.
+pub(crate) struct EpochSeconds;
+
+impl JsonSchema for EpochSeconds {
+    fn schema_name() -> Cow<'static, str> {
+        "EpochSeconds".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Unix timestamp in seconds, as a number or numeric string. 0 or an empty string means no value.",
+            "type": ["integer", "number", "string"],
+        })
+    }
+}
+
+impl JsonSchema for AudienceType {
+    fn schema_name() -> Cow<'static, str> {
+        "AudienceType".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Audience type of a channel, unknown values are allowed.",
+            "type": "string",
+            "examples": [
+                AudienceType::FamilyFriendly.as_str(),
+                AudienceType::Teen.as_str(),
+                AudienceType::EighteenPlus.as_str(),
+            ],
+        })
+    }
+}
+
+impl JsonSchema for DropsUpdateStatus {
+    fn schema_name() -> Cow<'static, str> {
+        "DropsUpdateStatus".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Outcome of updating a drops entitlement, unknown values are allowed.",
+            "type": "string",
+            "examples": [
+                DropsUpdateStatus::Success.as_str(),
+                DropsUpdateStatus::InvalidId.as_str(),
+                DropsUpdateStatus::NotFound.as_str(),
+                DropsUpdateStatus::Unauthorized.as_str(),
+                DropsUpdateStatus::UpdateFailed.as_str(),
+            ],
+        })
+    }
+}
+
+impl JsonSchema for LanguageCode {
+    fn schema_name() -> Cow<'static, str> {
+        "LanguageCode".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Two letter ISO 639-1 language code.",
+            "type": "string",
+            "pattern": "^[a-z]{2}$",
+        })
+    }
+}
+
+impl JsonSchema for ErrorStatus {
+    fn schema_name() -> Cow<'static, str> {
+        "ErrorStatus".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        // Not restricted to the known codes, unknown ones deserialize as `ErrorStatus::Other`
+        json_schema!({
+            "description": "Trovo api error code.",
+            "type": "integer",
+            "format": "int32",
+        })
+    }
+}
+
+#[cfg(feature = "chat")]
+impl JsonSchema for crate::chat::ChatMessageType {
+    fn schema_name() -> Cow<'static, str> {
+        "ChatMessageType".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Type of the chat message.",
+            "type": "integer",
+            "enum": crate::chat::ChatMessageType::CODES,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use serde_json::Value;
+
+    /// Checks a value against a generated schema, so the schema tests don't need a full json
+    /// schema validator.
+    ///
+    /// Supports the keywords schemars generates for this crate's types: `$ref` into `$defs`,
+    /// `type`, `enum`, `const`, `minimum`, `allOf`, `anyOf`, `oneOf`, `properties`, `required`,
+    /// `additionalProperties` and `items`. Others, e.g. `format` and `pattern`, are ignored.
+    pub(crate) fn validate(schema: &Value, value: &Value) -> Result<(), String> {
+        check(schema, schema, value, "")
+    }
+
+    fn check(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+        let schema = match schema {
+            Value::Bool(true) => return Ok(()),
+            Value::Bool(false) => return Err(format!("{}: no value allowed", path)),
+            Value::Object(schema) => schema,
+            _ => return Err(format!("{}: invalid schema {}", path, schema)),
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference
+                .strip_prefix("#/$defs/")
+                .ok_or_else(|| format!("{}: unsupported $ref {}", path, reference))?;
+            check(root, &root["$defs"][name], value, path)?;
+        }
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                types => types.as_str().into_iter().collect(),
+            };
+            if !types.iter().any(|type_| has_type(value, type_)) {
+                return Err(format!("{}: {} is not {:?}", path, value, types));
+            }
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                return Err(format!("{}: {} is not one of {:?}", path, value, options));
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                return Err(format!("{}: {} is not {}", path, value, expected));
+            }
+        }
+        if let (Some(minimum), Some(n)) = (
+            schema.get("minimum").and_then(Value::as_f64),
+            value.as_f64(),
+        ) {
+            if n < minimum {
+                return Err(format!("{}: {} is below {}", path, n, minimum));
+            }
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for option in all {
+                check(root, option, value, path)?;
+            }
+        }
+        for keyword in ["anyOf", "oneOf"] {
+            if let Some(options) = schema.get(keyword).and_then(Value::as_array) {
+                let matching = options
+                    .iter()
+                    .filter(|option| check(root, option, value, path).is_ok())
+                    .count();
+                if matching == 0 || (keyword == "oneOf" && matching > 1) {
+                    return Err(format!(
+                        "{}: {} matches {} of {}",
+                        path, value, matching, keyword
+                    ));
+                }
+            }
+        }
+
+        if let Some(object) = value.as_object() {
+            let required = schema.get("required").and_then(Value::as_array);
+            for name in required.into_iter().flatten().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    return Err(format!("{}: missing {}", path, name));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field) in object {
+                let field_path = format!("{}/{}", path, name);
+                match (
+                    properties.and_then(|p| p.get(name)),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(property), _) => check(root, property, field, &field_path)?,
+                    (None, Some(additional)) => check(root, additional, field, &field_path)?,
+                    (None, None) => {}
+                }
+            }
+        }
+        if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+            for (i, item) in array.iter().enumerate() {
+                check(root, items, item, &format!("{}/{}", path, i))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn has_type(value: &Value, type_: &str) -> bool {
+        match type_ {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "string" => value.is_string(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn validate_keywords() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["id"],
+            "properties": {
+                "id": {"type": "string"},
+                "kind": {"$ref": "#/$defs/Kind"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "count": {"type": ["integer", "null"], "minimum": 0},
+            },
+            "additionalProperties": false,
+            "$defs": {"Kind": {"enum": [1, 2]}},
+        });
+        let valid = serde_json::json!({"id": "a", "kind": 2, "tags": ["x"], "count": null});
+        assert_eq!(validate(&schema, &valid), Ok(()));
+
+        for invalid in [
+            serde_json::json!({"kind": 1}),
+            serde_json::json!({"id": 1}),
+            serde_json::json!({"id": "a", "kind": 3}),
+            serde_json::json!({"id": "a", "tags": [1]}),
+            serde_json::json!({"id": "a", "count": -1}),
+            serde_json::json!({"id": "a", "extra": true}),
+        ] {
+            assert!(validate(&schema, &invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{BoxError, HttpTransport, SendError};
+use futures::future::BoxFuture;
+use std::{
+    fmt,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{util::BoxCloneSyncService, Service, ServiceExt};
+
+/// Request passed through the tower service of a [`Client`](crate::Client).
+pub type TrovoRequest = reqwest::Request;
+
+/// Response returned by the tower service of a [`Client`](crate::Client).
+pub type TrovoResponse = reqwest::Response;
+
+/// An [`HttpTransport`] exposed as a [`tower::Service`], to be wrapped in middleware and passed
+/// to [`Client::with_service`](crate::Client::with_service).
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use tower::ServiceBuilder;
+/// use trovo::{Client, ClientId, TrovoService};
+///
+/// let service = ServiceBuilder::new()
+///     .concurrency_limit(4)
+///     .service(TrovoService::new(reqwest::Client::new()));
+/// let client = Client::with_service(service, ClientId::new("client-id"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrovoService {
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl TrovoService {
+    /// Sends requests via the given transport, e.g. a [`reqwest::Client`].
+    pub fn new(transport: impl HttpTransport + 'static) -> Self {
+        Self {
+            transport: Arc::new(transport),
+        }
+    }
+}
+
+impl Service<TrovoRequest> for TrovoService {
+    type Response = TrovoResponse;
+    type Error = reqwest::Error;
+    type Future = BoxFuture<'static, Result<TrovoResponse, reqwest::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: TrovoRequest) -> Self::Future {
+        let transport = self.transport.clone();
+        Box::pin(async move { transport.execute(request).await })
+    }
+}
+
+/// Type erased service used by the pipeline in place of its transport.
+#[derive(Clone)]
+pub(crate) struct ServiceStack(BoxCloneSyncService<TrovoRequest, TrovoResponse, BoxError>);
+
+impl ServiceStack {
+    pub(crate) fn new<S>(service: S) -> Self
+    where
+        S: Service<TrovoRequest, Response = TrovoResponse> + Clone + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        Self(BoxCloneSyncService::new(service.map_err(Into::into)))
+    }
+
+    /// Waits for the service to be ready and sends the request. Errors from the transport are
+    /// unwrapped so they are reported the same as without a service.
+    pub(crate) async fn call(&self, request: TrovoRequest) -> Result<TrovoResponse, SendError> {
+        self.0.clone().oneshot(request).await.map_err(|err| {
+            match err.downcast::<reqwest::Error>() {
+                Ok(err) => SendError::from(*err),
+                Err(err) => SendError::Service(Arc::from(err)),
+            }
+        })
+    }
+}
+
+impl fmt::Debug for ServiceStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ServiceStack").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, ClientId, RequestError, ScriptedTransport};
+    use reqwest::StatusCode;
+    use std::time::Duration;
+    use tower::limit::ConcurrencyLimit;
+
+    #[tokio::test]
+    async fn concurrency_limit_enforced() {
+        let transport = ScriptedTransport::new().with_delay(Duration::from_millis(20));
+        for _ in 0..6 {
+            transport.push(StatusCode::OK, r#"{"users": []}"#);
+        }
+        let service = ConcurrencyLimit::new(TrovoService::new(transport.clone()), 2);
+        let client = Client::with_service(service, ClientId::new("client-id"));
+
+        let lookups = (0..6).map(|i| client.users(vec![format!("user{}", i)]));
+        let results = futures::future::join_all(lookups).await;
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(transport.requests().len(), 6);
+        assert_eq!(transport.max_in_flight(), 2);
+    }
+
+    #[tokio::test]
+    async fn service_errors() {
+        let service = tower::service_fn(|_: TrovoRequest| async {
+            Err::<TrovoResponse, BoxError>("overloaded".into())
+        });
+        let client = Client::with_service(service, ClientId::new("client-id"));
+
+        let err = client.users(vec!["someone".to_string()]).await.unwrap_err();
+        assert!(matches!(err, RequestError::Service(_)));
+        assert_eq!(err.to_string(), "overloaded");
+        assert!(!err.is_retryable());
+    }
+}
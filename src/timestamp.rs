@@ -0,0 +1,118 @@
+//! The [`Timestamp`] type used by entities, and serde helpers for the api's epoch second fields.
+
+/// Point in time used for timestamps in entities.
+///
+/// This is [`chrono::DateTime<Utc>`](chrono::DateTime) with the default `chrono` feature,
+/// [`time::OffsetDateTime`] when the `time` feature is enabled and `chrono` is not, or unix
+/// seconds as an `i64` when neither is enabled.
+#[cfg(feature = "chrono")]
+pub type Timestamp = chrono::DateTime<chrono::Utc>;
+
+/// Point in time used for timestamps in entities.
+///
+/// This is [`chrono::DateTime<Utc>`](https://docs.rs/chrono) with the default `chrono` feature,
+/// [`time::OffsetDateTime`] when the `time` feature is enabled and `chrono` is not, or unix
+/// seconds as an `i64` when neither is enabled.
+#[cfg(all(feature = "time", not(feature = "chrono")))]
+pub type Timestamp = time::OffsetDateTime;
+
+/// Point in time used for timestamps in entities.
+///
+/// This is `chrono::DateTime<Utc>` with the default `chrono` feature, `time::OffsetDateTime` when
+/// the `time` feature is enabled and `chrono` is not, or unix seconds as an `i64` when neither is
+/// enabled.
+#[cfg(not(any(feature = "chrono", feature = "time")))]
+pub type Timestamp = i64;
+
+/// Timestamp from unix seconds and nanoseconds, `None` if out of range.
+pub(crate) fn from_unix(secs: i64, nanos: u32) -> Option<Timestamp> {
+    #[cfg(feature = "chrono")]
+    return chrono::DateTime::from_timestamp(secs, nanos);
+
+    #[cfg(all(feature = "time", not(feature = "chrono")))]
+    return time::OffsetDateTime::from_unix_timestamp_nanos(
+        secs as i128 * 1_000_000_000 + nanos as i128,
+    )
+    .ok();
+
+    #[cfg(not(any(feature = "chrono", feature = "time")))]
+    {
+        let _ = nanos;
+        Some(secs)
+    }
+}
+
+/// Unix seconds of a timestamp, rounded down.
+pub(crate) fn unix_seconds(timestamp: &Timestamp) -> i64 {
+    #[cfg(feature = "chrono")]
+    return timestamp.timestamp();
+
+    #[cfg(all(feature = "time", not(feature = "chrono")))]
+    return timestamp.unix_timestamp();
+
+    #[cfg(not(any(feature = "chrono", feature = "time")))]
+    *timestamp
+}
+
+/// Epoch second timestamps stored as numbers.
+#[cfg(feature = "chat")]
+pub(crate) mod seconds {
+    use super::{from_unix, unix_seconds, Timestamp};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Timestamp,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(unix_seconds(value))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Timestamp, D::Error> {
+        let secs = i64::deserialize(deserializer)?;
+        from_unix(secs, 0)
+            .ok_or_else(|| de::Error::custom(format!("timestamp {} out of range", secs)))
+    }
+}
+
+/// Epoch second timestamps, accepting numbers and numeric strings, where `0` and empty strings
+/// mean no value. Serialised as a number, with `None` as `0`.
+pub(crate) mod optional_seconds {
+    use super::{from_unix, unix_seconds, Timestamp};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<Timestamp>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(value.as_ref().map_or(0, unix_seconds))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Timestamp>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Int(i64),
+            Float(f64),
+            Str(String),
+        }
+
+        let secs = match Raw::deserialize(deserializer)? {
+            Raw::Int(secs) => secs as f64,
+            Raw::Float(secs) => secs,
+            Raw::Str(s) if s.trim().is_empty() => return Ok(None),
+            Raw::Str(s) => s.trim().parse().map_err(|_| {
+                de::Error::invalid_value(de::Unexpected::Str(&s), &"an epoch timestamp")
+            })?,
+        };
+        if secs == 0.0 {
+            return Ok(None);
+        }
+        from_unix(secs.trunc() as i64, (secs.fract() * 1e9) as u32)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("timestamp {} out of range", secs)))
+    }
+}
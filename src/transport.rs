@@ -0,0 +1,169 @@
+use std::fmt::Debug;
+
+/// Executes http requests on behalf of a [`Client`](crate::Client).
+///
+/// The default implementation simply delegates to a [`reqwest::Client`]. Alternative
+/// implementations are mostly useful for testing, see `ScriptedTransport` under the `test-util`
+/// feature.
+#[async_trait::async_trait]
+pub trait HttpTransport: Debug + Send + Sync {
+    /// Execute the given request, returning the response.
+    async fn execute(&self, request: reqwest::Request)
+        -> Result<reqwest::Response, reqwest::Error>;
+}
+
+#[async_trait::async_trait]
+impl HttpTransport for reqwest::Client {
+    async fn execute(
+        &self,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        reqwest::Client::execute(self, request).await
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+pub use scripted::*;
+
+#[cfg(any(test, feature = "test-util"))]
+mod scripted {
+    use super::HttpTransport;
+    use reqwest::{header::HeaderMap, Method, ResponseBuilderExt, StatusCode, Url};
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// A request that was passed to a [`ScriptedTransport`].
+    #[derive(Debug, Clone)]
+    pub struct RecordedRequest {
+        /// Http method of the request
+        pub method: Method,
+
+        /// Full url of the request
+        pub url: Url,
+
+        /// Headers sent with the request
+        pub headers: HeaderMap,
+
+        /// Body of the request, if any
+        pub body: Option<Vec<u8>>,
+    }
+
+    impl RecordedRequest {
+        /// Parse the request body as json.
+        ///
+        /// # Panics
+        ///
+        /// Panics if the request has no body or it isn't valid json.
+        pub fn json(&self) -> serde_json::Value {
+            serde_json::from_slice(self.body.as_deref().expect("request has no body"))
+                .expect("request body is not json")
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct Script {
+        responses: VecDeque<http::Response<String>>,
+        requests: Vec<RecordedRequest>,
+        delay: Option<Duration>,
+        in_flight: usize,
+        max_in_flight: usize,
+    }
+
+    /// A transport that replies with canned responses, in order, without touching the network.
+    ///
+    /// Clones share the same script, so a clone can be kept to push responses and inspect
+    /// requests after the original has been handed to
+    /// [`Client::with_transport`](crate::Client::with_transport).
+    #[derive(Debug, Clone, Default)]
+    pub struct ScriptedTransport {
+        script: Arc<Mutex<Script>>,
+    }
+
+    impl ScriptedTransport {
+        /// Creates a transport with no queued responses.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Queue a json response with the given status and body.
+        pub fn push(&self, status: StatusCode, body: impl Into<String>) -> &Self {
+            let response = http::Response::builder()
+                .status(status)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.into())
+                .unwrap();
+            self.push_response(response)
+        }
+
+        /// Queue a fully custom response.
+        pub fn push_response(&self, response: http::Response<String>) -> &Self {
+            self.script.lock().unwrap().responses.push_back(response);
+            self
+        }
+
+        /// Delay every response by the given duration, useful for testing concurrent requests.
+        pub fn with_delay(self, delay: Duration) -> Self {
+            self.script.lock().unwrap().delay = Some(delay);
+            self
+        }
+
+        /// The maximum number of requests that were being executed at the same time.
+        pub fn max_in_flight(&self) -> usize {
+            self.script.lock().unwrap().max_in_flight
+        }
+
+        /// Requests executed so far, in order.
+        pub fn requests(&self) -> Vec<RecordedRequest> {
+            self.script.lock().unwrap().requests.clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpTransport for ScriptedTransport {
+        async fn execute(
+            &self,
+            request: reqwest::Request,
+        ) -> Result<reqwest::Response, reqwest::Error> {
+            let (response, delay) = {
+                let mut script = self.script.lock().unwrap();
+                script.requests.push(RecordedRequest {
+                    method: request.method().clone(),
+                    url: request.url().clone(),
+                    headers: request.headers().clone(),
+                    body: request
+                        .body()
+                        .and_then(|body| body.as_bytes())
+                        .map(<[u8]>::to_vec),
+                });
+                script.in_flight += 1;
+                script.max_in_flight = script.max_in_flight.max(script.in_flight);
+
+                let response = script.responses.pop_front().unwrap_or_else(|| {
+                    http::Response::builder()
+                        .status(StatusCode::NOT_IMPLEMENTED)
+                        .body(format!("no scripted response for {}", request.url()))
+                        .unwrap()
+                });
+                (response, script.delay)
+            };
+
+            if let Some(delay) = delay {
+                crate::clock::sleep_until(std::time::Instant::now() + delay).await;
+            }
+            self.script.lock().unwrap().in_flight -= 1;
+
+            // Attach the request url like a real response would have
+            let (parts, body) = response.into_parts();
+            let mut builder = http::Response::builder()
+                .status(parts.status)
+                .url(request.url().clone());
+            if let Some(headers) = builder.headers_mut() {
+                headers.extend(parts.headers);
+            }
+            Ok(builder.body(body).unwrap().into())
+        }
+    }
+}
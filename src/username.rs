@@ -0,0 +1,125 @@
+use reqwest::Url;
+use std::fmt::{self, Display};
+
+/// A username to look up, see [`Client::user`](crate::Client::user) and
+/// [`Client::users`](crate::Client::users).
+///
+/// Converting from a string normalizes what people tend to paste in as a streamer's name: it
+/// trims whitespace, takes the last path segment of a `trovo.live` url, strips a leading `@`, and
+/// lowercases the result, which the api doesn't mind as usernames aren't case sensitive. Use
+/// [`Username::exact`] to look up a string as is.
+///
+/// ```
+/// # use trovo::Username;
+/// assert_eq!(Username::parse("https://trovo.live/s/SomeName").as_str(), "somename");
+/// assert_eq!(Username::parse(" @SomeName ").as_str(), "somename");
+/// assert_eq!(Username::exact("SomeName").as_str(), "SomeName");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Username(String);
+
+impl Username {
+    /// Normalizes the input into a username, see the [type docs](Username).
+    ///
+    /// Blank input stays blank, for the request validation to reject.
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+        let from_url = channel_url_name(input);
+        let name = from_url.as_deref().unwrap_or(input);
+        let name = name.strip_prefix('@').unwrap_or(name).trim();
+        Self(name.to_lowercase())
+    }
+
+    /// Uses the string as the username without normalizing it.
+    pub fn exact(username: impl Into<String>) -> Self {
+        Self(username.into())
+    }
+
+    /// Get the username as a string slice
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The last path segment of a `trovo.live` url, with or without the scheme
+fn channel_url_name(input: &str) -> Option<String> {
+    let url = match Url::parse(input) {
+        Ok(url) => url,
+        Err(_) => Url::parse(&format!("https://{}", input)).ok()?,
+    };
+    let host = url.host_str()?;
+    let is_trovo = host == "trovo.live" || host.ends_with(".trovo.live");
+    if !is_trovo || !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let segment = url.path_segments()?.rfind(|s| !s.is_empty())?;
+    Some(segment.to_owned())
+}
+
+impl Display for Username {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Username {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Username {
+    fn from(input: &str) -> Self {
+        Self::parse(input)
+    }
+}
+
+impl From<&String> for Username {
+    fn from(input: &String) -> Self {
+        Self::parse(input)
+    }
+}
+
+impl From<String> for Username {
+    fn from(input: String) -> Self {
+        Self::parse(&input)
+    }
+}
+
+impl From<Username> for String {
+    fn from(username: Username) -> Self {
+        username.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for messy in [
+            "somename",
+            "SomeName",
+            "  SomeName\n",
+            "@SomeName",
+            " @somename ",
+            "https://trovo.live/SomeName",
+            "https://trovo.live/s/SomeName",
+            "https://trovo.live/s/SomeName/?adtag=user.abc",
+            "http://www.trovo.live/s/SomeName",
+            "trovo.live/s/SomeName",
+            " https://trovo.live/s/@SomeName ",
+        ] {
+            assert_eq!(Username::parse(messy).as_str(), "somename", "{:?}", messy);
+        }
+
+        // Other urls aren't picked apart
+        assert_eq!(
+            Username::parse("https://example.com/s/SomeName").as_str(),
+            "https://example.com/s/somename"
+        );
+        assert_eq!(Username::parse(" ").as_str(), "");
+        assert_eq!(Username::exact(" @SomeName").as_str(), " @SomeName");
+    }
+}